@@ -8,7 +8,10 @@
 use redis_lib::{DEFAULT_PORT, server};
 
 use clap::Parser;
-use tokio::net::TcpListener;
+use std::net::{IpAddr, Ipv4Addr};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::Duration;
 use tokio::signal;
 
 #[cfg(feature = "otel")]
@@ -38,9 +41,54 @@ pub async fn main() -> redis_lib::Result<()> {
     let cmd = CliCommand::parse();
     // let port = cmd.port.unwrap_or(DEFAULT_PORT);
 
-    let listener = TcpListener::bind(&format!("127.0.0.1:{}", cmd.port)).await?;
-
-    server::run(listener, signal::ctrl_c()).await;
+    let listener = server::bind(cmd.bind, cmd.port).await?;
+
+    let mut config = server::Config {
+        password: cmd.requirepass,
+        dump_path: cmd.rdb,
+        aof_path: cmd.appendonly,
+        appendfsync: cmd.appendfsync,
+        access_log: cmd.access_log,
+        #[cfg(unix)]
+        unixsocket: cmd.unixsocket,
+        ..server::Config::default()
+    };
+    if let Some(databases) = cmd.databases {
+        config.databases = databases.get();
+    }
+    if let Some(capacity) = cmd.pubsub_capacity {
+        config.pubsub_capacity = capacity.get();
+    }
+    if let Some(max) = cmd.max_connections {
+        config.max_connections = max.get();
+    }
+    if let Some(acceptors) = cmd.acceptors {
+        config.acceptors = acceptors.get();
+    }
+    if let Some(seconds) = cmd.shutdown_timeout {
+        config.shutdown_timeout = Duration::from_secs(seconds);
+    }
+    if let Some(seconds) = cmd.timeout {
+        config.idle_timeout = Some(Duration::from_secs(seconds));
+    }
+    if let Some(seconds) = cmd.tcp_keepalive {
+        config.tcp.keepalive = (seconds > 0).then(|| Duration::from_secs(seconds));
+    }
+    if cmd.no_tcp_nodelay {
+        config.tcp.nodelay = false;
+    }
+    #[cfg(feature = "tls")]
+    if let (Some(cert), Some(key)) = (&cmd.tls_cert_file, &cmd.tls_key_file) {
+        config.tls = Some(redis_lib::tls::server_config(cert, key)?);
+    }
+    if let Some(len) = cmd.proto_max_bulk_len {
+        config.frame_limits.max_bulk_len = len;
+    }
+    if let Some(len) = cmd.proto_max_multibulk_len {
+        config.frame_limits.max_array_len = len;
+    }
+
+    server::run_with_config(listener, signal::ctrl_c(), config).await;
 
     Ok(())
 }
@@ -50,6 +98,88 @@ pub async fn main() -> redis_lib::Result<()> {
 struct CliCommand {
     #[clap(long, default_value_t = DEFAULT_PORT)]
     port: u16,
+
+    /// Address of the interface to listen on, `0.0.0.0` for all of them.
+    #[clap(long, default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    bind: IpAddr,
+
+    /// Number of logical databases, selected with `SELECT`.
+    #[clap(long)]
+    databases: Option<NonZeroUsize>,
+
+    /// Number of messages buffered per pub/sub subscriber before the oldest are skipped.
+    #[clap(long)]
+    pubsub_capacity: Option<NonZeroUsize>,
+
+    /// Maximum number of connections served at once, further ones waiting for a slot.
+    #[clap(long)]
+    max_connections: Option<NonZeroUsize>,
+
+    /// Number of tasks accepting connections concurrently.
+    #[clap(long)]
+    acceptors: Option<NonZeroUsize>,
+
+    /// Require clients to authenticate with `AUTH <password>`.
+    #[clap(long)]
+    requirepass: Option<String>,
+
+    /// Snapshot file loaded at startup if it exists, and written by `SAVE`.
+    #[clap(long)]
+    rdb: Option<PathBuf>,
+
+    /// Unix domain socket to also accept connections on.
+    #[cfg(unix)]
+    #[clap(long)]
+    unixsocket: Option<PathBuf>,
+
+    /// Append-only file logging the write commands.
+    #[clap(long)]
+    appendonly: Option<PathBuf>,
+
+    /// When the append-only file is flushed to disk: always, everysec or no.
+    #[clap(long, default_value_t = server::AppendFsync::default())]
+    appendfsync: server::AppendFsync,
+
+    /// File recording each command processed, with its client and key.
+    #[clap(long)]
+    access_log: Option<PathBuf>,
+
+    /// Seconds given to the connections to finish processing on shutdown.
+    #[clap(long)]
+    shutdown_timeout: Option<u64>,
+
+    /// Close the connections sending no command for this many seconds, `0` to
+    /// never close them.
+    #[clap(long)]
+    timeout: Option<u64>,
+
+    /// Seconds a connection is idle before TCP keepalive probes are sent, `0` to
+    /// disable them. Defaults to 300.
+    #[clap(long)]
+    tcp_keepalive: Option<u64>,
+
+    /// Leave Nagle's algorithm enabled on the connections, batching small replies.
+    #[clap(long)]
+    no_tcp_nodelay: bool,
+
+    /// Certificate chain presented to the clients, served over TLS along with
+    /// `--tls-key-file`.
+    #[cfg(feature = "tls")]
+    #[clap(long, requires = "tls_key_file")]
+    tls_cert_file: Option<PathBuf>,
+
+    /// Private key of the certificate given by `--tls-cert-file`.
+    #[cfg(feature = "tls")]
+    #[clap(long, requires = "tls_cert_file")]
+    tls_key_file: Option<PathBuf>,
+
+    /// Maximum length of a string sent by a client, in bytes.
+    #[clap(long)]
+    proto_max_bulk_len: Option<usize>,
+
+    /// Maximum number of entries of an array sent by a client.
+    #[clap(long)]
+    proto_max_multibulk_len: Option<usize>,
 }
 
 #[cfg(not(feature = "otel"))]
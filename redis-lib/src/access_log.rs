@@ -0,0 +1,62 @@
+//!
+//! The access log, recording the commands processed by the server.
+//!
+//! Each command is written as a line, before it is applied:
+//!
+//! ```text
+//! 1700000000.123456 id=3 db=0 cmd=set key="hello"
+//! ```
+//!
+//! The key is quoted and escaped, and only present for the commands applying to a
+//! single key. Unlike `MONITOR`, the arguments are not recorded.
+//!
+
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The access log of the server.
+#[derive(Debug)]
+pub(crate) struct AccessLog {
+    file: Mutex<File>,
+}
+
+impl AccessLog {
+    /// Open the access log at `path`, appending to the file if it exists.
+    pub(crate) fn open(path: &Path) -> io::Result<AccessLog> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(AccessLog {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Record the command `name`, sent by the client `id` to the database at
+    /// `index`, applying to `key` if any.
+    pub(crate) fn record(
+        &self,
+        id: u64,
+        index: usize,
+        name: &str,
+        key: Option<&str>,
+    ) -> io::Result<()> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut line = format!(
+            "{}.{:06} id={id} db={index} cmd={name}",
+            time.as_secs(),
+            time.subsec_micros()
+        );
+        if let Some(key) = key {
+            let _ = write!(line, " key={key:?}");
+        }
+        line.push('\n');
+
+        // Written at once, so that the lines of concurrent connections do not mix.
+        self.file.lock().unwrap().write_all(line.as_bytes())
+    }
+}
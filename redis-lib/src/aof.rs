@@ -0,0 +1,349 @@
+//!
+//! The append-only file, logging the write commands applied to the databases.
+//!
+//! Each command is appended as the RESP array of its name and arguments, as sent by
+//! the client, preceded by a `SELECT` whenever it applies to another database than
+//! the previous one. Replaying the file on startup restores the databases.
+//!
+
+use crate::frame::{self, Frame};
+use crate::{Command, Connection, Db};
+use bytes::{Buf, Bytes};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{debug, error, warn};
+
+/// When the append-only file is flushed to disk.
+///
+/// Commands are written to the file as soon as they are applied, so a crash of the
+/// server loses none of them. The policy only matters if the machine goes down.
+///
+/// The file is flushed from a background thread, not to hold up the commands
+/// appended meanwhile.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AppendFsync {
+    /// After every command, which is only acknowledged once flushed. The commands
+    /// appended while flushing are flushed together afterwards.
+    Always,
+
+    /// Once per second, from a background thread.
+    #[default]
+    EverySec,
+
+    /// Whenever the operating system decides to.
+    No,
+}
+
+/// The append-only file of the server.
+#[derive(Debug)]
+pub(crate) struct Aof {
+    path: PathBuf,
+    file: Arc<Mutex<AofFile>>,
+    fsync: AppendFsync,
+
+    /// With `AppendFsync::Always`, wakes up the thread flushing the file.
+    appended: Option<Sender<()>>,
+}
+
+#[derive(Debug)]
+struct AofFile {
+    file: File,
+
+    /// The database selected by the last `SELECT` written to the file.
+    selected: Option<usize>,
+
+    /// While the file is rewritten, the commands appended since the rewrite
+    /// started. They are appended to the rewritten file once complete.
+    rewrite_buffer: Option<Vec<u8>>,
+
+    /// The number of bytes appended since the file was opened.
+    appended: u64,
+
+    /// The number of bytes appended since the file was opened which are flushed to
+    /// disk.
+    synced: watch::Sender<u64>,
+}
+
+impl Aof {
+    /// Open the append-only file at `path`, creating it if needed. Commands are
+    /// appended after its current contents.
+    pub(crate) fn open(path: &Path, fsync: AppendFsync) -> io::Result<Aof> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let file = Arc::new(Mutex::new(AofFile {
+            file,
+            selected: None,
+            rewrite_buffer: None,
+            appended: 0,
+            synced: watch::Sender::new(0),
+        }));
+
+        let mut appended = None;
+        match fsync {
+            AppendFsync::Always => {
+                let (tx, rx) = mpsc::channel();
+                let file = Arc::downgrade(&file);
+                thread::spawn(move || sync_on_append(&file, &rx));
+                appended = Some(tx);
+            }
+            AppendFsync::EverySec => {
+                let file = Arc::downgrade(&file);
+                thread::spawn(move || sync_every_second(&file));
+            }
+            AppendFsync::No => {}
+        }
+
+        Ok(Aof {
+            path: path.to_path_buf(),
+            file,
+            fsync,
+            appended,
+        })
+    }
+
+    /// Start buffering the appended commands, to be added to the rewritten file by
+    /// `finish_rewrite`.
+    ///
+    /// Fails if a rewrite is already in progress.
+    pub(crate) fn start_rewrite(&self) -> crate::Result<()> {
+        let mut aof = self.file.lock().unwrap();
+        if aof.rewrite_buffer.is_some() {
+            return Err("Background append only file rewriting already in progress".into());
+        }
+        aof.rewrite_buffer = Some(Vec::new());
+        // The buffered commands must start with a `SELECT`.
+        aof.selected = None;
+
+        Ok(())
+    }
+
+    /// Replace the file with `commands`, followed by the commands appended since
+    /// `start_rewrite`.
+    ///
+    /// `commands` are written to a temporary file renamed to the append-only file, so
+    /// that it always holds a complete log.
+    pub(crate) fn finish_rewrite(&self, commands: &[Vec<Bytes>]) -> io::Result<()> {
+        let res = self.write_rewritten(commands);
+        self.file.lock().unwrap().rewrite_buffer = None;
+
+        res
+    }
+
+    fn write_rewritten(&self, commands: &[Vec<Bytes>]) -> io::Result<()> {
+        let mut buf = Vec::new();
+        for args in commands {
+            encode(&mut buf, args);
+        }
+        let tmp = self.path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(&buf)?;
+
+        // Appends wait for the file to be replaced.
+        let mut aof = self.file.lock().unwrap();
+        if let Some(buffer) = &aof.rewrite_buffer {
+            file.write_all(buffer)?;
+        }
+        file.sync_data()?;
+        fs::rename(&tmp, &self.path)?;
+        aof.file = OpenOptions::new().append(true).open(&self.path)?;
+        aof.synced.send_replace(aof.appended);
+        debug!(path = ?self.path, "append-only file rewritten");
+
+        Ok(())
+    }
+
+    /// Append the command `args`, applied to the database at `index`.
+    ///
+    /// With `AppendFsync::Always`, the command is flushed to disk by a background
+    /// thread, see `synced`.
+    pub(crate) fn append(&self, index: usize, args: &[Bytes]) -> io::Result<()> {
+        let mut buf = Vec::new();
+        let mut aof = self.file.lock().unwrap();
+        if aof.selected != Some(index) {
+            encode(
+                &mut buf,
+                &[Bytes::from("SELECT"), Bytes::from(index.to_string())],
+            );
+        }
+        encode(&mut buf, args);
+
+        aof.file.write_all(&buf)?;
+        aof.selected = Some(index);
+        aof.appended += buf.len() as u64;
+        if let Some(buffer) = &mut aof.rewrite_buffer {
+            buffer.extend_from_slice(&buf);
+        }
+        drop(aof);
+        if let Some(appended) = &self.appended {
+            // The thread outlives `self`.
+            let _ = appended.send(());
+        }
+
+        Ok(())
+    }
+
+    /// Returns when the file is flushed to disk.
+    pub(crate) fn fsync(&self) -> AppendFsync {
+        self.fsync
+    }
+
+    /// Wait for the commands appended so far to be flushed to disk.
+    ///
+    /// With `AppendFsync::No`, the file is flushed at once rather than whenever the
+    /// operating system decides to, on a blocking thread.
+    pub(crate) async fn synced(&self) -> io::Result<()> {
+        if self.fsync == AppendFsync::No {
+            let file = self.file.clone();
+            tokio::task::spawn_blocking(move || sync(&file))
+                .await
+                .map_err(io::Error::other)??;
+        }
+        let (appended, mut synced) = {
+            let aof = self.file.lock().unwrap();
+            (aof.appended, aof.synced.subscribe())
+        };
+        // The sender is kept alongside the file, which outlives `self`.
+        let _ = synced.wait_for(|&synced| synced >= appended).await;
+
+        Ok(())
+    }
+}
+
+/// Flush the file to disk, along with the commands appended so far.
+///
+/// The file is flushed through a handle of its own, so that commands are appended
+/// meanwhile.
+fn sync(file: &Mutex<AofFile>) -> io::Result<()> {
+    let (handle, appended) = {
+        let aof = file.lock().unwrap();
+        (aof.file.try_clone()?, aof.appended)
+    };
+    handle.sync_data()?;
+
+    // A rewrite may have flushed more meanwhile.
+    file.lock().unwrap().synced.send_if_modified(|synced| {
+        let modified = *synced < appended;
+        *synced = (*synced).max(appended);
+        modified
+    });
+
+    Ok(())
+}
+
+/// Apply the commands of the append-only file at `path` to `db`, as if issued by a
+/// client within `limits`, and return their number.
+///
+/// The last command may be truncated if the server stopped in the middle of writing
+/// it, in which case it is ignored and cut from the file, so that the commands
+/// appended afterwards can be replayed.
+pub(crate) async fn replay(path: &Path, db: &Db, limits: &frame::Limits) -> crate::Result<u64> {
+    let buf = tokio::fs::read(path).await?;
+    let mut src = Cursor::new(&buf[..]);
+    // The replies are not sent anywhere.
+    let mut dst = Connection::discarding();
+    let mut db = db.clone();
+    let mut replayed = 0;
+
+    while src.has_remaining() {
+        let start = src.position();
+        let frame = match Frame::parse(&mut src, limits) {
+            Ok(frame) => frame,
+            Err(frame::Error::Incomplete) => {
+                warn!(
+                    offset = start,
+                    "truncating the incomplete end of the append-only file"
+                );
+                let file = tokio::fs::OpenOptions::new().write(true).open(path).await?;
+                file.set_len(start).await?;
+                break;
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        match Command::from_frame(frame)? {
+            Command::Select(cmd) => cmd.apply(&mut db, &mut dst).await?,
+            cmd => cmd.apply(&db, &mut dst).await?,
+        }
+        replayed += 1;
+    }
+
+    Ok(replayed)
+}
+
+/// Flush the file to disk every second, until the `Aof` is dropped.
+fn sync_every_second(file: &Weak<Mutex<AofFile>>) {
+    loop {
+        thread::sleep(Duration::from_secs(1));
+        let Some(file) = file.upgrade() else {
+            return;
+        };
+        if let Err(err) = sync(&file) {
+            error!(cause = %err, "failed to sync the append-only file");
+        }
+    }
+}
+
+/// Flush the file to disk whenever commands are `appended`, until the `Aof` is
+/// dropped. A failed flush is retried every second, as the commands appended are
+/// only acknowledged once flushed.
+fn sync_on_append(file: &Weak<Mutex<AofFile>>, appended: &Receiver<()>) {
+    let mut failed = false;
+    loop {
+        if !failed && appended.recv().is_err() {
+            return;
+        }
+        // The commands appended so far are flushed at once.
+        while appended.try_recv().is_ok() {}
+        let Some(file) = file.upgrade() else {
+            return;
+        };
+        failed = match sync(&file) {
+            Ok(()) => false,
+            Err(err) => {
+                error!(cause = %err, "failed to sync the append-only file");
+                thread::sleep(Duration::from_secs(1));
+                true
+            }
+        };
+    }
+}
+
+/// Append the RESP array of `args` to `buf`.
+fn encode(buf: &mut Vec<u8>, args: &[Bytes]) {
+    buf.extend_from_slice(format!("*{}\r\n", args.len()).as_bytes());
+    for arg in args {
+        buf.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        buf.extend_from_slice(arg);
+        buf.extend_from_slice(b"\r\n");
+    }
+}
+
+impl FromStr for AppendFsync {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<AppendFsync, String> {
+        match s.to_lowercase().as_str() {
+            "always" => Ok(AppendFsync::Always),
+            "everysec" => Ok(AppendFsync::EverySec),
+            "no" => Ok(AppendFsync::No),
+            _ => Err(format!("invalid appendfsync policy `{s}`")),
+        }
+    }
+}
+
+impl fmt::Display for AppendFsync {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            AppendFsync::Always => "always",
+            AppendFsync::EverySec => "everysec",
+            AppendFsync::No => "no",
+        })
+    }
+}
@@ -0,0 +1,143 @@
+//! Configuring a client before connecting.
+
+use crate::clients::client::connect_within;
+use crate::clients::keepalive;
+use crate::connection::BUF_SIZE;
+use crate::{Client, Connection, Result, TcpOptions};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tracing::{debug, instrument};
+
+/// Configures a `Client`, connected by `connect`.
+///
+/// Created with `Client::builder`. The connection is set up as configured before
+/// being returned: authenticated, with its database selected and its name set.
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    addr: String,
+    connect_timeout: Option<Duration>,
+    password: Option<String>,
+    database: Option<u64>,
+    name: Option<String>,
+    tcp: TcpOptions,
+    keepalive: Option<Duration>,
+    read_buffer_size: usize,
+}
+
+impl ClientBuilder {
+    /// A builder of a client of the Redis server located at `addr`, configured like
+    /// `Client::connect` until changed.
+    pub fn new(addr: impl Into<String>) -> ClientBuilder {
+        ClientBuilder {
+            addr: addr.into(),
+            connect_timeout: None,
+            password: None,
+            database: None,
+            name: None,
+            tcp: TcpOptions {
+                nodelay: false,
+                keepalive: None,
+            },
+            keepalive: None,
+            read_buffer_size: BUF_SIZE,
+        }
+    }
+
+    /// Fail to connect if the connection is not established within `timeout`.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Authenticate the connection with [Auth] and `password`.
+    ///
+    /// [Auth]: crate::cmd::Auth
+    #[must_use]
+    pub fn password(mut self, password: impl Into<String>) -> ClientBuilder {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// [Select] the database at `index` instead of the first one.
+    ///
+    /// [Select]: crate::cmd::Select
+    #[must_use]
+    pub fn database(mut self, index: u64) -> ClientBuilder {
+        self.database = Some(index);
+        self
+    }
+
+    /// Name the connection, as listed by `CLIENT LIST`.
+    #[must_use]
+    pub fn name(mut self, name: impl Into<String>) -> ClientBuilder {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Disable Nagle's algorithm, so that commands are sent without delay.
+    #[must_use]
+    pub fn nodelay(mut self, nodelay: bool) -> ClientBuilder {
+        self.tcp.nodelay = nodelay;
+        self
+    }
+
+    /// Enable TCP keepalive, for the operating system to probe the server once the
+    /// connection is idle for `time`. Unlike `keepalive`, nothing is sent to the
+    /// server itself.
+    #[must_use]
+    pub fn tcp_keepalive(mut self, time: Duration) -> ClientBuilder {
+        self.tcp.keepalive = Some(time);
+        self
+    }
+
+    /// [Ping] the server every `period` from a background task, to detect a dead
+    /// connection and keep it from being closed while idle. Once a ping failed, the
+    /// commands fail.
+    ///
+    /// No ping is sent once the client subscribed to a channel or monitors the
+    /// server.
+    ///
+    /// [Ping]: crate::cmd::Ping
+    #[must_use]
+    pub fn keepalive(mut self, period: Duration) -> ClientBuilder {
+        self.keepalive = Some(period);
+        self
+    }
+
+    /// Read the replies into a buffer of `size` bytes initially rather than 4 KiB,
+    /// see `Connection::with_capacity`.
+    #[must_use]
+    pub fn read_buffer_size(mut self, size: usize) -> ClientBuilder {
+        self.read_buffer_size = size;
+        self
+    }
+
+    /// Connect to the server and set up the connection.
+    #[instrument(skip(self), fields(addr = self.addr))]
+    pub async fn connect(&self) -> Result<Client> {
+        let stream = match self.connect_timeout {
+            Some(timeout) => connect_within(self.addr.as_str(), timeout).await?,
+            None => TcpStream::connect(self.addr.as_str()).await?,
+        };
+        self.tcp.apply(&stream)?;
+        let mut client = Client::new(Connection::with_capacity(stream, self.read_buffer_size));
+
+        // Authenticated first, as the other commands are refused until then.
+        if let Some(password) = &self.password {
+            client.auth(password).await?;
+        }
+        if let Some(index) = self.database {
+            client.select(index).await?;
+        }
+        if let Some(name) = &self.name {
+            client.set_name(name).await?;
+        }
+        if let Some(period) = self.keepalive {
+            keepalive::spawn(&client.connection, period);
+        }
+        debug!("connected");
+
+        Ok(client)
+    }
+}
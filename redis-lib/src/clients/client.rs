@@ -3,17 +3,54 @@
 //! Provides an async connect and methods for issuing the supported commands.
 
 use crate::Result;
-use crate::cmd::{Get, Ping, Protocol, Publish, Set, Subscribe, Unsubscribe};
+use crate::clients::ClientBuilder;
+use crate::clients::keepalive::SharedConnection;
+use crate::cmd::{
+    self, Auth, BgRewriteAof, BgSave, ClientCommand, CommandCommand, ConfigCommand, DebugCommand,
+    Del, Discard, Dump, Exec, Expiration, Expire, ExpireCondition, Get, GetEx, Hello, Info,
+    KillFilter, LastSave, MemoryCommand, Move, Multi, ObjectCommand, PExpire, PExpireAt,
+    PSubscribe, PUnsubscribe, PauseMode, Persist, Ping, Protocol, PubSub, Publish, Quit, Rename,
+    RenameNx, ReplicaOf, Reset, Restore, SPublish, SSubscribe, SUnsubscribe, Save, Scan, Select,
+    Set, SlowLogCommand, Subscribe, SwapDb, Unsubscribe, Unwatch, Wait, WaitAof, Watch, XAdd,
+    XRead,
+};
 use crate::{Connection, Frame};
 use bytes::Bytes;
+use std::collections::VecDeque;
 use std::io::{Error, ErrorKind};
+#[cfg(unix)]
+use std::path::Path;
+#[cfg(feature = "tls")]
+use std::sync::Arc;
 use std::time::Duration;
+#[cfg(unix)]
+use tokio::net::UnixStream;
 use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::time;
+#[cfg(feature = "tls")]
+use tokio_rustls::TlsConnector;
+#[cfg(feature = "tls")]
+use tokio_rustls::rustls::{ClientConfig, pki_types::ServerName};
+use tokio_stream::Stream;
 use tracing::{debug, instrument};
 
 /// Backed by a single `TcpStream`.
 pub struct Client {
-    connection: Connection,
+    pub(super) connection: SharedConnection,
+
+    /// How long to wait for the reply to a command, forever if `None`.
+    command_timeout: Option<Duration>,
+
+    /// Set once a reply timed out, as it may still be received in place of the
+    /// reply to the next command.
+    timed_out: bool,
+
+    /// Set once keys are watched, until `exec`, `discard`, `unwatch` or `reset`.
+    watching: bool,
+
+    /// The keys invalidated by the server while waiting for a reply, with tracking
+    /// enabled.
+    invalidated: VecDeque<String>,
 }
 
 /// A client that has entered pub/sub mode.
@@ -24,6 +61,19 @@ pub struct Client {
 pub struct Subscriber {
     client: Client,
     subscribed_channels: Vec<String>,
+    subscribed_patterns: Vec<String>,
+    subscribed_shard_channels: Vec<String>,
+    /// Messages received while waiting for the reply to a `PING`.
+    received: VecDeque<Message>,
+}
+
+/// A client that has entered monitor mode.
+///
+/// The server sends it a line for every command it processes, until the connection
+/// is closed. The `Client` type is transitioned to a `Monitor` type, as no other
+/// command may be issued.
+pub struct Monitor {
+    client: Client,
 }
 
 /// A message received on a subscribed channel.
@@ -31,15 +81,149 @@ pub struct Subscriber {
 pub struct Message {
     pub channel: String,
     pub content: Bytes,
+    /// The subscribed pattern matching `channel`, if the message was received by a
+    /// pattern subscription.
+    pub pattern: Option<String>,
+}
+
+/// An entry read from a stream.
+#[derive(Debug, Clone)]
+pub struct StreamEntry {
+    pub id: String,
+    pub fields: Vec<(Bytes, Bytes)>,
+}
+
+/// A command recorded in the slow log of the server.
+#[derive(Debug, Clone)]
+pub struct SlowLogEntry {
+    pub id: u64,
+    /// The Unix time in seconds when the command was logged.
+    pub timestamp: u64,
+    pub duration: Duration,
+    /// The command name and its arguments.
+    pub args: Vec<Bytes>,
+    /// The `ip:port` address of the client which issued the command.
+    pub addr: String,
+    /// The name of the client, empty if it has none.
+    pub name: String,
+}
+
+/// The description of a command supported by the server.
+#[derive(Debug, Clone)]
+pub struct CommandInfo {
+    pub name: String,
+    /// The number of arguments, including the command name. A negative arity `-n`
+    /// stands for at least `n` arguments.
+    pub arity: i64,
+    pub flags: Vec<String>,
+}
+
+/// The documentation of a command supported by the server.
+#[derive(Debug, Clone)]
+pub struct CommandDocs {
+    pub summary: String,
+    pub arguments: Vec<ArgumentDocs>,
+}
+
+/// The documentation of a command argument.
+#[derive(Debug, Clone)]
+pub struct ArgumentDocs {
+    pub name: String,
+    /// The type of the argument, like `key`, `integer` or `oneof`.
+    pub kind: String,
+    /// The keyword preceding the argument, like `EX`.
+    pub token: Option<String>,
+    pub optional: bool,
+    /// Set if the argument may be repeated.
+    pub multiple: bool,
+    /// The arguments nested in a `oneof` or `block` argument.
+    pub arguments: Vec<ArgumentDocs>,
 }
 
 impl Client {
     /// Establish a connection with the Redis server located at `addr`.
     pub async fn connect<T: ToSocketAddrs>(addr: T) -> crate::Result<Client> {
         let stream = TcpStream::connect(addr).await?;
-        let connection = Connection::new(stream);
 
-        Ok(Client { connection })
+        Ok(Client::new(Connection::new(stream)))
+    }
+
+    /// Like `connect`, failing with a `TimedOut` error if the connection is not
+    /// established within `timeout`, such as when the host is unreachable.
+    pub async fn connect_timeout<T: ToSocketAddrs>(
+        addr: T,
+        timeout: Duration,
+    ) -> crate::Result<Client> {
+        let stream = connect_within(addr, timeout).await?;
+
+        Ok(Client::new(Connection::new(stream)))
+    }
+
+    /// Establish a TLS connection with the Redis server located at `addr`, whose
+    /// certificate must be valid for `domain` and trusted by `config`, see
+    /// `tls::client_config`.
+    #[cfg(feature = "tls")]
+    pub async fn connect_tls<T: ToSocketAddrs>(
+        addr: T,
+        domain: &str,
+        config: Arc<ClientConfig>,
+    ) -> crate::Result<Client> {
+        let domain = ServerName::try_from(domain.to_string())?;
+        let stream = TcpStream::connect(addr).await?;
+        let stream = TlsConnector::from(config).connect(domain, stream).await?;
+
+        Ok(Client::new(Connection::new_tls(stream)))
+    }
+
+    /// Establish a connection with the Redis server listening on the Unix domain
+    /// socket at `path`.
+    #[cfg(unix)]
+    pub async fn connect_unix<P: AsRef<Path>>(path: P) -> crate::Result<Client> {
+        let stream = UnixStream::connect(path).await?;
+
+        Ok(Client::new(Connection::new_unix(stream)))
+    }
+
+    pub(super) fn new(connection: Connection) -> Client {
+        Client {
+            connection: SharedConnection::new(connection),
+            command_timeout: None,
+            timed_out: false,
+            watching: false,
+            invalidated: VecDeque::new(),
+        }
+    }
+
+    /// Fail commands with a `TimedOut` error if their reply is not received within
+    /// `timeout`, instead of waiting forever for a hung server. Each reply to the
+    /// commands of a pipeline is waited for within `timeout` as well.
+    ///
+    /// The connection is unusable once a command timed out: the following commands
+    /// fail, as the late reply would be mistaken for theirs.
+    #[must_use]
+    pub fn with_command_timeout(mut self, timeout: Duration) -> Self {
+        self.command_timeout = Some(timeout);
+        self
+    }
+
+    /// Start configuring a connection to the Redis server located at `addr`.
+    pub fn builder(addr: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(addr)
+    }
+
+    /// Returns `true` once the connection is unusable, so that every following
+    /// command fails: it was closed, reading or writing failed, a keepalive ping
+    /// failed or a command timed out.
+    pub fn is_broken(&self) -> bool {
+        self.timed_out || self.connection.is_broken()
+    }
+
+    /// Returns `true` if the connection is usable and in no particular state, so
+    /// that it may be handed to another user: no reply is pending, as after a
+    /// command whose future was dropped, no transaction is open and no key is
+    /// watched.
+    pub fn is_idle(&self) -> bool {
+        !self.timed_out && !self.watching && self.connection.is_idle()
     }
 
     /// [Ping] to the server.
@@ -58,139 +242,1615 @@ impl Client {
         }
     }
 
-    /// Get the value of key.
+    /// Ask the server to close the connection, consuming the client.
+    #[instrument(skip(self))]
+    pub async fn quit(mut self) -> Result<()> {
+        let frame = Quit::new().into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Make the server stop processing the commands of the connection for
+    /// `duration`.
+    #[instrument(skip(self))]
+    pub async fn debug_sleep(&mut self, duration: Duration) -> Result<()> {
+        let frame = DebugCommand::Sleep { duration }.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Disable or enable the purge of expired keys by the server in the background.
+    /// Expired keys are still removed when read.
+    #[instrument(skip(self))]
+    pub async fn debug_set_active_expire(&mut self, enabled: bool) -> Result<()> {
+        let frame = DebugCommand::SetActiveExpire { enabled }.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Describe how the value at `key` is stored by the server.
     ///
-    /// # return
+    /// # Return
     ///
-    /// If the key does not exist the special value `None` is returned.
+    /// Returns `field:value` pairs separated by spaces, including the `encoding`
+    /// of the value.
     #[instrument(skip(self))]
-    pub async fn get(&mut self, key: &str) -> Result<Option<Bytes>> {
-        let frame = Get::new(key).into_frame();
+    pub async fn debug_object(&mut self, key: &str) -> Result<String> {
+        let frame = DebugCommand::Object {
+            key: key.to_string(),
+        }
+        .into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(details) => Ok(String::from_utf8(details.to_vec())?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get the `count` newest entries of the slow log, or the 10 newest without a
+    /// count. A negative count gets all the entries.
+    #[instrument(skip(self))]
+    pub async fn slowlog_get(&mut self, count: Option<i64>) -> Result<Vec<SlowLogEntry>> {
+        let frame = SlowLogCommand::Get { count }.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // The response is in the form of:
+        // [[id, timestamp, duration, [arg, ...], addr, name], ...]
+        let entries = match self.read_response().await? {
+            Frame::Array(entries) => entries,
+            other => return Err(other.to_error()),
+        };
+
+        let mut result = vec![];
+        for entry in entries {
+            let Frame::Array(entry) = entry else {
+                return Err(entry.to_error());
+            };
+            let [
+                Frame::Integer(id),
+                Frame::Integer(timestamp),
+                Frame::Integer(micros),
+                Frame::Array(args),
+                addr,
+                name,
+            ] = entry.as_slice()
+            else {
+                return Err(Frame::Array(entry).to_error());
+            };
+
+            let args = args
+                .iter()
+                .map(|arg| match arg {
+                    Frame::Bulk(arg) => Ok(arg.clone()),
+                    other => Err(other.to_error()),
+                })
+                .collect::<Result<_>>()?;
+
+            result.push(SlowLogEntry {
+                id: u64::try_from(*id)?,
+                timestamp: u64::try_from(*timestamp)?,
+                duration: Duration::from_micros(u64::try_from(*micros)?),
+                args,
+                addr: addr.to_string(),
+                name: name.to_string(),
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// Get the number of entries of the slow log.
+    #[instrument(skip(self))]
+    pub async fn slowlog_len(&mut self) -> Result<u64> {
+        let frame = SlowLogCommand::Len.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(len) => Ok(u64::try_from(len)?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Remove all the entries of the slow log.
+    #[instrument(skip(self))]
+    pub async fn slowlog_reset(&mut self) -> Result<()> {
+        let frame = SlowLogCommand::Reset.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Wait for the writes of the connection to be acknowledged by at least
+    /// `numreplicas` replicas, or for `timeout` to elapse. A zero `timeout` waits
+    /// forever.
+    ///
+    /// # Return
+    ///
+    /// Returns the number of replicas which acknowledged the writes.
+    #[instrument(skip(self))]
+    pub async fn wait(&mut self, numreplicas: u64, timeout: Duration) -> Result<u64> {
+        let frame = Wait::new(numreplicas, timeout).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(acknowledged) => Ok(u64::try_from(acknowledged)?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Wait for the writes of the connection to be flushed to the append-only file
+    /// of the server if `numlocal` is not zero, and of at least `numreplicas`
+    /// replicas, or for `timeout` to elapse. A zero `timeout` waits forever.
+    ///
+    /// # Return
+    ///
+    /// Returns the number of local and replica append-only files which flushed the
+    /// writes.
+    #[instrument(skip(self))]
+    pub async fn waitaof(
+        &mut self,
+        numlocal: u64,
+        numreplicas: u64,
+        timeout: Duration,
+    ) -> Result<(u64, u64)> {
+        let frame = WaitAof::new(numlocal, numreplicas, timeout).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(counts) => match &counts[..] {
+                [Frame::Integer(local), Frame::Integer(replicas)] => {
+                    Ok((u64::try_from(*local)?, u64::try_from(*replicas)?))
+                }
+                _ => Err(Frame::Array(counts).to_error()),
+            },
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Make the server a replica of the primary at `host` and `port`.
+    ///
+    /// The server replaces its databases with those of the primary, then keeps them
+    /// in sync in the background.
+    #[instrument(skip(self))]
+    pub async fn replicaof(&mut self, host: &str, port: u16) -> Result<()> {
+        let frame = ReplicaOf::new(host, port).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Make the server a primary again, keeping the keys replicated so far.
+    #[instrument(skip(self))]
+    pub async fn replicaof_no_one(&mut self) -> Result<()> {
+        let frame = ReplicaOf::no_one().into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get an estimate of the number of bytes used to store `key` and its value.
+    ///
+    /// # Return
+    ///
+    /// Returns `None` if there is no such key.
+    #[instrument(skip(self))]
+    pub async fn memory_usage(&mut self, key: &str) -> Result<Option<u64>> {
+        let frame = MemoryCommand::Usage {
+            key: key.to_string(),
+            samples: None,
+        }
+        .into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(usage) => Ok(Some(u64::try_from(usage)?)),
+            Frame::Null => Ok(None),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get the number of references to the value at `key`. Small integers are
+    /// shared, and report a very large count.
+    ///
+    /// # Return
+    ///
+    /// Returns `None` if there is no such key.
+    #[instrument(skip(self))]
+    pub async fn object_refcount(&mut self, key: &str) -> Result<Option<u64>> {
+        let frame = ObjectCommand::RefCount {
+            key: key.to_string(),
+        }
+        .into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(refcount) => Ok(Some(u64::try_from(refcount)?)),
+            Frame::Null => Ok(None),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get the access frequency counter of `key`. Fails unless the server uses an
+    /// LFU `maxmemory-policy`.
+    ///
+    /// # Return
+    ///
+    /// Returns `None` if there is no such key.
+    #[instrument(skip(self))]
+    pub async fn object_freq(&mut self, key: &str) -> Result<Option<u64>> {
+        let frame = ObjectCommand::Freq {
+            key: key.to_string(),
+        }
+        .into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(freq) => Ok(Some(u64::try_from(freq)?)),
+            Frame::Null => Ok(None),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Write a snapshot of all the databases to disk.
+    #[instrument(skip(self))]
+    pub async fn save(&mut self) -> Result<()> {
+        let frame = Save::new().into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Write a snapshot of all the databases to disk, in the background.
+    ///
+    /// Returns once the server started writing the snapshot.
+    #[instrument(skip(self))]
+    pub async fn bgsave(&mut self) -> Result<()> {
+        let frame = BgSave::new().into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "Background saving started" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Rewrite the append-only file of the server, in the background.
+    ///
+    /// Returns once the server started rewriting the file.
+    #[instrument(skip(self))]
+    pub async fn bgrewriteaof(&mut self) -> Result<()> {
+        let frame = BgRewriteAof::new().into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "Background append only file rewriting started" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get the Unix time, in seconds, of the last successful save to disk, or of
+    /// the start of the server if there was none.
+    #[instrument(skip(self))]
+    pub async fn lastsave(&mut self) -> Result<u64> {
+        let frame = LastSave::new().into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(timestamp) => Ok(u64::try_from(timestamp)?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Return the connection to the state it had when it was opened.
+    #[instrument(skip(self))]
+    pub async fn reset(&mut self) -> Result<()> {
+        let frame = Reset::new().into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // Ends any transaction.
+        let response = self.read_response().await;
+        self.connection.release();
+        self.watching = false;
+        match response? {
+            Frame::Simple(s) if s == "RESET" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Select the logical database at `index` for the following commands.
+    #[instrument(skip(self))]
+    pub async fn select(&mut self, index: u64) -> Result<()> {
+        let frame = Select::new(index).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Swap the contents of the logical databases at `index1` and `index2`.
+    #[instrument(skip(self))]
+    pub async fn swapdb(&mut self, index1: u64, index2: u64) -> Result<()> {
+        let frame = SwapDb::new(index1, index2).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Move `key` from the selected logical database to the one at `db`.
+    ///
+    /// # Return
+    ///
+    /// Returns `false` if the key does not exist, or if the destination database
+    /// already holds it.
+    #[instrument(skip(self))]
+    pub async fn move_key(&mut self, key: &str, db: u64) -> Result<bool> {
+        let frame = Move::new(key, db).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(moved) => Ok(moved == 1),
+            Frame::Boolean(moved) => Ok(moved),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Rename `key` to `newkey`, along with its expiration, replacing `newkey` if it
+    /// exists.
+    ///
+    /// # Return
+    ///
+    /// Fails if `key` does not exist.
+    #[instrument(skip(self))]
+    pub async fn rename(&mut self, key: &str, newkey: &str) -> Result<()> {
+        let frame = Rename::new(key, newkey).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Rename `key` to `newkey`, along with its expiration, unless `newkey` exists.
+    ///
+    /// # Return
+    ///
+    /// Returns `true` if the key was renamed. Fails if `key` does not exist.
+    #[instrument(skip(self))]
+    pub async fn renamenx(&mut self, key: &str, newkey: &str) -> Result<bool> {
+        let frame = RenameNx::new(key, newkey).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(renamed) => Ok(renamed == 1),
+            Frame::Boolean(renamed) => Ok(renamed),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Set `key` to expire in `seconds`, deleting it at once if `seconds` is not
+    /// positive, only if its current timeout meets `condition` when set.
+    ///
+    /// # Return
+    ///
+    /// Returns `true` if the timeout was set, or `false` if `key` does not exist or
+    /// the condition is not met.
+    #[instrument(skip(self))]
+    pub async fn expire(
+        &mut self,
+        key: &str,
+        seconds: i64,
+        condition: Option<ExpireCondition>,
+    ) -> Result<bool> {
+        let frame = Expire::new(key, seconds, condition).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(set) => Ok(set == 1),
+            Frame::Boolean(set) => Ok(set),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Like `expire`, with a timeout in milliseconds.
+    #[instrument(skip(self))]
+    pub async fn pexpire(
+        &mut self,
+        key: &str,
+        milliseconds: i64,
+        condition: Option<ExpireCondition>,
+    ) -> Result<bool> {
+        let frame = PExpire::new(key, milliseconds, condition).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(set) => Ok(set == 1),
+            Frame::Boolean(set) => Ok(set),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Like `expire`, with the Unix time at which `key` expires, in milliseconds.
+    #[instrument(skip(self))]
+    pub async fn pexpireat(
+        &mut self,
+        key: &str,
+        timestamp: i64,
+        condition: Option<ExpireCondition>,
+    ) -> Result<bool> {
+        let frame = PExpireAt::new(key, timestamp, condition).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(set) => Ok(set == 1),
+            Frame::Boolean(set) => Ok(set),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Remove the timeout of `key`, so that it is kept until deleted.
+    ///
+    /// # Return
+    ///
+    /// Returns `true` if the timeout was removed, or `false` if `key` does not exist
+    /// or has no timeout.
+    #[instrument(skip(self))]
+    pub async fn persist(&mut self, key: &str) -> Result<bool> {
+        let frame = Persist::new(key).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(removed) => Ok(removed == 1),
+            Frame::Boolean(removed) => Ok(removed),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Delete `keys`.
+    ///
+    /// # Return
+    ///
+    /// Returns the number of keys deleted, those which do not exist being ignored.
+    #[instrument(skip(self))]
+    pub async fn del(&mut self, keys: &[&str]) -> Result<u64> {
+        let frame = Del::new(keys.iter().map(ToString::to_string).collect()).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(deleted) => Ok(u64::try_from(deleted)?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Iterate the keys of the selected database, from `cursor`, 0 to start a new
+    /// iteration. Only the keys matching `pattern` are returned, if set, among about
+    /// `count` keys, or 10 without a count.
+    ///
+    /// # Return
+    ///
+    /// Returns the cursor to continue from, 0 once the iteration is complete, and
+    /// the keys. Every key present for the whole iteration is returned.
+    #[instrument(skip(self))]
+    pub async fn scan(
+        &mut self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: Option<u64>,
+    ) -> Result<(u64, Vec<String>)> {
+        let frame = Scan::new(cursor, pattern, count).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(frames) => match <[Frame; 2]>::try_from(frames) {
+                Ok([cursor, Frame::Array(keys)]) => {
+                    let cursor = cursor.to_string().parse()?;
+                    let keys = keys.iter().map(ToString::to_string).collect();
+                    Ok((cursor, keys))
+                }
+                Ok(frames) => Err(Frame::Array(frames.into()).to_error()),
+                Err(frames) => Err(Frame::Array(frames).to_error()),
+            },
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Serialize the value at `key`, to be recreated with `restore`.
+    ///
+    /// # Return
+    ///
+    /// Returns `None` if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn dump(&mut self, key: &str) -> Result<Option<Bytes>> {
+        let frame = Dump::new(key).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(payload) => Ok(Some(payload)),
+            Frame::Null => Ok(None),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Create `key` with the value serialized by `dump` in `payload`, expiring
+    /// after `ttl` if set.
+    ///
+    /// Fails if `key` already exists, unless `replace` is set.
+    #[instrument(skip(self, payload))]
+    pub async fn restore(
+        &mut self,
+        key: &str,
+        ttl: Option<Duration>,
+        payload: Bytes,
+        replace: bool,
+    ) -> Result<()> {
+        let frame = Restore::new(key, ttl, payload, replace).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Describe the commands supported by the server.
+    #[instrument(skip(self))]
+    pub async fn command_info(&mut self) -> Result<Vec<CommandInfo>> {
+        let frame = CommandCommand::List.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // The response is in the form of:
+        // [[name, arity, {flag, ...}], ...]
+        let commands = match self.read_response().await? {
+            Frame::Array(commands) => commands,
+            other => return Err(other.to_error()),
+        };
+
+        let mut result = vec![];
+        for command in commands {
+            let Frame::Array(command) = command else {
+                return Err(command.to_error());
+            };
+            let [name, arity, flags] = command.as_slice() else {
+                return Err(Frame::Array(command).to_error());
+            };
+            let flags = set_members(flags.clone())?;
+
+            let arity = match arity {
+                Frame::Integer(arity) => *arity,
+                other => return Err(other.to_error()),
+            };
+
+            result.push(CommandInfo {
+                name: name.to_string(),
+                arity,
+                flags: flags.iter().map(ToString::to_string).collect(),
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// Get the keys of an invocation of a command, given with its name as
+    /// `command`, without running it.
+    #[instrument(skip(self))]
+    pub async fn command_getkeys(&mut self, command: &[&str]) -> Result<Vec<String>> {
+        let frame = CommandCommand::GetKeys {
+            command: command.iter().map(ToString::to_string).collect(),
+        }
+        .into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(keys) => Ok(keys.iter().map(ToString::to_string).collect()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get the number of commands supported by the server.
+    #[instrument(skip(self))]
+    pub async fn command_count(&mut self) -> Result<u64> {
+        let frame = CommandCommand::Count.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(count) => Ok(u64::try_from(count)?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get the documentation of the given commands, or of every command if
+    /// `commands` is empty. Unknown commands are left out.
+    #[instrument(skip(self))]
+    pub async fn command_docs(&mut self, commands: &[&str]) -> Result<Vec<(String, CommandDocs)>> {
+        let frame = CommandCommand::Docs {
+            commands: commands.iter().map(ToString::to_string).collect(),
+        }
+        .into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // The response is in the form of:
+        // {name: {summary: ..., arguments: [{name: ..., type: ..., ...}, ...]}, ...}
+        let mut result = vec![];
+        for (name, docs) in map_entries(self.read_response().await?)? {
+            let mut summary = String::new();
+            let mut arguments = vec![];
+            for (key, value) in map_entries(docs)? {
+                match (key.to_string().as_str(), value) {
+                    ("summary", value) => summary = value.to_string(),
+                    ("arguments", Frame::Array(frames)) => {
+                        arguments = frames
+                            .into_iter()
+                            .map(parse_argument_docs)
+                            .collect::<Result<_>>()?;
+                    }
+                    _ => {}
+                }
+            }
+
+            result.push((name.to_string(), CommandDocs { summary, arguments }));
+        }
+
+        Ok(result)
+    }
+
+    /// Get the parameters of the server whose name matches the glob-style `pattern`.
+    ///
+    /// # Return
+    ///
+    /// Returns the name and value of each matching parameter.
+    #[instrument(skip(self))]
+    pub async fn config_get(&mut self, pattern: &str) -> Result<Vec<(String, String)>> {
+        let frame = ConfigCommand::Get {
+            pattern: pattern.to_string(),
+        }
+        .into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let entries = map_entries(self.read_response().await?)?;
+
+        Ok(entries
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect())
+    }
+
+    /// Set the parameter `name` of the server to `value`.
+    #[instrument(skip(self))]
+    pub async fn config_set(&mut self, name: &str, value: &str) -> Result<()> {
+        let frame = ConfigCommand::Set {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+        .into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get information and statistics about the server, optionally only those of
+    /// `section`.
+    ///
+    /// # Return
+    ///
+    /// Returns sections in the form of `# Section` headers followed by
+    /// `field:value` lines.
+    #[instrument(skip(self))]
+    pub async fn info(&mut self, section: Option<&str>) -> Result<String> {
+        let frame = Info::new(section.map(ToString::to_string)).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(info) | Frame::Verbatim { data: info, .. } => {
+                Ok(String::from_utf8(info.to_vec())?)
+            }
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Authenticate the connection with `password`.
+    #[instrument(skip(self, password))]
+    pub async fn auth(&mut self, password: &str) -> Result<()> {
+        let frame = Auth::new(password).into_frame();
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Switch the connection to the RESP version `protover`, 2 or 3. Without a
+    /// version, the connection keeps its current one.
+    ///
+    /// # Return
+    ///
+    /// Returns the properties reported by the server, such as `server`, `version`,
+    /// `proto` and `id`.
+    #[instrument(skip(self))]
+    pub async fn hello(&mut self, protover: Option<u64>) -> Result<Vec<(String, Frame)>> {
+        let frame = Hello::new(protover, None).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let entries = map_entries(self.read_response().await?)?;
+
+        Ok(entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect())
+    }
+
+    /// Assign `name` to the connection. An empty name removes the current one.
+    #[instrument(skip(self))]
+    pub async fn set_name(&mut self, name: &str) -> Result<()> {
+        let frame = ClientCommand::SetName {
+            name: name.to_string(),
+        }
+        .into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get the name of the connection, or `None` if it has no name.
+    #[instrument(skip(self))]
+    pub async fn get_name(&mut self) -> Result<Option<String>> {
+        let frame = ClientCommand::GetName.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(name) if name.is_empty() => Ok(None),
+            Frame::Bulk(name) => Ok(Some(String::from_utf8(name.to_vec())?)),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get the unique id the server assigned to the connection.
+    #[instrument(skip(self))]
+    pub async fn client_id(&mut self) -> Result<u64> {
+        let frame = ClientCommand::Id.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(id) => Ok(u64::try_from(id)?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Describe every connection to the server, one line each, in the form of
+    /// `id=<id> addr=<addr> name=<name> age=<seconds> cmd=<last command>`.
+    #[instrument(skip(self))]
+    pub async fn client_list(&mut self) -> Result<String> {
+        let frame = ClientCommand::List.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(list) => Ok(String::from_utf8(list.to_vec())?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Close the connections matching `filter`, other than this one.
+    ///
+    /// # Return
+    ///
+    /// Returns the number of connections closed.
+    #[instrument(skip(self))]
+    pub async fn client_kill(&mut self, filter: KillFilter) -> Result<u64> {
+        let frame = ClientCommand::Kill { filter }.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(killed) => Ok(u64::try_from(killed)?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Suspend the commands selected by `mode`, from all the connections, for `timeout`.
+    #[instrument(skip(self))]
+    pub async fn client_pause(&mut self, timeout: Duration, mode: PauseMode) -> Result<()> {
+        let frame = ClientCommand::Pause { timeout, mode }.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Enable or disable client-side caching. Once enabled, the server sends an
+    /// invalidation message when a key read by the connection is modified, received
+    /// with `next_invalidation`.
+    ///
+    /// The connection must use RESP3, see `hello`.
+    #[instrument(skip(self))]
+    pub async fn client_tracking(&mut self, enabled: bool) -> Result<()> {
+        let frame = ClientCommand::Tracking { enabled }.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Receive the next key invalidated by the server, waiting if necessary, with
+    /// tracking enabled. `None` indicates the connection was closed.
+    ///
+    /// The keys invalidated while waiting for the reply to another command are
+    /// received first.
+    pub async fn next_invalidation(&mut self) -> Result<Option<String>> {
+        while self.invalidated.is_empty() {
+            match self.connection.read_frame().await? {
+                Some(frame) => match invalidated_keys(&frame) {
+                    Some(keys) => self.invalidated.extend(keys),
+                    None => return Err(frame.to_error()),
+                },
+                None => return Ok(None),
+            }
+        }
+
+        Ok(self.invalidated.pop_front())
+    }
+
+    /// Get the value of key.
+    ///
+    /// # return
+    ///
+    /// If the key does not exist the special value `None` is returned.
+    #[instrument(skip(self))]
+    pub async fn get(&mut self, key: &str) -> Result<Option<Bytes>> {
+        let frame = Get::new(key).into_frame();
+        debug!(request = ?frame);
+
+        // Write the frame to the socket. This writes the full frame to the
+        // socket, waiting if necessary.
+        self.connection.write_frame(&frame).await?;
+
+        // Wait for the response from the server
+        //
+        // Both `Simple` and `Bulk` frames are accepted. `Null` represents the
+        // key not being present and `None` is returned.
+        match self.read_response().await? {
+            Frame::Simple(value) => Ok(Some(value.into())),
+            Frame::Bulk(value) => Ok(Some(value)),
+            Frame::Null => Ok(None),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get the value of key, setting its expiration as requested by `expiration` at
+    /// the same time.
+    ///
+    /// # return
+    ///
+    /// If the key does not exist the special value `None` is returned.
+    #[instrument(skip(self))]
+    pub async fn getex(
+        &mut self,
+        key: &str,
+        expiration: Option<Expiration>,
+    ) -> Result<Option<Bytes>> {
+        let frame = GetEx::new(key, expiration).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(value) => Ok(Some(value.into())),
+            Frame::Bulk(value) => Ok(Some(value)),
+            Frame::Null => Ok(None),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get the values of `keys`, in order, pipelining a `GET` per key instead of
+    /// issuing an `MGET`.
+    ///
+    /// # return
+    ///
+    /// `None` is returned for the keys which do not exist. Fails if any `GET`
+    /// fails.
+    #[instrument(skip(self))]
+    pub async fn get_multi(&mut self, keys: &[&str]) -> Result<Vec<Option<Bytes>>> {
+        let mut pipeline = self.pipeline();
+        for key in keys {
+            pipeline.get(key);
+        }
+
+        let mut values = Vec::with_capacity(keys.len());
+        for reply in pipeline.execute().await? {
+            values.push(match reply? {
+                Frame::Simple(value) => Some(value.into()),
+                Frame::Bulk(value) => Some(value),
+                Frame::Null => None,
+                other => return Err(other.to_error()),
+            });
+        }
+
+        Ok(values)
+    }
+
+    /// Set `key` to hold the given `value`.
+    ///
+    /// If key already holds a value, it is overwritten. Any previous time to
+    /// live associated with the key is discarded on successful SET operation.
+    #[instrument(skip(self))]
+    pub async fn set(&mut self, key: &str, value: Bytes) -> Result<()> {
+        self.set_cmd(Set::new(key, value, None)).await
+    }
+
+    /// Set `key` to hold the given `value`. The value expires after `expiration`
+    ///
+    /// If key already holds a value, it is overwritten. Any previous time to
+    /// live associated with the key is discarded on a successful SET operation.
+    ///
+    /// # Note
+    ///
+    /// This function assumes the client and server stay relatively synchronized in time.
+    /// The real world tends to not be so favorable.
+    #[instrument(skip(self))]
+    pub async fn set_expires(
+        &mut self,
+        key: &str,
+        value: Bytes,
+        expiration: Duration,
+    ) -> Result<()> {
+        self.set_cmd(Set::new(key, value, Some(expiration))).await
+    }
+
+    /// The core `SET` logic.
+    async fn set_cmd(&mut self, cmd: Set) -> Result<()> {
+        let frame = cmd.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // Wait for the response from the server.
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// publish `message` to the given `channel`.
+    ///
+    /// # Return
+    ///
+    /// Returns the number of subscribers currently listening on the channel.
+    #[instrument(skip(self))]
+    pub async fn publish(&mut self, channel: &str, message: Bytes) -> Result<u64> {
+        let frame = Publish::new(channel, message).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // Read the response from server.
+        match self.read_response().await? {
+            Frame::Integer(num) => Ok(u64::try_from(num)?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// publish `message` to the given shard `channel`.
+    ///
+    /// # Return
+    ///
+    /// Returns the number of subscribers currently listening on the shard channel.
+    #[instrument(skip(self))]
+    pub async fn spublish(&mut self, channel: &str, message: Bytes) -> Result<u64> {
+        let frame = SPublish::new(channel, message).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(num) => Ok(u64::try_from(num)?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// List the channels with at least one subscriber, optionally only those matching
+    /// the glob `pattern`.
+    #[instrument(skip(self))]
+    pub async fn pubsub_channels(&mut self, pattern: Option<&str>) -> Result<Vec<String>> {
+        let frame = PubSub::Channels {
+            pattern: pattern.map(ToString::to_string),
+        }
+        .into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Array(channels) => Ok(channels.iter().map(ToString::to_string).collect()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Report the number of subscribers of each of the given channels.
+    ///
+    /// # Return
+    ///
+    /// Returns `(channel, count)` pairs in the order of `channels`.
+    #[instrument(skip(self))]
+    pub async fn pubsub_numsub(&mut self, channels: &[&str]) -> Result<Vec<(String, u64)>> {
+        let frame = PubSub::NumSub {
+            channels: channels.iter().map(ToString::to_string).collect(),
+        }
+        .into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let frames = match self.read_response().await? {
+            Frame::Array(frames) => frames,
+            other => return Err(other.to_error()),
+        };
+
+        let mut counts = vec![];
+        for pair in frames.chunks(2) {
+            match pair {
+                [channel, Frame::Integer(count)] => {
+                    counts.push((channel.to_string(), u64::try_from(*count)?));
+                }
+                _ => return Err(Frame::Array(pair.to_vec()).to_error()),
+            }
+        }
+
+        Ok(counts)
+    }
+
+    /// Report the number of unique patterns subscribed to by all clients.
+    #[instrument(skip(self))]
+    pub async fn pubsub_numpat(&mut self) -> Result<u64> {
+        let frame = PubSub::NumPat.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Integer(num) => Ok(u64::try_from(num)?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Append an entry to the stream stored at `key`.
+    ///
+    /// `id` may be `*` to let the server generate the ID.
+    ///
+    /// # Return
+    ///
+    /// Returns the ID of the added entry.
+    #[instrument(skip(self))]
+    pub async fn xadd(
+        &mut self,
+        key: &str,
+        id: &str,
+        fields: Vec<(Bytes, Bytes)>,
+    ) -> Result<String> {
+        let frame = XAdd::new(key, id, fields).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(id) => Ok(String::from_utf8(id.to_vec())?),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Read the entries with an ID greater than the given one from each `(key, id)` stream.
+    ///
+    /// The ID `$` only returns entries added after the call. At most `count` entries
+    /// are returned per stream.
+    ///
+    /// # Return
+    ///
+    /// Returns the entries of each stream which has any.
+    #[instrument(skip(self))]
+    pub async fn xread(
+        &mut self,
+        count: Option<u64>,
+        streams: &[(&str, &str)],
+    ) -> Result<Vec<(String, Vec<StreamEntry>)>> {
+        let streams = streams
+            .iter()
+            .map(|(key, id)| ((*key).to_string(), (*id).to_string()))
+            .collect();
+        let frame = XRead::new(count, streams).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // The response is in the form of:
+        // [[key, [[id, [field, value, ...]], ...]], ...]
+        let streams = match self.read_response().await? {
+            Frame::Null => return Ok(vec![]),
+            Frame::Array(streams) => streams,
+            other => return Err(other.to_error()),
+        };
+
+        let mut result = vec![];
+        for stream in streams {
+            let Frame::Array(stream) = stream else {
+                return Err(stream.to_error());
+            };
+            let [key, Frame::Array(entries)] = stream.as_slice() else {
+                return Err(Frame::Array(stream).to_error());
+            };
+
+            let mut stream_entries = vec![];
+            for entry in entries {
+                let Frame::Array(entry) = entry else {
+                    return Err(entry.to_error());
+                };
+                let [id, Frame::Array(values)] = entry.as_slice() else {
+                    return Err(Frame::Array(entry.clone()).to_error());
+                };
+
+                let mut fields = vec![];
+                for pair in values.chunks(2) {
+                    match pair {
+                        [Frame::Bulk(field), Frame::Bulk(value)] => {
+                            fields.push((field.clone(), value.clone()));
+                        }
+                        _ => return Err(Frame::Array(values.clone()).to_error()),
+                    }
+                }
+
+                stream_entries.push(StreamEntry {
+                    id: id.to_string(),
+                    fields,
+                });
+            }
+
+            result.push((key.to_string(), stream_entries));
+        }
+
+        Ok(result)
+    }
+
+    /// Start a transaction.
+    ///
+    /// The commands sent with `queue` are executed by `exec`. No keepalive ping is
+    /// sent until then, as it would be queued as well.
+    #[instrument(skip(self))]
+    pub async fn multi(&mut self) -> Result<()> {
+        let frame = Multi::new().into_frame();
+        debug!(request = ?frame);
+
+        // Held from the request on, so that the connection is not released once
+        // the reply is read.
+        self.connection.hold();
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => Ok(()),
+            other => {
+                self.connection.release();
+                Err(other.to_error())
+            }
+        }
+    }
+
+    /// Queue `cmd` in the transaction started by `multi`.
+    #[instrument(skip(self))]
+    pub async fn queue<T: Protocol + std::fmt::Debug>(&mut self, cmd: T) -> Result<()> {
+        let frame = cmd.into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "QUEUED" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Execute the commands queued since `multi`.
+    ///
+    /// # Return
+    ///
+    /// Returns the reply of each queued command, in order. The reply of a failed
+    /// command is a `Frame::Error`. `None` is returned if the transaction was
+    /// aborted because a watched key was modified.
+    #[instrument(skip(self))]
+    pub async fn exec(&mut self) -> Result<Option<Vec<Frame>>> {
+        let frame = Exec::new().into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // The transaction ends, whether it was executed or not, and the keys are
+        // no longer watched.
+        let response = self.read_response().await;
+        self.connection.release();
+        self.watching = false;
+        match response? {
+            Frame::Array(replies) => Ok(Some(replies)),
+            Frame::Null => Ok(None),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Watch `keys`, so that the next `exec` is aborted if any of them is modified.
+    #[instrument(skip(self))]
+    pub async fn watch(&mut self, keys: &[&str]) -> Result<()> {
+        let frame = Watch::new(keys.iter().map(ToString::to_string).collect()).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(s) if s == "OK" => {
+                self.watching = true;
+                Ok(())
+            }
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Forget about all the watched keys.
+    #[instrument(skip(self))]
+    pub async fn unwatch(&mut self) -> Result<()> {
+        let frame = Unwatch::new().into_frame();
         debug!(request = ?frame);
 
-        // Write the frame to the socket. This writes the full frame to the
-        // socket, waiting if necessary.
         self.connection.write_frame(&frame).await?;
 
-        // Wait for the response from the server
-        //
-        // Both `Simple` and `Bulk` frames are accepted. `Null` represents the
-        // key not being present and `None` is returned.
         match self.read_response().await? {
-            Frame::Simple(value) => Ok(Some(value.into())),
-            Frame::Bulk(value) => Ok(Some(value)),
-            Frame::Null => Ok(None),
+            Frame::Simple(s) if s == "OK" => {
+                self.watching = false;
+                Ok(())
+            }
             other => Err(other.to_error()),
         }
     }
 
-    /// Set `key` to hold the given `value`.
-    ///
-    /// If key already holds a value, it is overwritten. Any previous time to
-    /// live associated with the key is discarded on successful SET operation.
-    #[instrument(skip(self))]
-    pub async fn set(&mut self, key: &str, value: Bytes) -> Result<()> {
-        self.set_cmd(Set::new(key, value, None)).await
-    }
-
-    /// Set `key` to hold the given `value`. The value expires after `expiration`
-    ///
-    /// If key already holds a value, it is overwritten. Any previous time to
-    /// live associated with the key is discarded on a successful SET operation.
-    ///
-    /// # Note
-    ///
-    /// This function assumes the client and server stay relatively synchronized in time.
-    /// The real world tends to not be so favorable.
+    /// Abort the transaction started by `multi`, dropping the queued commands.
     #[instrument(skip(self))]
-    pub async fn set_expires(
-        &mut self,
-        key: &str,
-        value: Bytes,
-        expiration: Duration,
-    ) -> Result<()> {
-        self.set_cmd(Set::new(key, value, Some(expiration))).await
-    }
-
-    /// The core `SET` logic.
-    async fn set_cmd(&mut self, cmd: Set) -> Result<()> {
-        let frame = cmd.into_frame();
+    pub async fn discard(&mut self) -> Result<()> {
+        let frame = Discard::new().into_frame();
         debug!(request = ?frame);
 
         self.connection.write_frame(&frame).await?;
 
-        // Wait for the response from the server.
-        match self.read_response().await? {
+        let response = self.read_response().await;
+        self.connection.release();
+        self.watching = false;
+        match response? {
             Frame::Simple(s) if s == "OK" => Ok(()),
             other => Err(other.to_error()),
         }
     }
 
-    /// publish `message` to the given `channel`.
+    /// Subscribes the client to the given channels.
     ///
-    /// # Return
+    /// Once a client issues a subscribe command, it may no longer issue any
+    /// non-pub/sub commands. The function consumes `self` and returns a `Subscriber`.
+    #[instrument(skip(self))]
+    pub async fn subscribe(mut self, channels: Vec<String>) -> Result<Subscriber> {
+        self.do_subscribe(&channels).await?;
+
+        Ok(Subscriber {
+            client: self,
+            subscribed_channels: deduplicated(channels),
+            subscribed_patterns: vec![],
+            subscribed_shard_channels: vec![],
+            received: VecDeque::new(),
+        })
+    }
+
+    /// Stream every command processed by the server.
     ///
-    /// Returns the number of subscribers currently listening on the channel.
+    /// The function consumes `self` and returns a `Monitor`, from which the
+    /// commands are read.
     #[instrument(skip(self))]
-    pub async fn publish(&mut self, channel: &str, message: Bytes) -> Result<u64> {
-        let frame = Publish::new(channel, message).into_frame();
+    pub async fn monitor(mut self) -> Result<Monitor> {
+        let frame = cmd::Monitor::new().into_frame();
         debug!(request = ?frame);
+        // Commands are pushed from now on.
+        self.connection.hold();
 
         self.connection.write_frame(&frame).await?;
 
-        // Read the response from server.
         match self.read_response().await? {
-            Frame::Integer(num) => Ok(num),
+            Frame::Simple(s) if s == "OK" => Ok(Monitor { client: self }),
             other => Err(other.to_error()),
         }
     }
 
-    /// Subscribes the client to the given channels.
+    /// Subscribes the client to the given glob patterns.
     ///
-    /// Once a client issues a subscribe command, it may no longer issue any
-    /// non-pub/sub commands. The function consumes `self` and returns a `Subscriber`.
+    /// Like `subscribe`, the function consumes `self` and returns a `Subscriber`.
     #[instrument(skip(self))]
-    pub async fn subscribe(mut self, channels: Vec<String>) -> Result<Subscriber> {
-        self.do_subscribe(&channels).await?;
+    pub async fn psubscribe(mut self, patterns: Vec<String>) -> Result<Subscriber> {
+        self.do_psubscribe(&patterns).await?;
+
+        Ok(Subscriber {
+            client: self,
+            subscribed_channels: vec![],
+            subscribed_patterns: deduplicated(patterns),
+            subscribed_shard_channels: vec![],
+            received: VecDeque::new(),
+        })
+    }
+
+    /// Subscribes the client to the given shard channels.
+    ///
+    /// Like `subscribe`, the function consumes `self` and returns a `Subscriber`.
+    #[instrument(skip(self))]
+    pub async fn ssubscribe(mut self, channels: Vec<String>) -> Result<Subscriber> {
+        self.do_ssubscribe(&channels).await?;
 
         Ok(Subscriber {
             client: self,
-            subscribed_channels: channels,
+            subscribed_channels: vec![],
+            subscribed_patterns: vec![],
+            subscribed_shard_channels: deduplicated(channels),
+            received: VecDeque::new(),
         })
     }
 
     async fn do_subscribe(&mut self, channels: &[String]) -> Result<()> {
         let frame = Subscribe::new(channels.to_vec()).into_frame();
+
+        self.subscribe_cmd(frame, "subscribe", channels).await
+    }
+
+    async fn do_psubscribe(&mut self, patterns: &[String]) -> Result<()> {
+        let frame = PSubscribe::new(patterns.to_vec()).into_frame();
+
+        self.subscribe_cmd(frame, "psubscribe", patterns).await
+    }
+
+    async fn do_ssubscribe(&mut self, channels: &[String]) -> Result<()> {
+        let frame = SSubscribe::new(channels.to_vec()).into_frame();
+
+        self.subscribe_cmd(frame, "ssubscribe", channels).await
+    }
+
+    /// The core `SUBSCRIBE`, `PSUBSCRIBE` and `SSUBSCRIBE` logic.
+    ///
+    /// `kind` is the name of the command, which the server echoes in its confirmations.
+    async fn subscribe_cmd(&mut self, frame: Frame, kind: &str, names: &[String]) -> Result<()> {
         debug!(request = ?frame);
+        // Messages are pushed from now on.
+        self.connection.hold();
 
         self.connection.write_frame(&frame).await?;
 
         // For each channel being subscribed to, the server responds with a confirmation message.
-        for channel in channels {
+        for name in names {
             let resp_frame = self.read_response().await?;
 
             // Verify the server responds.
             match resp_frame {
-                Frame::Array(ref frames) => match frames.as_slice() {
+                Frame::Array(ref frames) | Frame::Push(ref frames) => match frames.as_slice() {
                     // The server responds with an array frame in the form of:
                     // [ "subscribe", channel, num-subscribed ]
                     //
                     [subscribe, channel_name, ..]
-                        if *subscribe == "subscribe" && *channel_name == channel => {}
+                        if subscribe == kind && channel_name == name.as_str() => {}
+                    _ => return Err(resp_frame.to_error()),
+                },
+                other => return Err(other.to_error()),
+            };
+        }
+
+        Ok(())
+    }
+
+    /// The core `UNSUBSCRIBE`, `PUNSUBSCRIBE` and `SUNSUBSCRIBE` logic.
+    ///
+    /// `kind` is the name of the command, which the server echoes in its confirmations.
+    /// Each confirmed name is removed from `subscribed`. `others` is the number of
+    /// subscriptions of the other kinds counted along with those of `kind` in the
+    /// confirmations.
+    async fn unsubscribe_cmd(
+        &mut self,
+        frame: Frame,
+        kind: &str,
+        names: &[String],
+        subscribed: &mut Vec<String>,
+        others: usize,
+    ) -> Result<()> {
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // The server confirms each name given. Given none, it confirms each name it
+        // unsubscribes from, or sends a single confirmation without a name if there
+        // is none: the last one counts no subscription of `kind` left, whatever
+        // `subscribed` holds.
+        let mut confirmed = 0;
+        loop {
+            let resp_frame = self.read_response().await?;
+
+            let count = match resp_frame {
+                Frame::Array(ref frames) | Frame::Push(ref frames) => match frames.as_slice() {
+                    [unsubscribe, name, Frame::Integer(count)] if unsubscribe == kind => {
+                        subscribed.retain(|c| name != c.as_str());
+                        *count
+                    }
                     _ => return Err(resp_frame.to_error()),
                 },
                 other => return Err(other.to_error()),
             };
+            confirmed += 1;
+
+            let done = if names.is_empty() {
+                usize::try_from(count).is_ok_and(|count| count <= others)
+            } else {
+                confirmed == names.len()
+            };
+            if done {
+                break;
+            }
+        }
+        if names.is_empty() {
+            subscribed.clear();
         }
 
         Ok(())
     }
 
+    /// Issue the command made of `args`, its name followed by its arguments, and
+    /// return the reply as is. Allows issuing commands without a dedicated method.
+    ///
+    /// An error reply is returned as a `Frame::Error`, `Err` being returned only
+    /// if the command cannot be sent or the reply read.
+    #[instrument(skip(self))]
+    pub async fn send_command(&mut self, args: &[Bytes]) -> Result<Frame> {
+        let frame = Frame::Array(args.iter().cloned().map(Frame::Bulk).collect());
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let response = self.read_frame().await?;
+        debug!(?response);
+
+        response.ok_or_else(|| {
+            Error::new(ErrorKind::ConnectionReset, "connection reset by server").into()
+        })
+    }
+
     /// Reads a response frame from the socket.
     ///
     /// If an `Error` frame is received, it is converted to `Err`.
     async fn read_response(&mut self) -> Result<Frame> {
-        let response = self.connection.read_frame().await?;
+        let response = self.read_frame().await?;
         debug!(?response);
 
         match response {
@@ -203,6 +1863,76 @@ impl Client {
             }
         }
     }
+
+    /// Reads a frame from the socket, within the command timeout.
+    ///
+    /// Invalidation messages are set aside for `next_invalidation`, as they may be
+    /// received before the reply.
+    pub(super) async fn read_frame(&mut self) -> Result<Option<Frame>> {
+        loop {
+            let response = self.read_any_frame().await?;
+            match response.as_ref().and_then(invalidated_keys) {
+                Some(keys) => self.invalidated.extend(keys),
+                None => return Ok(response),
+            }
+        }
+    }
+
+    async fn read_any_frame(&mut self) -> Result<Option<Frame>> {
+        if self.timed_out {
+            let msg = "connection unusable after a command timed out";
+            return Err(Error::new(ErrorKind::NotConnected, msg).into());
+        }
+        let Some(timeout) = self.command_timeout else {
+            return self.connection.read_frame().await;
+        };
+
+        let Ok(response) = time::timeout(timeout, self.connection.read_frame()).await else {
+            self.timed_out = true;
+            let msg = format!("no reply received within {timeout:?}");
+            return Err(Error::new(ErrorKind::TimedOut, msg).into());
+        };
+        response
+    }
+}
+
+/// Connect to `addr`, failing with a `TimedOut` error after `timeout`.
+pub(super) async fn connect_within<T: ToSocketAddrs>(
+    addr: T,
+    timeout: Duration,
+) -> Result<TcpStream> {
+    let Ok(stream) = time::timeout(timeout, TcpStream::connect(addr)).await else {
+        let msg = format!("connecting timed out after {timeout:?}");
+        return Err(Error::new(ErrorKind::TimedOut, msg).into());
+    };
+
+    Ok(stream?)
+}
+
+impl Monitor {
+    /// Receive the next command processed by the server, waiting if necessary.
+    ///
+    /// Commands are described like
+    /// `1339518083.107412 [0 127.0.0.1:60866] "set" "key" "value"`, with the time
+    /// they were received at, the database and address of the client which issued
+    /// them, and their arguments. `None` indicates the connection was closed.
+    pub async fn next_command(&mut self) -> Result<Option<String>> {
+        match self.client.connection.read_frame().await? {
+            Some(Frame::Simple(line)) => Ok(Some(line)),
+            Some(other) => Err(other.to_error()),
+            None => Ok(None),
+        }
+    }
+
+    /// Convert the monitor into a `Stream` yielding the commands processed by the
+    /// server.
+    pub fn into_stream(mut self) -> impl Stream<Item = Result<String>> {
+        async_stream::try_stream! {
+            while let Some(line) = self.next_command().await? {
+                yield line;
+            }
+        }
+    }
 }
 
 impl Subscriber {
@@ -211,79 +1941,332 @@ impl Subscriber {
         &self.subscribed_channels
     }
 
+    /// Returns the set of patterns currently subscribed to.
+    pub fn get_subscribed_patterns(&self) -> &[String] {
+        &self.subscribed_patterns
+    }
+
+    /// Returns the set of shard channels currently subscribed to.
+    pub fn get_subscribed_shard_channels(&self) -> &[String] {
+        &self.subscribed_shard_channels
+    }
+
+    /// Returns `true` if `channel` is currently subscribed to.
+    pub fn is_subscribed(&self, channel: &str) -> bool {
+        self.subscribed_channels.iter().any(|c| c == channel)
+    }
+
+    /// Returns the number of channels, patterns and shard channels currently
+    /// subscribed to.
+    pub fn len(&self) -> usize {
+        self.subscribed_channels.len()
+            + self.subscribed_patterns.len()
+            + self.subscribed_shard_channels.len()
+    }
+
+    /// Returns `true` if nothing is subscribed to anymore, in which case no message
+    /// is received.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Unsubscribe from every channel, pattern and shard channel still subscribed
+    /// to, and return the client to issue other commands.
+    ///
+    /// Messages received but not read yet are dropped. Fails if a subscription is
+    /// still active afterwards.
+    #[instrument(skip(self))]
+    pub async fn into_client(mut self) -> Result<Client> {
+        if !self.subscribed_channels.is_empty() {
+            self.unsubscribe(&[]).await?;
+        }
+        if !self.subscribed_patterns.is_empty() {
+            self.punsubscribe(&[]).await?;
+        }
+        if !self.subscribed_shard_channels.is_empty() {
+            self.sunsubscribe(&[]).await?;
+        }
+
+        let remaining = self.len();
+        if remaining > 0 {
+            return Err(format!("still subscribed to {remaining} channels or patterns").into());
+        }
+
+        let mut client = self.client;
+        client.connection.release();
+
+        Ok(client)
+    }
+
     /// Receive the next message published on a subscribed channel, waiting if necessary.
     ///
     /// `None` indicates the subscription has been terminated.
     pub async fn next_message(&mut self) -> Result<Option<Message>> {
+        // Messages which arrived while waiting for the reply of another command.
+        if let Some(message) = self.received.pop_front() {
+            return Ok(Some(message));
+        }
+
         match self.client.connection.read_frame().await? {
             Some(frame) => {
                 debug!(?frame);
 
-                match frame {
-                    Frame::Array(ref frames) => match frames.as_slice() {
-                        [message, channel, content] if *message == "message" => Ok(Some(Message {
-                            channel: channel.to_string(),
-                            content: Bytes::from(content.to_string()),
-                        })),
-                        _ => Err(frame.to_error()),
-                    },
-                    other => Err(other.to_error()),
-                }
+                parse_message(frame).map(Some)
             }
             None => Ok(None),
         }
     }
 
+    /// Ping the server while subscribed.
+    ///
+    /// # Return
+    ///
+    /// Returns `msg`, or an empty payload if `msg` is `None`.
+    #[instrument(skip(self))]
+    pub async fn ping(&mut self, msg: Option<Bytes>) -> Result<Bytes> {
+        let frame = Ping::new(msg).into_frame();
+        debug!(request = ?frame);
+        self.client.connection.write_frame(&frame).await?;
+
+        // Messages published before the `PING` was handled are received first.
+        loop {
+            let resp_frame = self.client.read_response().await?;
+
+            match resp_frame {
+                Frame::Array(ref frames) | Frame::Push(ref frames) => match frames.as_slice() {
+                    [pong, Frame::Bulk(payload)] if pong == "pong" => return Ok(payload.clone()),
+                    _ => self.received.push_back(parse_message(resp_frame)?),
+                },
+                // The usual reply, under RESP3.
+                Frame::Simple(pong) if pong == "PONG" => return Ok(Bytes::new()),
+                Frame::Bulk(payload) => return Ok(payload),
+                other => return Err(other.to_error()),
+            }
+        }
+    }
+
+    /// Ping the server with `msg` while subscribed, returning the copy of `msg`
+    /// echoed by the server.
+    #[instrument(skip(self, msg))]
+    pub async fn ping_with(&mut self, msg: impl Into<Bytes>) -> Result<Bytes> {
+        self.ping(Some(msg.into())).await
+    }
+
     /// Subscribe channels
     #[instrument(skip(self))]
     pub async fn subscribe(&mut self, channels: &[String]) -> Result<()> {
         self.client.do_subscribe(channels).await?;
-        self.subscribed_channels
-            .extend(channels.iter().map(Clone::clone));
+        add_subscriptions(&mut self.subscribed_channels, channels);
+
+        Ok(())
+    }
+
+    /// Subscribe glob patterns
+    #[instrument(skip(self))]
+    pub async fn psubscribe(&mut self, patterns: &[String]) -> Result<()> {
+        self.client.do_psubscribe(patterns).await?;
+        add_subscriptions(&mut self.subscribed_patterns, patterns);
+
+        Ok(())
+    }
+
+    /// Subscribe shard channels
+    #[instrument(skip(self))]
+    pub async fn ssubscribe(&mut self, channels: &[String]) -> Result<()> {
+        self.client.do_ssubscribe(channels).await?;
+        add_subscriptions(&mut self.subscribed_shard_channels, channels);
 
         Ok(())
     }
 
+    /// Leave the subscribed state, unsubscribing from everything, and get the
+    /// client back.
+    ///
+    /// Messages received before the server applies the reset are discarded.
+    #[instrument(skip(self))]
+    pub async fn reset(mut self) -> Result<Client> {
+        let frame = Reset::new().into_frame();
+        debug!(request = ?frame);
+
+        self.client.connection.write_frame(&frame).await?;
+
+        loop {
+            match self.client.read_response().await? {
+                Frame::Simple(s) if s == "RESET" => return Ok(self.client),
+                Frame::Array(_) | Frame::Push(_) => {}
+                other => return Err(other.to_error()),
+            }
+        }
+    }
+
     /// Unsubscribe channels
     #[instrument(skip(self))]
     pub async fn unsubscribe(&mut self, channels: &[String]) -> Result<()> {
         let frame = Unsubscribe::new(channels).into_frame();
-        debug!(request = ?frame);
 
-        self.client.connection.write_frame(&frame).await?;
+        self.client
+            .unsubscribe_cmd(
+                frame,
+                "unsubscribe",
+                channels,
+                &mut self.subscribed_channels,
+                self.subscribed_patterns.len(),
+            )
+            .await
+    }
 
-        // if the input channel list is empty, server acknowledges as unsubscribing
-        // from all subscribed channels.
-        let num = if channels.is_empty() {
-            self.subscribed_channels.len()
-        } else {
-            channels.len()
-        };
+    /// Unsubscribe glob patterns
+    #[instrument(skip(self))]
+    pub async fn punsubscribe(&mut self, patterns: &[String]) -> Result<()> {
+        let frame = PUnsubscribe::new(patterns).into_frame();
 
-        // Read the response
-        for _ in 0..num {
-            let resp_frame = self.client.read_response().await?;
+        self.client
+            .unsubscribe_cmd(
+                frame,
+                "punsubscribe",
+                patterns,
+                &mut self.subscribed_patterns,
+                self.subscribed_channels.len(),
+            )
+            .await
+    }
 
-            match resp_frame {
-                Frame::Array(ref frames) => match frames.as_slice() {
-                    [unsubscribe, channel, ..] if *unsubscribe == "unsubscribe" => {
-                        let len = self.subscribed_channels.len();
-                        if len == 0 {
-                            return Err(resp_frame.to_error());
-                        }
+    /// Unsubscribe shard channels
+    #[instrument(skip(self))]
+    pub async fn sunsubscribe(&mut self, channels: &[String]) -> Result<()> {
+        let frame = SUnsubscribe::new(channels).into_frame();
 
-                        self.subscribed_channels.retain(|c| *channel != &c);
-                        // Only a single channel should be removed from subscribed_channels.
-                        if self.subscribed_channels.len() != len - 1 {
-                            return Err(resp_frame.to_error());
-                        }
-                    }
-                    _ => return Err(resp_frame.to_error()),
-                },
-                other => return Err(other.to_error()),
-            };
+        self.client
+            .unsubscribe_cmd(
+                frame,
+                "sunsubscribe",
+                channels,
+                &mut self.subscribed_shard_channels,
+                // Shard channels are counted apart from the other subscriptions.
+                0,
+            )
+            .await
+    }
+}
+
+/// Add the `names` not `subscribed` to yet to `subscribed`. Like in Redis, a name is
+/// subscribed to at most once.
+fn add_subscriptions(subscribed: &mut Vec<String>, names: &[String]) {
+    for name in names {
+        if !subscribed.contains(name) {
+            subscribed.push(name.clone());
         }
+    }
+}
 
-        Ok(())
+/// Returns `names` without the repeated ones, as subscribed to.
+fn deduplicated(names: Vec<String>) -> Vec<String> {
+    let mut subscribed = vec![];
+    add_subscriptions(&mut subscribed, &names);
+
+    subscribed
+}
+
+/// Returns the entries of a map.
+///
+/// RESP2 connections receive maps as flat arrays of keys and values.
+fn map_entries(frame: Frame) -> Result<Vec<(Frame, Frame)>> {
+    match frame {
+        Frame::Map(entries) => Ok(entries),
+        Frame::Array(frames) if frames.len() % 2 == 0 => {
+            let mut entries = vec![];
+            let mut frames = frames.into_iter();
+            while let (Some(key), Some(value)) = (frames.next(), frames.next()) {
+                entries.push((key, value));
+            }
+
+            Ok(entries)
+        }
+        other => Err(other.to_error()),
+    }
+}
+
+/// Returns the keys of an invalidation message pushed by the server, or `None` if
+/// `frame` is not one.
+fn invalidated_keys(frame: &Frame) -> Option<Vec<String>> {
+    match frame {
+        Frame::Push(frames) => match frames.as_slice() {
+            [invalidate, Frame::Array(keys)] if invalidate == "invalidate" => {
+                Some(keys.iter().map(ToString::to_string).collect())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the members of a set.
+///
+/// RESP2 connections receive sets as arrays.
+fn set_members(frame: Frame) -> Result<Vec<Frame>> {
+    match frame {
+        Frame::Set(members) | Frame::Array(members) => Ok(members),
+        other => Err(other.to_error()),
+    }
+}
+
+/// Converts the documentation of an argument, as returned by `COMMAND DOCS`.
+fn parse_argument_docs(frame: Frame) -> Result<ArgumentDocs> {
+    let mut docs = ArgumentDocs {
+        name: String::new(),
+        kind: String::new(),
+        token: None,
+        optional: false,
+        multiple: false,
+        arguments: vec![],
+    };
+
+    for (key, value) in map_entries(frame)? {
+        match (key.to_string().as_str(), value) {
+            ("name", value) => docs.name = value.to_string(),
+            ("type", value) => docs.kind = value.to_string(),
+            ("token", value) => docs.token = Some(value.to_string()),
+            ("flags", flags) => {
+                let flags = set_members(flags)?;
+                docs.optional = flags.iter().any(|flag| flag == "optional");
+                docs.multiple = flags.iter().any(|flag| flag == "multiple");
+            }
+            ("arguments", Frame::Array(frames)) => {
+                docs.arguments = frames
+                    .into_iter()
+                    .map(parse_argument_docs)
+                    .collect::<Result<_>>()?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(docs)
+}
+
+/// Converts a frame pushed by the server to a subscriber into a `Message`.
+fn parse_message(frame: Frame) -> Result<Message> {
+    match frame {
+        Frame::Array(ref frames) | Frame::Push(ref frames) => match frames.as_slice() {
+            [message, channel, Frame::Bulk(content)] if message == "message" => Ok(Message {
+                channel: channel.to_string(),
+                content: content.clone(),
+                pattern: None,
+            }),
+            [pmessage, pattern, channel, Frame::Bulk(content)] if pmessage == "pmessage" => {
+                Ok(Message {
+                    channel: channel.to_string(),
+                    content: content.clone(),
+                    pattern: Some(pattern.to_string()),
+                })
+            }
+            [smessage, channel, Frame::Bulk(content)] if smessage == "smessage" => Ok(Message {
+                channel: channel.to_string(),
+                content: content.clone(),
+                pattern: None,
+            }),
+            _ => Err(frame.to_error()),
+        },
+        other => Err(other.to_error()),
     }
 }
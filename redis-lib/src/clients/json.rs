@@ -0,0 +1,28 @@
+//! Storing values serialized as JSON, with the `json` feature.
+
+use crate::{Client, Result};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tracing::instrument;
+
+impl Client {
+    /// Set `key` to hold `value` serialized as JSON.
+    #[instrument(skip(self, value))]
+    pub async fn set_json<T: Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        let value = serde_json::to_vec(value)?;
+
+        self.set(key, value.into()).await
+    }
+
+    /// Get the value of `key`, deserialized from JSON. `None` is returned if the
+    /// key does not exist.
+    ///
+    /// Fails if the value is not the JSON of a `T`.
+    #[instrument(skip(self))]
+    pub async fn get_json<T: DeserializeOwned>(&mut self, key: &str) -> Result<Option<T>> {
+        match self.get(key).await? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+}
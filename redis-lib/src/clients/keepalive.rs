@@ -0,0 +1,195 @@
+//! Pinging the server in the background, to detect a dead connection and keep
+//! intermediaries from closing an idle one.
+
+use crate::cmd::{Ping, Protocol};
+use crate::{Connection, Frame, Result};
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tokio::time::{self, MissedTickBehavior};
+use tracing::{debug, warn};
+
+/// The connection of a `Client`, shared with its keepalive task.
+///
+/// The connection is locked from writing a request until its reply is read, so
+/// that no ping is sent in between.
+pub(super) struct SharedConnection {
+    shared: Arc<Shared>,
+
+    /// Held while replies are expected.
+    guard: Option<OwnedMutexGuard<Connection>>,
+
+    /// The number of requests written whose reply is not read yet.
+    outstanding: usize,
+
+    /// Set once the server sends frames which are not replies to requests, as in
+    /// pub/sub mode. The connection is not released anymore.
+    held: bool,
+
+    /// Set once reading or writing failed, or the server closed the connection.
+    lost: bool,
+}
+
+struct Shared {
+    connection: Arc<Mutex<Connection>>,
+
+    /// Set once a ping failed.
+    broken: AtomicBool,
+
+    /// The frames pushed by the server while waiting for a `PONG`, such as
+    /// invalidation messages, returned by `read_frame` before any other.
+    pushed: std::sync::Mutex<VecDeque<Frame>>,
+}
+
+impl SharedConnection {
+    pub(super) fn new(connection: Connection) -> SharedConnection {
+        SharedConnection {
+            shared: Arc::new(Shared {
+                connection: Arc::new(Mutex::new(connection)),
+                broken: AtomicBool::new(false),
+                pushed: std::sync::Mutex::default(),
+            }),
+            guard: None,
+            outstanding: 0,
+            held: false,
+            lost: false,
+        }
+    }
+
+    /// Returns `true` once the connection is known to be unusable: reading or
+    /// writing failed, the server closed it or a keepalive ping failed.
+    pub(super) fn is_broken(&self) -> bool {
+        self.lost || self.shared.broken.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the connection is usable, with no reply expected and not
+    /// held, so that it may be handed over as is.
+    pub(super) fn is_idle(&self) -> bool {
+        !self.is_broken() && self.outstanding == 0 && !self.held
+    }
+
+    /// Write `frame`, a request whose reply is read by `read_frame`.
+    pub(super) async fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        // Counted before writing, as the request may be partially written if the
+        // future is dropped.
+        self.outstanding += 1;
+        let res = self.lock().await?.write_frame(frame).await;
+        self.lost |= res.is_err();
+        res?;
+
+        Ok(())
+    }
+
+    /// Write `frames`, requests whose replies are read by `read_frame`.
+    pub(super) async fn write_frames(&mut self, frames: &[Frame]) -> Result<()> {
+        self.outstanding += frames.len();
+        let res = self.lock().await?.write_frames(frames).await;
+        self.lost |= res.is_err();
+        res?;
+
+        Ok(())
+    }
+
+    /// Read a frame, releasing the connection once every reply is read.
+    pub(super) async fn read_frame(&mut self) -> Result<Option<Frame>> {
+        // Not a reply, so none is expected less.
+        if let Some(frame) = self.shared.pushed.lock().unwrap().pop_front() {
+            return Ok(Some(frame));
+        }
+        let res = self.lock().await?.read_frame().await;
+        self.lost |= !matches!(res, Ok(Some(_)));
+        let frame = res?;
+        self.outstanding = self.outstanding.saturating_sub(1);
+        if self.outstanding == 0 && !self.held {
+            self.guard = None;
+        }
+
+        Ok(frame)
+    }
+
+    /// Keep the connection until released, as the server sends frames which are not
+    /// replies to requests, or as a ping would not be replied to as usual, such as
+    /// in a transaction where it is queued.
+    pub(super) fn hold(&mut self) {
+        self.held = true;
+    }
+
+    /// Release the connection held by `hold`, once the server only sends replies
+    /// to requests again and pings are replied to as usual.
+    pub(super) fn release(&mut self) {
+        self.held = false;
+        self.outstanding = 0;
+        self.guard = None;
+    }
+
+    async fn lock(&mut self) -> Result<&mut Connection> {
+        if self.shared.broken.load(Ordering::Relaxed) {
+            let msg = "connection broken, a keepalive ping failed";
+            return Err(Error::new(ErrorKind::NotConnected, msg).into());
+        }
+        if self.guard.is_none() {
+            self.guard = Some(self.shared.connection.clone().lock_owned().await);
+        }
+
+        Ok(self.guard.as_mut().unwrap())
+    }
+}
+
+/// Ping over `connection` every `period`, until the client owning it is dropped or
+/// a ping fails. The next request then fails.
+pub(super) fn spawn(connection: &SharedConnection, period: Duration) {
+    let shared = Arc::downgrade(&connection.shared);
+
+    tokio::spawn(async move {
+        let mut interval = time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick completes immediately.
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            let Some(shared) = shared.upgrade() else {
+                return;
+            };
+            let mut connection = shared.connection.lock().await;
+            // The client was dropped while waiting for the connection.
+            if Arc::strong_count(&shared) == 1 {
+                return;
+            }
+
+            if let Err(err) = ping(&shared, &mut connection, period).await {
+                warn!(cause = %err, "keepalive ping failed");
+                shared.broken.store(true, Ordering::Relaxed);
+                return;
+            }
+        }
+    });
+}
+
+/// Ping the server, failing if no `PONG` is received within `timeout`. The frames
+/// pushed meanwhile are kept in `shared` for the client to read.
+async fn ping(shared: &Shared, connection: &mut Connection, timeout: Duration) -> Result<()> {
+    let frame = Ping::new(None).into_frame();
+    debug!(request = ?frame, "keepalive");
+    connection.write_frame(&frame).await?;
+
+    let deadline = time::Instant::now() + timeout;
+    loop {
+        let Ok(response) = time::timeout_at(deadline, connection.read_frame()).await else {
+            let msg = format!("no reply to ping within {timeout:?}");
+            return Err(Error::new(ErrorKind::TimedOut, msg).into());
+        };
+        match response? {
+            Some(Frame::Simple(s)) if s == "PONG" => return Ok(()),
+            Some(frame @ Frame::Push(_)) => shared.pushed.lock().unwrap().push_back(frame),
+            Some(frame) => return Err(frame.to_error()),
+            None => {
+                let msg = "connection reset by server";
+                return Err(Error::new(ErrorKind::ConnectionReset, msg).into());
+            }
+        }
+    }
+}
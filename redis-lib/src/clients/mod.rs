@@ -1,2 +1,25 @@
+mod builder;
+pub use builder::ClientBuilder;
+
 mod client;
-pub use client::{Client, Message, Subscriber};
+pub use client::{
+    ArgumentDocs, Client, CommandDocs, CommandInfo, Message, Monitor, SlowLogEntry, StreamEntry,
+    Subscriber,
+};
+
+#[cfg(feature = "json")]
+mod json;
+
+mod keepalive;
+
+mod multiplexed;
+pub use multiplexed::MultiplexedClient;
+
+mod pipeline;
+pub use pipeline::Pipeline;
+
+mod pool;
+pub use pool::{Pool, PooledClient};
+
+mod reconnecting;
+pub use reconnecting::ReconnectingClient;
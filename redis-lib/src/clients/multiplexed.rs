@@ -0,0 +1,131 @@
+//! A connection shared by concurrent tasks, without locking.
+
+use crate::cmd::{Get, Ping, Protocol, Set};
+use crate::{Connection, Frame, Result};
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
+
+/// A request submitted to the task owning the connection, with where to send its
+/// reply.
+type Request = (Frame, oneshot::Sender<Result<Frame>>);
+
+/// A handle to a connection owned by a background task, cloned to share the
+/// connection between tasks.
+///
+/// Commands submitted concurrently are written as they come, without waiting for
+/// the replies to the previous ones, and their replies are matched in order. The
+/// connection is closed once every handle is dropped.
+#[derive(Clone)]
+pub struct MultiplexedClient {
+    requests: mpsc::Sender<Request>,
+}
+
+impl MultiplexedClient {
+    /// Establish a connection with the Redis server located at `addr`.
+    pub async fn connect<T: ToSocketAddrs>(addr: T) -> Result<MultiplexedClient> {
+        let stream = TcpStream::connect(addr).await?;
+        let connection = Connection::new(stream);
+
+        // Bounds the number of commands waiting to be written.
+        let (requests, rx) = mpsc::channel(128);
+        tokio::spawn(run(connection, rx));
+
+        Ok(MultiplexedClient { requests })
+    }
+
+    /// Send `cmd` and wait for its reply. An error reply is returned as an error.
+    pub async fn send(&self, cmd: impl Protocol) -> Result<Frame> {
+        let (tx, rx) = oneshot::channel();
+        if self.requests.send((cmd.into_frame(), tx)).await.is_err() {
+            return Err(closed().into());
+        }
+
+        // The responder is dropped without reply if the connection is lost.
+        rx.await.unwrap_or_else(|_| Err(closed().into()))
+    }
+
+    /// [Ping] to the server, returning `PONG` if no message is given, or a copy of
+    /// `msg` otherwise.
+    ///
+    /// [Ping]: crate::cmd::Ping
+    pub async fn ping(&self, msg: Option<Bytes>) -> Result<Bytes> {
+        match self.send(Ping::new(msg)).await? {
+            Frame::Simple(value) => Ok(value.into()),
+            Frame::Bulk(value) => Ok(value),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Get the value of `key`, `None` if it does not exist.
+    pub async fn get(&self, key: &str) -> Result<Option<Bytes>> {
+        match self.send(Get::new(key)).await? {
+            Frame::Simple(value) => Ok(Some(value.into())),
+            Frame::Bulk(value) => Ok(Some(value)),
+            Frame::Null => Ok(None),
+            other => Err(other.to_error()),
+        }
+    }
+
+    /// Set `key` to hold `value`.
+    pub async fn set(&self, key: &str, value: Bytes) -> Result<()> {
+        match self.send(Set::new(key, value, None)).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            other => Err(other.to_error()),
+        }
+    }
+}
+
+/// Write the submitted requests to `connection` and send back their replies, until
+/// every handle is dropped and the pending replies are received, or the connection
+/// is lost.
+async fn run(mut connection: Connection, mut requests: mpsc::Receiver<Request>) {
+    // The responders of the requests written, in order.
+    let mut pending: VecDeque<oneshot::Sender<Result<Frame>>> = VecDeque::new();
+    let mut open = true;
+
+    while open || !pending.is_empty() {
+        tokio::select! {
+            request = requests.recv(), if open => {
+                let Some((frame, responder)) = request else {
+                    open = false;
+                    continue;
+                };
+                debug!(request = ?frame);
+                if let Err(err) = connection.write_frame(&frame).await {
+                    debug!(cause = %err, "failed to write a multiplexed request");
+                    let _ = responder.send(Err(err.into()));
+                    return;
+                }
+                pending.push_back(responder);
+            }
+            // Reading is cancel safe: a partially read frame stays buffered.
+            response = connection.read_frame(), if !pending.is_empty() => {
+                let responder = pending.pop_front().unwrap();
+                let reply = match response {
+                    Ok(Some(Frame::Error(msg))) => Err(msg.into()),
+                    Ok(Some(frame)) => Ok(frame),
+                    // The connection is lost: the other responders are dropped.
+                    Ok(None) => {
+                        let _ = responder.send(Err(closed().into()));
+                        return;
+                    }
+                    Err(err) => {
+                        let _ = responder.send(Err(err));
+                        return;
+                    }
+                };
+                debug!(response = ?reply);
+                let _ = responder.send(reply);
+            }
+        }
+    }
+}
+
+/// The error of the connection being closed.
+fn closed() -> Error {
+    Error::new(ErrorKind::ConnectionReset, "connection reset by server")
+}
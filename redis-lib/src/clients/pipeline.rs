@@ -0,0 +1,98 @@
+//! Pipelining several commands over one round trip.
+
+use crate::cmd::{Get, Ping, Protocol, Set};
+use crate::{Client, Frame, Result};
+use bytes::Bytes;
+use std::io::{Error, ErrorKind};
+use tracing::{debug, instrument};
+
+/// A batch of commands sent together by `execute`, created with `Client::pipeline`.
+///
+/// The commands are written at once and their replies read afterwards, instead of
+/// waiting for each reply before sending the next command.
+pub struct Pipeline<'a> {
+    client: &'a mut Client,
+    frames: Vec<Frame>,
+}
+
+impl Client {
+    /// Start a pipeline of commands, sent over this connection by
+    /// `Pipeline::execute`.
+    pub fn pipeline(&mut self) -> Pipeline<'_> {
+        Pipeline {
+            client: self,
+            frames: vec![],
+        }
+    }
+}
+
+impl Pipeline<'_> {
+    /// Add `cmd` to the pipeline.
+    pub fn push(&mut self, cmd: impl Protocol) -> &mut Self {
+        self.frames.push(cmd.into_frame());
+        self
+    }
+
+    /// Add a [Get] of `key` to the pipeline.
+    ///
+    /// [Get]: crate::cmd::Get
+    pub fn get(&mut self, key: &str) -> &mut Self {
+        self.push(Get::new(key))
+    }
+
+    /// Add a [Set] of `key` to `value` to the pipeline.
+    ///
+    /// [Set]: crate::cmd::Set
+    pub fn set(&mut self, key: &str, value: Bytes) -> &mut Self {
+        self.push(Set::new(key, value, None))
+    }
+
+    /// Add a [Ping] to the pipeline.
+    ///
+    /// [Ping]: crate::cmd::Ping
+    pub fn ping(&mut self, msg: Option<Bytes>) -> &mut Self {
+        self.push(Ping::new(msg))
+    }
+
+    /// Returns the number of commands in the pipeline.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns true if the pipeline holds no command.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Send the commands with a single flush, then read their replies in order.
+    ///
+    /// Each command succeeds or fails on its own: an error reply is returned in
+    /// place of its reply, and the following commands are still applied. Failing
+    /// to send the commands or to read a reply fails the whole pipeline.
+    ///
+    /// Like for other commands, invalidation messages received meanwhile are set
+    /// aside for `Client::next_invalidation`.
+    #[instrument(skip(self), fields(len = self.frames.len()))]
+    pub async fn execute(self) -> Result<Vec<Result<Frame>>> {
+        debug!(requests = ?self.frames);
+        self.client.connection.write_frames(&self.frames).await?;
+
+        let mut replies = Vec::with_capacity(self.frames.len());
+        for _ in 0..self.frames.len() {
+            let response = self.client.read_frame().await?;
+            debug!(?response);
+
+            replies.push(match response {
+                Some(Frame::Error(msg)) => Err(msg.into()),
+                Some(frame) => Ok(frame),
+                None => {
+                    let err = Error::new(ErrorKind::ConnectionReset, "connection reset by server");
+
+                    return Err(err.into());
+                }
+            });
+        }
+
+        Ok(replies)
+    }
+}
@@ -0,0 +1,117 @@
+//! A pool of clients shared by concurrent tasks.
+
+use crate::{Client, Result};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::debug;
+
+/// A fixed number of connections to a server, checked out by `get`.
+///
+/// Connections are established on demand, when no idle one is available, and
+/// reused afterwards. Cloning the pool shares its connections.
+#[derive(Clone)]
+pub struct Pool {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    /// The address of the server.
+    addr: String,
+
+    /// The connections not checked out.
+    idle: Mutex<Vec<Client>>,
+
+    /// One permit per connection which may be checked out.
+    permits: Arc<Semaphore>,
+}
+
+/// A client checked out of a `Pool`, returned to it when dropped if idle.
+pub struct PooledClient {
+    /// `None` once discarded or returned to the pool.
+    client: Option<Client>,
+    shared: Arc<Shared>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Pool {
+    /// A pool of up to `size` connections to the server at `addr`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn new(addr: impl Into<String>, size: usize) -> Pool {
+        assert!(size > 0, "the pool must hold at least one connection");
+
+        Pool {
+            shared: Arc::new(Shared {
+                addr: addr.into(),
+                idle: Mutex::new(Vec::with_capacity(size)),
+                permits: Arc::new(Semaphore::new(size)),
+            }),
+        }
+    }
+
+    /// Check out a client, waiting for one to be returned if all are in use.
+    ///
+    /// An idle connection is reused, otherwise a new one is established.
+    pub async fn get(&self) -> Result<PooledClient> {
+        let permit = self.shared.permits.clone().acquire_owned().await?;
+
+        let idle = self.shared.idle.lock().unwrap().pop();
+        let client = if let Some(client) = idle {
+            client
+        } else {
+            debug!(addr = self.shared.addr, "connecting a pooled client");
+            Client::connect(self.shared.addr.as_str()).await?
+        };
+
+        Ok(PooledClient {
+            client: Some(client),
+            shared: self.shared.clone(),
+            _permit: permit,
+        })
+    }
+
+    /// Returns the number of established connections not checked out.
+    pub fn idle(&self) -> usize {
+        self.shared.idle.lock().unwrap().len()
+    }
+}
+
+impl PooledClient {
+    /// Close the connection instead of returning it to the pool, such as after
+    /// an error leaving it in an unknown state. A new one is established when
+    /// needed.
+    pub fn discard(mut self) {
+        self.client = None;
+    }
+}
+
+impl Deref for PooledClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledClient {
+    fn deref_mut(&mut self) -> &mut Client {
+        self.client.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        // A reply pending or a transaction left open would be seen by the next user.
+        match self.client.take() {
+            Some(client) if client.is_idle() => self.shared.idle.lock().unwrap().push(client),
+            Some(_) => debug!(
+                addr = self.shared.addr,
+                "discarding a pooled client not idle"
+            ),
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! A client reconnecting to the server when the connection is lost.
+
+use crate::{Client, Result};
+use bytes::Bytes;
+use std::io::{self, ErrorKind};
+use std::time::Duration;
+use tokio::time;
+use tracing::{debug, warn};
+
+/// Wraps a `Client`, reconnecting to the server when the connection is lost.
+///
+/// A command failing because of the connection is retried once reconnected, for
+/// the idempotent commands of its methods only. Other commands are issued through
+/// `client`, and retrying them is the caller's responsibility, as the server may
+/// have applied them before the connection was lost.
+pub struct ReconnectingClient {
+    /// The address of the server.
+    addr: String,
+
+    /// `None` while disconnected.
+    client: Option<Client>,
+
+    /// The delay before the first reconnection attempt, doubled after each failed
+    /// attempt.
+    initial_backoff: Duration,
+
+    /// The number of reconnection attempts before giving up.
+    attempts: u32,
+}
+
+impl ReconnectingClient {
+    /// Establish a connection with the Redis server located at `addr`.
+    pub async fn connect(addr: impl Into<String>) -> Result<ReconnectingClient> {
+        let addr = addr.into();
+        let client = Client::connect(addr.as_str()).await?;
+
+        Ok(ReconnectingClient {
+            addr,
+            client: Some(client),
+            initial_backoff: Duration::from_millis(50),
+            attempts: 6,
+        })
+    }
+
+    /// Make up to `attempts` reconnection attempts, the first one after
+    /// `initial_backoff` and each following one after twice the previous delay.
+    #[must_use]
+    pub fn with_backoff(mut self, initial_backoff: Duration, attempts: u32) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.attempts = attempts;
+        self
+    }
+
+    /// Returns the client, reconnecting first if the connection was lost, including
+    /// while issuing a command through a client returned earlier. A command failing
+    /// because of the connection is not retried.
+    pub async fn client(&mut self) -> Result<&mut Client> {
+        if self.client.as_ref().is_none_or(Client::is_broken) {
+            self.client = None;
+            self.reconnect().await?;
+        }
+
+        Ok(self.client.as_mut().unwrap())
+    }
+
+    /// [Ping] to the server, retried once reconnected if the connection was lost.
+    ///
+    /// [Ping]: crate::cmd::Ping
+    pub async fn ping(&mut self, msg: Option<Bytes>) -> Result<Bytes> {
+        self.retry(async |client| client.ping(msg.clone()).await)
+            .await
+    }
+
+    /// Get the value of `key`, retried once reconnected if the connection was lost.
+    pub async fn get(&mut self, key: &str) -> Result<Option<Bytes>> {
+        self.retry(async |client| client.get(key).await).await
+    }
+
+    /// Set `key` to hold `value`, retried once reconnected if the connection was
+    /// lost.
+    pub async fn set(&mut self, key: &str, value: Bytes) -> Result<()> {
+        self.retry(async |client| client.set(key, value.clone()).await)
+            .await
+    }
+
+    /// Issue the command of `op`, reconnecting and issuing it again if the
+    /// connection is lost.
+    async fn retry<T>(&mut self, mut op: impl AsyncFnMut(&mut Client) -> Result<T>) -> Result<T> {
+        match op(self.client().await?).await {
+            Err(err) if is_disconnection(&err) => {
+                warn!(cause = %err, "connection lost, reconnecting");
+                self.client = None;
+                op(self.client().await?).await
+            }
+            res => res,
+        }
+    }
+
+    /// Connect to the server, backing off exponentially between failed attempts.
+    async fn reconnect(&mut self) -> Result<()> {
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1;
+
+        loop {
+            time::sleep(backoff).await;
+            match Client::connect(self.addr.as_str()).await {
+                Ok(client) => {
+                    debug!(addr = self.addr, attempt, "reconnected");
+                    self.client = Some(client);
+                    return Ok(());
+                }
+                Err(err) if attempt >= self.attempts => return Err(err),
+                Err(err) => debug!(cause = %err, attempt, "failed to reconnect"),
+            }
+
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+/// Returns true if `err` means the connection was lost.
+fn is_disconnection(err: &crate::Error) -> bool {
+    err.downcast_ref::<io::Error>().is_some_and(|err| {
+        matches!(
+            err.kind(),
+            ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::BrokenPipe
+                | ErrorKind::UnexpectedEof
+                | ErrorKind::NotConnected
+        )
+    })
+}
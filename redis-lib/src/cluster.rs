@@ -0,0 +1,57 @@
+//!
+//! Mapping keys to the hash slots of Redis Cluster, for clients sharding keys
+//! across several servers.
+//!
+//! Like in Redis Cluster, the key space is split into 16384 slots. A key belongs to
+//! the slot given by the CRC16 of the key, modulo 16384. Only the hash tag of the
+//! key is hashed if it has one: the part between its first `{` and the next `}`,
+//! unless empty. Keys sharing a hash tag, like `{user1000}.following` and
+//! `{user1000}.followers`, belong to the same slot.
+//!
+
+/// The number of hash slots.
+pub const SLOTS: u16 = 16384;
+
+/// The CRC16 of each byte value, with the XMODEM polynomial used by Redis.
+const CRC16_TABLE: [u16; 256] = crc16_table();
+
+/// Returns the hash slot of `key`, between 0 and `SLOTS - 1`.
+pub fn key_slot(key: &[u8]) -> u16 {
+    crc16(hash_tag(key).unwrap_or(key)) % SLOTS
+}
+
+/// The hash tag of `key`, if it has a non-empty one.
+fn hash_tag(key: &[u8]) -> Option<&[u8]> {
+    let start = key.iter().position(|&b| b == b'{')? + 1;
+    let len = key[start..].iter().position(|&b| b == b'}')?;
+
+    (len > 0).then(|| &key[start..start + len])
+}
+
+/// The CRC16 of `bytes`, also used to check the payloads of `RESTORE`.
+pub(crate) fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, &b| {
+        (crc << 8) ^ CRC16_TABLE[usize::from((crc >> 8) as u8 ^ b)]
+    })
+}
+
+const fn crc16_table() -> [u16; 256] {
+    let mut table = [0; 256];
+    let mut i: u16 = 0;
+    while i < 256 {
+        let mut crc = i << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            };
+            bit += 1;
+        }
+        table[i as usize] = crc;
+        i += 1;
+    }
+
+    table
+}
@@ -0,0 +1,81 @@
+use crate::cmd::{Parse, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Frame};
+use bytes::Bytes;
+use std::fmt;
+use tracing::{debug, instrument};
+
+/// Authenticate the connection with the password configured on the server.
+///
+/// Until authenticated, a connection to a server requiring a password may only
+/// issue `AUTH`.
+pub struct Auth {
+    password: String,
+}
+
+// Keeps the password out of the logs.
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Auth").finish_non_exhaustive()
+    }
+}
+
+impl Auth {
+    pub fn new(password: impl ToString) -> Auth {
+        Auth {
+            password: password.to_string(),
+        }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// AUTH password
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Auth> {
+        let password = parse.next_string()?;
+
+        Ok(Auth { password })
+    }
+
+    /// Check the password against `required`, the password configured on the server,
+    /// and record the outcome in `authenticated`.
+    #[instrument(skip(self, required, authenticated, dst))]
+    pub(crate) async fn apply(
+        self,
+        required: Option<&str>,
+        authenticated: &mut bool,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let resp_frame = match required {
+            None => Frame::Error(
+                "ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?"
+                    .to_string(),
+            ),
+            Some(required) if required == self.password => {
+                *authenticated = true;
+                Frame::Simple("OK".to_string())
+            }
+            Some(_) => Frame::Error(
+                "WRONGPASS invalid username-password pair or user is disabled.".to_string(),
+            ),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for Auth {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("auth".as_bytes()));
+        frame.push_bulk(Bytes::from(self.password.into_bytes()));
+
+        frame.into()
+    }
+}
@@ -0,0 +1,258 @@
+use crate::cmd::{Parse, ParseError, Protocol, help_frame};
+use crate::frame::PushFrame;
+use crate::tracking::Invalidations;
+use crate::{ClientHandle, Connection, Db, Frame};
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Inspect and manage the connection of the client.
+///
+/// # Subcommands
+///
+/// * SETNAME `name` -- Assign a name to the connection. An empty name removes it.
+/// * GETNAME -- Return the name of the connection.
+/// * ID -- Return the unique id of the connection.
+/// * LIST -- Describe every connection to the server, one line each.
+/// * KILL `filter` -- Close the connections matching the filter, other than the
+///   current one, and return how many were closed.
+/// * PAUSE `timeout` `[WRITE|ALL]` -- Suspend the processing of commands from all
+///   the connections for `timeout` milliseconds.
+/// * NO-EVICT `ON|OFF` -- Accepted for compatibility. The server never evicts
+///   connections, so the flag has no effect.
+/// * TRACKING `ON|OFF` -- Enable or disable client-side caching: once a key read
+///   by the connection is modified, it is sent an invalidation message. Requires
+///   RESP3, see `HELLO`.
+/// * HELP -- Describe the subcommands.
+#[derive(Debug)]
+pub enum ClientCommand {
+    SetName { name: String },
+    GetName,
+    Id,
+    List,
+    Kill { filter: KillFilter },
+    Pause { timeout: Duration, mode: PauseMode },
+    NoEvict { enabled: bool },
+    Tracking { enabled: bool },
+    Help,
+}
+
+/// Selects the connections to close with `CLIENT KILL`.
+#[derive(Debug, Clone)]
+pub enum KillFilter {
+    /// The connection with the given id.
+    Id(u64),
+    /// The connections from the given `ip:port` address.
+    Addr(String),
+}
+
+/// The commands suspended by `CLIENT PAUSE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseMode {
+    /// Only the commands which modify the data, or publish messages.
+    Write,
+    /// Every command.
+    All,
+}
+
+impl ClientCommand {
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// CLIENT SETNAME name
+    /// CLIENT GETNAME
+    /// CLIENT ID
+    /// CLIENT LIST
+    /// CLIENT KILL <ID id | ADDR ip:port>
+    /// CLIENT PAUSE timeout [WRITE | ALL]
+    /// CLIENT NO-EVICT <ON | OFF>
+    /// CLIENT TRACKING <ON | OFF>
+    /// CLIENT HELP
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ClientCommand> {
+        match parse.next_string()?.to_uppercase().as_str() {
+            "SETNAME" => Ok(ClientCommand::SetName {
+                name: parse.next_string()?,
+            }),
+            "GETNAME" => Ok(ClientCommand::GetName),
+            "ID" => Ok(ClientCommand::Id),
+            "LIST" => Ok(ClientCommand::List),
+            "KILL" => {
+                let filter = match parse.next_string()?.to_uppercase().as_str() {
+                    "ID" => KillFilter::Id(parse.next_int()?),
+                    "ADDR" => KillFilter::Addr(parse.next_string()?),
+                    other => {
+                        return Err(format!("unsupported `CLIENT KILL` filter `{other}`").into());
+                    }
+                };
+
+                Ok(ClientCommand::Kill { filter })
+            }
+            "PAUSE" => {
+                let timeout = Duration::from_millis(parse.next_int()?);
+                let mode = match parse.next_string() {
+                    Ok(s) if s.to_uppercase() == "WRITE" => PauseMode::Write,
+                    Ok(s) if s.to_uppercase() == "ALL" => PauseMode::All,
+                    Ok(s) => return Err(format!("unsupported `CLIENT PAUSE` mode `{s}`").into()),
+                    Err(ParseError::EndOfStream) => PauseMode::All,
+                    Err(err) => return Err(err.into()),
+                };
+
+                Ok(ClientCommand::Pause { timeout, mode })
+            }
+            "NO-EVICT" => {
+                let enabled = match parse.next_string()?.to_uppercase().as_str() {
+                    "ON" => true,
+                    "OFF" => false,
+                    other => {
+                        return Err(format!("unsupported `CLIENT NO-EVICT` flag `{other}`").into());
+                    }
+                };
+
+                Ok(ClientCommand::NoEvict { enabled })
+            }
+            "TRACKING" => {
+                let enabled = match parse.next_string()?.to_uppercase().as_str() {
+                    "ON" => true,
+                    "OFF" => false,
+                    other => {
+                        return Err(format!("unsupported `CLIENT TRACKING` flag `{other}`").into());
+                    }
+                };
+
+                Ok(ClientCommand::Tracking { enabled })
+            }
+            "HELP" => Ok(ClientCommand::Help),
+            other => Err(format!("unknown `CLIENT` subcommand `{other}`").into()),
+        }
+    }
+
+    /// Apply the subcommand to the connection registered as `client`.
+    ///
+    /// `invalidations` holds the keys invalidated for the connection while it has
+    /// tracking enabled.
+    #[instrument(skip(self, client, db, invalidations, dst))]
+    pub(crate) async fn apply(
+        self,
+        client: &ClientHandle,
+        db: &Db,
+        invalidations: &mut Option<Invalidations>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let resp_frame = match self {
+            // Names are printable ASCII without spaces, like in Redis.
+            ClientCommand::SetName { name }
+                if !name.bytes().all(|b| (b'!'..=b'~').contains(&b)) =>
+            {
+                Frame::Error(
+                    "ERR Client names cannot contain spaces, newlines or special characters."
+                        .to_string(),
+                )
+            }
+            ClientCommand::SetName { name } => {
+                client.set_name(Some(name).filter(|name| !name.is_empty()));
+                Frame::Simple("OK".to_string())
+            }
+            ClientCommand::GetName => {
+                Frame::Bulk(client.name().map(Bytes::from).unwrap_or_default())
+            }
+            ClientCommand::Id => Frame::unsigned(client.id()),
+            ClientCommand::List => Frame::Bulk(Bytes::from(client.registry().list())),
+            ClientCommand::Kill { filter } => {
+                Frame::unsigned(client.registry().kill(&filter, client.id()) as u64)
+            }
+            ClientCommand::Pause { timeout, mode } => {
+                client.registry().pause(timeout, mode);
+                Frame::Simple("OK".to_string())
+            }
+            ClientCommand::NoEvict { .. } => Frame::Simple("OK".to_string()),
+            // The invalidation messages are pushed, which RESP2 does not allow.
+            ClientCommand::Tracking { enabled: true } if dst.protocol() < 3 => {
+                Frame::Error("ERR Client tracking requires RESP3, switch with HELLO 3".to_string())
+            }
+            ClientCommand::Tracking { enabled } => {
+                *invalidations = enabled.then(|| db.enable_tracking(client.id()));
+                Frame::Simple("OK".to_string())
+            }
+            ClientCommand::Help => help_frame(
+                "CLIENT",
+                &[
+                    "GETNAME",
+                    "    Return the name of the current connection.",
+                    "ID",
+                    "    Return the ID of the current connection.",
+                    "KILL (ID <client-id> | ADDR <ip:port>)",
+                    "    Kill connections by client ID or address.",
+                    "LIST",
+                    "    Return information about client connections.",
+                    "NO-EVICT (ON|OFF)",
+                    "    Accepted for compatibility, connections are never evicted.",
+                    "PAUSE <timeout> [WRITE|ALL]",
+                    "    Suspend all, or just write, clients for <timeout> milliseconds.",
+                    "SETNAME <name>",
+                    "    Assign the name <name> to the current connection.",
+                    "TRACKING (ON|OFF)",
+                    "    Control server assisted client side caching, under RESP3.",
+                ],
+            ),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for ClientCommand {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("client".as_bytes()));
+        match self {
+            ClientCommand::SetName { name } => {
+                frame.push_bulk(Bytes::from("setname".as_bytes()));
+                frame.push_bulk(Bytes::from(name.into_bytes()));
+            }
+            ClientCommand::GetName => frame.push_bulk(Bytes::from("getname".as_bytes())),
+            ClientCommand::Id => frame.push_bulk(Bytes::from("id".as_bytes())),
+            ClientCommand::List => frame.push_bulk(Bytes::from("list".as_bytes())),
+            ClientCommand::Kill { filter } => {
+                frame.push_bulk(Bytes::from("kill".as_bytes()));
+                match filter {
+                    KillFilter::Id(id) => {
+                        frame.push_bulk(Bytes::from("id".as_bytes()));
+                        frame.push_int(id);
+                    }
+                    KillFilter::Addr(addr) => {
+                        frame.push_bulk(Bytes::from("addr".as_bytes()));
+                        frame.push_bulk(Bytes::from(addr.into_bytes()));
+                    }
+                }
+            }
+            ClientCommand::Pause { timeout, mode } => {
+                frame.push_bulk(Bytes::from("pause".as_bytes()));
+                frame.push_int(timeout.as_millis() as u64);
+                let mode = match mode {
+                    PauseMode::Write => "write",
+                    PauseMode::All => "all",
+                };
+                frame.push_bulk(Bytes::from(mode.as_bytes()));
+            }
+            ClientCommand::NoEvict { enabled } => {
+                frame.push_bulk(Bytes::from("no-evict".as_bytes()));
+                let flag = if enabled { "on" } else { "off" };
+                frame.push_bulk(Bytes::from(flag.as_bytes()));
+            }
+            ClientCommand::Tracking { enabled } => {
+                frame.push_bulk(Bytes::from("tracking".as_bytes()));
+                let flag = if enabled { "on" } else { "off" };
+                frame.push_bulk(Bytes::from(flag.as_bytes()));
+            }
+            ClientCommand::Help => frame.push_bulk(Bytes::from("help".as_bytes())),
+        }
+
+        frame.into()
+    }
+}
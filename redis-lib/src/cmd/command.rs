@@ -0,0 +1,1045 @@
+use crate::cmd::{Parse, ParseError, Protocol, help_frame};
+use crate::frame::PushFrame;
+use crate::{Connection, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Describe the commands supported by the server.
+///
+/// # Subcommands
+///
+/// * (none) -- Return the name, arity and flags of each command.
+/// * COUNT -- Return the number of commands.
+/// * DOCS `[command ...]` -- Return the summary and arguments of the given commands,
+///   or of every command if none is given.
+/// * GETKEYS `command [arg ...]` -- Return the keys of the given invocation of a
+///   command, without running it.
+/// * HELP -- Describe the subcommands.
+#[derive(Debug)]
+pub enum CommandCommand {
+    List,
+    Count,
+    Docs { commands: Vec<String> },
+    GetKeys { command: Vec<String> },
+    Help,
+}
+
+/// The metadata of a supported command.
+#[derive(Debug)]
+pub(crate) struct CommandSpec {
+    pub(crate) name: &'static str,
+
+    /// The number of arguments, including the command name. A negative arity `-n`
+    /// stands for at least `n` arguments.
+    pub(crate) arity: i64,
+
+    pub(crate) flags: &'static [&'static str],
+
+    /// Where the keys are among the arguments.
+    keys: KeySpec,
+
+    /// A one-line description of the command.
+    pub(crate) summary: &'static str,
+
+    pub(crate) arguments: &'static [Argument],
+}
+
+/// The positions of the keys among the arguments of a command, the command name
+/// being at position 0.
+#[derive(Debug)]
+enum KeySpec {
+    None,
+    /// The arguments from `first` to `last` included, every `step`. A negative
+    /// `last` counts from the end, `-1` being the last argument.
+    Range {
+        first: usize,
+        last: i64,
+        step: usize,
+    },
+    /// The first half of the arguments following `STREAMS`, as in `XREAD`.
+    Streams,
+}
+
+/// The documentation of a command argument.
+#[derive(Debug)]
+pub(crate) struct Argument {
+    name: &'static str,
+    kind: ArgumentKind,
+
+    /// The keyword preceding the argument, like `EX`.
+    token: Option<&'static str>,
+    optional: bool,
+
+    /// Set if the argument may be repeated.
+    multiple: bool,
+}
+
+#[derive(Debug)]
+enum ArgumentKind {
+    Key,
+    String,
+    Integer,
+    Pattern,
+    /// A keyword without a value.
+    PureToken,
+    /// Exactly one of the arguments.
+    OneOf(&'static [Argument]),
+    /// All of the arguments, in order.
+    Block(&'static [Argument]),
+}
+
+/// The commands supported by the server, ordered by name.
+///
+/// This is the source of truth for `COMMAND`. A command added to `Command` must
+/// be described here as well.
+pub(crate) const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "auth",
+        arity: -2,
+        flags: &["noscript", "loading", "stale", "fast", "no_auth"],
+        keys: KeySpec::None,
+        summary: "Authenticates the connection.",
+        arguments: &[arg("password", ArgumentKind::String)],
+    },
+    CommandSpec {
+        name: "bgrewriteaof",
+        arity: 1,
+        flags: &["admin", "noscript", "noasync"],
+        keys: KeySpec::None,
+        summary: "Asynchronously rewrites the append-only file to disk.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "bgsave",
+        arity: 1,
+        flags: &["admin", "noscript", "noasync"],
+        keys: KeySpec::None,
+        summary: "Asynchronously saves the database(s) to disk.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "client",
+        arity: -2,
+        flags: &["admin", "noscript", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "A container for client connection commands.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "command",
+        arity: -1,
+        flags: &["loading", "stale"],
+        keys: KeySpec::None,
+        summary: "Returns detailed information about all commands.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "config",
+        arity: -2,
+        flags: &["admin", "noscript", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "A container for server configuration commands.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "debug",
+        arity: -2,
+        flags: &["admin", "noscript", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "A container for debugging commands.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "del",
+        arity: -2,
+        flags: &["write"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: -1,
+            step: 1,
+        },
+        summary: "Deletes one or more keys.",
+        arguments: &[arg("key", ArgumentKind::Key).multiple()],
+    },
+    CommandSpec {
+        name: "discard",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast"],
+        keys: KeySpec::None,
+        summary: "Discards a transaction.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "dump",
+        arity: 2,
+        flags: &["readonly"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 1,
+            step: 1,
+        },
+        summary: "Returns a serialized representation of the value stored at a key.",
+        arguments: &[arg("key", ArgumentKind::Key)],
+    },
+    CommandSpec {
+        name: "exec",
+        arity: 1,
+        flags: &["noscript", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "Executes all commands in a transaction.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "expire",
+        arity: -3,
+        flags: &["write", "fast"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 1,
+            step: 1,
+        },
+        summary: "Sets the expiration time of a key in seconds.",
+        arguments: &[
+            arg("key", ArgumentKind::Key),
+            arg("seconds", ArgumentKind::Integer),
+            arg("condition", ArgumentKind::OneOf(EXPIRE_CONDITIONS)).optional(),
+        ],
+    },
+    CommandSpec {
+        name: "get",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 1,
+            step: 1,
+        },
+        summary: "Returns the string value of a key.",
+        arguments: &[arg("key", ArgumentKind::Key)],
+    },
+    CommandSpec {
+        name: "getex",
+        arity: -2,
+        flags: &["write", "fast"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 1,
+            step: 1,
+        },
+        summary: "Returns the string value of a key after setting its expiration time.",
+        arguments: &[
+            arg("key", ArgumentKind::Key),
+            arg(
+                "expiration",
+                ArgumentKind::OneOf(&[
+                    arg("seconds", ArgumentKind::Integer).token("EX"),
+                    arg("milliseconds", ArgumentKind::Integer).token("PX"),
+                    arg("unix-time-seconds", ArgumentKind::Integer).token("EXAT"),
+                    arg("unix-time-milliseconds", ArgumentKind::Integer).token("PXAT"),
+                    arg("persist", ArgumentKind::PureToken).token("PERSIST"),
+                ]),
+            )
+            .optional(),
+        ],
+    },
+    CommandSpec {
+        name: "hello",
+        arity: -1,
+        flags: &["noscript", "loading", "stale", "fast", "no_auth"],
+        keys: KeySpec::None,
+        summary: "Handshakes with the Redis server.",
+        arguments: &[arg(
+            "arguments",
+            ArgumentKind::Block(&[
+                arg("protover", ArgumentKind::Integer),
+                arg(
+                    "auth",
+                    ArgumentKind::Block(&[
+                        arg("username", ArgumentKind::String),
+                        arg("password", ArgumentKind::String),
+                    ]),
+                )
+                .token("AUTH")
+                .optional(),
+            ]),
+        )
+        .optional()],
+    },
+    CommandSpec {
+        name: "info",
+        arity: -1,
+        flags: &["loading", "stale"],
+        keys: KeySpec::None,
+        summary: "Returns information and statistics about the server.",
+        arguments: &[arg("section", ArgumentKind::String).optional()],
+    },
+    CommandSpec {
+        name: "lastsave",
+        arity: 1,
+        flags: &["loading", "stale", "fast"],
+        keys: KeySpec::None,
+        summary: "Returns the Unix timestamp of the last successful save to disk.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "memory",
+        arity: -2,
+        flags: &[],
+        keys: KeySpec::None,
+        summary: "A container for memory diagnostics commands.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "monitor",
+        arity: 1,
+        flags: &["admin", "noscript", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "Listens for all requests received by the server in real-time.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "move",
+        arity: 3,
+        flags: &["write", "fast"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 1,
+            step: 1,
+        },
+        summary: "Moves a key to another database.",
+        arguments: &[
+            arg("key", ArgumentKind::Key),
+            arg("db", ArgumentKind::Integer),
+        ],
+    },
+    CommandSpec {
+        name: "multi",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast"],
+        keys: KeySpec::None,
+        summary: "Starts a transaction.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "object",
+        arity: -2,
+        flags: &[],
+        keys: KeySpec::Range {
+            first: 2,
+            last: 2,
+            step: 1,
+        },
+        summary: "A container for object introspection commands.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "persist",
+        arity: 2,
+        flags: &["write", "fast"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 1,
+            step: 1,
+        },
+        summary: "Removes the expiration time of a key.",
+        arguments: &[arg("key", ArgumentKind::Key)],
+    },
+    CommandSpec {
+        name: "pexpire",
+        arity: -3,
+        flags: &["write", "fast"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 1,
+            step: 1,
+        },
+        summary: "Sets the expiration time of a key in milliseconds.",
+        arguments: &[
+            arg("key", ArgumentKind::Key),
+            arg("milliseconds", ArgumentKind::Integer),
+            arg("condition", ArgumentKind::OneOf(EXPIRE_CONDITIONS)).optional(),
+        ],
+    },
+    CommandSpec {
+        name: "pexpireat",
+        arity: -3,
+        flags: &["write", "fast"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 1,
+            step: 1,
+        },
+        summary: "Sets the expiration time of a key to a Unix milliseconds timestamp.",
+        arguments: &[
+            arg("key", ArgumentKind::Key),
+            arg("unix-time-milliseconds", ArgumentKind::Integer),
+            arg("condition", ArgumentKind::OneOf(EXPIRE_CONDITIONS)).optional(),
+        ],
+    },
+    CommandSpec {
+        name: "ping",
+        arity: -1,
+        flags: &["fast"],
+        keys: KeySpec::None,
+        summary: "Returns the server's liveliness response.",
+        arguments: &[arg("message", ArgumentKind::String).optional()],
+    },
+    CommandSpec {
+        name: "psubscribe",
+        arity: -2,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "Listens for messages published to channels that match one or more patterns.",
+        arguments: &[arg("pattern", ArgumentKind::Pattern).multiple()],
+    },
+    CommandSpec {
+        name: "psync",
+        arity: -3,
+        flags: &["admin", "noscript", "no_async_loading", "no_multi"],
+        keys: KeySpec::None,
+        summary: "An internal command used in replication.",
+        arguments: &[
+            arg("replicationid", ArgumentKind::String),
+            arg("offset", ArgumentKind::Integer),
+        ],
+    },
+    CommandSpec {
+        name: "publish",
+        arity: 3,
+        flags: &["pubsub", "loading", "stale", "fast"],
+        keys: KeySpec::None,
+        summary: "Posts a message to a channel.",
+        arguments: &[
+            arg("channel", ArgumentKind::String),
+            arg("message", ArgumentKind::String),
+        ],
+    },
+    CommandSpec {
+        name: "pubsub",
+        arity: -2,
+        flags: &["pubsub", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "A container for Pub/Sub commands.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "punsubscribe",
+        arity: -1,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "Stops listening to messages published to channels that match one or more patterns.",
+        arguments: &[arg("pattern", ArgumentKind::Pattern).optional().multiple()],
+    },
+    CommandSpec {
+        name: "quit",
+        arity: -1,
+        flags: &["noscript", "loading", "stale", "fast", "no_auth"],
+        keys: KeySpec::None,
+        summary: "Closes the connection.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "rename",
+        arity: 3,
+        flags: &["write"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 2,
+            step: 1,
+        },
+        summary: "Renames a key and overwrites the destination.",
+        arguments: &[
+            arg("key", ArgumentKind::Key),
+            arg("newkey", ArgumentKind::Key),
+        ],
+    },
+    CommandSpec {
+        name: "renamenx",
+        arity: 3,
+        flags: &["write", "fast"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 2,
+            step: 1,
+        },
+        summary: "Renames a key only when the target key name doesn't exist.",
+        arguments: &[
+            arg("key", ArgumentKind::Key),
+            arg("newkey", ArgumentKind::Key),
+        ],
+    },
+    CommandSpec {
+        name: "replicaof",
+        arity: 3,
+        flags: &["admin", "noscript", "stale", "no_async_loading"],
+        keys: KeySpec::None,
+        summary: "Configures a server as replica of another, or promotes it to a master.",
+        arguments: &[arg(
+            "args",
+            ArgumentKind::OneOf(&[
+                arg(
+                    "host-port",
+                    ArgumentKind::Block(&[
+                        arg("host", ArgumentKind::String),
+                        arg("port", ArgumentKind::Integer),
+                    ]),
+                ),
+                arg(
+                    "no-one",
+                    ArgumentKind::Block(&[
+                        arg("no", ArgumentKind::PureToken).token("NO"),
+                        arg("one", ArgumentKind::PureToken).token("ONE"),
+                    ]),
+                ),
+            ]),
+        )],
+    },
+    CommandSpec {
+        name: "reset",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast", "no_auth"],
+        keys: KeySpec::None,
+        summary: "Resets the connection.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "restore",
+        arity: -4,
+        flags: &["write", "denyoom"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 1,
+            step: 1,
+        },
+        summary: "Creates a key from the serialized representation of a value.",
+        arguments: &[
+            arg("key", ArgumentKind::Key),
+            arg("ttl", ArgumentKind::Integer),
+            arg("serialized-value", ArgumentKind::String),
+            arg("replace", ArgumentKind::PureToken)
+                .token("REPLACE")
+                .optional(),
+            arg("absttl", ArgumentKind::PureToken)
+                .token("ABSTTL")
+                .optional(),
+        ],
+    },
+    CommandSpec {
+        name: "save",
+        arity: 1,
+        flags: &["admin", "noscript", "noasync"],
+        keys: KeySpec::None,
+        summary: "Synchronously saves the database(s) to disk.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "scan",
+        arity: -2,
+        flags: &["readonly"],
+        keys: KeySpec::None,
+        summary: "Iterates over the key names in the database.",
+        arguments: &[
+            arg("cursor", ArgumentKind::Integer),
+            arg("pattern", ArgumentKind::Pattern)
+                .token("MATCH")
+                .optional(),
+            arg("count", ArgumentKind::Integer)
+                .token("COUNT")
+                .optional(),
+        ],
+    },
+    CommandSpec {
+        name: "select",
+        arity: 2,
+        flags: &["loading", "stale", "fast"],
+        keys: KeySpec::None,
+        summary: "Changes the selected database.",
+        arguments: &[arg("index", ArgumentKind::Integer)],
+    },
+    CommandSpec {
+        name: "set",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 1,
+            step: 1,
+        },
+        summary: "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist.",
+        arguments: &[
+            arg("key", ArgumentKind::Key),
+            arg("value", ArgumentKind::String),
+            arg(
+                "expiration",
+                ArgumentKind::OneOf(&[
+                    arg("seconds", ArgumentKind::Integer).token("EX"),
+                    arg("milliseconds", ArgumentKind::Integer).token("PX"),
+                    arg("unix-time-seconds", ArgumentKind::Integer).token("EXAT"),
+                    arg("unix-time-milliseconds", ArgumentKind::Integer).token("PXAT"),
+                ]),
+            )
+            .optional(),
+        ],
+    },
+    CommandSpec {
+        name: "slowlog",
+        arity: -2,
+        flags: &["admin", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "A container for slow log commands.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "spublish",
+        arity: 3,
+        flags: &["pubsub", "loading", "stale", "fast"],
+        keys: KeySpec::None,
+        summary: "Post a message to a shard channel.",
+        arguments: &[
+            arg("shardchannel", ArgumentKind::String),
+            arg("message", ArgumentKind::String),
+        ],
+    },
+    CommandSpec {
+        name: "ssubscribe",
+        arity: -2,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "Listens for messages published to shard channels.",
+        arguments: &[arg("shardchannel", ArgumentKind::String).multiple()],
+    },
+    CommandSpec {
+        name: "subscribe",
+        arity: -2,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "Listens for messages published to channels.",
+        arguments: &[arg("channel", ArgumentKind::String).multiple()],
+    },
+    CommandSpec {
+        name: "sunsubscribe",
+        arity: -1,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "Stops listening to messages posted to shard channels.",
+        arguments: &[arg("shardchannel", ArgumentKind::String)
+            .optional()
+            .multiple()],
+    },
+    CommandSpec {
+        name: "swapdb",
+        arity: 3,
+        flags: &["write", "fast"],
+        keys: KeySpec::None,
+        summary: "Swaps two Redis databases.",
+        arguments: &[
+            arg("index1", ArgumentKind::Integer),
+            arg("index2", ArgumentKind::Integer),
+        ],
+    },
+    CommandSpec {
+        name: "unsubscribe",
+        arity: -1,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        keys: KeySpec::None,
+        summary: "Stops listening to messages posted to channels.",
+        arguments: &[arg("channel", ArgumentKind::String).optional().multiple()],
+    },
+    CommandSpec {
+        name: "unwatch",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast"],
+        keys: KeySpec::None,
+        summary: "Forgets about watched keys of a transaction.",
+        arguments: &[],
+    },
+    CommandSpec {
+        name: "wait",
+        arity: 3,
+        flags: &[],
+        keys: KeySpec::None,
+        summary: "Blocks until the asynchronous replication of all preceding write commands sent by the connection is completed.",
+        arguments: &[
+            arg("numreplicas", ArgumentKind::Integer),
+            arg("timeout", ArgumentKind::Integer),
+        ],
+    },
+    CommandSpec {
+        name: "waitaof",
+        arity: 4,
+        flags: &[],
+        keys: KeySpec::None,
+        summary: "Blocks until all of the preceding write commands sent by the connection are written to the append-only file of the primary and/or replicas.",
+        arguments: &[
+            arg("numlocal", ArgumentKind::Integer),
+            arg("numreplicas", ArgumentKind::Integer),
+            arg("timeout", ArgumentKind::Integer),
+        ],
+    },
+    CommandSpec {
+        name: "watch",
+        arity: -2,
+        flags: &["noscript", "loading", "stale", "fast"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: -1,
+            step: 1,
+        },
+        summary: "Monitors changes to keys to determine the execution of a transaction.",
+        arguments: &[arg("key", ArgumentKind::Key).multiple()],
+    },
+    CommandSpec {
+        name: "xadd",
+        arity: -5,
+        flags: &["write", "denyoom", "fast"],
+        keys: KeySpec::Range {
+            first: 1,
+            last: 1,
+            step: 1,
+        },
+        summary: "Appends a new message to a stream. Creates the key if it doesn't exist.",
+        arguments: &[
+            arg("key", ArgumentKind::Key),
+            arg(
+                "id-selector",
+                ArgumentKind::OneOf(&[
+                    arg("auto-id", ArgumentKind::PureToken).token("*"),
+                    arg("id", ArgumentKind::String),
+                ]),
+            ),
+            arg(
+                "data",
+                ArgumentKind::Block(&[
+                    arg("field", ArgumentKind::String),
+                    arg("value", ArgumentKind::String),
+                ]),
+            )
+            .multiple(),
+        ],
+    },
+    CommandSpec {
+        name: "xread",
+        arity: -4,
+        flags: &["readonly"],
+        keys: KeySpec::Streams,
+        summary: "Returns messages from multiple streams with IDs greater than the ones requested.",
+        arguments: &[
+            arg("count", ArgumentKind::Integer)
+                .token("COUNT")
+                .optional(),
+            arg(
+                "streams",
+                ArgumentKind::Block(&[
+                    arg("key", ArgumentKind::Key).multiple(),
+                    arg("id", ArgumentKind::String).multiple(),
+                ]),
+            )
+            .token("STREAMS"),
+        ],
+    },
+];
+
+/// The conditions of `EXPIRE` and `PEXPIRE` on the current timeout of the key.
+const EXPIRE_CONDITIONS: &[Argument] = &[
+    arg("nx", ArgumentKind::PureToken).token("NX"),
+    arg("xx", ArgumentKind::PureToken).token("XX"),
+    arg("gt", ArgumentKind::PureToken).token("GT"),
+    arg("lt", ArgumentKind::PureToken).token("LT"),
+];
+
+/// A required argument, appearing once.
+const fn arg(name: &'static str, kind: ArgumentKind) -> Argument {
+    Argument {
+        name,
+        kind,
+        token: None,
+        optional: false,
+        multiple: false,
+    }
+}
+
+impl Argument {
+    const fn token(self, token: &'static str) -> Argument {
+        Argument {
+            token: Some(token),
+            ..self
+        }
+    }
+
+    const fn optional(self) -> Argument {
+        Argument {
+            optional: true,
+            ..self
+        }
+    }
+
+    const fn multiple(self) -> Argument {
+        Argument {
+            multiple: true,
+            ..self
+        }
+    }
+
+    /// Describe the argument as a map, like Redis does in `COMMAND DOCS`.
+    fn to_frame(&self) -> Frame {
+        let kind = match self.kind {
+            ArgumentKind::Key => "key",
+            ArgumentKind::String => "string",
+            ArgumentKind::Integer => "integer",
+            ArgumentKind::Pattern => "pattern",
+            ArgumentKind::PureToken => "pure-token",
+            ArgumentKind::OneOf(_) => "oneof",
+            ArgumentKind::Block(_) => "block",
+        };
+
+        let mut entries = vec![(bulk("name"), bulk(self.name)), (bulk("type"), bulk(kind))];
+        if let Some(token) = self.token {
+            entries.push((bulk("token"), bulk(token)));
+        }
+
+        let mut flags = vec![];
+        if self.optional {
+            flags.push(Frame::Simple("optional".to_string()));
+        }
+        if self.multiple {
+            flags.push(Frame::Simple("multiple".to_string()));
+        }
+        if !flags.is_empty() {
+            entries.push((bulk("flags"), Frame::Set(flags)));
+        }
+
+        if let ArgumentKind::OneOf(arguments) | ArgumentKind::Block(arguments) = self.kind {
+            let arguments = arguments.iter().map(Argument::to_frame).collect();
+            entries.push((bulk("arguments"), Frame::Array(arguments)));
+        }
+
+        Frame::Map(entries)
+    }
+}
+
+fn bulk(s: &'static str) -> Frame {
+    Frame::Bulk(Bytes::from_static(s.as_bytes()))
+}
+
+impl CommandCommand {
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 1 entry.
+    ///
+    /// ```text
+    /// COMMAND
+    /// COMMAND COUNT
+    /// COMMAND DOCS [command ...]
+    /// COMMAND GETKEYS command [arg ...]
+    /// COMMAND HELP
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<CommandCommand> {
+        let subcommand = match parse.next_string() {
+            Ok(subcommand) => subcommand,
+            Err(ParseError::EndOfStream) => return Ok(CommandCommand::List),
+            Err(err) => return Err(err.into()),
+        };
+
+        match subcommand.to_uppercase().as_str() {
+            "COUNT" => Ok(CommandCommand::Count),
+            "DOCS" => {
+                let mut commands = vec![];
+                loop {
+                    match parse.next_string() {
+                        Ok(command) => commands.push(command.to_lowercase()),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Ok(CommandCommand::Docs { commands })
+            }
+            "GETKEYS" => {
+                let mut command = vec![parse.next_string()?];
+                loop {
+                    match parse.next_string() {
+                        Ok(arg) => command.push(arg),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Ok(CommandCommand::GetKeys { command })
+            }
+            "HELP" => Ok(CommandCommand::Help),
+            other => Err(format!("unknown `COMMAND` subcommand `{other}`").into()),
+        }
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match self {
+            CommandCommand::List => COMMANDS
+                .iter()
+                .map(CommandSpec::to_frame)
+                .collect::<Vec<_>>()
+                .into(),
+            CommandCommand::Count => Frame::unsigned(COMMANDS.len() as u64),
+            // Unknown commands are left out.
+            CommandCommand::Docs { commands } => Frame::Map(
+                COMMANDS
+                    .iter()
+                    .filter(|spec| commands.is_empty() || commands.iter().any(|c| c == spec.name))
+                    .map(|spec| (bulk(spec.name), spec.docs_frame()))
+                    .collect(),
+            ),
+            CommandCommand::GetKeys { command } => match getkeys(&command) {
+                Ok(keys) => keys
+                    .into_iter()
+                    .map(|key| Frame::Bulk(Bytes::from(key.clone())))
+                    .collect::<Vec<_>>()
+                    .into(),
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            CommandCommand::Help => help_frame(
+                "COMMAND",
+                &[
+                    "(no subcommand)",
+                    "    Return details about all commands.",
+                    "COUNT",
+                    "    Return the total number of commands in this server.",
+                    "DOCS [<command-name> ...]",
+                    "    Return documentation details about multiple commands.",
+                    "    If no command names are given, documentation details for all",
+                    "    commands are returned.",
+                    "GETKEYS <full-command>",
+                    "    Return the keys from a full command.",
+                ],
+            ),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl CommandSpec {
+    /// The metadata of the command `name`, in lowercase.
+    pub(crate) fn find(name: &str) -> Option<&'static CommandSpec> {
+        COMMANDS.iter().find(|spec| spec.name == name)
+    }
+
+    /// Returns `true` if `argc` arguments, including the command name, satisfy the
+    /// arity of the command.
+    pub(crate) fn accepts(&self, argc: usize) -> bool {
+        let arity = usize::try_from(self.arity.unsigned_abs()).unwrap_or(usize::MAX);
+        if self.arity < 0 {
+            argc >= arity
+        } else {
+            argc == arity
+        }
+    }
+
+    /// The positions of the keys among `args`, including the command name. An empty
+    /// list stands for arguments not matching the key specification.
+    fn key_positions(&self, args: &[String]) -> Vec<usize> {
+        let len = args.len();
+        match self.keys {
+            KeySpec::None => vec![],
+            KeySpec::Range { first, last, step } => {
+                let last = if last < 0 {
+                    len.checked_sub(usize::try_from(last.unsigned_abs()).unwrap_or(usize::MAX))
+                } else {
+                    usize::try_from(last).ok()
+                };
+                match last {
+                    Some(last) => (first..=last.min(len - 1)).step_by(step).collect(),
+                    None => vec![],
+                }
+            }
+            KeySpec::Streams => {
+                let Some(streams) = args
+                    .iter()
+                    .position(|arg| arg.eq_ignore_ascii_case("streams"))
+                else {
+                    return vec![];
+                };
+                // As many keys as IDs.
+                let rest = len - streams - 1;
+                if rest % 2 != 0 {
+                    return vec![];
+                }
+                (streams + 1..=streams + rest / 2).collect()
+            }
+        }
+    }
+
+    /// Describe the command as `[name, arity, {flag ...}]`.
+    fn to_frame(&self) -> Frame {
+        let flags = self
+            .flags
+            .iter()
+            .map(|flag| Frame::Simple((*flag).to_string()))
+            .collect();
+
+        Frame::Array(vec![
+            bulk(self.name),
+            Frame::Integer(self.arity),
+            Frame::Set(flags),
+        ])
+    }
+
+    /// Describe the command as `{summary, arguments}`.
+    fn docs_frame(&self) -> Frame {
+        let arguments = self.arguments.iter().map(Argument::to_frame).collect();
+
+        Frame::Map(vec![
+            (bulk("summary"), bulk(self.summary)),
+            (bulk("arguments"), Frame::Array(arguments)),
+        ])
+    }
+}
+
+/// The keys of the invocation `command` of a command, including its name.
+fn getkeys(command: &[String]) -> Result<Vec<&String>, &'static str> {
+    let Some(spec) = CommandSpec::find(&command[0].to_lowercase()) else {
+        return Err("ERR Invalid command specified");
+    };
+    if !spec.accepts(command.len()) {
+        return Err("ERR Invalid number of arguments specified for command");
+    }
+    if matches!(spec.keys, KeySpec::None) {
+        return Err("ERR The command has no key arguments");
+    }
+
+    let positions = spec.key_positions(command);
+    if positions.is_empty() {
+        return Err("ERR Invalid arguments specified for command");
+    }
+
+    Ok(positions.into_iter().map(|i| &command[i]).collect())
+}
+
+impl Protocol for CommandCommand {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("command".as_bytes()));
+        match self {
+            CommandCommand::List => {}
+            CommandCommand::Count => frame.push_bulk(Bytes::from("count".as_bytes())),
+            CommandCommand::Docs { commands } => {
+                frame.push_bulk(Bytes::from("docs".as_bytes()));
+                for command in commands {
+                    frame.push_bulk(Bytes::from(command.into_bytes()));
+                }
+            }
+            CommandCommand::GetKeys { command } => {
+                frame.push_bulk(Bytes::from("getkeys".as_bytes()));
+                for arg in command {
+                    frame.push_bulk(Bytes::from(arg.into_bytes()));
+                }
+            }
+            CommandCommand::Help => frame.push_bulk(Bytes::from("help".as_bytes())),
+        }
+
+        frame.into()
+    }
+}
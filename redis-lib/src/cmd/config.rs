@@ -0,0 +1,104 @@
+use crate::cmd::{Parse, Protocol, help_frame};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Inspect and change the parameters of the server.
+///
+/// # Subcommands
+///
+/// * GET `pattern` -- Return the name and value of the parameters whose name
+///   matches the glob-style `pattern`.
+/// * SET `parameter` `value` -- Change the value of the parameter. The change
+///   takes effect immediately.
+#[derive(Debug)]
+pub enum ConfigCommand {
+    Get { pattern: String },
+    Set { name: String, value: String },
+    Help,
+}
+
+impl ConfigCommand {
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// CONFIG GET pattern
+    /// CONFIG SET parameter value
+    /// CONFIG HELP
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ConfigCommand> {
+        match parse.next_string()?.to_uppercase().as_str() {
+            // Parameter names are case-insensitive.
+            "GET" => Ok(ConfigCommand::Get {
+                pattern: parse.next_string()?.to_lowercase(),
+            }),
+            "SET" => Ok(ConfigCommand::Set {
+                name: parse.next_string()?.to_lowercase(),
+                value: parse.next_string()?,
+            }),
+            "HELP" => Ok(ConfigCommand::Help),
+            other => Err(format!("unknown `CONFIG` subcommand `{other}`").into()),
+        }
+    }
+
+    /// Apply the subcommand to the parameters of the server holding `db`.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match self {
+            ConfigCommand::Get { pattern } => Frame::Map(
+                db.settings()
+                    .get(&pattern)
+                    .into_iter()
+                    .map(|(name, value)| {
+                        (
+                            Frame::Bulk(Bytes::from_static(name.as_bytes())),
+                            Frame::Bulk(Bytes::from(value)),
+                        )
+                    })
+                    .collect(),
+            ),
+            ConfigCommand::Set { name, value } => match db.settings().set(&name, &value) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err) => Frame::Error(err),
+            },
+            ConfigCommand::Help => help_frame(
+                "CONFIG",
+                &[
+                    "GET <pattern>",
+                    "    Return parameters matching the glob-like <pattern> and their values.",
+                    "SET <directive> <value>",
+                    "    Set the configuration <directive> to <value>.",
+                ],
+            ),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for ConfigCommand {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("config".as_bytes()));
+        match self {
+            ConfigCommand::Get { pattern } => {
+                frame.push_bulk(Bytes::from("get".as_bytes()));
+                frame.push_bulk(Bytes::from(pattern.into_bytes()));
+            }
+            ConfigCommand::Set { name, value } => {
+                frame.push_bulk(Bytes::from("set".as_bytes()));
+                frame.push_bulk(Bytes::from(name.into_bytes()));
+                frame.push_bulk(Bytes::from(value.into_bytes()));
+            }
+            ConfigCommand::Help => frame.push_bulk(Bytes::from("help".as_bytes())),
+        }
+
+        frame.into()
+    }
+}
@@ -0,0 +1,129 @@
+use crate::cmd::{Parse, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::time;
+use tracing::{debug, error, instrument};
+
+/// Commands useful to debug the server, or to test clients against it.
+///
+/// # Subcommands
+///
+/// * SLEEP `seconds` -- Stop processing the commands of the connection for the
+///   given, possibly fractional, number of seconds.
+/// * OBJECT `key` -- Describe how the value at `key` is stored.
+/// * RELOAD -- Write a snapshot of the databases to disk, like `SAVE`, then replace
+///   the databases with the snapshot.
+/// * SET-ACTIVE-EXPIRE `0|1` -- Disable or enable the purge of expired keys in the
+///   background. Expired keys are still removed when read.
+#[derive(Debug)]
+pub enum DebugCommand {
+    Sleep { duration: Duration },
+    Object { key: String },
+    Reload,
+    SetActiveExpire { enabled: bool },
+}
+
+impl DebugCommand {
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// DEBUG SLEEP seconds
+    /// DEBUG OBJECT key
+    /// DEBUG RELOAD
+    /// DEBUG SET-ACTIVE-EXPIRE 0|1
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<DebugCommand> {
+        match parse.next_string()?.to_uppercase().as_str() {
+            "SLEEP" => {
+                let duration = parse
+                    .next_string()?
+                    .parse()
+                    .ok()
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                    .ok_or("ERR value is not a valid float")?;
+
+                Ok(DebugCommand::Sleep { duration })
+            }
+            "OBJECT" => Ok(DebugCommand::Object {
+                key: parse.next_string()?,
+            }),
+            "RELOAD" => Ok(DebugCommand::Reload),
+            "SET-ACTIVE-EXPIRE" => {
+                let enabled = match parse.next_string()?.as_str() {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err("ERR value is out of range, must be 0 or 1".into()),
+                };
+
+                Ok(DebugCommand::SetActiveExpire { enabled })
+            }
+            other => Err(format!("unknown `DEBUG` subcommand `{other}`").into()),
+        }
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match self {
+            DebugCommand::Sleep { duration } => {
+                time::sleep(duration).await;
+                Frame::Simple("OK".to_string())
+            }
+            DebugCommand::Object { key } => match db.debug_object(&key) {
+                Some((refcount, encoding, serialized_len)) => Frame::Bulk(Bytes::from(format!(
+                    "Value at:0x0 refcount:{refcount} encoding:{encoding} serializedlength:{serialized_len}"
+                ))),
+                None => Frame::Error("ERR no such key".to_string()),
+            },
+            DebugCommand::Reload => {
+                let path = db.settings().dump_path();
+                match db.dump_to(&path).and_then(|()| db.load_from(&path)) {
+                    Ok(()) => Frame::Simple("OK".to_string()),
+                    Err(err) => {
+                        error!(cause = %err, ?path, "failed to reload snapshot");
+                        Frame::Error(format!("ERR {err}"))
+                    }
+                }
+            }
+            DebugCommand::SetActiveExpire { enabled } => {
+                db.set_active_expire(enabled);
+                Frame::Simple("OK".to_string())
+            }
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for DebugCommand {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("debug".as_bytes()));
+        match self {
+            DebugCommand::Sleep { duration } => {
+                frame.push_bulk(Bytes::from("sleep".as_bytes()));
+                let seconds = duration.as_secs_f64().to_string();
+                frame.push_bulk(Bytes::from(seconds.into_bytes()));
+            }
+            DebugCommand::Object { key } => {
+                frame.push_bulk(Bytes::from("object".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+            }
+            DebugCommand::Reload => frame.push_bulk(Bytes::from("reload".as_bytes())),
+            DebugCommand::SetActiveExpire { enabled } => {
+                frame.push_bulk(Bytes::from("set-active-expire".as_bytes()));
+                let enabled = if enabled { "1" } else { "0" };
+                frame.push_bulk(Bytes::from(enabled.as_bytes()));
+            }
+        }
+
+        frame.into()
+    }
+}
@@ -0,0 +1,63 @@
+use crate::cmd::Protocol;
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame, Parse};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Serialize the value stored at key, so that it can be recreated with `RESTORE`.
+///
+/// The serialization holds the value only, not the key nor its expiration, and is
+/// specific to this server. The reply is nil if the key does not exist.
+#[derive(Debug)]
+pub struct Dump {
+    key: String,
+}
+
+impl Dump {
+    pub fn new(key: impl ToString) -> Dump {
+        Dump {
+            key: key.to_string(),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// DUMP key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Dump> {
+        let key = parse.next_string()?;
+
+        Ok(Dump { key })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match db.dump(&self.key) {
+            Some(payload) => Frame::Bulk(Bytes::from(payload)),
+            None => Frame::Null,
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for Dump {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("dump".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+
+        frame.into()
+    }
+}
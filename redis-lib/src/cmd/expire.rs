@@ -0,0 +1,296 @@
+use crate::cmd::{Parse, ParseError, Protocol};
+use crate::db::instant_at;
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use std::time::UNIX_EPOCH;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, instrument};
+
+/// Set a timeout on a key, in seconds, after which it is deleted. A timeout which
+/// is not positive deletes the key at once.
+///
+/// The reply is `1` if the timeout was set, or `0` if the key does not exist or the
+/// condition is not met. RESP3 clients receive a boolean instead.
+#[derive(Debug)]
+pub struct Expire {
+    key: String,
+    seconds: i64,
+    condition: Option<ExpireCondition>,
+}
+
+/// Like `Expire`, with a timeout in milliseconds.
+#[derive(Debug)]
+pub struct PExpire {
+    key: String,
+    milliseconds: i64,
+    condition: Option<ExpireCondition>,
+}
+
+/// Like `Expire`, with the Unix time at which the key expires, in milliseconds. A
+/// time in the past deletes the key at once.
+///
+/// Write commands are propagated with it, rather than with a timeout, so that the
+/// key expires at the same time once replayed.
+#[derive(Debug)]
+pub struct PExpireAt {
+    key: String,
+    timestamp: i64,
+    condition: Option<ExpireCondition>,
+}
+
+/// The condition on the current timeout of a key for `EXPIRE` and `PEXPIRE` to
+/// set the new one. A key without timeout is considered to have an infinite one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    /// Only if the key has no timeout.
+    Nx,
+    /// Only if the key has a timeout.
+    Xx,
+    /// Only if the new timeout is greater than the current one.
+    Gt,
+    /// Only if the new timeout is less than the current one.
+    Lt,
+}
+
+impl Expire {
+    pub fn new(key: impl ToString, seconds: i64, condition: Option<ExpireCondition>) -> Expire {
+        Expire {
+            key: key.to_string(),
+            seconds,
+            condition,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing three or four entries.
+    ///
+    /// ```text
+    /// EXPIRE key seconds [NX | XX | GT | LT]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Expire> {
+        let key = parse.next_string()?;
+        let seconds = parse.next_signed_int()?;
+        let condition = ExpireCondition::parse_frames(parse)?;
+
+        Ok(Expire {
+            key,
+            seconds,
+            condition,
+        })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match self.seconds.checked_mul(1000).and_then(deadline) {
+            Some(when) => Frame::Boolean(db.expire(&self.key, when, self.condition)),
+            None => Frame::Error("ERR invalid expire time in 'expire' command".to_string()),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for Expire {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("expire".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.seconds.to_string()));
+        if let Some(condition) = self.condition {
+            frame.push_bulk(Bytes::from(condition.as_str()));
+        }
+
+        frame.into()
+    }
+}
+
+impl PExpire {
+    pub fn new(
+        key: impl ToString,
+        milliseconds: i64,
+        condition: Option<ExpireCondition>,
+    ) -> PExpire {
+        PExpire {
+            key: key.to_string(),
+            milliseconds,
+            condition,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing three or four entries.
+    ///
+    /// ```text
+    /// PEXPIRE key milliseconds [NX | XX | GT | LT]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PExpire> {
+        let key = parse.next_string()?;
+        let milliseconds = parse.next_signed_int()?;
+        let condition = ExpireCondition::parse_frames(parse)?;
+
+        Ok(PExpire {
+            key,
+            milliseconds,
+            condition,
+        })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match deadline(self.milliseconds) {
+            Some(when) => Frame::Boolean(db.expire(&self.key, when, self.condition)),
+            None => Frame::Error("ERR invalid expire time in 'pexpire' command".to_string()),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for PExpire {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("pexpire".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.milliseconds.to_string()));
+        if let Some(condition) = self.condition {
+            frame.push_bulk(Bytes::from(condition.as_str()));
+        }
+
+        frame.into()
+    }
+}
+
+impl PExpireAt {
+    pub fn new(
+        key: impl ToString,
+        timestamp: i64,
+        condition: Option<ExpireCondition>,
+    ) -> PExpireAt {
+        PExpireAt {
+            key: key.to_string(),
+            timestamp,
+            condition,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing three or four entries.
+    ///
+    /// ```text
+    /// PEXPIREAT key unix-time-milliseconds [NX | XX | GT | LT]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PExpireAt> {
+        let key = parse.next_string()?;
+        let timestamp = parse.next_signed_int()?;
+        let condition = ExpireCondition::parse_frames(parse)?;
+
+        Ok(PExpireAt {
+            key,
+            timestamp,
+            condition,
+        })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // A time before the Unix epoch is as past as the epoch itself.
+        let unix = Duration::from_millis(u64::try_from(self.timestamp).unwrap_or(0));
+        let resp_frame = match UNIX_EPOCH.checked_add(unix).and_then(instant_at) {
+            Some(when) => Frame::Boolean(db.expire(&self.key, when, self.condition)),
+            None => Frame::Error("ERR invalid expire time in 'pexpireat' command".to_string()),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for PExpireAt {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("pexpireat".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.timestamp.to_string()));
+        if let Some(condition) = self.condition {
+            frame.push_bulk(Bytes::from(condition.as_str()));
+        }
+
+        frame.into()
+    }
+}
+
+impl ExpireCondition {
+    /// Parse the condition following the timeout, if any.
+    fn parse_frames(parse: &mut Parse) -> crate::Result<Option<ExpireCondition>> {
+        let condition = match parse.next_string() {
+            Ok(s) => s,
+            Err(ParseError::EndOfStream) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        match condition.to_uppercase().as_str() {
+            "NX" => Ok(Some(ExpireCondition::Nx)),
+            "XX" => Ok(Some(ExpireCondition::Xx)),
+            "GT" => Ok(Some(ExpireCondition::Gt)),
+            "LT" => Ok(Some(ExpireCondition::Lt)),
+            _ => Err(format!("Unsupported option {condition}").into()),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ExpireCondition::Nx => "NX",
+            ExpireCondition::Xx => "XX",
+            ExpireCondition::Gt => "GT",
+            ExpireCondition::Lt => "LT",
+        }
+    }
+
+    /// Returns `true` if a key expiring at `current`, `None` for never, may be set to
+    /// expire at `when` instead.
+    pub(crate) fn allows(self, current: Option<Instant>, when: Instant) -> bool {
+        match (self, current) {
+            (ExpireCondition::Nx, current) => current.is_none(),
+            (ExpireCondition::Xx, current) => current.is_some(),
+            (ExpireCondition::Gt, Some(current)) => when > current,
+            (ExpireCondition::Lt, Some(current)) => when < current,
+            // Never expiring is later than any timeout.
+            (ExpireCondition::Gt, None) => false,
+            (ExpireCondition::Lt, None) => true,
+        }
+    }
+}
+
+/// When a key given `milliseconds` to live expires, now if it is not positive.
+/// Returns `None` if it is too far in the future to be represented.
+fn deadline(milliseconds: i64) -> Option<Instant> {
+    let timeout = Duration::from_millis(u64::try_from(milliseconds).unwrap_or(0));
+    Instant::now().checked_add(timeout)
+}
@@ -44,11 +44,11 @@ impl Get {
     /// [apply]: crate::cmd::Command::apply
     #[instrument(skip(self, db, dst))]
     pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
-        let resp_frame = if let Some(value) = db.get(&self.key) {
-            Frame::Bulk(value)
-        } else {
+        let resp_frame = match db.get(&self.key) {
+            Ok(Some(value)) => Frame::Bulk(value),
             // there is no value.
-            Frame::Null
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
         };
 
         debug!(?resp_frame);
@@ -0,0 +1,122 @@
+use crate::cmd::{Parse, ParseError, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, instrument};
+
+/// Get the value of key, and optionally set its expiration at the same time.
+///
+/// If the key does not exist the special value nil is returned. An error is
+/// returned if the value stored at key is not a string.
+///
+/// # Options
+///
+/// * EX `seconds` -- Set the specified expire time, in seconds.
+/// * PX `milliseconds` -- Set the specified expire time, in milliseconds.
+/// * EXAT `timestamp` -- Set the Unix time at which the key expires, in seconds.
+/// * PXAT `timestamp` -- Set the Unix time at which the key expires, in milliseconds.
+/// * PERSIST -- Remove the expire time of the key.
+#[derive(Debug)]
+pub struct GetEx {
+    key: String,
+    expiration: Option<Expiration>,
+}
+
+/// The expiration set by `GETEX` on the key it reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiration {
+    /// Expire once the duration elapsed, `EX` and `PX`.
+    After(Duration),
+    /// Expire at the given time, `EXAT` and `PXAT`. A time in the past deletes the
+    /// key once read.
+    At(SystemTime),
+    /// Never expire, `PERSIST`.
+    Persist,
+}
+
+impl GetEx {
+    pub fn new(key: impl ToString, expiration: Option<Expiration>) -> GetEx {
+        GetEx {
+            key: key.to_string(),
+            expiration,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// GETEX key [EX seconds | PX milliseconds | EXAT timestamp | PXAT timestamp | PERSIST]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetEx> {
+        let key = parse.next_string()?;
+
+        let option = match parse.next_string() {
+            Ok(option) => option,
+            Err(ParseError::EndOfStream) => return Ok(GetEx::new(key, None)),
+            Err(err) => return Err(err.into()),
+        };
+        let expiration = match option.to_uppercase().as_str() {
+            "EX" => Expiration::After(Duration::from_secs(parse.next_int()?)),
+            "PX" => Expiration::After(Duration::from_millis(parse.next_int()?)),
+            "EXAT" => Expiration::At(unix_time(Duration::from_secs(parse.next_int()?))?),
+            "PXAT" => Expiration::At(unix_time(Duration::from_millis(parse.next_int()?))?),
+            "PERSIST" => Expiration::Persist,
+            _ => return Err(format!("unsupported `GETEX` option `{option}`").into()),
+        };
+
+        Ok(GetEx::new(key, Some(expiration)))
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match db.getex(&self.key, self.expiration) {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for GetEx {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("getex".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        match self.expiration {
+            Some(Expiration::After(timeout)) => {
+                frame.push_bulk(Bytes::from("px".as_bytes()));
+                frame.push_int(u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX));
+            }
+            Some(Expiration::At(time)) => {
+                // A time before the Unix epoch is as past as the epoch itself.
+                let unix = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+                frame.push_bulk(Bytes::from("pxat".as_bytes()));
+                frame.push_int(u64::try_from(unix.as_millis()).unwrap_or(u64::MAX));
+            }
+            Some(Expiration::Persist) => frame.push_bulk(Bytes::from("persist".as_bytes())),
+            None => {}
+        }
+
+        frame.into()
+    }
+}
+
+/// The time `unix` after the Unix epoch.
+fn unix_time(unix: Duration) -> crate::Result<SystemTime> {
+    UNIX_EPOCH
+        .checked_add(unix)
+        .ok_or_else(|| "ERR invalid expire time in 'getex' command".into())
+}
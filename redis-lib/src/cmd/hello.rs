@@ -0,0 +1,139 @@
+use crate::cmd::{Parse, ParseError, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Frame};
+use bytes::Bytes;
+use std::fmt;
+use tracing::{debug, instrument};
+
+/// Switch the connection to the given protocol version, optionally authenticating
+/// at the same time.
+///
+/// The reply is a map of server and connection properties. Without a protocol
+/// version, the connection keeps its current one.
+pub struct Hello {
+    protover: Option<u64>,
+    /// `(username, password)`
+    auth: Option<(String, String)>,
+}
+
+// Keeps the password out of the logs.
+impl fmt::Debug for Hello {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hello")
+            .field("protover", &self.protover)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Hello {
+    pub fn new(protover: Option<u64>, auth: Option<(String, String)>) -> Hello {
+        Hello { protover, auth }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 1 entry.
+    ///
+    /// ```text
+    /// HELLO [protover [AUTH username password]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Hello> {
+        let protover = match parse.next_int() {
+            Ok(protover) => Some(protover),
+            Err(ParseError::EndOfStream) => return Ok(Hello::new(None, None)),
+            Err(ParseError::InvalidInteger) => {
+                let msg = "ERR Protocol version is not an integer or out of range";
+                return Err(ParseError::Reply(msg.to_string()).into());
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let auth = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "AUTH" => {
+                Some((parse.next_string()?, parse.next_string()?))
+            }
+            Ok(s) => {
+                let msg = format!("ERR Syntax error in HELLO option '{s}'");
+                return Err(ParseError::Reply(msg).into());
+            }
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Hello { protover, auth })
+    }
+
+    /// Negotiate the protocol of `dst`, the connection with the given `id`.
+    ///
+    /// `required` is the password configured on the server, and `authenticated`
+    /// records whether the connection is authenticated.
+    #[instrument(skip(self, required, authenticated, dst))]
+    pub(crate) async fn apply(
+        self,
+        required: Option<&str>,
+        authenticated: &mut bool,
+        id: u64,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let protocol = match self.protover {
+            None => Ok(dst.protocol()),
+            Some(2) => Ok(2),
+            Some(3) => Ok(3),
+            Some(_) => Err("NOPROTO unsupported protocol version"),
+        };
+
+        let resp_frame = match (protocol, self.auth) {
+            (Err(err), _) => Frame::Error(err.to_string()),
+            (Ok(_), Some((username, password)))
+                if username != "default" || required.is_some_and(|r| r != password) =>
+            {
+                Frame::Error(
+                    "WRONGPASS invalid username-password pair or user is disabled.".to_string(),
+                )
+            }
+            (Ok(_), None) if !*authenticated => Frame::Error(
+                "NOAUTH HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client and select the RESP protocol version at the same time"
+                    .to_string(),
+            ),
+            (Ok(protocol), _) => {
+                *authenticated = true;
+                dst.set_protocol(protocol);
+
+                Frame::Map(vec![
+                    (bulk("server"), bulk("redis")),
+                    (bulk("version"), bulk(env!("CARGO_PKG_VERSION"))),
+                    (bulk("proto"), Frame::Integer(protocol.into())),
+                    (bulk("id"), Frame::unsigned(id)),
+                    (bulk("mode"), bulk("standalone")),
+                    (bulk("role"), bulk("master")),
+                ])
+            }
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+fn bulk(s: &'static str) -> Frame {
+    Frame::Bulk(Bytes::from_static(s.as_bytes()))
+}
+
+impl Protocol for Hello {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("hello".as_bytes()));
+        if let Some(protover) = self.protover {
+            frame.push_int(protover);
+        }
+        if let Some((username, password)) = self.auth {
+            frame.push_bulk(Bytes::from("auth".as_bytes()));
+            frame.push_bulk(Bytes::from(username.into_bytes()));
+            frame.push_bulk(Bytes::from(password.into_bytes()));
+        }
+
+        frame.into()
+    }
+}
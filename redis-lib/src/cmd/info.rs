@@ -0,0 +1,153 @@
+use crate::cmd::{Parse, ParseError, Protocol};
+use crate::frame::PushFrame;
+use crate::{ClientRegistry, Connection, Db, Frame, server};
+use bytes::Bytes;
+use std::fmt::Write;
+use tracing::{debug, instrument};
+
+/// Return information and statistics about the server.
+///
+/// The reply is a text verbatim string of sections, each starting with a
+/// `# Section` header followed by `field:value` lines. RESP2 clients receive a bulk
+/// string. The supported sections are `server`, `clients`, `memory`, `stats`,
+/// `replication` and `keyspace`.
+/// Without a section, all of them are returned.
+#[derive(Debug, Default)]
+pub struct Info {
+    section: Option<String>,
+}
+
+impl Info {
+    pub fn new(section: Option<String>) -> Info {
+        Info { section }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 1 entry.
+    ///
+    /// ```text
+    /// INFO [section]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Info> {
+        match parse.next_string() {
+            Ok(section) => Ok(Info::new(Some(section.to_lowercase()))),
+            Err(ParseError::EndOfStream) => Ok(Info::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Describe the server holding `db`, whose connections are in `clients`.
+    #[instrument(skip(self, db, clients, dst))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        clients: &ClientRegistry,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let all = matches!(
+            self.section.as_deref(),
+            None | Some("all" | "default" | "everything")
+        );
+        let wants = |section: &str| all || self.section.as_deref() == Some(section);
+
+        let mut info = String::new();
+        if wants("server") {
+            let uptime = db.uptime().as_secs();
+            let _ = write!(
+                info,
+                "# Server\r\nredis_version:{}\r\nredis_mode:standalone\r\nprocess_id:{}\r\nuptime_in_seconds:{}\r\nuptime_in_days:{}\r\n",
+                env!("CARGO_PKG_VERSION"),
+                std::process::id(),
+                uptime,
+                uptime / (24 * 60 * 60),
+            );
+        }
+        if wants("clients") {
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+            let _ = write!(info, "# Clients\r\nconnected_clients:{}\r\n", clients.len());
+        }
+        if wants("memory") {
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+            let (maxmemory, policy) = db.settings().maxmemory();
+            let _ = write!(
+                info,
+                "# Memory\r\nused_memory:{}\r\nmaxmemory:{}\r\nmaxmemory_policy:{policy}\r\n",
+                db.used_memory(),
+                maxmemory.unwrap_or(0),
+            );
+        }
+        if wants("stats") {
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+            let metrics = server::metrics();
+            let _ = write!(
+                info,
+                "# Stats\r\ntotal_connections_received:{}\r\ntotal_commands_processed:{}\r\ntotal_net_input_bytes:{}\r\ntotal_net_output_bytes:{}\r\nevicted_keys:{}\r\nkeyspace_hits:{}\r\nkeyspace_misses:{}\r\n",
+                metrics.total_connections(),
+                metrics.commands_processed(),
+                metrics.net_input_bytes(),
+                metrics.net_output_bytes(),
+                metrics.evicted_keys(),
+                metrics.keyspace_hits(),
+                metrics.keyspace_misses(),
+            );
+        }
+        if wants("replication") {
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+            let replication = db.replication();
+            match replication.primary() {
+                Some((host, port)) => {
+                    let _ = write!(
+                        info,
+                        "# Replication\r\nrole:slave\r\nmaster_host:{host}\r\nmaster_port:{port}\r\n"
+                    );
+                }
+                None => info.push_str("# Replication\r\nrole:master\r\n"),
+            }
+            let _ = write!(
+                info,
+                "connected_slaves:{}\r\nmaster_replid:{}\r\n",
+                replication.replicas(),
+                replication.replid(),
+            );
+        }
+        if wants("keyspace") {
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+            info.push_str("# Keyspace\r\n");
+            for (index, keys, expires) in db.keyspace() {
+                let _ = write!(info, "db{index}:keys={keys},expires={expires}\r\n");
+            }
+        }
+
+        let resp_frame = Frame::Verbatim {
+            format: *b"txt",
+            data: Bytes::from(info),
+        };
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for Info {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("info".as_bytes()));
+        if let Some(section) = self.section {
+            frame.push_bulk(Bytes::from(section.into_bytes()));
+        }
+
+        frame.into()
+    }
+}
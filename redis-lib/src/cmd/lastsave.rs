@@ -0,0 +1,42 @@
+use crate::cmd::Protocol;
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the Unix time, in seconds, of the last successful snapshot of the
+/// keyspace to disk.
+///
+/// ```text
+/// LASTSAVE
+/// ```
+///
+/// Until the first snapshot, this is the time the server started.
+#[derive(Debug, Default)]
+pub struct LastSave;
+
+impl LastSave {
+    pub fn new() -> LastSave {
+        LastSave
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = Frame::unsigned(db.last_save());
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for LastSave {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("lastsave".as_bytes()));
+
+        frame.into()
+    }
+}
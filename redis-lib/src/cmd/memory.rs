@@ -0,0 +1,81 @@
+use crate::cmd::{Parse, ParseError, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Report the memory used by the server.
+///
+/// # Subcommands
+///
+/// * USAGE `key` `[SAMPLES count]` -- Return an estimate of the number of bytes
+///   used to store the key and its value, or nil if there is no such key. The
+///   whole value is always measured, so `SAMPLES` is accepted but has no effect.
+#[derive(Debug)]
+pub enum MemoryCommand {
+    Usage { key: String, samples: Option<u64> },
+}
+
+impl MemoryCommand {
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 3 entries.
+    ///
+    /// ```text
+    /// MEMORY USAGE key [SAMPLES count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<MemoryCommand> {
+        match parse.next_string()?.to_uppercase().as_str() {
+            "USAGE" => {
+                let key = parse.next_string()?;
+                let samples = match parse.next_string() {
+                    Ok(s) if s.to_uppercase() == "SAMPLES" => Some(parse.next_int()?),
+                    Ok(_) => return Err(ParseError::Reply("ERR syntax error".to_string()).into()),
+                    Err(ParseError::EndOfStream) => None,
+                    Err(err) => return Err(err.into()),
+                };
+
+                Ok(MemoryCommand::Usage { key, samples })
+            }
+            other => {
+                let msg = format!("ERR unknown subcommand '{other}'. Try MEMORY HELP.");
+                Err(ParseError::Reply(msg).into())
+            }
+        }
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match self {
+            MemoryCommand::Usage { key, .. } => match db.memory_usage(&key) {
+                Some(usage) => Frame::unsigned(usage as u64),
+                None => Frame::Null,
+            },
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for MemoryCommand {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("memory".as_bytes()));
+        match self {
+            MemoryCommand::Usage { key, samples } => {
+                frame.push_bulk(Bytes::from("usage".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+                if let Some(samples) = samples {
+                    frame.push_bulk(Bytes::from("samples".as_bytes()));
+                    frame.push_int(samples);
+                }
+            }
+        }
+
+        frame.into()
+    }
+}
@@ -2,34 +2,197 @@
 //! some supported Redis commands
 //!
 
+mod auth;
+pub use auth::Auth;
+
+mod client;
+pub use client::{ClientCommand, KillFilter, PauseMode};
+
+mod command;
+pub use command::CommandCommand;
+use command::CommandSpec;
+
+mod config;
+pub use config::ConfigCommand;
+
+mod debug;
+pub use debug::DebugCommand;
+
+mod del;
+pub use del::Del;
+
+mod dump;
+pub use dump::Dump;
+
+mod expire;
+pub use expire::{Expire, ExpireCondition, PExpire, PExpireAt};
+
 mod get;
 pub use get::Get;
 
+mod getex;
+pub use getex::{Expiration, GetEx};
+
+mod hello;
+pub use hello::Hello;
+
+mod persist;
+pub use persist::Persist;
+
+mod psubscribe;
+pub use psubscribe::PSubscribe;
+
+mod psync;
+pub use psync::PSync;
+
+mod pubsub;
+pub use pubsub::PubSub;
+
+mod punsubscribe;
+pub use punsubscribe::PUnsubscribe;
+
 mod publish;
 pub use publish::Publish;
 
+mod quit;
+pub use quit::Quit;
+
+mod rename;
+pub use rename::{Rename, RenameNx};
+
+mod replicaof;
+pub use replicaof::ReplicaOf;
+
+mod reset;
+pub use reset::Reset;
+
+mod restore;
+pub use restore::Restore;
+
+mod save;
+pub use save::{BgRewriteAof, BgSave, Save};
+
+mod scan;
+pub use scan::Scan;
+
+mod select;
+pub use select::Select;
+
 mod set;
 pub use set::Set;
 
+mod slowlog;
+pub use slowlog::SlowLogCommand;
+
+mod spublish;
+pub use spublish::{SPublish, SSubscribe, SUnsubscribe};
+
 mod subscribe;
 pub use subscribe::{Subscribe, Unsubscribe};
 
+mod swapdb;
+pub use swapdb::SwapDb;
+
+mod info;
+pub use info::Info;
+
+mod lastsave;
+pub use lastsave::LastSave;
+
+mod memory;
+pub use memory::MemoryCommand;
+
+mod monitor;
+pub use monitor::Monitor;
+pub(crate) use monitor::format_command;
+
+mod r#move;
+pub use r#move::Move;
+
+mod object;
+pub use object::ObjectCommand;
+
 mod ping;
 pub use ping::Ping;
 
+mod transaction;
+pub use transaction::{Discard, Exec, Multi, Unwatch, Watch};
+pub(crate) use transaction::{Transaction, Watched};
+
 mod unknown;
 pub use unknown::Unknown;
 
-use crate::{Connection, Db, Frame, Parse, ParseError, Shutdown};
+mod wait;
+pub use wait::Wait;
+
+mod waitaof;
+pub use waitaof::WaitAof;
+
+mod xadd;
+pub use xadd::XAdd;
+
+mod xread;
+pub use xread::XRead;
+
+use crate::{Connection, Db, Frame, Parse, ParseError};
+use std::borrow::Cow;
 
 #[derive(Debug)]
 pub enum Command {
+    Auth(Auth),
+    Client(ClientCommand),
+    Config(ConfigCommand),
+    Debug(DebugCommand),
+    Command(CommandCommand),
     Get(Get),
+    GetEx(GetEx),
+    Hello(Hello),
+    Info(Info),
     Publish(Publish),
+    PubSub(PubSub),
     Set(Set),
+    SPublish(SPublish),
+    SSubscribe(SSubscribe),
+    SUnsubscribe(SUnsubscribe),
     Subscribe(Subscribe),
+    PSubscribe(PSubscribe),
     Unsubscribe(Unsubscribe),
+    PUnsubscribe(PUnsubscribe),
     Ping(Ping),
+    Multi(Multi),
+    Exec(Exec),
+    Discard(Discard),
+    Watch(Watch),
+    Unwatch(Unwatch),
+    Quit(Quit),
+    Reset(Reset),
+    Select(Select),
+    SwapDb(SwapDb),
+    Move(Move),
+    Rename(Rename),
+    RenameNx(RenameNx),
+    Expire(Expire),
+    PExpire(PExpire),
+    PExpireAt(PExpireAt),
+    Persist(Persist),
+    Del(Del),
+    Dump(Dump),
+    Restore(Restore),
+    Scan(Scan),
+    LastSave(LastSave),
+    SlowLog(SlowLogCommand),
+    Monitor(Monitor),
+    ReplicaOf(ReplicaOf),
+    PSync(PSync),
+    Wait(Wait),
+    WaitAof(WaitAof),
+    Memory(MemoryCommand),
+    Object(ObjectCommand),
+    Save(Save),
+    BgSave(BgSave),
+    BgRewriteAof(BgRewriteAof),
+    XAdd(XAdd),
+    XRead(XRead),
     Unknown(Unknown),
 }
 
@@ -38,23 +201,89 @@ impl Command {
     pub fn from_frame(frame: Frame) -> crate::Result<Command> {
         let mut parse = Parse::new(frame)?;
 
-        // All redis commands begin with the command name as a string.
-        let command_name = parse.next_string()?.to_lowercase();
+        // All redis commands begin with the command name as a string, matched
+        // case-insensitively. Lowercase names, the most common, are not copied.
+        let command_name = parse.next_string()?;
+        let lowercase = if command_name.bytes().any(|b| b.is_ascii_uppercase()) {
+            Cow::Owned(command_name.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(command_name.as_str())
+        };
+
+        // Like in Redis, the number of arguments is checked before parsing them.
+        let spec = CommandSpec::find(&lowercase);
+        if let Some(spec) = spec {
+            if !spec.accepts(parse.remaining() + 1) {
+                return Err(ParseError::WrongArity(spec.name).into());
+            }
+        }
 
         // Match the command name, delegating the rest of the parsing to the specific `Command`.
-        let command = match &command_name[..] {
+        let command = match &*lowercase {
+            "auth" => Command::Auth(Auth::parse_frames(&mut parse)?),
+            "client" => Command::Client(ClientCommand::parse_frames(&mut parse)?),
+            "config" => Command::Config(ConfigCommand::parse_frames(&mut parse)?),
+            "debug" => Command::Debug(DebugCommand::parse_frames(&mut parse)?),
+            "command" => Command::Command(CommandCommand::parse_frames(&mut parse)?),
             "get" => Command::Get(Get::parse_frames(&mut parse)?),
+            "getex" => Command::GetEx(GetEx::parse_frames(&mut parse)?),
+            "hello" => Command::Hello(Hello::parse_frames(&mut parse)?),
+            "info" => Command::Info(Info::parse_frames(&mut parse)?),
             "publish" => Command::Publish(Publish::parse_frames(&mut parse)?),
+            "pubsub" => Command::PubSub(PubSub::parse_frames(&mut parse)?),
             "set" => Command::Set(Set::parse_frames(&mut parse)?),
+            "spublish" => Command::SPublish(SPublish::parse_frames(&mut parse)?),
+            "ssubscribe" => Command::SSubscribe(SSubscribe::parse_frames(&mut parse)?),
+            "sunsubscribe" => Command::SUnsubscribe(SUnsubscribe::parse_frames(&mut parse)?),
             "subscribe" => Command::Subscribe(Subscribe::parse_frames(&mut parse)?),
+            "psubscribe" => Command::PSubscribe(PSubscribe::parse_frames(&mut parse)?),
             "unsubscribe" => Command::Unsubscribe(Unsubscribe::parse_frames(&mut parse)?),
+            "punsubscribe" => Command::PUnsubscribe(PUnsubscribe::parse_frames(&mut parse)?),
             "ping" => Command::Ping(Ping::parse_frames(&mut parse)?),
-            // The command is not supported.
+            "multi" => Command::Multi(Multi::new()),
+            "exec" => Command::Exec(Exec::new()),
+            "discard" => Command::Discard(Discard::new()),
+            "watch" => Command::Watch(Watch::parse_frames(&mut parse)?),
+            "unwatch" => Command::Unwatch(Unwatch::new()),
+            "quit" => Command::Quit(Quit::new()),
+            "lastsave" => Command::LastSave(LastSave::new()),
+            "slowlog" => Command::SlowLog(SlowLogCommand::parse_frames(&mut parse)?),
+            "monitor" => Command::Monitor(Monitor::new()),
+            "replicaof" => Command::ReplicaOf(ReplicaOf::parse_frames(&mut parse)?),
+            "psync" => Command::PSync(PSync::parse_frames(&mut parse)?),
+            "wait" => Command::Wait(Wait::parse_frames(&mut parse)?),
+            "waitaof" => Command::WaitAof(WaitAof::parse_frames(&mut parse)?),
+            "memory" => Command::Memory(MemoryCommand::parse_frames(&mut parse)?),
+            "object" => Command::Object(ObjectCommand::parse_frames(&mut parse)?),
+            "save" => Command::Save(Save::new()),
+            "bgsave" => Command::BgSave(BgSave::new()),
+            "bgrewriteaof" => Command::BgRewriteAof(BgRewriteAof::new()),
+            "reset" => Command::Reset(Reset::new()),
+            "select" => Command::Select(Select::parse_frames(&mut parse)?),
+            "swapdb" => Command::SwapDb(SwapDb::parse_frames(&mut parse)?),
+            "move" => Command::Move(Move::parse_frames(&mut parse)?),
+            "rename" => Command::Rename(Rename::parse_frames(&mut parse)?),
+            "renamenx" => Command::RenameNx(RenameNx::parse_frames(&mut parse)?),
+            "expire" => Command::Expire(Expire::parse_frames(&mut parse)?),
+            "pexpire" => Command::PExpire(PExpire::parse_frames(&mut parse)?),
+            "pexpireat" => Command::PExpireAt(PExpireAt::parse_frames(&mut parse)?),
+            "persist" => Command::Persist(Persist::parse_frames(&mut parse)?),
+            "del" => Command::Del(Del::parse_frames(&mut parse)?),
+            "dump" => Command::Dump(Dump::parse_frames(&mut parse)?),
+            "restore" => Command::Restore(Restore::parse_frames(&mut parse)?),
+            "scan" => Command::Scan(Scan::parse_frames(&mut parse)?),
+            "xadd" => Command::XAdd(XAdd::parse_frames(&mut parse)?),
+            "xread" => Command::XRead(XRead::parse_frames(&mut parse)?),
+            // The command is not supported. It is reported as sent.
             _ => {
-                return Ok(Command::Unknown(Unknown::new(command_name)));
+                return Ok(Command::Unknown(Unknown::new(&command_name)));
             }
         };
 
+        // Arguments left over once parsed are too many for the command.
+        if let (Some(spec), 1..) = (spec, parse.remaining()) {
+            return Err(ParseError::WrongArity(spec.name).into());
+        }
         // If there is remaining `Frame`, this indicates an unexpected frame format, and error
         // would be returned.
         parse.check_done()?;
@@ -66,41 +295,237 @@ impl Command {
     ///
     /// The response is written to `dst`. This is called by the server in order
     /// to execute a received command.
-    pub(crate) async fn apply(
-        self,
-        db: &Db,
-        dst: &mut Connection,
-        shutdown: &mut Shutdown,
-    ) -> crate::Result<()> {
-        use Command::*;
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        use self::Command::*;
 
         match self {
+            Self::Command(cmd) => cmd.apply(dst).await,
+            Config(cmd) => cmd.apply(db, dst).await,
+            Debug(cmd) => cmd.apply(db, dst).await,
             Get(cmd) => cmd.apply(db, dst).await,
+            GetEx(cmd) => cmd.apply(db, dst).await,
             Publish(cmd) => cmd.apply(db, dst).await,
+            PubSub(cmd) => cmd.apply(db, dst).await,
             Set(cmd) => cmd.apply(db, dst).await,
-            Subscribe(cmd) => cmd.apply(db, dst, shutdown).await,
+            SPublish(cmd) => cmd.apply(db, dst).await,
+            SwapDb(cmd) => cmd.apply(db, dst).await,
+            Move(cmd) => cmd.apply(db, dst).await,
+            Rename(cmd) => cmd.apply(db, dst).await,
+            RenameNx(cmd) => cmd.apply(db, dst).await,
+            Expire(cmd) => cmd.apply(db, dst).await,
+            PExpire(cmd) => cmd.apply(db, dst).await,
+            PExpireAt(cmd) => cmd.apply(db, dst).await,
+            Persist(cmd) => cmd.apply(db, dst).await,
+            Del(cmd) => cmd.apply(db, dst).await,
+            Dump(cmd) => cmd.apply(db, dst).await,
+            Restore(cmd) => cmd.apply(db, dst).await,
+            Scan(cmd) => cmd.apply(db, dst).await,
+            LastSave(cmd) => cmd.apply(db, dst).await,
+            SlowLog(cmd) => cmd.apply(db, dst).await,
+            ReplicaOf(cmd) => cmd.apply(db, dst).await,
+            Wait(cmd) => cmd.apply(dst).await,
+            WaitAof(cmd) => cmd.apply(db, dst).await,
+            Memory(cmd) => cmd.apply(db, dst).await,
+            Object(cmd) => cmd.apply(db, dst).await,
+            Save(cmd) => cmd.apply(db, dst).await,
+            BgSave(cmd) => cmd.apply(db, dst).await,
+            BgRewriteAof(cmd) => cmd.apply(db, dst).await,
             Ping(cmd) => cmd.apply(dst).await,
+            XAdd(cmd) => cmd.apply(db, dst).await,
+            XRead(cmd) => cmd.apply(db, dst).await,
             Unknown(cmd) => cmd.apply(dst).await,
-            // `Unsubscribe` cannot be applied. It may only be received from the
-            // context of a `Subscribe` command.
+            // The unsubscribe commands cannot be applied. They may only be
+            // received from the context of a `Subscribe` command.
             Unsubscribe(_) => Err("`Unsubscribe` is unsupported in this context".into()),
+            PUnsubscribe(_) => Err("`PUnsubscribe` is unsupported in this context".into()),
+            SUnsubscribe(_) => Err("`SUnsubscribe` is unsupported in this context".into()),
+            // Transactions are handled by the connection, which holds their state.
+            Multi(_) => Err("`Multi` is unsupported in this context".into()),
+            Exec(_) => Err("`Exec` is unsupported in this context".into()),
+            Discard(_) => Err("`Discard` is unsupported in this context".into()),
+            Auth(_) => Err("`Auth` is unsupported in this context".into()),
+            Hello(_) => Err("`Hello` is unsupported in this context".into()),
+            Client(_) => Err("`Client` is unsupported in this context".into()),
+            Info(_) => Err("`Info` is unsupported in this context".into()),
+            Watch(_) => Err("`Watch` is unsupported in this context".into()),
+            Unwatch(_) => Err("`Unwatch` is unsupported in this context".into()),
+            Quit(_) => Err("`Quit` is unsupported in this context".into()),
+            Reset(_) => Err("`Reset` is unsupported in this context".into()),
+            Select(_) => Err("`Select` is unsupported in this context".into()),
+            // The subscribed state may end with a `RESET`, which the connection applies.
+            Subscribe(_) => Err("`Subscribe` is unsupported in this context".into()),
+            PSubscribe(_) => Err("`PSubscribe` is unsupported in this context".into()),
+            SSubscribe(_) => Err("`SSubscribe` is unsupported in this context".into()),
+            Monitor(_) => Err("`Monitor` is unsupported in this context".into()),
+            PSync(_) => Err("`PSync` is unsupported in this context".into()),
+        }
+    }
+
+    /// Returns `true` if the command modifies the data or publishes messages, so
+    /// that a proxy sends it to the primary rather than to a replica.
+    ///
+    /// These are the commands suspended by `CLIENT PAUSE WRITE`.
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Command::Set(_)
+                | Command::GetEx(_)
+                | Command::XAdd(_)
+                | Command::Publish(_)
+                | Command::SPublish(_)
+                | Command::SwapDb(_)
+                | Command::Move(_)
+                | Command::Rename(_)
+                | Command::RenameNx(_)
+                | Command::Expire(_)
+                | Command::PExpire(_)
+                | Command::PExpireAt(_)
+                | Command::Persist(_)
+                | Command::Del(_)
+                | Command::Restore(_)
+                | Command::Exec(_)
+        )
+    }
+
+    /// Returns `true` if the command may grow the memory used by the keys, in which
+    /// case it is refused while `maxmemory` is exceeded and no key can be evicted.
+    pub(crate) fn may_use_memory(&self) -> bool {
+        matches!(
+            self,
+            Command::Set(_) | Command::XAdd(_) | Command::Restore(_)
+        )
+    }
+
+    /// Returns `true` if the command may wait for an event before replying, rather
+    /// than being applied at once.
+    pub(crate) fn is_blocking(&self) -> bool {
+        matches!(self, Command::Wait(_) | Command::WaitAof(_))
+    }
+
+    /// Returns `true` if the command modifies the keyspace, in which case it is
+    /// appended to the append-only file and sent to the replicas once applied.
+    ///
+    /// `EXEC` propagates the commands of the transaction itself.
+    pub(crate) fn is_logged(&self) -> bool {
+        matches!(
+            self,
+            Command::Set(_)
+                | Command::GetEx(_)
+                | Command::XAdd(_)
+                | Command::SwapDb(_)
+                | Command::Move(_)
+                | Command::Rename(_)
+                | Command::RenameNx(_)
+                | Command::Expire(_)
+                | Command::PExpire(_)
+                | Command::PExpireAt(_)
+                | Command::Persist(_)
+                | Command::Del(_)
+                | Command::Restore(_)
+        )
+    }
+
+    /// Returns the key the command applies to, for the commands applying to a
+    /// single key.
+    pub(crate) fn key(&self) -> Option<&str> {
+        match self {
+            Command::Get(cmd) => Some(cmd.key()),
+            Command::GetEx(cmd) => Some(cmd.key()),
+            Command::Set(cmd) => Some(cmd.key()),
+            Command::Move(cmd) => Some(cmd.key()),
+            Command::Expire(cmd) => Some(cmd.key()),
+            Command::PExpire(cmd) => Some(cmd.key()),
+            Command::PExpireAt(cmd) => Some(cmd.key()),
+            Command::Persist(cmd) => Some(cmd.key()),
+            Command::Dump(cmd) => Some(cmd.key()),
+            Command::Restore(cmd) => Some(cmd.key()),
+            Command::XAdd(cmd) => Some(cmd.key()),
+            _ => None,
         }
     }
 
+    /// The flags of the command, as reported by `COMMAND`: `readonly`, `write`,
+    /// `pubsub` or `admin` among others. An unknown command has none.
+    pub fn flags(&self) -> &'static [&'static str] {
+        CommandSpec::find(self.get_name()).map_or(&[], |spec| spec.flags)
+    }
+
     /// Returns the command name
     pub(crate) fn get_name(&self) -> &str {
         match self {
+            Command::Auth(_) => "auth",
+            Command::Client(_) => "client",
+            Command::Command(_) => "command",
+            Command::Config(_) => "config",
+            Command::Debug(_) => "debug",
             Command::Get(_) => "get",
-            Command::Publish(_) => "pub",
+            Command::GetEx(_) => "getex",
+            Command::Hello(_) => "hello",
+            Command::Info(_) => "info",
+            Command::Publish(_) => "publish",
+            Command::PubSub(_) => "pubsub",
             Command::Set(_) => "set",
+            Command::SPublish(_) => "spublish",
+            Command::SSubscribe(_) => "ssubscribe",
+            Command::SUnsubscribe(_) => "sunsubscribe",
             Command::Subscribe(_) => "subscribe",
+            Command::PSubscribe(_) => "psubscribe",
             Command::Unsubscribe(_) => "unsubscribe",
+            Command::PUnsubscribe(_) => "punsubscribe",
             Command::Ping(_) => "ping",
+            Command::Multi(_) => "multi",
+            Command::Exec(_) => "exec",
+            Command::Discard(_) => "discard",
+            Command::Watch(_) => "watch",
+            Command::Unwatch(_) => "unwatch",
+            Command::Quit(_) => "quit",
+            Command::LastSave(_) => "lastsave",
+            Command::SlowLog(_) => "slowlog",
+            Command::Monitor(_) => "monitor",
+            Command::ReplicaOf(_) => "replicaof",
+            Command::PSync(_) => "psync",
+            Command::Wait(_) => "wait",
+            Command::WaitAof(_) => "waitaof",
+            Command::Memory(_) => "memory",
+            Command::Object(_) => "object",
+            Command::Save(_) => "save",
+            Command::BgSave(_) => "bgsave",
+            Command::BgRewriteAof(_) => "bgrewriteaof",
+            Command::Reset(_) => "reset",
+            Command::Select(_) => "select",
+            Command::SwapDb(_) => "swapdb",
+            Command::Move(_) => "move",
+            Command::Rename(_) => "rename",
+            Command::RenameNx(_) => "renamenx",
+            Command::Expire(_) => "expire",
+            Command::PExpire(_) => "pexpire",
+            Command::PExpireAt(_) => "pexpireat",
+            Command::Persist(_) => "persist",
+            Command::Del(_) => "del",
+            Command::Dump(_) => "dump",
+            Command::Restore(_) => "restore",
+            Command::Scan(_) => "scan",
+            Command::XAdd(_) => "xadd",
+            Command::XRead(_) => "xread",
             Command::Unknown(cmd) => cmd.get_name(),
         }
     }
 }
 
+/// The reply to the `HELP` subcommand of the container `command`, like in Redis: a
+/// usage line, the `lines` describing each subcommand, then `HELP` itself.
+pub(crate) fn help_frame(command: &str, lines: &[&str]) -> Frame {
+    let usage = format!("{command} <subcommand> [<arg> [value] [opt] ...]. Subcommands are:");
+    let lines = lines.iter().chain(&["HELP", "    Print this help."]);
+
+    Frame::Array(
+        std::iter::once(usage)
+            .chain(lines.map(ToString::to_string))
+            .map(Frame::Simple)
+            .collect(),
+    )
+}
+
 pub trait Protocol {
     /// Converts the command into an equivalent `Frame`.
     ///
@@ -0,0 +1,105 @@
+use crate::cmd::Protocol;
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame, Shutdown};
+use bytes::Bytes;
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, instrument};
+
+/// Stream every command processed by the server.
+///
+/// ```text
+/// MONITOR
+/// ```
+///
+/// The server replies OK, then sends a line for each command issued by any client,
+/// like `1339518083.107412 [0 127.0.0.1:60866] "set" "key" "value"`, until the
+/// connection is closed.
+#[derive(Debug, Default)]
+pub struct Monitor;
+
+impl Monitor {
+    pub fn new() -> Monitor {
+        Monitor
+    }
+
+    /// Stream the commands processed by the server holding `db` to `dst`.
+    ///
+    /// Requests received in the meantime are ignored. Returns when the connection
+    /// is closed, or the shutdown signal is received.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let mut commands = db.monitor();
+
+        let resp_frame = Frame::Simple("OK".to_string());
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        loop {
+            select! {
+                res = commands.recv() => match res {
+                    Ok(line) => dst.write_frame(&Frame::Simple(line)).await?,
+                    // A slow monitor misses the lines it fell behind on.
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                res = dst.read_frame() => {
+                    if res?.is_none() {
+                        return Ok(());
+                    }
+                }
+                _ = shutdown.recv() => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Formats the command `args`, issued from `addr` on the database `index`, as sent
+/// to monitors.
+pub(crate) fn format_command(index: usize, addr: &str, args: &[Bytes]) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let mut line = format!(
+        "{}.{:06} [{index} {addr}]",
+        now.as_secs(),
+        now.subsec_micros()
+    );
+    for arg in args {
+        line.push_str(" \"");
+        // Escape the argument like Redis does, keeping the line printable.
+        for &b in arg {
+            match b {
+                b'\\' => line.push_str("\\\\"),
+                b'"' => line.push_str("\\\""),
+                b'\n' => line.push_str("\\n"),
+                b'\r' => line.push_str("\\r"),
+                b'\t' => line.push_str("\\t"),
+                b' '..=b'~' => line.push(char::from(b)),
+                b => {
+                    let _ = write!(line, "\\x{b:02x}");
+                }
+            }
+        }
+        line.push('"');
+    }
+
+    line
+}
+
+impl Protocol for Monitor {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("monitor".as_bytes()));
+
+        frame.into()
+    }
+}
@@ -0,0 +1,73 @@
+use crate::cmd::{Parse, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Move a key, along with its expiration, from the selected database to another one.
+///
+/// The reply is `1` if the key was moved, or `0` if it does not exist or the
+/// destination database already holds the key. RESP3 clients receive a boolean
+/// instead.
+#[derive(Debug)]
+pub struct Move {
+    key: String,
+    db: u64,
+}
+
+impl Move {
+    pub fn new(key: impl ToString, db: u64) -> Move {
+        Move {
+            key: key.to_string(),
+            db,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// MOVE key db
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Move> {
+        let key = parse.next_string()?;
+        let db = parse.next_int()?;
+
+        Ok(Move { key, db })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let moved = match usize::try_from(self.db) {
+            Ok(index) => db.move_key(&self.key, index),
+            Err(_) => Err("ERR DB index is out of range".into()),
+        };
+
+        let resp_frame = match moved {
+            Ok(moved) => Frame::Boolean(moved),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for Move {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("move".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_int(self.db);
+
+        frame.into()
+    }
+}
@@ -0,0 +1,117 @@
+use crate::cmd::{Parse, Protocol, help_frame};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Inspect the internals of the value stored at a key.
+///
+/// # Subcommands
+///
+/// * ENCODING `key` -- Return the internal representation of the value, like
+///   `int` or `embstr`.
+/// * REFCOUNT `key` -- Return the number of references to the value. Like in
+///   Redis, small integers are shared, and report a very large count.
+/// * FREQ `key` -- Return the access frequency counter of the key. Only available
+///   under an LFU `maxmemory-policy`.
+/// * HELP -- Describe the subcommands.
+///
+/// They reply nil if there is no such key. None counts as an access to the key.
+#[derive(Debug)]
+pub enum ObjectCommand {
+    Encoding { key: String },
+    RefCount { key: String },
+    Freq { key: String },
+    Help,
+}
+
+impl ObjectCommand {
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// OBJECT ENCODING key
+    /// OBJECT REFCOUNT key
+    /// OBJECT FREQ key
+    /// OBJECT HELP
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ObjectCommand> {
+        match parse.next_string()?.to_uppercase().as_str() {
+            "ENCODING" => Ok(ObjectCommand::Encoding {
+                key: parse.next_string()?,
+            }),
+            "REFCOUNT" => Ok(ObjectCommand::RefCount {
+                key: parse.next_string()?,
+            }),
+            "FREQ" => Ok(ObjectCommand::Freq {
+                key: parse.next_string()?,
+            }),
+            "HELP" => Ok(ObjectCommand::Help),
+            other => Err(format!("unknown `OBJECT` subcommand `{other}`").into()),
+        }
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match self {
+            ObjectCommand::Encoding { key } => match db.debug_object(&key) {
+                Some((_, encoding, _)) => Frame::Bulk(Bytes::from_static(encoding.as_bytes())),
+                None => Frame::Null,
+            },
+            ObjectCommand::RefCount { key } => match db.object_refcount(&key) {
+                Some(refcount) => Frame::unsigned(refcount),
+                None => Frame::Null,
+            },
+            ObjectCommand::Freq { key } => match db.object_freq(&key) {
+                Ok(Some(freq)) => Frame::unsigned(u64::from(freq)),
+                Ok(None) => Frame::Null,
+                Err(err) => Frame::Error(err.to_string()),
+            },
+            ObjectCommand::Help => help_frame(
+                "OBJECT",
+                &[
+                    "ENCODING <key>",
+                    "    Return the kind of internal representation used in order to store the value",
+                    "    associated with a <key>.",
+                    "FREQ <key>",
+                    "    Return the access frequency counter of the <key>, under an LFU",
+                    "    maxmemory-policy.",
+                    "REFCOUNT <key>",
+                    "    Return the number of references of the value associated with the specified",
+                    "    <key>.",
+                ],
+            ),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for ObjectCommand {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("object".as_bytes()));
+        match self {
+            ObjectCommand::Encoding { key } => {
+                frame.push_bulk(Bytes::from("encoding".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+            }
+            ObjectCommand::RefCount { key } => {
+                frame.push_bulk(Bytes::from("refcount".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+            }
+            ObjectCommand::Freq { key } => {
+                frame.push_bulk(Bytes::from("freq".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+            }
+            ObjectCommand::Help => frame.push_bulk(Bytes::from("help".as_bytes())),
+        }
+
+        frame.into()
+    }
+}
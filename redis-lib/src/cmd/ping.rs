@@ -46,6 +46,28 @@ impl Ping {
 
         Ok(())
     }
+
+    /// Apply the `Ping` command to a client in the subscribed state.
+    ///
+    /// In this context, the reply to a RESP2 client is an array of `pong` and the
+    /// message, which is empty if none was provided. RESP3 clients tell replies
+    /// apart from messages, and receive the usual reply.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply_subscribed(self, dst: &mut Connection) -> crate::Result<()> {
+        if dst.protocol() >= 3 {
+            return self.apply(dst).await;
+        }
+
+        let mut resp_frame = vec![];
+        resp_frame.push_bulk(Bytes::from_static(b"pong"));
+        resp_frame.push_bulk(self.echo.unwrap_or_default());
+        let resp_frame = resp_frame.into();
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
 }
 
 impl Protocol for Ping {
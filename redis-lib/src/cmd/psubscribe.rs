@@ -0,0 +1,74 @@
+use crate::cmd::subscribe::{Pending, subscribed_state};
+use crate::cmd::{Parse, ParseError, Protocol, Reset};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame, Shutdown};
+use bytes::Bytes;
+
+/// client subscribes to one or more glob patterns.
+///
+/// Messages published to any channel matching a pattern are delivered to the
+/// client as `pmessage` frames. Like `SUBSCRIBE`, this puts the client in the
+/// subscribed state.
+#[derive(Debug)]
+pub struct PSubscribe {
+    patterns: Vec<String>,
+}
+
+impl PSubscribe {
+    pub(crate) fn new(patterns: Vec<String>) -> PSubscribe {
+        PSubscribe { patterns }
+    }
+
+    pub(crate) fn into_patterns(self) -> Vec<String> {
+        self.patterns
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// PSUBSCRIBE pattern [pattern ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PSubscribe> {
+        use ParseError::EndOfStream;
+
+        let mut patterns = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => patterns.push(s),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(PSubscribe { patterns })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<Option<Reset>> {
+        let pending = Pending {
+            patterns: self.patterns,
+            ..Pending::default()
+        };
+        subscribed_state(pending, db, dst, shutdown).await
+    }
+}
+
+impl Protocol for PSubscribe {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("psubscribe".as_bytes()));
+        for pattern in self.patterns {
+            frame.push_bulk(Bytes::from(pattern.into_bytes()));
+        }
+
+        frame.into()
+    }
+}
@@ -0,0 +1,107 @@
+use crate::cmd::{Parse, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame, Shutdown};
+use bytes::Bytes;
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, instrument};
+
+/// Synchronize a replica with the server, see the `replication` module.
+///
+/// The server replies `+FULLRESYNC <replid> <offset>` followed by a snapshot of its
+/// databases, then sends the write commands it applies until the connection is
+/// closed. The replication id and offset of the replica are ignored: partial
+/// resynchronization is not supported.
+#[derive(Debug)]
+pub struct PSync {
+    replid: String,
+    offset: i64,
+}
+
+impl PSync {
+    /// `replid` is `?` and `offset` is -1 for a replica which was never synchronized.
+    pub fn new(replid: impl ToString, offset: i64) -> PSync {
+        PSync {
+            replid: replid.to_string(),
+            offset,
+        }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// PSYNC replicationid offset
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PSync> {
+        let replid = parse.next_string()?;
+        let offset = parse.next_signed_int()?;
+
+        Ok(PSync { replid, offset })
+    }
+
+    /// Send a snapshot of `db`, then the write commands applied to it, to `dst`.
+    ///
+    /// Requests received in the meantime are ignored. Returns when the connection
+    /// is closed, or the shutdown signal is received. A replica falling too far
+    /// behind is disconnected.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let (snapshot, mut commands) = db.sync_replica().await;
+
+        let replid = db.replication().replid();
+        let resp_frame = Frame::Simple(format!("FULLRESYNC {replid} 0"));
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+        dst.write_frame(&Frame::Bulk(Bytes::from(snapshot))).await?;
+
+        // The database of the last command sent.
+        let mut selected = None;
+        loop {
+            select! {
+                res = commands.recv() => match res {
+                    Ok((index, args)) => {
+                        if selected != Some(index) {
+                            let select = vec![Bytes::from("SELECT"), Bytes::from(index.to_string())];
+                            dst.write_frame(&command_frame(select)).await?;
+                            selected = Some(index);
+                        }
+                        dst.write_frame(&command_frame(args)).await?;
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        return Err(format!("replica missed {missed} write commands").into());
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                res = dst.read_frame() => {
+                    if res?.is_none() {
+                        return Ok(());
+                    }
+                }
+                _ = shutdown.recv() => return Ok(()),
+            }
+        }
+    }
+}
+
+/// The request frame of the command `args`.
+fn command_frame(args: Vec<Bytes>) -> Frame {
+    Frame::Array(args.into_iter().map(Frame::Bulk).collect())
+}
+
+impl Protocol for PSync {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("psync".as_bytes()));
+        frame.push_bulk(Bytes::from(self.replid));
+        frame.push_bulk(Bytes::from(self.offset.to_string()));
+
+        frame.into()
+    }
+}
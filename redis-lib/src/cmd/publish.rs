@@ -37,7 +37,7 @@ impl Publish {
     pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
         // Calling `db.publish` dispatches the message into the appropriate channel.
         let n_subscribers = db.publish(&self.channel, self.message);
-        let frame = Frame::Integer(n_subscribers as u64);
+        let frame = Frame::unsigned(n_subscribers as u64);
 
         dst.write_frame(&frame).await?;
 
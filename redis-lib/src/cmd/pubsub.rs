@@ -0,0 +1,133 @@
+use crate::cmd::{Parse, ParseError, Protocol, help_frame};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Introspect the state of the pub/sub subsystem.
+///
+/// # Subcommands
+///
+/// * CHANNELS `[pattern]` -- List the channels with at least one subscriber,
+///   optionally only those matching the glob `pattern`.
+/// * NUMSUB `[channel ...]` -- Report the number of subscribers of each channel.
+/// * NUMPAT -- Report the number of unique patterns subscribed to by all clients.
+/// * HELP -- Describe the subcommands.
+#[derive(Debug)]
+pub enum PubSub {
+    Channels { pattern: Option<String> },
+    NumSub { channels: Vec<String> },
+    NumPat,
+    Help,
+}
+
+impl PubSub {
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// PUBSUB CHANNELS [pattern]
+    /// PUBSUB NUMSUB [channel [channel ...]]
+    /// PUBSUB NUMPAT
+    /// PUBSUB HELP
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PubSub> {
+        use ParseError::EndOfStream;
+
+        match parse.next_string()?.to_uppercase().as_str() {
+            "CHANNELS" => {
+                let pattern = match parse.next_string() {
+                    Ok(pattern) => Some(pattern),
+                    Err(EndOfStream) => None,
+                    Err(err) => return Err(err.into()),
+                };
+
+                Ok(PubSub::Channels { pattern })
+            }
+            "NUMSUB" => {
+                let mut channels = vec![];
+                loop {
+                    match parse.next_string() {
+                        Ok(channel) => channels.push(channel),
+                        Err(EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Ok(PubSub::NumSub { channels })
+            }
+            "NUMPAT" => Ok(PubSub::NumPat),
+            "HELP" => Ok(PubSub::Help),
+            other => Err(format!("unknown `PUBSUB` subcommand `{other}`").into()),
+        }
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match self {
+            PubSub::Channels { pattern } => {
+                let mut channels = vec![];
+                for channel in db.channels(pattern.as_deref()) {
+                    channels.push_bulk(Bytes::from(channel));
+                }
+
+                channels.into()
+            }
+            // A flat array of channel and count pairs.
+            PubSub::NumSub { channels } => {
+                let mut counts = vec![];
+                for (channel, count) in db.numsub(channels) {
+                    counts.push_bulk(Bytes::from(channel));
+                    counts.push_int(count as u64);
+                }
+
+                counts.into()
+            }
+            PubSub::NumPat => Frame::unsigned(db.numpat() as u64),
+            PubSub::Help => help_frame(
+                "PUBSUB",
+                &[
+                    "CHANNELS [<pattern>]",
+                    "    Return the currently active channels matching a <pattern> (default: '*').",
+                    "NUMPAT",
+                    "    Return number of subscriptions to patterns.",
+                    "NUMSUB [<channel> ...]",
+                    "    Return the number of subscribers for the specified channels, excluding",
+                    "    pattern subscriptions (default: no channels).",
+                ],
+            ),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for PubSub {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("pubsub".as_bytes()));
+        match self {
+            PubSub::Channels { pattern } => {
+                frame.push_bulk(Bytes::from("channels".as_bytes()));
+                if let Some(pattern) = pattern {
+                    frame.push_bulk(Bytes::from(pattern.into_bytes()));
+                }
+            }
+            PubSub::NumSub { channels } => {
+                frame.push_bulk(Bytes::from("numsub".as_bytes()));
+                for channel in channels {
+                    frame.push_bulk(Bytes::from(channel.into_bytes()));
+                }
+            }
+            PubSub::NumPat => frame.push_bulk(Bytes::from("numpat".as_bytes())),
+            PubSub::Help => frame.push_bulk(Bytes::from("help".as_bytes())),
+        }
+
+        frame.into()
+    }
+}
@@ -0,0 +1,41 @@
+use crate::cmd::Protocol;
+use crate::frame::PushFrame;
+use crate::{Connection, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Ask the server to close the connection.
+///
+/// ```text
+/// QUIT
+/// ```
+///
+/// The server replies OK, then closes the connection once the reply is written.
+#[derive(Debug, Default)]
+pub struct Quit;
+
+impl Quit {
+    pub fn new() -> Quit {
+        Quit
+    }
+
+    /// Acknowledge the request. The connection is closed by the caller.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = Frame::Simple("OK".to_string());
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for Quit {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("quit".as_bytes()));
+
+        frame.into()
+    }
+}
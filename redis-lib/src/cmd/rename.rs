@@ -0,0 +1,122 @@
+use crate::cmd::{Parse, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Rename a key, along with its expiration, replacing the destination key if it
+/// exists.
+///
+/// Fails if the key does not exist.
+#[derive(Debug)]
+pub struct Rename {
+    key: String,
+    newkey: String,
+}
+
+/// Rename a key, along with its expiration, only if the destination key does not
+/// exist.
+///
+/// The reply is `1` if the key was renamed, or `0` if the destination key exists.
+/// RESP3 clients receive a boolean instead. Fails if the key does not exist.
+#[derive(Debug)]
+pub struct RenameNx {
+    key: String,
+    newkey: String,
+}
+
+impl Rename {
+    pub fn new(key: impl ToString, newkey: impl ToString) -> Rename {
+        Rename {
+            key: key.to_string(),
+            newkey: newkey.to_string(),
+        }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// RENAME key newkey
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Rename> {
+        let key = parse.next_string()?;
+        let newkey = parse.next_string()?;
+
+        Ok(Rename { key, newkey })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match db.rename_with_options(&self.key, &self.newkey, false) {
+            Ok(_) => Frame::Simple("OK".to_string()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for Rename {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("rename".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.newkey.into_bytes()));
+
+        frame.into()
+    }
+}
+
+impl RenameNx {
+    pub fn new(key: impl ToString, newkey: impl ToString) -> RenameNx {
+        RenameNx {
+            key: key.to_string(),
+            newkey: newkey.to_string(),
+        }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// RENAMENX key newkey
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<RenameNx> {
+        let key = parse.next_string()?;
+        let newkey = parse.next_string()?;
+
+        Ok(RenameNx { key, newkey })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match db.rename_with_options(&self.key, &self.newkey, true) {
+            Ok(renamed) => Frame::Boolean(renamed),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for RenameNx {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("renamenx".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.newkey.into_bytes()));
+
+        frame.into()
+    }
+}
@@ -0,0 +1,92 @@
+use crate::cmd::{Parse, Protocol};
+use crate::frame::PushFrame;
+use crate::replication;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Make the server a replica of the primary at `host` and `port`, or a primary
+/// again with `NO ONE`.
+///
+/// The server replies at once, then follows the primary in the background: its
+/// databases are replaced by a snapshot of the primary, then kept in sync with the
+/// write commands the primary applies. See the `replication` module. Meanwhile,
+/// the write commands of its clients are refused.
+///
+/// Once promoted, the server keeps the keys replicated so far and accepts writes
+/// again.
+#[derive(Debug)]
+pub struct ReplicaOf {
+    /// The host and port of the primary, `None` for `NO ONE`.
+    primary: Option<(String, String)>,
+}
+
+impl ReplicaOf {
+    pub fn new(host: impl ToString, port: u16) -> ReplicaOf {
+        ReplicaOf {
+            primary: Some((host.to_string(), port.to_string())),
+        }
+    }
+
+    /// Stop following the primary, see `REPLICAOF NO ONE`.
+    pub fn no_one() -> ReplicaOf {
+        ReplicaOf { primary: None }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// REPLICAOF <host port | NO ONE>
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ReplicaOf> {
+        let host = parse.next_string()?;
+        let port = parse.next_string()?;
+        if host.eq_ignore_ascii_case("no") && port.eq_ignore_ascii_case("one") {
+            return Ok(ReplicaOf::no_one());
+        }
+
+        Ok(ReplicaOf {
+            primary: Some((host, port)),
+        })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match self.primary {
+            None => {
+                db.replication().unfollow();
+                Frame::Simple("OK".to_string())
+            }
+            Some((host, port)) => match port.parse() {
+                Ok(port) => {
+                    replication::start(db, host, port);
+                    Frame::Simple("OK".to_string())
+                }
+                Err(_) => Frame::Error("ERR Invalid master port".to_string()),
+            },
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for ReplicaOf {
+    fn into_frame(self) -> Frame {
+        let (host, port) = self
+            .primary
+            .unwrap_or_else(|| ("NO".to_string(), "ONE".to_string()));
+
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("replicaof".as_bytes()));
+        frame.push_bulk(Bytes::from(host));
+        frame.push_bulk(Bytes::from(port));
+
+        frame.into()
+    }
+}
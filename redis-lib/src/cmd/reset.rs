@@ -0,0 +1,60 @@
+use crate::cmd::{Protocol, Transaction, Watched};
+use crate::frame::PushFrame;
+use crate::{ClientHandle, Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Return the connection to the state it had when it was opened.
+///
+/// ```text
+/// RESET
+/// ```
+///
+/// Any transaction is discarded, the watched keys are unwatched, the channels and
+/// patterns are unsubscribed, the name is cleared, the database `0` is selected and
+/// the protocol is back to RESP2. On a server requiring a password, the connection
+/// has to authenticate again, which the caller takes care of.
+#[derive(Debug, Default)]
+pub struct Reset;
+
+impl Reset {
+    pub fn new() -> Reset {
+        Reset
+    }
+
+    /// Reset the state of `client`, the connection writing to `dst`.
+    ///
+    /// Leaving the subscribed state, which unsubscribes everything, is done before
+    /// applying the command.
+    #[instrument(skip(self, db, client, transaction, watched, dst))]
+    pub(crate) async fn apply(
+        self,
+        db: &mut Db,
+        client: &ClientHandle,
+        transaction: &mut Option<Transaction>,
+        watched: &mut Watched,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        *transaction = None;
+        watched.clear();
+        client.set_name(None);
+        // The database `0` always exists.
+        *db = db.select(0).unwrap();
+        dst.set_protocol(2);
+
+        let resp_frame = Frame::Simple("RESET".to_string());
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for Reset {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("reset".as_bytes()));
+
+        frame.into()
+    }
+}
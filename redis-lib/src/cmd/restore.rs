@@ -0,0 +1,142 @@
+use crate::cmd::{Parse, ParseError, Protocol};
+use crate::db::instant_at;
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+use tracing::{debug, instrument};
+
+/// Create `key` with a value serialized by `DUMP`.
+///
+/// The key expires after `ttl` milliseconds, or never if `ttl` is 0. An error is
+/// returned if the key already exists, or if the serialized value is invalid.
+///
+/// # Options
+///
+/// * REPLACE -- Overwrite the key if it already exists.
+/// * ABSTTL -- `ttl` is the Unix time at which the key expires, in milliseconds.
+#[derive(Debug)]
+pub struct Restore {
+    key: String,
+    ttl: Option<Duration>,
+    /// The Unix time at which the key expires, set by `ABSTTL` instead of `ttl`.
+    expire_at: Option<SystemTime>,
+    payload: Bytes,
+    replace: bool,
+}
+
+impl Restore {
+    pub fn new(
+        key: impl ToString,
+        ttl: Option<Duration>,
+        payload: Bytes,
+        replace: bool,
+    ) -> Restore {
+        Restore {
+            key: key.to_string(),
+            ttl,
+            expire_at: None,
+            payload,
+            replace,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 4 entries.
+    ///
+    /// ```text
+    /// RESTORE key ttl serialized-value [REPLACE] [ABSTTL]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Restore> {
+        let key = parse.next_string()?;
+        let ttl = match parse.next_int()? {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        };
+        let payload = parse.next_bytes()?;
+        let mut replace = false;
+        let mut absttl = false;
+        loop {
+            match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "REPLACE" => replace = true,
+                Ok(s) if s.to_uppercase() == "ABSTTL" => absttl = true,
+                Ok(_) => return Err(ParseError::Reply("ERR syntax error".to_string()).into()),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let (ttl, expire_at) = match ttl {
+            Some(ttl) if absttl => (None, Some(UNIX_EPOCH.checked_add(ttl).ok_or_else(invalid)?)),
+            ttl => (ttl, None),
+        };
+
+        Ok(Restore {
+            key,
+            ttl,
+            expire_at,
+            payload,
+            replace,
+        })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let expires_at = match (self.ttl, self.expire_at) {
+            (Some(ttl), _) => Instant::now().checked_add(ttl).map(Some),
+            (None, Some(time)) => instant_at(time).map(Some),
+            (None, None) => Some(None),
+        };
+        let res = match expires_at {
+            Some(expires_at) => db.restore(self.key, &self.payload, expires_at, self.replace),
+            None => Err(invalid().into()),
+        };
+        let resp_frame = match res {
+            Ok(()) => Frame::Simple("OK".to_string()),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for Restore {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("restore".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        let ttl = match (self.ttl, self.expire_at) {
+            (Some(ttl), _) => ttl.as_millis() as u64,
+            (None, Some(time)) => {
+                let unix = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+                u64::try_from(unix.as_millis()).unwrap_or(u64::MAX)
+            }
+            (None, None) => 0,
+        };
+        frame.push_int(ttl);
+        frame.push_bulk(self.payload);
+        if self.replace {
+            frame.push_bulk(Bytes::from("replace".as_bytes()));
+        }
+        if self.expire_at.is_some() {
+            frame.push_bulk(Bytes::from("absttl".as_bytes()));
+        }
+
+        frame.into()
+    }
+}
+
+/// The error of a `ttl` too far in the future to be represented.
+fn invalid() -> ParseError {
+    ParseError::Reply("ERR Invalid TTL value, must be >= 0".to_string())
+}
@@ -0,0 +1,131 @@
+use crate::cmd::Protocol;
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, error, instrument};
+
+/// Write a snapshot of all the databases to disk.
+///
+/// ```text
+/// SAVE
+/// ```
+///
+/// The snapshot is written to the `dbfilename` file of the `dir` directory. The
+/// keyspace is locked while the snapshot is taken, but not while it is written.
+#[derive(Debug, Default)]
+pub struct Save;
+
+impl Save {
+    pub fn new() -> Save {
+        Save
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let path = db.settings().dump_path();
+        let resp_frame = match db.dump_to(&path) {
+            Ok(()) => Frame::Simple("OK".to_string()),
+            Err(err) => {
+                error!(cause = %err, ?path, "failed to save snapshot");
+                Frame::Error(format!("ERR {err}"))
+            }
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+/// Write a snapshot of all the databases to disk in the background.
+///
+/// ```text
+/// BGSAVE
+/// ```
+///
+/// Like `SAVE`, but the server replies as soon as the snapshot is taken, and writes
+/// it to disk in the background.
+#[derive(Debug, Default)]
+pub struct BgSave;
+
+impl BgSave {
+    pub fn new() -> BgSave {
+        BgSave
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let path = db.settings().dump_path();
+        let resp_frame = match db.dump_in_background(path) {
+            Ok(()) => Frame::Simple("Background saving started".to_string()),
+            Err(err) => Frame::Error(format!("ERR {err}")),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+/// Rewrite the append-only file in the background.
+///
+/// ```text
+/// BGREWRITEAOF
+/// ```
+///
+/// The file is replaced by the minimal commands reproducing the databases, such as
+/// a single `SET` per string key, followed by the commands applied during the
+/// rewrite. The server replies as soon as the rewrite starts.
+#[derive(Debug, Default)]
+pub struct BgRewriteAof;
+
+impl BgRewriteAof {
+    pub fn new() -> BgRewriteAof {
+        BgRewriteAof
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match db.rewrite_aof_in_background().await {
+            Ok(()) => Frame::Simple("Background append only file rewriting started".to_string()),
+            Err(err) => Frame::Error(format!("ERR {err}")),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for Save {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("save".as_bytes()));
+
+        frame.into()
+    }
+}
+
+impl Protocol for BgSave {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("bgsave".as_bytes()));
+
+        frame.into()
+    }
+}
+
+impl Protocol for BgRewriteAof {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("bgrewriteaof".as_bytes()));
+
+        frame.into()
+    }
+}
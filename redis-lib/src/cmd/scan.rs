@@ -0,0 +1,115 @@
+use crate::cmd::{Parse, ParseError, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// The number of keys visited by a call, without the COUNT option.
+const DEFAULT_COUNT: u64 = 10;
+
+/// Incrementally iterate the keys of the selected database.
+///
+/// An iteration starts with the cursor 0. Each call returns the cursor to pass to
+/// the next one, along with some keys, and the iteration ends once the cursor 0 is
+/// returned. Like in Redis, every key present for the whole iteration is returned,
+/// even if keys are added or removed in between. A key added or removed during the
+/// iteration may or may not be returned.
+///
+/// # Options
+///
+/// * MATCH `pattern` -- Only return the keys matching the glob-style `pattern`.
+///   Keys are filtered once visited, so that a call may return no key at all.
+/// * COUNT `count` -- Visit about `count` keys rather than 10.
+#[derive(Debug)]
+pub struct Scan {
+    cursor: u64,
+    pattern: Option<String>,
+    count: Option<u64>,
+}
+
+impl Scan {
+    pub fn new(cursor: u64, pattern: Option<&str>, count: Option<u64>) -> Scan {
+        Scan {
+            cursor,
+            pattern: pattern.map(ToString::to_string),
+            count,
+        }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// SCAN cursor [MATCH pattern] [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Scan> {
+        let cursor = parse.next_int()?;
+
+        let mut pattern = None;
+        let mut count = None;
+        loop {
+            match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "MATCH" => pattern = Some(parse.next_string()?),
+                Ok(s) if s.to_uppercase() == "COUNT" => match parse.next_int()? {
+                    0 => return Err(ParseError::Reply("ERR syntax error".to_string()).into()),
+                    n => count = Some(n),
+                },
+                // Only the MATCH and COUNT options are supported.
+                Ok(_) => return Err(ParseError::Reply("ERR syntax error".to_string()).into()),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Scan {
+            cursor,
+            pattern,
+            count,
+        })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let count = self.count.unwrap_or(DEFAULT_COUNT);
+        let (cursor, keys) = db.scan(
+            self.cursor,
+            self.pattern.as_deref(),
+            usize::try_from(count).unwrap_or(usize::MAX),
+        );
+
+        let keys = keys
+            .into_iter()
+            .map(|key| Frame::Bulk(Bytes::from(key)))
+            .collect();
+        let resp_frame = Frame::Array(vec![
+            Frame::Bulk(Bytes::from(cursor.to_string())),
+            Frame::Array(keys),
+        ]);
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for Scan {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("scan".as_bytes()));
+        // Cursors take the whole range of `u64`, beyond that of integer frames.
+        frame.push_bulk(Bytes::from(self.cursor.to_string()));
+        if let Some(pattern) = self.pattern {
+            frame.push_bulk(Bytes::from("match".as_bytes()));
+            frame.push_bulk(Bytes::from(pattern.into_bytes()));
+        }
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from("count".as_bytes()));
+            frame.push_int(count);
+        }
+
+        frame.into()
+    }
+}
@@ -0,0 +1,63 @@
+use crate::cmd::{Parse, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Select the logical database the connection reads and writes keys in.
+///
+/// New connections use the database `0`.
+#[derive(Debug)]
+pub struct Select {
+    index: u64,
+}
+
+impl Select {
+    pub fn new(index: u64) -> Select {
+        Select { index }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing two entries.
+    ///
+    /// ```text
+    /// SELECT index
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Select> {
+        let index = parse.next_int()?;
+
+        Ok(Select { index })
+    }
+
+    /// Replace `db` with the handle of the selected database.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &mut Db, dst: &mut Connection) -> crate::Result<()> {
+        let selected = usize::try_from(self.index)
+            .ok()
+            .and_then(|index| db.select(index));
+
+        let resp_frame = match selected {
+            Some(selected) => {
+                *db = selected;
+                Frame::Simple("OK".to_string())
+            }
+            None => Frame::Error("ERR DB index is out of range".to_string()),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for Select {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("select".as_bytes()));
+        frame.push_int(self.index);
+
+        frame.into()
+    }
+}
@@ -1,8 +1,10 @@
 use crate::cmd::{Parse, ParseError, Protocol};
+use crate::db::instant_at;
 use crate::frame::PushFrame;
 use crate::{Connection, Db, Frame};
 use bytes::Bytes;
-use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Instant};
 use tracing::{debug, instrument};
 
 /// Set `key` `value`.
@@ -17,12 +19,23 @@ use tracing::{debug, instrument};
 ///
 /// * EX `seconds` -- Set the specified expire time, in seconds.
 /// * PX `milliseconds` -- Set the specified expire time, in milliseconds.
+/// * EXAT `timestamp` -- Set the Unix time at which the key expires, in seconds.
+/// * PXAT `timestamp` -- Set the Unix time at which the key expires, in
+///   milliseconds.
+///
+/// An expire time which is not positive, or too far in the future, is refused. A
+/// positive Unix time in the past deletes the key at once.
 #[derive(Debug)]
 pub struct Set {
     key: String,
     value: Bytes,
-    /// When to expire the key
+    /// When to expire the key. Zero stands for a non-positive expire time, which
+    /// is refused once applied.
     expire: Option<Duration>,
+    /// The Unix time at which the key expires, set by `EXAT` and `PXAT` instead of
+    /// `expire`. The Unix epoch stands for a non-positive time, which is refused
+    /// once applied.
+    expire_at: Option<SystemTime>,
 }
 
 impl Set {
@@ -31,6 +44,7 @@ impl Set {
             key: key.to_string(),
             value,
             expire,
+            expire_at: None,
         }
     }
 
@@ -51,7 +65,7 @@ impl Set {
     /// Expects an array frame containing at least 3 entries.
     ///
     /// ```text
-    /// SET key value [EX seconds|PX milliseconds]
+    /// SET key value [EX seconds|PX milliseconds|EXAT timestamp|PXAT timestamp]
     /// ```
     pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Set> {
         use ParseError::EndOfStream;
@@ -61,15 +75,28 @@ impl Set {
 
         // The expiration is optional. If nothing else follows, then it is `None`.
         let mut expire = None;
+        let mut expire_at = None;
         // Attempt to parse `expire`.
         match parse.next_string() {
             Ok(s) if s.to_uppercase() == "EX" => {
-                let secs = parse.next_int()?;
-                expire = Some(Duration::from_secs(secs));
+                let secs = parse.next_signed_int()?;
+                expire = Some(Duration::from_secs(u64::try_from(secs).unwrap_or(0)));
             }
             Ok(s) if s.to_uppercase() == "PX" => {
-                let ms = parse.next_int()?;
-                expire = Some(Duration::from_millis(ms));
+                let ms = parse.next_signed_int()?;
+                expire = Some(Duration::from_millis(u64::try_from(ms).unwrap_or(0)));
+            }
+            Ok(s) if s.to_uppercase() == "EXAT" => {
+                let secs = parse.next_signed_int()?;
+                expire_at = Some(unix_time(Duration::from_secs(
+                    u64::try_from(secs).unwrap_or(0),
+                )));
+            }
+            Ok(s) if s.to_uppercase() == "PXAT" => {
+                let ms = parse.next_signed_int()?;
+                expire_at = Some(unix_time(Duration::from_millis(
+                    u64::try_from(ms).unwrap_or(0),
+                )));
             }
             // Currently, redis-demo does not support any of the other SET options.
             Ok(_) => return Err("currently `SET` only supports the expiration option".into()),
@@ -80,15 +107,32 @@ impl Set {
             Err(err) => return Err(err.into()),
         }
 
-        Ok(Set { key, value, expire })
+        Ok(Set {
+            key,
+            value,
+            expire,
+            expire_at,
+        })
     }
 
     /// [apply]: crate::cmd::Command::apply
     #[instrument(skip(self, db, dst))]
     pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
-        db.set(self.key, self.value, self.expire);
+        let expires_at = match (self.expire, self.expire_at) {
+            (Some(expire), _) if expire.is_zero() => None,
+            (Some(expire), _) => Instant::now().checked_add(expire).map(Some),
+            (None, Some(time)) if time == UNIX_EPOCH => None,
+            (None, Some(time)) => instant_at(time).map(Some),
+            (None, None) => Some(None),
+        };
+        let resp_frame = match expires_at {
+            Some(expires_at) => {
+                db.set(self.key, self.value, expires_at);
+                Frame::Simple("OK".to_string())
+            }
+            None => Frame::Error("ERR invalid expire time in 'set' command".to_string()),
+        };
 
-        let resp_frame = Frame::Simple("OK".to_string());
         debug!(?resp_frame);
         // Write the `resp_frame` to the client
         dst.write_frame(&resp_frame).await?;
@@ -106,8 +150,18 @@ impl Protocol for Set {
         if let Some(ms) = self.expire {
             frame.push_bulk(Bytes::from("px".as_bytes()));
             frame.push_int(ms.as_millis() as u64);
+        } else if let Some(time) = self.expire_at {
+            let unix = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+            frame.push_bulk(Bytes::from("pxat".as_bytes()));
+            frame.push_int(u64::try_from(unix.as_millis()).unwrap_or(u64::MAX));
         }
 
         frame.into()
     }
 }
+
+/// The time `unix` after the Unix epoch, the epoch itself standing for an invalid
+/// time if it is too far in the future to be represented.
+fn unix_time(unix: Duration) -> SystemTime {
+    UNIX_EPOCH.checked_add(unix).unwrap_or(UNIX_EPOCH)
+}
@@ -0,0 +1,112 @@
+use crate::cmd::{Parse, ParseError, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// The number of entries returned by `SLOWLOG GET` without a count, like in Redis.
+const DEFAULT_COUNT: i64 = 10;
+
+/// Inspect the log of the commands which took longer to execute than the
+/// `slowlog-log-slower-than` parameter, in microseconds.
+///
+/// # Subcommands
+///
+/// * GET `[count]` -- Return the `count` newest entries, 10 by default, or all of
+///   them if `count` is negative. Each entry is an array of its id, the Unix time it was logged at, the duration of the
+///   command in microseconds, the command and its arguments, the address of the
+///   client and its name.
+/// * LEN -- Return the number of entries.
+/// * RESET -- Remove all the entries.
+#[derive(Debug)]
+pub enum SlowLogCommand {
+    Get { count: Option<i64> },
+    Len,
+    Reset,
+}
+
+impl SlowLogCommand {
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// SLOWLOG GET [count]
+    /// SLOWLOG LEN
+    /// SLOWLOG RESET
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SlowLogCommand> {
+        match parse.next_string()?.to_uppercase().as_str() {
+            "GET" => {
+                let count = match parse.next_signed_int() {
+                    Ok(count) => Some(count),
+                    Err(ParseError::EndOfStream) => None,
+                    Err(err) => return Err(err.into()),
+                };
+
+                Ok(SlowLogCommand::Get { count })
+            }
+            "LEN" => Ok(SlowLogCommand::Len),
+            "RESET" => Ok(SlowLogCommand::Reset),
+            other => Err(format!("unknown `SLOWLOG` subcommand `{other}`").into()),
+        }
+    }
+
+    /// Apply the subcommand to the slow log of the server holding `db`.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let resp_frame = match self {
+            SlowLogCommand::Get { count } => {
+                let count = count.unwrap_or(DEFAULT_COUNT);
+                // A negative count does not fit and selects all the entries.
+                let entries = db
+                    .slowlog()
+                    .get(usize::try_from(count).unwrap_or(usize::MAX));
+
+                let entries = entries.into_iter().map(|entry| {
+                    let mut frame = vec![];
+                    frame.push_int(entry.id);
+                    frame.push_int(entry.timestamp);
+                    frame.push_int(u64::try_from(entry.duration.as_micros()).unwrap_or(u64::MAX));
+                    frame.push(Frame::Array(
+                        entry.args.into_iter().map(Frame::Bulk).collect(),
+                    ));
+                    frame.push_bulk(Bytes::from(entry.addr));
+                    frame.push_bulk(Bytes::from(entry.name));
+                    Frame::Array(frame)
+                });
+
+                Frame::Array(entries.collect())
+            }
+            SlowLogCommand::Len => Frame::unsigned(db.slowlog().len() as u64),
+            SlowLogCommand::Reset => {
+                db.slowlog().reset();
+                Frame::Simple("OK".to_string())
+            }
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for SlowLogCommand {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("slowlog".as_bytes()));
+        match self {
+            SlowLogCommand::Get { count } => {
+                frame.push_bulk(Bytes::from("get".as_bytes()));
+                if let Some(count) = count {
+                    frame.push(Frame::Integer(count));
+                }
+            }
+            SlowLogCommand::Len => frame.push_bulk(Bytes::from("len".as_bytes())),
+            SlowLogCommand::Reset => frame.push_bulk(Bytes::from("reset".as_bytes())),
+        }
+
+        frame.into()
+    }
+}
@@ -0,0 +1,183 @@
+use crate::cmd::subscribe::{Pending, subscribed_state};
+use crate::cmd::{Parse, ParseError, Protocol, Reset};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame, Shutdown};
+use bytes::Bytes;
+
+/// Send a message into a specific shard channel.
+///
+/// Shard channels live in a key-space of their own: the message only reaches
+/// clients subscribed with `SSUBSCRIBE`, never `SUBSCRIBE` or `PSUBSCRIBE` ones.
+#[derive(Debug)]
+pub struct SPublish {
+    channel: String,
+    message: Bytes,
+}
+
+/// client subscribes to one or more shard channels.
+///
+/// Messages are delivered to the client as `smessage` frames. Like `SUBSCRIBE`,
+/// this puts the client in the subscribed state.
+#[derive(Debug)]
+pub struct SSubscribe {
+    channels: Vec<String>,
+}
+
+/// client unsubscribes one or more shard channels.
+///
+/// When no channels are specified, the client unsubscribes from all the subscribed
+/// shard channels.
+#[derive(Clone, Debug)]
+pub struct SUnsubscribe {
+    channels: Vec<String>,
+}
+
+impl SPublish {
+    pub(crate) fn new(channel: impl ToString, message: Bytes) -> SPublish {
+        SPublish {
+            channel: channel.to_string(),
+            message,
+        }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// SPUBLISH shardchannel message
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SPublish> {
+        let channel = parse.next_string()?;
+        let message = parse.next_bytes()?;
+
+        Ok(SPublish { channel, message })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let n_subscribers = db.spublish(&self.channel, self.message);
+        let frame = Frame::unsigned(n_subscribers as u64);
+
+        dst.write_frame(&frame).await?;
+
+        Ok(())
+    }
+}
+
+impl SSubscribe {
+    pub(crate) fn new(channels: Vec<String>) -> SSubscribe {
+        SSubscribe { channels }
+    }
+
+    pub(crate) fn into_channels(self) -> Vec<String> {
+        self.channels
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// SSUBSCRIBE shardchannel [shardchannel ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SSubscribe> {
+        use ParseError::EndOfStream;
+
+        let mut channels = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => channels.push(s),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(SSubscribe { channels })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<Option<Reset>> {
+        let pending = Pending {
+            shard_channels: self.channels,
+            ..Pending::default()
+        };
+        subscribed_state(pending, db, dst, shutdown).await
+    }
+}
+
+impl SUnsubscribe {
+    pub(crate) fn new(channels: &[String]) -> SUnsubscribe {
+        SUnsubscribe {
+            channels: channels.to_vec(),
+        }
+    }
+
+    pub(crate) fn into_channels(self) -> Vec<String> {
+        self.channels
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing at least one entry.
+    ///
+    /// ```text
+    /// SUNSUBSCRIBE [shardchannel [shardchannel ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<SUnsubscribe, ParseError> {
+        use ParseError::EndOfStream;
+
+        let mut channels = vec![];
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => channels.push(s),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(SUnsubscribe { channels })
+    }
+}
+
+impl Protocol for SPublish {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("spublish".as_bytes()));
+        frame.push_bulk(Bytes::from(self.channel.into_bytes()));
+        frame.push_bulk(self.message);
+
+        frame.into()
+    }
+}
+
+impl Protocol for SSubscribe {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("ssubscribe".as_bytes()));
+        for channel in self.channels {
+            frame.push_bulk(Bytes::from(channel.into_bytes()));
+        }
+
+        frame.into()
+    }
+}
+
+impl Protocol for SUnsubscribe {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("sunsubscribe".as_bytes()));
+        for channel in self.channels {
+            frame.push_bulk(Bytes::from(channel.into_bytes()));
+        }
+
+        frame.into()
+    }
+}
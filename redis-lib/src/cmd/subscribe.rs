@@ -1,4 +1,4 @@
-use crate::cmd::{Parse, ParseError, Protocol, Unknown};
+use crate::cmd::{Parse, ParseError, Protocol, Reset, Unknown};
 use crate::frame::PushFrame;
 use crate::{Command, Connection, Db, Frame, Shutdown};
 use bytes::Bytes;
@@ -10,8 +10,8 @@ use tokio_stream::{Stream, StreamExt, StreamMap};
 /// client subscribes to one or more channels.
 ///
 /// Once the client enters the subscribed state, it is not supposed to issue any
-/// other commands, except for additional `SUBSCRIBE`, `PSUBSCRIBE`, `UNSUBSCRIBE`,
-/// `PUNSUBSCRIBE`, `PING` and `QUIT` commands.
+/// other commands, except for additional `SUBSCRIBE`, `PSUBSCRIBE`, `SSUBSCRIBE`,
+/// `UNSUBSCRIBE`, `PUNSUBSCRIBE`, `SUNSUBSCRIBE`, `PING` and `QUIT` commands.
 #[derive(Debug)]
 pub struct Subscribe {
     channels: Vec<String>,
@@ -28,6 +28,41 @@ pub struct Unsubscribe {
 /// Stream of messages. The stream receives messages from the `broadcast::Receiver`.
 type Messages = Pin<Box<dyn Stream<Item = Bytes> + Send>>;
 
+/// Stream of messages received on a pattern subscription, along with the channel
+/// each message was published to.
+type PatternMessages = Pin<Box<dyn Stream<Item = (String, Bytes)> + Send>>;
+
+/// The active subscriptions of a client in the subscribed state.
+#[derive(Default)]
+struct Subscriptions {
+    channels: StreamMap<String, Messages>,
+    patterns: StreamMap<String, PatternMessages>,
+    shard_channels: StreamMap<String, Messages>,
+}
+
+/// Subscriptions requested by the client which are not active yet.
+#[derive(Default)]
+pub(super) struct Pending {
+    pub(super) channels: Vec<String>,
+    pub(super) patterns: Vec<String>,
+    pub(super) shard_channels: Vec<String>,
+}
+
+impl Subscriptions {
+    /// The number of channels and patterns subscribed to.
+    ///
+    /// Shard channels are counted separately, see `shard_channels`.
+    fn len(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+}
+
+impl Pending {
+    fn is_empty(&self) -> bool {
+        self.channels.is_empty() && self.patterns.is_empty() && self.shard_channels.is_empty()
+    }
+}
+
 impl Subscribe {
     pub(crate) fn new(channels: Vec<String>) -> Subscribe {
         Subscribe { channels }
@@ -38,12 +73,14 @@ impl Subscribe {
     /// Expects an array frame containing two or more entries.
     ///
     /// ```text
-    /// SUBSCRIBE [channel [channel ...]]
+    /// SUBSCRIBE channel [channel ...]
     /// ```
+    ///
+    /// Without any channel, fails with the same error as Redis.
     pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Subscribe> {
         use ParseError::EndOfStream;
 
-        let mut channels = vec![parse.next_string()?];
+        let mut channels = vec![];
 
         loop {
             match parse.next_string() {
@@ -52,6 +89,9 @@ impl Subscribe {
                 Err(err) => return Err(err.into()),
             }
         }
+        if channels.is_empty() {
+            return Err(ParseError::WrongArity("subscribe").into());
+        }
 
         Ok(Subscribe { channels })
     }
@@ -60,61 +100,109 @@ impl Subscribe {
     ///
     /// [apply]: crate::cmd::Command::apply
     pub(crate) async fn apply(
-        mut self,
+        self,
         db: &Db,
         dst: &mut Connection,
         shutdown: &mut Shutdown,
-    ) -> crate::Result<()> {
-        // An individual client may subscribe to multiple channels and may
-        // dynamically add and remove channels from its subscription set. To
-        // handle this, a `StreamMap` is used to track active subscriptions.
-        let mut subscriptions = StreamMap::new();
+    ) -> crate::Result<Option<Reset>> {
+        let pending = Pending {
+            channels: self.channels,
+            ..Pending::default()
+        };
+        subscribed_state(pending, db, dst, shutdown).await
+    }
+}
 
-        loop {
-            // `self.channels` is used to track additional channels to subscribe
-            // to. When new `SUBSCRIBE` commands are received during the
-            // execution of `apply`, the new channels are pushed onto this vec.
-            for channel in self.channels.drain(..) {
-                subscribe_to_channel(channel, &mut subscriptions, db, dst).await?;
-            }
+/// Serve a client in the subscribed state until it disconnects, the server shuts down,
+/// it sends `RESET`, or it unsubscribes from every channel and pattern, returning
+/// to the normal state.
+///
+/// `pending` holds the initial subscriptions requested by the client. The `RESET`
+/// ending the subscribed state is returned, for the connection to apply it.
+pub(super) async fn subscribed_state(
+    mut pending: Pending,
+    db: &Db,
+    dst: &mut Connection,
+    shutdown: &mut Shutdown,
+) -> crate::Result<Option<Reset>> {
+    // An individual client may subscribe to multiple channels and patterns and may
+    // dynamically add and remove them from its subscription set. To handle this,
+    // `StreamMap`s are used to track active subscriptions.
+    let mut subscriptions = Subscriptions::default();
+
+    loop {
+        // `pending` is used to track additional subscriptions. When new `SUBSCRIBE`,
+        // `PSUBSCRIBE` or `SSUBSCRIBE` commands are received, the new channels or
+        // patterns are pushed onto its vecs.
+        for channel in pending.channels.drain(..) {
+            subscribe_to_channel(channel, &mut subscriptions, db, dst).await?;
+        }
+        for pattern in pending.patterns.drain(..) {
+            subscribe_to_pattern(pattern, &mut subscriptions, db, dst).await?;
+        }
+        for channel in pending.shard_channels.drain(..) {
+            subscribe_to_shard_channel(channel, &mut subscriptions, db, dst).await?;
+        }
 
-            // Wait for one of the following to happen:
-            //
-            // - Receive a message from one of the subscribed channels.
-            // - Receive a subscribe or unsubscribe command from the client.
-            // - A server shutdown signal.
-            select! {
-                Some((channel, msg)) = subscriptions.next() => {
-                    dst.write_frame(&make_message_frame(channel, msg)).await?;
-                }
-                res = dst.read_frame() => {
-                    let frame = match res? {
-                        Some(frame) => frame,
-                        // This happens if the remote client has disconnected.
-                        None => return Ok(())
-                    };
-
-                    handle_command(
-                        frame,
-                        &mut self.channels,
-                        &mut subscriptions,
-                        dst,
-                    ).await?;
+        // Wait for one of the following to happen:
+        //
+        // - Receive a message from one of the subscribed channels or patterns.
+        // - Receive a subscribe or unsubscribe command from the client.
+        // - A server shutdown signal.
+        select! {
+            Some((channel, msg)) = subscriptions.channels.next() => {
+                dst.write_frame(&make_message_frame(channel, msg)).await?;
+            }
+            Some((pattern, (channel, msg))) = subscriptions.patterns.next() => {
+                dst.write_frame(&make_pmessage_frame(pattern, channel, msg)).await?;
+            }
+            Some((channel, msg)) = subscriptions.shard_channels.next() => {
+                dst.write_frame(&make_smessage_frame(channel, msg)).await?;
+            }
+            res = dst.read_frame() => {
+                let frame = match res? {
+                    Some(frame) => frame,
+                    // This happens if the remote client has disconnected.
+                    None => return Ok(None)
+                };
+
+                let reset = handle_command(
+                    frame,
+                    &mut pending,
+                    &mut subscriptions,
+                    dst,
+                ).await?;
+                // Dropping the subscriptions unsubscribes from everything.
+                if reset.is_some() {
+                    return Ok(reset);
                 }
-                _ = shutdown.recv() => {
-                    return Ok(());
+                if subscriptions.len() + subscriptions.shard_channels.len() == 0
+                    && pending.is_empty()
+                {
+                    return Ok(None);
                 }
-            };
-        }
+            }
+            _ = shutdown.recv() => {
+                return Ok(None);
+            }
+        };
     }
 }
 
 async fn subscribe_to_channel(
     channel: String,
-    subscriptions: &mut StreamMap<String, Messages>,
+    subscriptions: &mut Subscriptions,
     db: &Db,
     dst: &mut Connection,
 ) -> crate::Result<()> {
+    // Like in Redis, subscribing again to a channel keeps the subscription as is,
+    // only confirmed again with the same count.
+    if subscriptions.channels.contains_key(&channel) {
+        let frame = make_subscribe_frame(channel, subscriptions.len());
+        dst.write_frame(&frame).await?;
+        return Ok(());
+    }
+
     let mut rx = db.subscribe(channel.clone());
 
     let rx = Box::pin(async_stream::stream! {
@@ -129,7 +217,7 @@ async fn subscribe_to_channel(
     });
 
     // Track subscription in this client's subscription set.
-    subscriptions.insert(channel.clone(), rx);
+    subscriptions.channels.insert(channel.clone(), rx);
 
     // Respond with the successful subscription
     let frame = make_subscribe_frame(channel, subscriptions.len());
@@ -138,57 +226,218 @@ async fn subscribe_to_channel(
     Ok(())
 }
 
-/// Handle a command received while inside `Subscribe::apply`.
-/// Only `SUBSCRIBE` and `UNSUBSCRIBE` commands are permitted in this context.
+async fn subscribe_to_pattern(
+    pattern: String,
+    subscriptions: &mut Subscriptions,
+    db: &Db,
+    dst: &mut Connection,
+) -> crate::Result<()> {
+    if subscriptions.patterns.contains_key(&pattern) {
+        let frame = make_psubscribe_frame(pattern, subscriptions.len());
+        dst.write_frame(&frame).await?;
+        return Ok(());
+    }
+
+    let mut rx = db.psubscribe(pattern.clone());
+
+    let rx = Box::pin(async_stream::stream! {
+        loop {
+            match rx.recv().await {
+                Ok(msg) => yield msg,
+                // If we lagged in consuming messages, just resume.
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(_) => break,
+            }
+        }
+    });
+
+    subscriptions.patterns.insert(pattern.clone(), rx);
+
+    let frame = make_psubscribe_frame(pattern, subscriptions.len());
+    dst.write_frame(&frame).await?;
+
+    Ok(())
+}
+
+async fn subscribe_to_shard_channel(
+    channel: String,
+    subscriptions: &mut Subscriptions,
+    db: &Db,
+    dst: &mut Connection,
+) -> crate::Result<()> {
+    if subscriptions.shard_channels.contains_key(&channel) {
+        let frame = make_ssubscribe_frame(channel, subscriptions.shard_channels.len());
+        dst.write_frame(&frame).await?;
+        return Ok(());
+    }
+
+    let mut rx = db.ssubscribe(channel.clone());
+
+    let rx = Box::pin(async_stream::stream! {
+        loop {
+            match rx.recv().await {
+                Ok(msg) => yield msg,
+                // If we lagged in consuming messages, just resume.
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(_) => break,
+            }
+        }
+    });
+
+    subscriptions.shard_channels.insert(channel.clone(), rx);
+
+    let frame = make_ssubscribe_frame(channel, subscriptions.shard_channels.len());
+    dst.write_frame(&frame).await?;
+
+    Ok(())
+}
+
+/// Handle a command received while in the subscribed state.
+/// Only the subscribe and unsubscribe family of commands and `PING` are permitted in
+/// this context.
 ///
-/// Any new subscriptions are appended to `subscribe_to` instead of modifying `subscriptions`.
+/// Any new subscriptions are appended to `pending` instead of modifying `subscriptions`.
 async fn handle_command(
     frame: Frame,
-    subscribe_to: &mut Vec<String>,
-    subscriptions: &mut StreamMap<String, Messages>,
+    pending: &mut Pending,
+    subscriptions: &mut Subscriptions,
     dst: &mut Connection,
-) -> crate::Result<()> {
+) -> crate::Result<Option<Reset>> {
     match Command::from_frame(frame)? {
         Command::Subscribe(subscribe) => {
-            subscribe_to.extend(subscribe.channels.into_iter());
+            pending.channels.extend(subscribe.channels);
+        }
+        Command::PSubscribe(psubscribe) => {
+            pending.patterns.extend(psubscribe.into_patterns());
+        }
+        Command::SSubscribe(ssubscribe) => {
+            pending.shard_channels.extend(ssubscribe.into_channels());
         }
         Command::Unsubscribe(mut unsubscribe) => {
             // If no channels are specified, this requests unsubscribing from all channels.
+            // Like in Redis, a single confirmation without a channel is sent if there
+            // is none.
             if unsubscribe.channels.is_empty() {
-                unsubscribe.channels = subscriptions.keys().map(|s| s.to_string()).collect();
+                unsubscribe.channels = subscriptions.channels.keys().cloned().collect();
+                if unsubscribe.channels.is_empty() {
+                    let resp_frame = make_unsubscribe_frame(None, subscriptions.len());
+                    dst.write_frame(&resp_frame).await?;
+                }
             }
 
             for channel in unsubscribe.channels {
-                subscriptions.remove(&channel);
+                subscriptions.channels.remove(&channel);
 
-                let resp_frame = make_unsubscribe_frame(channel, subscriptions.len());
+                let resp_frame = make_unsubscribe_frame(Some(channel), subscriptions.len());
                 dst.write_frame(&resp_frame).await?;
             }
         }
+        Command::PUnsubscribe(punsubscribe) => {
+            let mut patterns = punsubscribe.into_patterns();
+            // If no patterns are specified, this requests unsubscribing from all patterns.
+            if patterns.is_empty() {
+                patterns = subscriptions.patterns.keys().cloned().collect();
+                if patterns.is_empty() {
+                    let resp_frame = make_punsubscribe_frame(None, subscriptions.len());
+                    dst.write_frame(&resp_frame).await?;
+                }
+            }
+
+            for pattern in patterns {
+                subscriptions.patterns.remove(&pattern);
+
+                let resp_frame = make_punsubscribe_frame(Some(pattern), subscriptions.len());
+                dst.write_frame(&resp_frame).await?;
+            }
+        }
+        Command::SUnsubscribe(sunsubscribe) => {
+            let mut channels = sunsubscribe.into_channels();
+            // If no channels are specified, this requests unsubscribing from all shard channels.
+            if channels.is_empty() {
+                channels = subscriptions.shard_channels.keys().cloned().collect();
+                if channels.is_empty() {
+                    let resp_frame =
+                        make_sunsubscribe_frame(None, subscriptions.shard_channels.len());
+                    dst.write_frame(&resp_frame).await?;
+                }
+            }
+
+            for channel in channels {
+                subscriptions.shard_channels.remove(&channel);
+
+                let resp_frame =
+                    make_sunsubscribe_frame(Some(channel), subscriptions.shard_channels.len());
+                dst.write_frame(&resp_frame).await?;
+            }
+        }
+        Command::Ping(ping) => {
+            ping.apply_subscribed(dst).await?;
+        }
+        Command::Reset(reset) => return Ok(Some(reset)),
         command => {
             let cmd = Unknown::new(command.get_name());
             cmd.apply(dst).await?;
         }
     }
-    Ok(())
+    Ok(None)
 }
 
+// The notifications of the subscribed state are push frames, which RESP3 clients
+// can tell apart from replies, and which are sent as arrays to RESP2 clients.
+
 fn make_subscribe_frame(channel: String, n_subs: usize) -> Frame {
     let mut response = vec![];
     response.push_bulk(Bytes::from_static(b"subscribe"));
     response.push_bulk(Bytes::from(channel));
     response.push_int(n_subs as u64);
 
-    response.into()
+    Frame::Push(response)
 }
 
-fn make_unsubscribe_frame(channel: String, n_subs: usize) -> Frame {
+/// `channel` is `None` when there was no channel to unsubscribe from.
+fn make_unsubscribe_frame(channel: Option<String>, n_subs: usize) -> Frame {
     let mut response = vec![];
     response.push_bulk(Bytes::from_static(b"unsubscribe"));
+    response.push(channel.map_or(Frame::Null, |channel| Frame::Bulk(channel.into())));
+    response.push_int(n_subs as u64);
+
+    Frame::Push(response)
+}
+
+fn make_psubscribe_frame(pattern: String, n_subs: usize) -> Frame {
+    let mut response = vec![];
+    response.push_bulk(Bytes::from_static(b"psubscribe"));
+    response.push_bulk(Bytes::from(pattern));
+    response.push_int(n_subs as u64);
+
+    Frame::Push(response)
+}
+
+fn make_punsubscribe_frame(pattern: Option<String>, n_subs: usize) -> Frame {
+    let mut response = vec![];
+    response.push_bulk(Bytes::from_static(b"punsubscribe"));
+    response.push(pattern.map_or(Frame::Null, |pattern| Frame::Bulk(pattern.into())));
+    response.push_int(n_subs as u64);
+
+    Frame::Push(response)
+}
+
+fn make_ssubscribe_frame(channel: String, n_subs: usize) -> Frame {
+    let mut response = vec![];
+    response.push_bulk(Bytes::from_static(b"ssubscribe"));
     response.push_bulk(Bytes::from(channel));
     response.push_int(n_subs as u64);
 
-    response.into()
+    Frame::Push(response)
+}
+
+fn make_sunsubscribe_frame(channel: Option<String>, n_subs: usize) -> Frame {
+    let mut response = vec![];
+    response.push_bulk(Bytes::from_static(b"sunsubscribe"));
+    response.push(channel.map_or(Frame::Null, |channel| Frame::Bulk(channel.into())));
+    response.push_int(n_subs as u64);
+
+    Frame::Push(response)
 }
 
 /// Creates a message informing the client about a new message on a channel that
@@ -199,7 +448,30 @@ fn make_message_frame(channel: String, msg: Bytes) -> Frame {
     response.push_bulk(Bytes::from(channel));
     response.push_bulk(msg);
 
-    response.into()
+    Frame::Push(response)
+}
+
+/// Creates a message informing the client about a new message on a channel that
+/// matches a pattern the client subscribes to.
+fn make_pmessage_frame(pattern: String, channel: String, msg: Bytes) -> Frame {
+    let mut response = vec![];
+    response.push_bulk(Bytes::from_static(b"pmessage"));
+    response.push_bulk(Bytes::from(pattern));
+    response.push_bulk(Bytes::from(channel));
+    response.push_bulk(msg);
+
+    Frame::Push(response)
+}
+
+/// Creates a message informing the client about a new message on a shard channel
+/// that the client subscribes to.
+fn make_smessage_frame(channel: String, msg: Bytes) -> Frame {
+    let mut response = vec![];
+    response.push_bulk(Bytes::from_static(b"smessage"));
+    response.push_bulk(Bytes::from(channel));
+    response.push_bulk(msg);
+
+    Frame::Push(response)
 }
 
 impl Unsubscribe {
@@ -0,0 +1,66 @@
+use crate::cmd::{Parse, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Swap the contents of two logical databases.
+///
+/// Connections having selected one of the databases see the keys of the other
+/// one from then on.
+#[derive(Debug)]
+pub struct SwapDb {
+    index1: u64,
+    index2: u64,
+}
+
+impl SwapDb {
+    pub fn new(index1: u64, index2: u64) -> SwapDb {
+        SwapDb { index1, index2 }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// SWAPDB index1 index2
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SwapDb> {
+        let index1 = parse.next_int()?;
+        let index2 = parse.next_int()?;
+
+        Ok(SwapDb { index1, index2 })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let swapped = match (usize::try_from(self.index1), usize::try_from(self.index2)) {
+            (Ok(index1), Ok(index2)) => db.swapdb(index1, index2),
+            _ => false,
+        };
+
+        let resp_frame = if swapped {
+            Frame::Simple("OK".to_string())
+        } else {
+            Frame::Error("ERR DB index is out of range".to_string())
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for SwapDb {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("swapdb".as_bytes()));
+        frame.push_int(self.index1);
+        frame.push_int(self.index2);
+
+        frame.into()
+    }
+}
@@ -0,0 +1,396 @@
+use crate::cmd::{Parse, ParseError, Protocol};
+use crate::connection::WriteBuffer;
+use crate::frame::PushFrame;
+use crate::{Command, Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Mark the start of a transaction.
+///
+/// ```text
+/// MULTI
+/// ```
+///
+/// Subsequent commands are queued for atomic execution by `EXEC`.
+#[derive(Debug, Default)]
+pub struct Multi;
+
+/// Execute all the commands queued since `MULTI`.
+///
+/// ```text
+/// EXEC
+/// ```
+///
+/// The reply is an array holding the reply of each queued command, in order. If
+/// any of the keys watched with `WATCH` was modified, the commands are not executed
+/// and the reply is Null.
+#[derive(Debug, Default)]
+pub struct Exec;
+
+/// Abort a transaction, discarding the commands queued since `MULTI`.
+///
+/// ```text
+/// DISCARD
+/// ```
+#[derive(Debug, Default)]
+pub struct Discard;
+
+/// Watch the given keys, so that a later `EXEC` is aborted if any of them is
+/// modified in the meantime.
+///
+/// ```text
+/// WATCH key [key ...]
+/// ```
+#[derive(Debug)]
+pub struct Watch {
+    keys: Vec<String>,
+}
+
+/// Forget about all the keys watched by the connection.
+///
+/// ```text
+/// UNWATCH
+/// ```
+#[derive(Debug, Default)]
+pub struct Unwatch;
+
+/// The commands queued by a connection in a transaction.
+///
+/// A connection is in a transaction from `MULTI` until `EXEC` or `DISCARD`.
+#[derive(Debug, Default)]
+pub(crate) struct Transaction {
+    /// The queued commands, along with their name and arguments for the append-only
+    /// file.
+    queued: Vec<(Command, Vec<Bytes>)>,
+
+    /// Set when a command is rejected while queueing. `EXEC` then discards the
+    /// transaction instead of executing it.
+    aborted: bool,
+}
+
+/// The keys watched by a connection, along with their database and their version at
+/// the time of `WATCH`.
+#[derive(Debug, Default)]
+pub(crate) struct Watched {
+    keys: Vec<(Db, String, u64)>,
+}
+
+impl Multi {
+    pub fn new() -> Multi {
+        Multi
+    }
+
+    /// Start queueing the commands of the connection into `transaction`.
+    #[instrument(skip(self, transaction, dst))]
+    pub(crate) async fn apply(
+        self,
+        transaction: &mut Option<Transaction>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let resp_frame = if transaction.is_some() {
+            Frame::Error("ERR MULTI calls can not be nested".to_string())
+        } else {
+            *transaction = Some(Transaction::default());
+            Frame::Simple("OK".to_string())
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Exec {
+    pub fn new() -> Exec {
+        Exec
+    }
+
+    /// Apply the commands queued in `transaction`, ending the transaction.
+    ///
+    /// No other write command is applied from checking the keys in `watched` until
+    /// the last queued command, see `Db::applying_transaction`. The replies are
+    /// buffered meanwhile, then written at once, so that a slow peer does not hold
+    /// up the other connections.
+    ///
+    /// The keys in `watched` are unwatched in any case. A queued `SELECT` replaces
+    /// `db` for the rest of the transaction and afterwards.
+    #[instrument(skip(self, db, dst, transaction, watched))]
+    pub(crate) async fn apply(
+        self,
+        db: &mut Db,
+        dst: &mut Connection,
+        transaction: &mut Option<Transaction>,
+        watched: &mut Watched,
+    ) -> crate::Result<()> {
+        let transaction_guard = db.applying_transaction().await;
+        let resp_frame = match transaction.take() {
+            None => Frame::Error("ERR EXEC without MULTI".to_string()),
+            Some(Transaction { aborted: true, .. }) => Frame::Error(
+                "EXECABORT Transaction discarded because of previous errors.".to_string(),
+            ),
+            // A watched key was modified, so the commands are not executed.
+            Some(_) if watched.is_modified() => Frame::Null,
+            Some(Transaction { queued, .. }) => {
+                let len = queued.len();
+                let buffer = WriteBuffer::default();
+                let mut replies = Connection::buffering(buffer.clone());
+                replies.set_protocol(dst.protocol());
+                for (cmd, args) in queued {
+                    let logged = cmd.is_logged();
+                    let _applying = if logged {
+                        Some(db.applying_queued().await)
+                    } else {
+                        None
+                    };
+                    match cmd {
+                        Command::Unwatch(cmd) => cmd.apply(watched, &mut replies).await?,
+                        Command::Select(cmd) => cmd.apply(db, &mut replies).await?,
+                        cmd => cmd.apply(db, &mut replies).await?,
+                    }
+                    if logged {
+                        db.propagate(&args);
+                    }
+                }
+                drop(transaction_guard);
+                watched.clear();
+                db.synced_if_always().await;
+
+                // The reply of each command follows the array header. A queued
+                // `HELLO` switches the protocol of the connection.
+                replies.flush().await?;
+                dst.set_protocol(replies.protocol());
+                dst.write_array_len(len).await?;
+                dst.write_encoded(&buffer.take()).await?;
+
+                return Ok(());
+            }
+        };
+        drop(transaction_guard);
+        watched.clear();
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Discard {
+    pub fn new() -> Discard {
+        Discard
+    }
+
+    /// Drop the commands queued in `transaction`, ending the transaction. The keys
+    /// in `watched` are unwatched.
+    #[instrument(skip(self, transaction, watched, dst))]
+    pub(crate) async fn apply(
+        self,
+        transaction: &mut Option<Transaction>,
+        watched: &mut Watched,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let resp_frame = if transaction.take().is_some() {
+            watched.clear();
+            Frame::Simple("OK".to_string())
+        } else {
+            Frame::Error("ERR DISCARD without MULTI".to_string())
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Watch {
+    pub fn new(keys: Vec<String>) -> Watch {
+        Watch { keys }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 2 entries.
+    ///
+    /// ```text
+    /// WATCH key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Watch> {
+        use ParseError::EndOfStream;
+
+        let mut keys = vec![parse.next_string()?];
+
+        loop {
+            match parse.next_string() {
+                Ok(s) => keys.push(s),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Watch { keys })
+    }
+
+    /// Record the current version of the keys into `watched`.
+    ///
+    /// `in_transaction` tells whether the connection is between `MULTI` and `EXEC`,
+    /// where `WATCH` is not allowed.
+    #[instrument(skip(self, db, watched, dst))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        in_transaction: bool,
+        watched: &mut Watched,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let resp_frame = if in_transaction {
+            Frame::Error("ERR WATCH inside MULTI is not allowed".to_string())
+        } else {
+            for key in self.keys {
+                let version = db.version(&key);
+                watched.keys.push((db.clone(), key, version));
+            }
+            Frame::Simple("OK".to_string())
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Unwatch {
+    pub fn new() -> Unwatch {
+        Unwatch
+    }
+
+    /// Clear `watched`.
+    #[instrument(skip(self, watched, dst))]
+    pub(crate) async fn apply(
+        self,
+        watched: &mut Watched,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        watched.clear();
+
+        let resp_frame = Frame::Simple("OK".to_string());
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Watched {
+    /// Returns `true` if any of the watched keys was modified since it was watched.
+    fn is_modified(&self) -> bool {
+        self.keys
+            .iter()
+            .any(|(db, key, version)| db.version(key) != *version)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.keys.clear();
+    }
+}
+
+impl Transaction {
+    /// Queue `cmd`, issued with `args`, for execution by `EXEC`.
+    ///
+    /// Commands which cannot run in a transaction are rejected, which aborts the
+    /// transaction.
+    pub(crate) async fn queue(
+        &mut self,
+        cmd: Command,
+        args: Vec<Bytes>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let resp_frame = match cmd {
+            Command::Unknown(cmd) => {
+                self.abort();
+                return cmd.apply(dst).await;
+            }
+            Command::Subscribe(_)
+            | Command::PSubscribe(_)
+            | Command::SSubscribe(_)
+            | Command::Unsubscribe(_)
+            | Command::PUnsubscribe(_)
+            | Command::SUnsubscribe(_)
+            | Command::Monitor(_)
+            | Command::PSync(_)
+            // They need the state of the connection, which `EXEC` does not have.
+            | Command::Client(_)
+            | Command::Info(_) => {
+                self.abort();
+                Frame::Error(format!(
+                    "ERR Command not allowed inside a transaction: '{}'",
+                    cmd.get_name()
+                ))
+            }
+            cmd => {
+                self.queued.push((cmd, args));
+                Frame::Simple("QUEUED".to_string())
+            }
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+
+    /// Mark the transaction to be discarded by `EXEC`.
+    pub(crate) fn abort(&mut self) {
+        self.aborted = true;
+    }
+}
+
+impl Protocol for Multi {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("multi".as_bytes()));
+
+        frame.into()
+    }
+}
+
+impl Protocol for Exec {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("exec".as_bytes()));
+
+        frame.into()
+    }
+}
+
+impl Protocol for Discard {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("discard".as_bytes()));
+
+        frame.into()
+    }
+}
+
+impl Protocol for Watch {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("watch".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+
+        frame.into()
+    }
+}
+
+impl Protocol for Unwatch {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("unwatch".as_bytes()));
+
+        frame.into()
+    }
+}
@@ -0,0 +1,73 @@
+use crate::cmd::{Parse, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Frame};
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Wait for the writes of the connection to be acknowledged by at least
+/// `numreplicas` replicas, or for `timeout` milliseconds to elapse.
+///
+/// Returns the number of replicas which acknowledged the writes. A `timeout` of
+/// zero waits forever.
+#[derive(Debug)]
+pub struct Wait {
+    numreplicas: u64,
+    timeout: Duration,
+}
+
+impl Wait {
+    pub fn new(numreplicas: u64, timeout: Duration) -> Wait {
+        Wait {
+            numreplicas,
+            timeout,
+        }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// WAIT numreplicas timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Wait> {
+        let numreplicas = parse.next_int()?;
+        let timeout = Duration::from_millis(parse.next_int()?);
+
+        Ok(Wait::new(numreplicas, timeout))
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let acknowledged = wait_for_replicas(self.numreplicas, self.timeout);
+        let resp_frame = Frame::unsigned(acknowledged);
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+/// Wait for `numreplicas` replicas to acknowledge the writes made so far, for at
+/// most `timeout`. Returns the number of replicas which acknowledged them.
+///
+/// The replicas do not acknowledge the write commands they apply, so none is
+/// reported at once.
+fn wait_for_replicas(numreplicas: u64, timeout: Duration) -> u64 {
+    debug!(numreplicas, ?timeout, "no replica to wait for");
+    0
+}
+
+impl Protocol for Wait {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("wait".as_bytes()));
+        frame.push_int(self.numreplicas);
+        frame.push_int(u64::try_from(self.timeout.as_millis()).unwrap_or(u64::MAX));
+
+        frame.into()
+    }
+}
@@ -0,0 +1,91 @@
+use crate::cmd::{Parse, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Wait for the writes of the connection to be flushed to the append-only file of
+/// at least `numlocal` servers, the local one, and of `numreplicas` replicas, or for
+/// `timeout` milliseconds to elapse.
+///
+/// Returns the number of local and replica append-only files which flushed the
+/// writes. A `timeout` of zero waits forever.
+#[derive(Debug)]
+pub struct WaitAof {
+    numlocal: u64,
+    numreplicas: u64,
+    timeout: Duration,
+}
+
+impl WaitAof {
+    pub fn new(numlocal: u64, numreplicas: u64, timeout: Duration) -> WaitAof {
+        WaitAof {
+            numlocal,
+            numreplicas,
+            timeout,
+        }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// WAITAOF numlocal numreplicas timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<WaitAof> {
+        let numlocal = parse.next_int()?;
+        let numreplicas = parse.next_int()?;
+        let timeout = Duration::from_millis(parse.next_int()?);
+
+        Ok(WaitAof::new(numlocal, numreplicas, timeout))
+    }
+
+    /// The replicas do not acknowledge the write commands they apply, so none is
+    /// reported, and only the local append-only file is waited for.
+    ///
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let timeout = if self.numlocal == 0 {
+            // Only report whether the writes are flushed already.
+            Some(Duration::ZERO)
+        } else if self.timeout.is_zero() {
+            None
+        } else {
+            Some(self.timeout)
+        };
+
+        let resp_frame = match db.wait_for_aof(timeout).await {
+            None if self.numlocal > 0 => Frame::Error(
+                "ERR WAITAOF cannot be used when numlocal is set but appendonly is disabled."
+                    .to_string(),
+            ),
+            synced => {
+                debug!(numreplicas = self.numreplicas, "no replica to wait for");
+                Frame::Array(vec![
+                    Frame::unsigned(u64::from(synced == Some(true))),
+                    Frame::unsigned(0),
+                ])
+            }
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for WaitAof {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("waitaof".as_bytes()));
+        frame.push_int(self.numlocal);
+        frame.push_int(self.numreplicas);
+        frame.push_int(u64::try_from(self.timeout.as_millis()).unwrap_or(u64::MAX));
+
+        frame.into()
+    }
+}
@@ -0,0 +1,91 @@
+use crate::cmd::{Parse, ParseError, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Append an entry of field-value pairs to the stream stored at `key`.
+///
+/// If `key` does not exist, a new stream is created. The ID `*` lets the server
+/// generate the entry ID, otherwise the given ID must be greater than the last
+/// ID in the stream.
+#[derive(Debug)]
+pub struct XAdd {
+    key: String,
+    id: String,
+    fields: Vec<(Bytes, Bytes)>,
+}
+
+impl XAdd {
+    pub fn new(key: impl ToString, id: impl ToString, fields: Vec<(Bytes, Bytes)>) -> XAdd {
+        XAdd {
+            key: key.to_string(),
+            id: id.to_string(),
+            fields,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 5 entries.
+    ///
+    /// ```text
+    /// XADD key <* | id> field value [field value ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XAdd> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+        let id = parse.next_string()?;
+
+        let mut fields = vec![(parse.next_bytes()?, parse.next_bytes()?)];
+        loop {
+            match parse.next_bytes() {
+                Ok(field) => fields.push((field, parse.next_bytes()?)),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(XAdd { key, id, fields })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let id = if self.id == "*" {
+            Ok(None)
+        } else {
+            self.id.parse().map(Some)
+        };
+
+        let resp_frame = match id.and_then(|id| db.xadd(self.key, id, self.fields)) {
+            Ok(id) => Frame::Bulk(Bytes::from(id.to_string())),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for XAdd {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("xadd".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.id.into_bytes()));
+        for (field, value) in self.fields {
+            frame.push_bulk(field);
+            frame.push_bulk(value);
+        }
+
+        frame.into()
+    }
+}
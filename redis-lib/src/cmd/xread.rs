@@ -0,0 +1,146 @@
+use crate::cmd::{Parse, ParseError, Protocol};
+use crate::frame::PushFrame;
+use crate::{Connection, Db, Frame};
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Read entries from one or more streams, only returning entries with an ID
+/// greater than the last received ID reported by the caller.
+///
+/// The special ID `$` stands for the last ID in the stream, so only entries added
+/// afterwards would be returned. Currently, only the non-blocking form is supported.
+///
+/// # Options
+///
+/// * COUNT `count` -- Return at most `count` entries per stream.
+#[derive(Debug)]
+pub struct XRead {
+    count: Option<u64>,
+    /// `(key, id)` pairs
+    streams: Vec<(String, String)>,
+}
+
+impl XRead {
+    pub fn new(count: Option<u64>, streams: Vec<(String, String)>) -> XRead {
+        XRead { count, streams }
+    }
+
+    /// # Format
+    ///
+    /// Expects an array frame containing at least 4 entries.
+    ///
+    /// ```text
+    /// XREAD [COUNT count] STREAMS key [key ...] id [id ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XRead> {
+        use ParseError::EndOfStream;
+
+        let mut count = None;
+        loop {
+            match parse.next_string()?.to_uppercase().as_str() {
+                "COUNT" => count = Some(parse.next_int()?),
+                "STREAMS" => break,
+                // Only the COUNT option is supported.
+                _ => return Err(ParseError::Reply("ERR syntax error".to_string()).into()),
+            }
+        }
+
+        let mut args = vec![];
+        loop {
+            match parse.next_string() {
+                Ok(s) => args.push(s),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        // The keys come first, followed by the same number of IDs.
+        if args.is_empty() || args.len() % 2 != 0 {
+            let msg = "ERR Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.";
+            return Err(ParseError::Reply(msg.to_string()).into());
+        }
+        let ids = args.split_off(args.len() / 2);
+        let streams = args.into_iter().zip(ids).collect();
+
+        Ok(XRead { count, streams })
+    }
+
+    /// [apply]: crate::cmd::Command::apply
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let streams = self
+            .streams
+            .into_iter()
+            .map(|(key, id)| {
+                if id == "$" {
+                    Ok((key, None))
+                } else {
+                    id.parse().map(|id| (key, Some(id)))
+                }
+            })
+            .collect::<crate::Result<Vec<_>>>();
+        let count = self
+            .count
+            .map(|count| usize::try_from(count).unwrap_or(usize::MAX));
+
+        let resp_frame = match streams.and_then(|streams| db.xread(streams, count)) {
+            // None of the streams has new entries.
+            Ok(streams) if streams.is_empty() => Frame::Null,
+            // [[key, [[id, [field, value, ...]], ...]], ...]
+            Ok(streams) => streams
+                .into_iter()
+                .map(|(key, entries)| {
+                    let entries = entries
+                        .into_iter()
+                        .map(|(id, fields)| {
+                            let mut values = vec![];
+                            for (field, value) in fields {
+                                values.push_bulk(field);
+                                values.push_bulk(value);
+                            }
+
+                            let mut entry = vec![];
+                            entry.push_bulk(Bytes::from(id.to_string()));
+                            entry.push(values.into());
+                            entry.into()
+                        })
+                        .collect::<Vec<Frame>>();
+
+                    let mut stream = vec![];
+                    stream.push_bulk(Bytes::from(key));
+                    stream.push(entries.into());
+                    stream.into()
+                })
+                .collect::<Vec<Frame>>()
+                .into(),
+            Err(err) => Frame::Error(err.to_string()),
+        };
+
+        debug!(?resp_frame);
+        dst.write_frame(&resp_frame).await?;
+
+        Ok(())
+    }
+}
+
+impl Protocol for XRead {
+    fn into_frame(self) -> Frame {
+        let mut frame = vec![];
+        frame.push_bulk(Bytes::from("xread".as_bytes()));
+        if let Some(count) = self.count {
+            frame.push_bulk(Bytes::from("count".as_bytes()));
+            frame.push_int(count);
+        }
+        frame.push_bulk(Bytes::from("streams".as_bytes()));
+
+        let (keys, ids): (Vec<_>, Vec<_>) = self.streams.into_iter().unzip();
+        for key in keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        for id in ids {
+            frame.push_bulk(Bytes::from(id.into_bytes()));
+        }
+
+        frame.into()
+    }
+}
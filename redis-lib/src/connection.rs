@@ -1,61 +1,369 @@
 use crate::frame::{self, Frame};
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
+use socket2::{SockRef, TcpKeepalive};
+use std::fmt;
 use std::io::{self, Cursor};
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+use tokio::io::{
+    AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter, Chain, ReadBuf, Take,
+};
 use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
+#[cfg(feature = "tls")]
+use tokio_rustls::TlsStream;
 
-/// `Connection` is to read(receive) and write(Send) `Frame` on the underlying `TcpStream`.
+/// The options of a TCP socket, set by the server on the accepted connections and
+/// by `ClientBuilder` on the connections it establishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpOptions {
+    /// Disable Nagle's algorithm (`TCP_NODELAY`), so that small requests and
+    /// replies are sent without delay.
+    pub nodelay: bool,
+
+    /// When set, enable `SO_KEEPALIVE` for the operating system to probe the peer
+    /// once the connection is idle for this long, closing it if the peer is gone.
+    pub keepalive: Option<Duration>,
+}
+
+/// `Connection` is to read(receive) and write(Send) `Frame` on the underlying byte stream.
 ///
 /// `read_buf` is filled up until there are enough bytes to create a full frame. Once this happens,
 /// the `Connection` creates the frame and returns it to the caller.
 #[derive(Debug)]
 pub struct Connection {
-    stream: BufWriter<TcpStream>,
+    stream: BufWriter<Box<dyn Stream>>,
     // The buffer for reading frames.
     read_buf: BytesMut,
+    /// The initial capacity of `read_buf`, which it shrinks back to once it grew
+    /// for a large frame, see `shrink_read_buf`.
+    read_capacity: usize,
+    /// The RESP version spoken by the peer, 2 or 3. Frames only existing in RESP3
+    /// are downgraded when writing to a RESP2 peer.
+    protocol: u8,
+    /// The bounds on the frames read, beyond which the peer is misbehaving.
+    limits: frame::Limits,
+    /// The bytes of the bulk string last streamed by `read_frame_streaming` which
+    /// are still to be read from `stream`, followed by its `\r\n`. They are
+    /// discarded before reading the next frame.
+    unread: usize,
+    /// While set, `write_frame` leaves the frames in the write buffer rather than
+    /// flushing it, see `set_flush_deferred`.
+    flush_deferred: bool,
+    /// The number of frames started being written, see `frames_written`.
+    frames_written: Arc<AtomicU64>,
+}
+
+/// The bytes written to a `Connection::buffering`, kept in memory until taken.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct WriteBuffer(Arc<Mutex<Vec<u8>>>);
+
+/// A frame read by `Connection::read_frame_streaming`.
+#[derive(Debug)]
+pub enum StreamedFrame<'a> {
+    /// A frame read as a whole.
+    Frame(Frame),
+
+    /// A bulk string longer than the threshold, whose payload is read from the
+    /// `BulkReader` as it arrives.
+    Bulk(BulkReader<'a>),
+}
+
+/// The part of a bulk string payload already buffered by a connection, followed by
+/// the rest from its stream.
+type BulkPayload<'a> = Chain<Cursor<Bytes>, Take<&'a mut BufWriter<Box<dyn Stream>>>>;
+
+/// Reads the payload of a bulk string from its `Connection`, without buffering it.
+///
+/// The payload may be left partially read: the rest is discarded when the next
+/// frame is read from the connection.
+#[derive(Debug)]
+pub struct BulkReader<'a> {
+    reader: BulkPayload<'a>,
+    /// The length of the payload.
+    len: usize,
+    /// The bytes left in the buffered part of the payload.
+    buffered: usize,
+    /// The `unread` count of the connection.
+    unread: &'a mut usize,
+}
+
+/// The byte stream underlying a `Connection`.
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send + fmt::Debug {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + fmt::Debug> Stream for T {}
+
+/// The default initial capacity of the buffer frames are read into.
+pub(crate) const BUF_SIZE: usize = 4 * 1024;
+
+/// The length of the longest decimal written: `u64::MAX` has 20 digits, like
+/// `i64::MIN` has 19 and a sign.
+const MAX_DECIMAL_LEN: usize = 20;
+
+/// How many times its initial capacity the read buffer may keep, once drained,
+/// before shrinking back to it. Frames up to that size are read without the buffer
+/// being reallocated every time.
+const SHRINK_FACTOR: usize = 16;
+
+impl TcpOptions {
+    /// Set the options on `stream`.
+    pub fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        stream.set_nodelay(self.nodelay)?;
+        if let Some(time) = self.keepalive {
+            let keepalive = TcpKeepalive::new().with_time(time);
+            SockRef::from(stream).set_tcp_keepalive(&keepalive)?;
+        }
+
+        Ok(())
+    }
 }
 
-const BUF_SIZE: usize = 4 * 1024;
+/// The longest inline command accepted, like in Redis.
+const MAX_INLINE_LEN: usize = 64 * 1024;
 
 impl Connection {
     pub fn new(stream: TcpStream) -> Connection {
+        Connection::from_stream(stream)
+    }
+
+    /// A `Connection` over TLS, established by a client or accepted by the server.
+    #[cfg(feature = "tls")]
+    pub fn new_tls(stream: impl Into<TlsStream<TcpStream>>) -> Connection {
+        Connection::from_stream(stream.into())
+    }
+
+    /// A `Connection` over a Unix domain socket.
+    #[cfg(unix)]
+    pub fn new_unix(stream: UnixStream) -> Connection {
+        Connection::from_stream(stream)
+    }
+
+    /// A `Connection` without a peer: nothing is read and the written frames are
+    /// discarded. Used to apply commands which are not sent by a client.
+    pub(crate) fn discarding() -> Connection {
+        Connection::from_stream(tokio::io::join(tokio::io::empty(), tokio::io::sink()))
+    }
+
+    /// A `Connection` without a peer: nothing is read and the written frames are
+    /// kept in `buffer`, to be sent later on another connection with
+    /// `write_encoded`.
+    pub(crate) fn buffering(buffer: WriteBuffer) -> Connection {
+        Connection::from_stream(tokio::io::join(tokio::io::empty(), buffer))
+    }
+
+    /// A `Connection` over any byte stream, such as an in-memory pipe created by
+    /// `tokio::io::duplex`.
+    pub fn from_stream<S>(stream: S) -> Connection
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + fmt::Debug + 'static,
+    {
+        Connection::with_capacity(stream, BUF_SIZE)
+    }
+
+    /// A `Connection` over any byte stream, reading frames into a buffer of
+    /// `capacity` bytes initially rather than 4 KiB. The buffer grows as needed to
+    /// hold larger frames.
+    pub fn with_capacity<S>(stream: S, capacity: usize) -> Connection
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + fmt::Debug + 'static,
+    {
         Connection {
-            stream: BufWriter::new(stream),
-            read_buf: BytesMut::with_capacity(BUF_SIZE),
+            stream: BufWriter::new(Box::new(stream)),
+            read_buf: BytesMut::with_capacity(capacity),
+            read_capacity: capacity,
+            protocol: 2,
+            limits: frame::Limits::default(),
+            unread: 0,
+            flush_deferred: false,
+            frames_written: Arc::default(),
         }
     }
 
+    /// Returns the RESP version spoken on the connection.
+    pub fn protocol(&self) -> u8 {
+        self.protocol
+    }
+
+    /// Switch the RESP version spoken on the connection, as negotiated by `HELLO`.
+    pub fn set_protocol(&mut self, protocol: u8) {
+        self.protocol = protocol;
+    }
+
+    /// Set the bounds on the frames read. Reading a frame beyond them fails, without
+    /// buffering the frame.
+    pub fn set_limits(&mut self, limits: frame::Limits) {
+        self.limits = limits;
+    }
+
+    /// Returns `true` if bytes of the next frame are already buffered, such as when
+    /// the peer pipelines requests.
+    pub(crate) fn has_buffered_input(&self) -> bool {
+        !self.read_buf.is_empty()
+    }
+
+    /// Defer the flushes of `write_frame` while `deferred` is set, so that the
+    /// replies to pipelined requests are written together. The frames written are
+    /// still flushed before waiting for the peer to send more data, or by `flush`.
+    pub(crate) fn set_flush_deferred(&mut self, deferred: bool) {
+        self.flush_deferred = deferred;
+    }
+
+    /// Returns the number of frames started being written so far, updated while
+    /// the connection is borrowed elsewhere. It tells whether a command started
+    /// replying, after which interrupting it would leave a partial reply.
+    pub(crate) fn frames_written(&self) -> Arc<AtomicU64> {
+        self.frames_written.clone()
+    }
+
     /// Read a single `Frame` value from the underlying stream.
     ///
     /// The function waits until it has retrieved enough data to parse a frame.
     /// Any data remaining in the read buffer after the frame has been parsed is
-    /// kept there for the next call to `read_frame`.
+    /// kept there for the next call to `read_frame`. The frames left in the write
+    /// buffer are flushed before waiting for more data.
     ///
     /// # Returns
     ///
     /// On success, the received frame is returned. If the `TcpStream`
     /// is closed in a way that doesn't break a frame in half, it returns
     /// `None`. Otherwise, an error is returned.
+    ///
+    /// # Errors
+    ///
+    /// A peer closing the connection in the middle of a frame fails with an
+    /// `io::Error` of kind `ConnectionReset`, and malformed data with a
+    /// `frame::Error`, so that callers can tell them apart by downcasting.
     pub async fn read_frame(&mut self) -> crate::Result<Option<Frame>> {
+        self.discard_unread().await?;
+
         loop {
             // Attempt to parse a frame from the buffered data. If enough data
             // has been buffered, the frame is returned.
+            // Parsing the frame consumes the capacity it took.
+            let capacity = self.read_buf.capacity();
             if let Some(frame) = self.parse_frame()? {
+                self.shrink_read_buf(capacity);
                 return Ok(Some(frame));
             }
 
             // There is not enough buffered data to read a frame. Attempt to
             // read more data from `TcpStream`.
             // `0` indicates "end of stream".
-            if 0 == self.stream.read_buf(&mut self.read_buf).await? {
+            if 0 == self.fill_read_buf().await? {
                 // The remote closed the connection. For this to be a clean
                 // shutdown, there should be no data in the read buffer.
                 if self.read_buf.is_empty() {
                     return Ok(None);
                 }
-                return Err("connection reset by peer".into());
+                return Err(reset_by_peer());
+            }
+        }
+    }
+
+    /// Like `read_frame`, except that a bulk string longer than `threshold` bytes is
+    /// not buffered: its payload is read from the returned `BulkReader` as it
+    /// arrives. This bounds the memory used to relay large values.
+    ///
+    /// Only bulk strings sent on their own are streamed, not those inside arrays.
+    pub async fn read_frame_streaming(
+        &mut self,
+        threshold: usize,
+    ) -> crate::Result<Option<StreamedFrame<'_>>> {
+        self.discard_unread().await?;
+
+        loop {
+            let mut buf = Cursor::new(&self.read_buf[..]);
+            match Frame::check_bulk_header(&mut buf, &self.limits) {
+                Ok(Some(len)) if len > threshold => {
+                    let header = buf.position() as usize;
+                    self.read_buf.advance(header);
+
+                    return Ok(Some(StreamedFrame::Bulk(self.bulk_reader(len))));
+                }
+                Ok(_) | Err(frame::Error::Incomplete) => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            let capacity = self.read_buf.capacity();
+            if let Some(frame) = self.parse_frame()? {
+                self.shrink_read_buf(capacity);
+                return Ok(Some(StreamedFrame::Frame(frame)));
+            }
+
+            if 0 == self.fill_read_buf().await? {
+                if self.read_buf.is_empty() {
+                    return Ok(None);
+                }
+                return Err(reset_by_peer());
+            }
+        }
+    }
+
+    /// Read more data from the stream into the read buffer, returning how many
+    /// bytes were read, `0` at the end of the stream.
+    ///
+    /// The frames left in the write buffer are flushed first: the peer may be
+    /// waiting for them before sending more.
+    async fn fill_read_buf(&mut self) -> io::Result<usize> {
+        if !self.stream.buffer().is_empty() {
+            self.stream.flush().await?;
+        }
+
+        self.stream.read_buf(&mut self.read_buf).await
+    }
+
+    /// Shrink the read buffer back to its initial capacity if it grew far beyond it,
+    /// such as to read a large frame, once what is left fits in it. Otherwise, a
+    /// connection would hold the memory of its largest frame until closed.
+    ///
+    /// `capacity` is that of the buffer before the frame just read was consumed.
+    fn shrink_read_buf(&mut self, capacity: usize) {
+        if capacity <= self.read_capacity * SHRINK_FACTOR
+            || self.read_buf.len() > self.read_capacity
+        {
+            return;
+        }
+        let mut read_buf = BytesMut::with_capacity(self.read_capacity);
+        read_buf.extend_from_slice(&self.read_buf);
+        self.read_buf = read_buf;
+    }
+
+    /// Returns a reader of the `len` bytes of payload starting the buffer, the
+    /// header of the bulk string having been consumed.
+    fn bulk_reader(&mut self, len: usize) -> BulkReader<'_> {
+        let buffered = len.min(self.read_buf.len());
+        let head = self.read_buf.split_to(buffered).freeze();
+        let rest = len - buffered;
+        self.unread = rest + 2;
+
+        BulkReader {
+            reader: AsyncReadExt::chain(Cursor::new(head), (&mut self.stream).take(rest as u64)),
+            len,
+            buffered,
+            unread: &mut self.unread,
+        }
+    }
+
+    /// Discard the rest of the bulk string last streamed, see `read_frame_streaming`.
+    async fn discard_unread(&mut self) -> crate::Result<()> {
+        let buffered = self.unread.min(self.read_buf.len());
+        self.read_buf.advance(buffered);
+        self.unread -= buffered;
+
+        if self.unread > 0 {
+            let mut rest = (&mut self.stream).take(self.unread as u64);
+            let discarded = tokio::io::copy(&mut rest, &mut tokio::io::sink()).await?;
+            if discarded < self.unread as u64 {
+                return Err(reset_by_peer());
             }
+            self.unread = 0;
         }
+
+        Ok(())
     }
 
     /// Tries to parse a frame from the buffer. If the buffer contains enough
@@ -65,22 +373,31 @@ impl Connection {
     fn parse_frame(&mut self) -> crate::Result<Option<Frame>> {
         use frame::Error::Incomplete;
 
+        // Data not starting with a frame type byte is an inline command, as typed in
+        // `telnet`.
+        while let Some(&byte) = self.read_buf.first() {
+            if Frame::is_type_byte(byte) {
+                break;
+            }
+            match self.parse_inline()? {
+                // Empty lines are ignored, like in Redis.
+                Some(args) if args.is_empty() => {}
+                Some(args) => return Ok(Some(Frame::Array(args))),
+                None => return Ok(None),
+            }
+        }
+
         // Cursor implements `Buf` from the `bytes` crate
         let mut buf = Cursor::new(&self.read_buf[..]);
 
-        // The first step is to check if enough data has been buffered to parse
-        // a single frame. This step is usually much faster than doing a full
-        // parse of the frame, and allows us to skip allocating data structures
-        // to hold the frame data unless we know the full frame has been
-        // received.
-        match Frame::check(&mut buf) {
-            Ok(_) => {
-                // The `check` function will have advanced the cursor until the end of the frame.
+        // The frame is parsed in a single pass, `parse` failing with `Incomplete` if
+        // it is not fully buffered yet. It is then parsed again from the start once
+        // more data is read, which is rare enough to be cheaper than checking every
+        // frame before parsing it.
+        match Frame::parse(&mut buf, &self.limits) {
+            Ok(frame) => {
+                // The `parse` function will have advanced the cursor until the end of the frame.
                 let len = buf.position() as usize;
-
-                // Reset the position to zero before passing the cursor to `Frame::parse`.
-                buf.set_position(0);
-                let frame = Frame::parse(&mut buf)?;
                 // Discard the parsed data from the read buffer.
                 self.read_buf.advance(len);
 
@@ -93,29 +410,90 @@ impl Connection {
         }
     }
 
+    /// Tries to parse an inline command, a line of space-separated arguments, from
+    /// the buffer. Arguments may be quoted to hold spaces, with double quotes
+    /// allowing escapes like `\n`.
+    ///
+    /// If the buffer holds the whole line, its arguments are returned as bulk frames
+    /// and the line is removed from the buffer.
+    fn parse_inline(&mut self) -> Result<Option<Vec<Frame>>, frame::Error> {
+        let Some(end) = self.read_buf.iter().position(|&byte| byte == b'\n') else {
+            if self.read_buf.len() > MAX_INLINE_LEN {
+                return Err("protocol error; too big inline request".into());
+            }
+            return Ok(None);
+        };
+        if end > MAX_INLINE_LEN {
+            return Err("protocol error; too big inline request".into());
+        }
+
+        let line = self.read_buf.split_to(end + 1);
+        let line = line[..end].strip_suffix(b"\r").unwrap_or(&line[..end]);
+
+        split_inline(line).map(Some)
+    }
+
     /// Write a single `Frame` to the underlying stream.
+    ///
+    /// The stream is flushed, unless flushes are deferred, see
+    /// `set_flush_deferred`.
     pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
-        match frame {
-            Frame::Array(val) => {
-                // Encode the frame type prefix. For an array, it is `*`.
-                self.stream.write_u8(b'*').await?;
-                // Encode the length of the array.
-                self.write_decimal(val.len() as u64).await?;
+        self.write_value(frame).await?;
 
-                for entry in val {
-                    self.write_value(entry).await?;
-                }
-            }
-            // The frame type is a literal. Encode the value directly.
-            _ => self.write_value(frame).await?,
+        if self.flush_deferred {
+            return Ok(());
         }
-
         // flush the calls above.
         self.stream.flush().await
     }
 
-    /// Write a frame literal to the stream.
+    /// Write a single `Frame` to the write buffer, without flushing it. The frame
+    /// is sent once the buffer is full, or flushed by `flush`.
+    pub async fn write_frame_no_flush(&mut self, frame: &Frame) -> io::Result<()> {
+        self.write_value(frame).await
+    }
+
+    /// Flush the frames left in the write buffer to the underlying stream.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush().await
+    }
+
+    /// Write `frames` one after another, flushing the stream once at the end.
+    pub(crate) async fn write_frames(&mut self, frames: &[Frame]) -> io::Result<()> {
+        for frame in frames {
+            self.write_value(frame).await?;
+        }
+
+        self.stream.flush().await
+    }
+
+    /// Write frames encoded already, such as those kept by a `Connection::buffering`.
+    ///
+    /// The stream is flushed, unless flushes are deferred, see
+    /// `set_flush_deferred`.
+    pub(crate) async fn write_encoded(&mut self, encoded: &[u8]) -> io::Result<()> {
+        self.stream.write_all(encoded).await?;
+
+        if self.flush_deferred {
+            return Ok(());
+        }
+        self.stream.flush().await
+    }
+
+    /// Write the header of an array of `len` frames.
+    ///
+    /// The entries are written afterwards, one `write_frame` call each.
+    pub(crate) async fn write_array_len(&mut self, len: usize) -> io::Result<()> {
+        self.frames_written.fetch_add(1, Ordering::Relaxed);
+        self.stream.write_u8(b'*').await?;
+        self.write_decimal(len as u64).await?;
+
+        self.stream.flush().await
+    }
+
+    /// Write a frame to the stream. Arrays are encoded recursively.
     async fn write_value(&mut self, frame: &Frame) -> io::Result<()> {
+        self.frames_written.fetch_add(1, Ordering::Relaxed);
         match frame {
             Frame::Simple(val) => {
                 self.stream.write_u8(b'+').await?;
@@ -132,7 +510,61 @@ impl Connection {
                 self.write_decimal(*val).await?;
             }
             Frame::Null => {
-                self.stream.write_all(b"$-1\r\n").await?;
+                // RESP2 has no null, so a null bulk string is sent instead.
+                if self.protocol >= 3 {
+                    self.stream.write_all(b"_\r\n").await?;
+                } else {
+                    self.stream.write_all(b"$-1\r\n").await?;
+                }
+            }
+            Frame::Double(val) => {
+                let val = format_double(*val);
+                // RESP2 has no doubles, so the number is sent as a bulk string.
+                if self.protocol >= 3 {
+                    self.stream.write_u8(b',').await?;
+                } else {
+                    self.stream.write_u8(b'$').await?;
+                    self.write_decimal(val.len() as u64).await?;
+                }
+                self.stream.write_all(val.as_bytes()).await?;
+                self.stream.write_all(b"\r\n").await?;
+            }
+            Frame::Boolean(val) => {
+                // RESP2 has no booleans, so the value is sent as an integer.
+                if self.protocol >= 3 {
+                    self.stream
+                        .write_all(if *val { b"#t\r\n" } else { b"#f\r\n" })
+                        .await?;
+                } else {
+                    self.stream.write_u8(b':').await?;
+                    self.write_decimal(u64::from(*val)).await?;
+                }
+            }
+            Frame::BigNumber(val) => {
+                // RESP2 has no big numbers, so the digits are sent as a bulk string.
+                if self.protocol >= 3 {
+                    self.stream.write_u8(b'(').await?;
+                } else {
+                    self.stream.write_u8(b'$').await?;
+                    self.write_decimal(val.len() as u64).await?;
+                }
+                self.stream.write_all(val.as_bytes()).await?;
+                self.stream.write_all(b"\r\n").await?;
+            }
+            Frame::Verbatim { format, data } => {
+                // RESP2 has no verbatim strings, so the data is sent as a bulk
+                // string, without its format.
+                if self.protocol >= 3 {
+                    self.stream.write_u8(b'=').await?;
+                    self.write_decimal(4 + data.len() as u64).await?;
+                    self.stream.write_all(format).await?;
+                    self.stream.write_u8(b':').await?;
+                } else {
+                    self.stream.write_u8(b'$').await?;
+                    self.write_decimal(data.len() as u64).await?;
+                }
+                self.stream.write_all(data).await?;
+                self.stream.write_all(b"\r\n").await?;
             }
             Frame::Bulk(val) => {
                 let len = val.len();
@@ -142,20 +574,74 @@ impl Connection {
                 self.stream.write_all(val).await?;
                 self.stream.write_all(b"\r\n").await?;
             }
-            Frame::Array(_) => unreachable!(),
+            Frame::Array(val) => {
+                // Encode the frame type prefix. For an array, it is `*`.
+                self.stream.write_u8(b'*').await?;
+                // Encode the length of the array.
+                self.write_decimal(val.len() as u64).await?;
+
+                for entry in val {
+                    // Boxed as an async fn cannot recurse directly.
+                    Box::pin(self.write_value(entry)).await?;
+                }
+            }
+            Frame::Map(val) => {
+                // RESP2 has no maps, so the keys and values are sent as an array.
+                if self.protocol >= 3 {
+                    self.stream.write_u8(b'%').await?;
+                    self.write_decimal(val.len() as u64).await?;
+                } else {
+                    self.stream.write_u8(b'*').await?;
+                    self.write_decimal(2 * val.len() as u64).await?;
+                }
+
+                for (key, value) in val {
+                    Box::pin(self.write_value(key)).await?;
+                    Box::pin(self.write_value(value)).await?;
+                }
+            }
+            Frame::Set(val) => {
+                // RESP2 has no sets, so the members are sent as an array.
+                let prefix = if self.protocol >= 3 { b'~' } else { b'*' };
+                self.stream.write_u8(prefix).await?;
+                self.write_decimal(val.len() as u64).await?;
+
+                for entry in val {
+                    Box::pin(self.write_value(entry)).await?;
+                }
+            }
+            Frame::Push(val) => {
+                // RESP2 has no push data, so the entries are sent as an array.
+                let prefix = if self.protocol >= 3 { b'>' } else { b'*' };
+                self.stream.write_u8(prefix).await?;
+                self.write_decimal(val.len() as u64).await?;
+
+                for entry in val {
+                    Box::pin(self.write_value(entry)).await?;
+                }
+            }
         }
 
         Ok(())
     }
 
-    /// Write a decimal frame to the stream
-    async fn write_decimal(&mut self, val: u64) -> io::Result<()> {
+    /// Write a possibly negative decimal, as found in the integer frames and the
+    /// headers of the others, to the stream.
+    ///
+    /// Fails instead of writing a truncated value if `val` takes more than
+    /// `MAX_DECIMAL_LEN` characters.
+    async fn write_decimal(&mut self, val: impl fmt::Display) -> io::Result<()> {
         use std::io::Write;
 
         // val should be converted to string before writing it to the stream.
-        let mut buf = [0u8; 20];
+        let mut buf = [0u8; MAX_DECIMAL_LEN];
         let mut buf = Cursor::new(&mut buf[..]);
-        write!(&mut buf, "{}", val)?;
+        write!(&mut buf, "{val}").map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("decimal {val} longer than {MAX_DECIMAL_LEN} characters"),
+            )
+        })?;
 
         let pos = buf.position() as usize;
         self.stream.write_all(&buf.get_ref()[..pos]).await?;
@@ -164,3 +650,184 @@ impl Connection {
         Ok(())
     }
 }
+
+/// The payload of a double frame: `inf`, `-inf` and `nan` for the special values.
+fn format_double(val: f64) -> String {
+    if val.is_nan() {
+        "nan".to_string()
+    } else {
+        // Infinities are formatted as `inf` and `-inf`.
+        val.to_string()
+    }
+}
+
+impl BulkReader<'_> {
+    /// Returns the length of the payload, in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the payload is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl WriteBuffer {
+    /// Returns the bytes written so far, leaving the buffer empty.
+    pub(crate) fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+impl AsyncWrite for WriteBuffer {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for BulkReader<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        ready!(Pin::new(&mut self.reader).poll_read(cx, buf))?;
+        let read = buf.filled().len() - before;
+
+        let from_buffer = read.min(self.buffered);
+        self.buffered -= from_buffer;
+        *self.unread -= read - from_buffer;
+
+        // The stream ended before the payload and its `\r\n`.
+        if read == 0 && buf.remaining() > 0 && *self.unread > 2 {
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The error of a peer closing the connection in the middle of a frame.
+fn reset_by_peer() -> crate::Error {
+    io::Error::new(io::ErrorKind::ConnectionReset, "connection reset by peer").into()
+}
+
+/// Split an inline command into its arguments, honoring quotes.
+fn split_inline(line: &[u8]) -> Result<Vec<Frame>, frame::Error> {
+    const UNBALANCED: &str = "protocol error; unbalanced quotes in request";
+
+    let mut args = vec![];
+    let mut bytes = line.iter().copied().peekable();
+    loop {
+        while bytes.next_if(u8::is_ascii_whitespace).is_some() {}
+        let Some(first) = bytes.next() else {
+            return Ok(args);
+        };
+
+        let mut arg = vec![];
+        match first {
+            b'"' => loop {
+                match bytes.next().ok_or(UNBALANCED)? {
+                    b'"' => break,
+                    b'\\' => arg.push(match bytes.next().ok_or(UNBALANCED)? {
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        other => other,
+                    }),
+                    byte => arg.push(byte),
+                }
+            },
+            b'\'' => loop {
+                match bytes.next().ok_or(UNBALANCED)? {
+                    b'\'' => break,
+                    b'\\' if bytes.peek() == Some(&b'\'') => arg.push(bytes.next().unwrap()),
+                    byte => arg.push(byte),
+                }
+            },
+            byte => {
+                arg.push(byte);
+                while let Some(byte) = bytes.next_if(|byte| !byte.is_ascii_whitespace()) {
+                    arg.push(byte);
+                }
+            }
+        }
+
+        // A closing quote must end the argument.
+        if matches!(first, b'"' | b'\'')
+            && bytes.peek().is_some_and(|byte| !byte.is_ascii_whitespace())
+        {
+            return Err("protocol error; closing quote must be followed by a space".into());
+        }
+        args.push(Frame::Bulk(arg.into()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn read_buf_shrinks_after_large_frame() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let mut client = Connection::from_stream(client);
+        let mut server = Connection::from_stream(server);
+
+        let small = Frame::Bulk("x".repeat(100).into());
+        client.write_frame(&small).await.unwrap();
+        assert_eq!(server.read_frame().await.unwrap().unwrap(), small);
+        assert!(server.read_buf.capacity() <= BUF_SIZE);
+
+        let large = Frame::Bulk("x".repeat(1024 * 1024).into());
+        let (written, read) = tokio::join!(client.write_frame(&large), server.read_frame());
+        written.unwrap();
+        assert_eq!(read.unwrap().unwrap(), large);
+        assert!(server.read_buf.is_empty());
+        assert!(server.read_buf.capacity() <= BUF_SIZE);
+
+        // Small frames keep the buffer within its initial capacity.
+        for _ in 0..100 {
+            client.write_frame(&small).await.unwrap();
+        }
+        for _ in 0..100 {
+            assert_eq!(server.read_frame().await.unwrap().unwrap(), small);
+            assert!(server.read_buf.capacity() <= BUF_SIZE);
+        }
+    }
+
+    #[tokio::test]
+    async fn write_widest_decimals() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut client = Connection::from_stream(client);
+
+        client.write_decimal(u64::MAX).await.unwrap();
+        client.write_decimal(i64::MIN).await.unwrap();
+        let err = client.write_decimal(u128::MAX).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        client.stream.flush().await.unwrap();
+        drop(client);
+
+        let mut written = String::new();
+        server.read_to_string(&mut written).await.unwrap();
+        let mut lines = written.split_terminator("\r\n");
+        assert_eq!(lines.next().unwrap().parse(), Ok(u64::MAX));
+        assert_eq!(lines.next().unwrap().parse(), Ok(i64::MIN));
+        assert_eq!(lines.next(), None);
+    }
+}
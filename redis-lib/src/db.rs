@@ -1,9 +1,28 @@
+use crate::aof::AppendFsync;
+use crate::cmd::{Expiration, ExpireCondition};
+use crate::glob::glob_match;
+use crate::metrics;
+use crate::replication::{Feed, Replication};
+use crate::settings::{KeyspaceEvents, MaxmemoryPolicy};
+use crate::tracking::{Invalidations, Tracking};
+use crate::{AccessLog, Aof, Settings, SlowLog};
 use bytes::Bytes;
-use std::collections::{BTreeSet, HashMap};
-use std::sync::{Arc, Mutex};
-use tokio::sync::{Notify, broadcast};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, RandomState};
+use std::mem::size_of;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::{Notify, OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock, broadcast};
 use tokio::time::{self, Duration, Instant};
-use tracing::debug;
+use tracing::{debug, error};
+
+mod rdb;
 
 #[derive(Debug)]
 pub(crate) struct DbDropGuard {
@@ -12,8 +31,10 @@ pub(crate) struct DbDropGuard {
 
 /// Server state shared across all connections.
 ///
-/// `Db` contains a `HashMap` storing the key/value data and all
-/// `broadcast::Sender` values for active pub/sub channels.
+/// The server holds several logical databases, each a `HashMap` storing key/value
+/// data, and all `broadcast::Sender` values for active pub/sub channels. A `Db`
+/// reads and writes the keys of one of the databases, selected by index. The
+/// pub/sub channels are shared by all the databases.
 ///
 /// Cloning `Db` is shallow and only incurs an atomic ref count increment.
 ///
@@ -22,44 +43,224 @@ pub(crate) struct DbDropGuard {
 #[derive(Debug, Clone)]
 pub(crate) struct Db {
     shared: Arc<Shared>,
+
+    /// The index of the selected database in `State::databases`.
+    index: usize,
 }
 
+/// Default number of logical databases.
+pub(crate) const DEFAULT_DATABASES: usize = 16;
+
+/// Default capacity of the pub/sub broadcast channels.
+pub(crate) const DEFAULT_PUBSUB_CAPACITY: usize = 1024;
+
+/// The greatest capacity of the pub/sub broadcast channels, which allocate it up
+/// front for every channel.
+pub(crate) const MAX_PUBSUB_CAPACITY: usize = 1 << 20;
+
+/// The error of the commands applied to a key holding another type of value than
+/// the one they expect.
+const WRONGTYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+/// The access frequency of a new key, so that it is not evicted before it had a
+/// chance to be read, like in Redis.
+const LFU_INIT_VAL: u8 = 5;
+
+/// The number of keys sampled from each database to pick the key to evict, like the
+/// default `maxmemory-samples` of Redis.
+const MAXMEMORY_SAMPLES: usize = 5;
+
+/// The reference count reported for the shared integers, like in Redis.
+const SHARED_REFCOUNT: u64 = i32::MAX as u64;
+
+/// Strings holding an integer from 0 to `SHARED_INTEGERS` excluded are reported as
+/// shared, like the integers Redis allocates once at startup.
+const SHARED_INTEGERS: i64 = 10_000;
+
 #[derive(Debug)]
 struct Shared {
     state: Mutex<State>,
 
     /// Notifies the background task handling entry expiration or the shutdown signal.
     background_task: Notify,
+
+    /// When the server started, reported by `INFO`.
+    started_at: Instant,
+
+    /// The Unix time in seconds of the last snapshot of the keyspace, or of the
+    /// start of the server until the first one.
+    last_save: AtomicU64,
+
+    /// Set while a snapshot is written, so that there is only one at a time.
+    saving: AtomicBool,
+
+    /// Cleared to stop the background task from purging expired keys, leaving them
+    /// to be removed when read, see `DEBUG SET-ACTIVE-EXPIRE`.
+    active_expire: AtomicBool,
+
+    /// The parameters read with `CONFIG GET` and changed with `CONFIG SET`.
+    settings: Mutex<Settings>,
+
+    /// The commands which took the longest to execute, see `SLOWLOG`.
+    slowlog: Mutex<SlowLog>,
+
+    /// Broadcasts the commands processed by the server to the `MONITOR` clients.
+    monitor: broadcast::Sender<String>,
+
+    /// The append-only file logging the write commands, once enabled.
+    aof: OnceLock<Aof>,
+
+    /// The access log recording the commands processed, once enabled.
+    access_log: OnceLock<AccessLog>,
+
+    /// Held for reading from applying a write command until it is propagated, and
+    /// for writing while starting to rewrite the append-only file or to synchronize
+    /// a replica, so that they start in between commands.
+    applying: Arc<RwLock<()>>,
+
+    /// Held for writing while `EXEC` applies a transaction, and for reading by any
+    /// other write command, so that none is applied in the middle of a transaction.
+    transaction: Arc<RwLock<()>>,
+
+    /// The replicas of the server, and its primary if it is a replica.
+    replication: Replication,
 }
 
 #[derive(Debug)]
 struct State {
-    entries: HashMap<String, Entry>,
+    /// The logical databases, selected with `SELECT`.
+    databases: Vec<Keyspace>,
 
-    /// The pub/sub key-space.  
+    /// The pub/sub key-space.
+    ///
+    /// A channel is removed once its last `ChannelReceiver` is dropped.
     pub_sub: HashMap<String, broadcast::Sender<Bytes>>,
 
+    /// Pattern subscriptions, keyed by glob pattern. Messages carry the channel they
+    /// were published to.
+    ///
+    /// A pattern is removed once its last `ChannelReceiver` is dropped.
+    pattern_pub_sub: HashMap<String, broadcast::Sender<(String, Bytes)>>,
+
+    /// The sharded pub/sub key-space, kept apart from `pub_sub`: messages sent with
+    /// `SPUBLISH` only reach `SSUBSCRIBE` subscribers.
+    ///
+    /// A shard channel is removed once its last `ChannelReceiver` is dropped.
+    shard_pub_sub: HashMap<String, broadcast::Sender<Bytes>>,
+
+    /// The keys read by the connections with tracking enabled, see `CLIENT TRACKING`.
+    tracking: Tracking,
+
+    /// The last version given to an `Entry`, in any of the databases.
+    version: u64,
+
+    /// Setting this to `true` signals to the background task to exit.
+    shutdown: bool,
+}
+
+/// The keys of a logical database.
+#[derive(Debug, Default)]
+struct Keyspace {
+    entries: HashMap<String, Entry>,
+
     /// Tracks key TTLs.
     ///
     /// A `BTreeSet` is used to maintain expirations sorted by when they expire.
     /// This allows the background task to iterate this map to find the value expiring next.
     expirations: BTreeSet<(Instant, String)>,
 
-    /// Setting this to `true` signals to the background task to exit.
-    shutdown: bool,
+    /// The sum of the `Entry::memory_usage` of the entries, kept up to date by
+    /// `insert` and `remove`.
+    used_memory: usize,
+
+    /// The keys sorted by `scan_position`, which `SCAN` iterates in order. Unlike a
+    /// position in `entries`, which changes whenever the map grows, the position of
+    /// a key never changes, so that a cursor stays valid as keys come and go.
+    scan_order: BTreeSet<(u64, String)>,
+
+    /// The version of the last deletion of a key, which is the version of any
+    /// missing key: `WATCH` then detects a key created and deleted in the meantime.
+    deleted: u64,
+}
+
+/// Held from applying a write command until it is propagated, see `Db::applying`.
+#[derive(Debug)]
+pub(crate) struct Applying {
+    _transaction: Option<OwnedRwLockReadGuard<()>>,
+    _applying: OwnedRwLockReadGuard<()>,
 }
 
 /// key-value
 #[derive(Debug)]
 struct Entry {
-    data: Bytes,
+    value: Value,
 
     expires_at: Option<Instant>,
+
+    /// Changes on every write to the key, so that `WATCH` can detect modifications.
+    version: u64,
+
+    /// When the key was last read or written. The least recently accessed keys
+    /// are evicted first by the `allkeys-lru` policy.
+    last_accessed: Instant,
+
+    /// How many times the key was read or written, from `LFU_INIT_VAL` and up to
+    /// 255. The least frequently accessed keys are evicted first by the
+    /// `allkeys-lfu` policy. Unlike in Redis, the counter is linear and does not
+    /// decay over time.
+    frequency: u8,
+}
+
+/// The value stored at a key.
+#[derive(Debug, Clone)]
+pub(crate) enum Value {
+    String(Bytes),
+    Stream(Stream),
+}
+
+/// An append-only log of field-value pairs, ordered by `StreamId`.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Stream {
+    entries: BTreeMap<StreamId, Vec<(Bytes, Bytes)>>,
+
+    /// The greatest ID ever added to the stream. New IDs must be greater than it.
+    last_id: StreamId,
+}
+
+/// A key of a snapshot: the index of its database, the key, its value and when it
+/// expires.
+pub(crate) type SnapshotKey = (usize, String, Value, Option<Instant>);
+
+/// An entry read from a stream.
+pub(crate) type StreamEntry = (StreamId, Vec<(Bytes, Bytes)>);
+
+/// ID of a stream entry, in the form `<milliseconds>-<sequence>`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct StreamId {
+    ms: u64,
+    seq: u64,
+}
+
+/// Receives the messages published to a channel, or to the channels matching a
+/// pattern.
+///
+/// Dropping the last receiver of a channel removes the channel from the pub/sub
+/// key-space, so channels nobody listens to anymore do not accumulate.
+#[derive(Debug)]
+pub(crate) struct ChannelReceiver<T = Bytes> {
+    /// Always `Some`, until taken in `drop`.
+    rx: Option<broadcast::Receiver<T>>,
+    channel: String,
+    db: Db,
+    /// The pub/sub key-space holding the channel.
+    channels: fn(&mut State) -> &mut HashMap<String, broadcast::Sender<T>>,
 }
 
 impl DbDropGuard {
-    pub(crate) fn new() -> DbDropGuard {
-        DbDropGuard { db: Db::new() }
+    pub(crate) fn new(databases: usize, pubsub_capacity: usize) -> DbDropGuard {
+        DbDropGuard {
+            db: Db::new(databases, pubsub_capacity),
+        }
     }
 
     pub(crate) fn db(&self) -> Db {
@@ -73,188 +274,2365 @@ impl Drop for DbDropGuard {
         state.shutdown = true;
         drop(state);
 
+        // The link to the primary holds the state as well.
+        self.db.shared.replication.unfollow();
+
         self.db.shared.background_task.notify_one();
     }
 }
 
 impl Db {
-    /// Create an empty `Db` instance, spawns a background task to manage key expiration.
-    pub(crate) fn new() -> Db {
+    /// Create `databases` empty databases, spawns a background task to manage key
+    /// expiration. The returned `Db` selects the database `0`.
+    ///
+    /// The pub/sub channels buffer up to `pubsub_capacity` messages per subscriber.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `databases` or `pubsub_capacity` is zero, or if `pubsub_capacity`
+    /// exceeds `MAX_PUBSUB_CAPACITY`.
+    pub(crate) fn new(databases: usize, pubsub_capacity: usize) -> Db {
+        assert!(databases > 0, "the number of databases must be positive");
+        assert!(pubsub_capacity > 0, "pub/sub capacity must be positive");
+        assert!(
+            pubsub_capacity <= MAX_PUBSUB_CAPACITY,
+            "pub/sub capacity must be at most {MAX_PUBSUB_CAPACITY}"
+        );
+
         let shared = Arc::new(Shared {
             state: Mutex::new(State {
-                entries: HashMap::new(),
+                databases: (0..databases).map(|_| Keyspace::default()).collect(),
                 pub_sub: HashMap::new(),
-                expirations: BTreeSet::new(),
+                pattern_pub_sub: HashMap::new(),
+                shard_pub_sub: HashMap::new(),
+                tracking: Tracking::default(),
+                version: 0,
                 shutdown: false,
             }),
             background_task: Notify::new(),
+            started_at: Instant::now(),
+            last_save: AtomicU64::new(unix_time().as_secs()),
+            saving: AtomicBool::new(false),
+            active_expire: AtomicBool::new(true),
+            settings: Mutex::new(Settings::new(databases, pubsub_capacity)),
+            slowlog: Mutex::default(),
+            monitor: broadcast::channel(pubsub_capacity).0,
+            aof: OnceLock::new(),
+            access_log: OnceLock::new(),
+            applying: Arc::default(),
+            transaction: Arc::default(),
+            replication: Replication::new(),
         });
 
         // Start the background task.
         tokio::spawn(purge_expired_tasks(shared.clone()));
 
-        Db { shared }
+        Db { shared, index: 0 }
     }
 
-    /// Get the value associated with a key.
-    ///
-    /// Returns `None` if there is no value associated with the key. This may be
-    /// due to never having assigned a value to the key or a previously assigned
-    /// value expired.
-    pub(crate) fn get(&self, key: &str) -> Option<Bytes> {
+    /// Returns a `Db` for the database at `index`, or `None` if there is no such
+    /// database.
+    pub(crate) fn select(&self, index: usize) -> Option<Db> {
         let state = self.shared.state.lock().unwrap();
-        // `Bytes::clone` is a shallow clone
-        state.entries.get(key).map(|entry| entry.data.clone())
+
+        (index < state.databases.len()).then(|| Db {
+            shared: self.shared.clone(),
+            index,
+        })
+    }
+
+    /// The index of the selected database.
+    pub(crate) fn index(&self) -> usize {
+        self.index
     }
 
-    /// Set the value associated with a key along with an optional expiration.
+    /// Returns a `Receiver` for the commands processed by the server.
+    pub(crate) fn monitor(&self) -> broadcast::Receiver<String> {
+        self.shared.monitor.subscribe()
+    }
+
+    /// Send the command described by `line` to the `MONITOR` clients.
     ///
-    /// If a value is already associated with the key, it is removed.
-    pub(crate) fn set(&self, key: String, value: Bytes, expire: Option<Duration>) {
-        let mut state = self.shared.state.lock().unwrap();
+    /// `line` is only called when there are clients to send it to.
+    pub(crate) fn feed_monitors(&self, line: impl FnOnce() -> String) {
+        if self.shared.monitor.receiver_count() > 0 {
+            let _ = self.shared.monitor.send(line());
+        }
+    }
 
-        // If this `set` becomes the key that expires **next**, the background
-        // task needs to be notified so it can update its state.
-        let mut notify = false;
+    /// Log the write commands applied from now on to `aof`.
+    ///
+    /// Has no effect if the append-only file is already enabled.
+    pub(crate) fn enable_aof(&self, aof: Aof) {
+        let _ = self.shared.aof.set(aof);
+    }
 
-        let expires_at = expire.map(|duration| {
-            let when = Instant::now() + duration;
-            // Only notify the worker task if the newly inserted expiration is the
-            // **next** key to evict.
-            notify = state
-                .next_expiration()
-                .map(|expiration| expiration > when)
-                .unwrap_or(true);
+    /// Record the commands processed from now on to `access_log`.
+    ///
+    /// Has no effect if the access log is already enabled.
+    pub(crate) fn enable_access_log(&self, access_log: AccessLog) {
+        let _ = self.shared.access_log.set(access_log);
+    }
 
-            when
-        });
+    /// Record the command `name`, sent by the client `id` to the selected database,
+    /// to the access log if it is enabled. A failure is logged rather than reported
+    /// to the client.
+    pub(crate) fn log_access(&self, id: u64, name: &str, key: Option<&str>) {
+        if let Some(access_log) = self.shared.access_log.get() {
+            if let Err(err) = access_log.record(id, self.index, name, key) {
+                error!(cause = %err, "failed to write to the access log");
+            }
+        }
+    }
 
-        // Insert the entry into the `HashMap`.
-        let prev = state.entries.insert(
-            key.clone(),
-            Entry {
-                data: value,
-                expires_at,
-            },
-        );
+    /// Propagate the write command `args`, applied to the selected database: append
+    /// it to the append-only file if it is enabled, and send it to the replicas.
+    /// Relative timeouts are made absolute beforehand, see `absolute_expirations`.
+    ///
+    /// The command has been applied already, so a failure is logged rather than
+    /// reported to the client.
+    pub(crate) fn propagate(&self, args: &[Bytes]) {
+        self.propagate_to(self.index, args);
+    }
 
-        // If there was a value previously associated with the key **and** it
-        // had an expiration time. The associated entry in the `expirations` map
-        // must also be removed. This avoids leaking data.
-        if let Some(prev) = prev {
-            if let Some(when) = prev.expires_at {
-                state.expirations.remove(&(when, key.clone()));
+    /// Like `propagate`, for a command applied to the database at `index`.
+    fn propagate_to(&self, index: usize, args: &[Bytes]) {
+        let absolute = absolute_expirations(args);
+        let args = absolute.as_deref().unwrap_or(args);
+        if let Some(aof) = self.shared.aof.get() {
+            if let Err(err) = aof.append(index, args) {
+                error!(cause = %err, "failed to append to the append-only file");
             }
         }
+        self.shared.replication.feed(index, args);
+    }
 
-        if let Some(when) = expires_at {
-            state.expirations.insert((when, key));
+    /// Propagate a `DEL` of each key `evicted` by `State::evict`, with the index of
+    /// its database, so that it is neither restored from the append-only file nor
+    /// kept by the replicas.
+    fn propagate_evictions(&self, evicted: Vec<(usize, String)>) {
+        for (index, key) in evicted {
+            self.propagate_to(index, &[Bytes::from_static(b"del"), Bytes::from(key)]);
         }
+    }
 
-        drop(state);
+    /// Wait for the write commands propagated so far to be flushed to the
+    /// append-only file, for at most `timeout` if any.
+    ///
+    /// Returns `None` if the append-only file is disabled, or whether the commands
+    /// were flushed in time. A failure to flush is logged, and reported as a timeout.
+    pub(crate) async fn wait_for_aof(&self, timeout: Option<Duration>) -> Option<bool> {
+        let aof = self.shared.aof.get()?;
+        let synced = match timeout {
+            Some(timeout) => time::timeout(timeout, aof.synced()).await.ok(),
+            None => Some(aof.synced().await),
+        };
+        match synced {
+            Some(Ok(())) => Some(true),
+            Some(Err(err)) => {
+                error!(cause = %err, "failed to sync the append-only file");
+                Some(false)
+            }
+            None => Some(false),
+        }
+    }
 
-        if notify {
-            self.shared.background_task.notify_one();
+    /// With `appendfsync always`, wait for the write commands propagated so far to be
+    /// flushed to the append-only file, so that they are acknowledged only then.
+    pub(crate) async fn synced_if_always(&self) {
+        let Some(aof) = self.shared.aof.get() else {
+            return;
+        };
+        if aof.fsync() == AppendFsync::Always {
+            // The commands are flushed by a background thread, which retries on failure.
+            let _ = aof.synced().await;
         }
     }
 
-    /// Returns a `Receiver` for the requested channel.
-    pub(crate) fn subscribe(&self, key: String) -> broadcast::Receiver<Bytes> {
-        use std::collections::hash_map::Entry;
+    /// Returns a guard to hold from applying a write command until it is propagated,
+    /// so that `rewrite_aof_in_background` and `sync_replica` see either both or
+    /// neither. The command waits for any transaction being applied, see
+    /// `applying_transaction`.
+    pub(crate) async fn applying(&self) -> Applying {
+        let transaction = self.shared.transaction.clone().read_owned().await;
+        Applying {
+            _transaction: Some(transaction),
+            _applying: self.shared.applying.clone().read_owned().await,
+        }
+    }
 
-        let mut state = self.shared.state.lock().unwrap();
+    /// Returns a guard to hold while applying a transaction, see `EXEC`, so that no
+    /// other write command is applied meanwhile. The commands of the transaction
+    /// are applied with the guards of `applying_queued`.
+    pub(crate) async fn applying_transaction(&self) -> OwnedRwLockWriteGuard<()> {
+        self.shared.transaction.clone().write_owned().await
+    }
 
-        match state.pub_sub.entry(key) {
-            Entry::Occupied(e) => e.get().subscribe(),
-            // No broadcast channel exists yet, so create one.
-            Entry::Vacant(e) => {
-                let (tx, rx) = broadcast::channel(1024);
-                e.insert(tx);
+    /// Like `applying`, for a command of the transaction guarded by
+    /// `applying_transaction`.
+    pub(crate) async fn applying_queued(&self) -> Applying {
+        Applying {
+            _transaction: None,
+            _applying: self.shared.applying.clone().read_owned().await,
+        }
+    }
 
-                rx
+    /// The replicas of the server, and its primary if it is a replica.
+    pub(crate) fn replication(&self) -> &Replication {
+        &self.shared.replication
+    }
+
+    /// Returns a snapshot of all the databases for a new replica, see `PSYNC`, and
+    /// the write commands applied after it is taken.
+    pub(crate) async fn sync_replica(&self) -> (Vec<u8>, Feed) {
+        let _paused = self.shared.applying.clone().write_owned().await;
+        (self.encode_snapshot(), self.shared.replication.subscribe())
+    }
+
+    /// Rewrite the append-only file with the commands reproducing the databases,
+    /// see `BGREWRITEAOF`.
+    ///
+    /// The commands are listed under the lock, then written by a background task.
+    pub(crate) async fn rewrite_aof_in_background(&self) -> crate::Result<()> {
+        let Some(aof) = self.shared.aof.get() else {
+            return Err("the append-only file is disabled".into());
+        };
+
+        let commands = {
+            let _paused = self.shared.applying.clone().write_owned().await;
+            aof.start_rewrite()?;
+            self.aof_commands()
+        };
+
+        let db = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let aof = db.shared.aof.get().unwrap();
+            if let Err(err) = aof.finish_rewrite(&commands) {
+                error!(cause = %err, "failed to rewrite the append-only file");
             }
-        }
+        });
+
+        Ok(())
     }
 
-    /// Publish a message to the channel. Returns the number of subscribers listening on the channel.
-    pub(crate) fn publish(&self, key: &str, value: Bytes) -> usize {
+    /// The commands reproducing all the databases: a `SELECT` for each database
+    /// holding keys, followed by a `SET` for each string and an `XADD` for each
+    /// stream entry. Keys with a timeout expire at the same Unix time once replayed:
+    /// strings are set with `PXAT`, and streams followed by a `PEXPIREAT`.
+    fn aof_commands(&self) -> Vec<Vec<Bytes>> {
         let state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+        let unix_now = unix_time();
+        // Rounded up, so that the key does not expire early.
+        let unix_millis = |when: Instant| {
+            let at = unix_now + when.saturating_duration_since(now);
+            Bytes::from(at.as_micros().div_ceil(1000).to_string())
+        };
+        let mut commands = Vec::new();
 
-        state
-            .pub_sub
-            .get(key)
-            // On a successful message send on the broadcast channel, the number
-            // of subscribers is returned. An error indicates there are no
-            // receivers, in which case, `0` should be returned.
-            .map(|tx| tx.send(value).unwrap_or(0))
-            // If there is no entry for the channel key, then there are no subscribers.
-            .unwrap_or(0)
+        for (index, keyspace) in state.databases.iter().enumerate() {
+            if keyspace.entries.is_empty() {
+                continue;
+            }
+            commands.push(vec![Bytes::from("SELECT"), Bytes::from(index.to_string())]);
+
+            for (key, entry) in &keyspace.entries {
+                // Not purged yet, but gone already.
+                if entry.expires_at.is_some_and(|when| when <= now) {
+                    continue;
+                }
+                let key = Bytes::from(key.clone());
+                match &entry.value {
+                    Value::String(value) => {
+                        let mut args = vec![Bytes::from("SET"), key, value.clone()];
+                        if let Some(when) = entry.expires_at {
+                            args.push(Bytes::from("PXAT"));
+                            args.push(unix_millis(when));
+                        }
+                        commands.push(args);
+                    }
+                    Value::Stream(stream) => {
+                        for (id, fields) in &stream.entries {
+                            let mut args = vec![
+                                Bytes::from("XADD"),
+                                key.clone(),
+                                Bytes::from(id.to_string()),
+                            ];
+                            for (field, value) in fields {
+                                args.push(field.clone());
+                                args.push(value.clone());
+                            }
+                            commands.push(args);
+                        }
+                        if let Some(when) = entry.expires_at {
+                            commands.push(vec![Bytes::from("PEXPIREAT"), key, unix_millis(when)]);
+                        }
+                    }
+                }
+            }
+        }
+
+        commands
     }
-}
 
-impl Shared {
-    /// Purge all expired keys and return the `Instant` at which the **next**
-    /// key will expire. The background task will sleep until this instant.
-    fn purge_expired_keys(&self) -> Option<Instant> {
-        let mut state = self.state.lock().unwrap();
+    /// Write a snapshot of all the databases to `path`, see `SAVE`.
+    ///
+    /// The snapshot is taken under the lock, then written to a temporary file
+    /// renamed to `path`, so that `path` always holds a complete snapshot.
+    pub(crate) fn dump_to(&self, path: &Path) -> crate::Result<()> {
+        self.start_saving()?;
+        let res = self.write_snapshot(path, &self.encode_snapshot());
+        self.shared.saving.store(false, Ordering::Release);
 
-        if state.shutdown {
-            return None;
+        res
+    }
+
+    /// Like `dump_to`, but the snapshot is encoded and written by a background
+    /// task, see `BGSAVE`. Only taking the snapshot holds the lock.
+    pub(crate) fn dump_in_background(&self, path: PathBuf) -> crate::Result<()> {
+        self.start_saving()?;
+        let keys = self.snapshot();
+
+        let db = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let snapshot = rdb::encode(&keys, Instant::now(), unix_time());
+            if let Err(err) = db.write_snapshot(&path, &snapshot) {
+                error!(cause = %err, ?path, "failed to save snapshot");
+            }
+            db.shared.saving.store(false, Ordering::Release);
+        });
+
+        Ok(())
+    }
+
+    /// Fails if a snapshot is already being written.
+    fn start_saving(&self) -> crate::Result<()> {
+        if self.shared.saving.swap(true, Ordering::Acquire) {
+            return Err("Background save already in progress".into());
         }
 
-        // This is needed to make the borrow checker happy. In short, `lock()`
-        // returns a `MutexGuard` and not a `&mut State`. The borrow checker is
-        // not able to see "through" the mutex guard and determine that it is
-        // safe to access both `state.expirations` and `state.entries` mutably,
-        let state = &mut *state;
+        Ok(())
+    }
 
-        // Find all keys scheduled to expire **before** now.
+    /// Returns a copy of the live keys of all the databases, ordered by database,
+    /// for inspecting or exporting them without holding the lock. Expired keys
+    /// which are not deleted yet are left out.
+    ///
+    /// Copying a value is cheap: strings are reference counted.
+    pub(crate) fn snapshot(&self) -> Vec<SnapshotKey> {
+        let state = self.shared.state.lock().unwrap();
         let now = Instant::now();
 
-        while let Some(&(when, ref key)) = state.expirations.iter().next() {
-            if when > now {
-                return Some(when);
+        let mut keys = vec![];
+        for (index, keyspace) in state.databases.iter().enumerate() {
+            let live = keyspace
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.expires_at.is_none_or(|when| when > now));
+            for (key, entry) in live {
+                keys.push((index, key.clone(), entry.value.clone(), entry.expires_at));
             }
+        }
 
-            // The key expired, remove it
-            state.entries.remove(key);
-            state.expirations.remove(&(when, key.clone()));
+        keys
+    }
+
+    /// Encode all the databases, see `rdb::encode`.
+    fn encode_snapshot(&self) -> Vec<u8> {
+        rdb::encode(&self.snapshot(), Instant::now(), unix_time())
+    }
+
+    fn write_snapshot(&self, path: &Path, snapshot: &[u8]) -> crate::Result<()> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, snapshot)?;
+        fs::rename(&tmp, path)?;
+
+        let now = unix_time().as_secs();
+        self.shared.last_save.store(now, Ordering::Relaxed);
+        debug!(?path, "snapshot saved");
+
+        Ok(())
+    }
+
+    /// Replace the keys of all the databases with those of the snapshot at `path`,
+    /// written by `dump_to`. Keys which expired since are skipped.
+    pub(crate) fn load_from(&self, path: &Path) -> crate::Result<()> {
+        self.load(&fs::read(path)?)?;
+        debug!(?path, "snapshot loaded");
+
+        Ok(())
+    }
+
+    /// Replace the keys of all the databases with those of `snapshot`, encoded like
+    /// by `dump_to`. Keys which expired since are skipped.
+    pub(crate) fn load(&self, snapshot: &[u8]) -> crate::Result<()> {
+        let keys = rdb::decode(snapshot)?;
+        let (now, unix_now) = (Instant::now(), unix_time());
+
+        let mut state = self.shared.state.lock().unwrap();
+        if keys
+            .iter()
+            .any(|(index, ..)| *index >= state.databases.len())
+        {
+            return Err("ERR DB index is out of range".into());
         }
 
-        None
+        for index in 0..state.databases.len() {
+            state.databases[index] = Keyspace::default();
+            state.mark_deleted(index);
+        }
+        for (index, key, value, expires_at) in keys {
+            let expires_at = match expires_at.map(|unix| unix.checked_sub(unix_now)) {
+                None => None,
+                Some(Some(remaining)) if !remaining.is_zero() => Some(now + remaining),
+                Some(_) => continue,
+            };
+
+            let version = state.next_version();
+            state.databases[index].insert(
+                key,
+                Entry {
+                    value,
+                    expires_at,
+                    version,
+                    last_accessed: now,
+                    frequency: LFU_INIT_VAL,
+                },
+            );
+        }
+        drop(state);
+
+        // The next key to expire may have changed.
+        self.shared.background_task.notify_one();
+
+        Ok(())
     }
 
-    fn is_shutdown(&self) -> bool {
-        self.state.lock().unwrap().shutdown
+    /// The Unix time in seconds of the last snapshot of the keyspace, see `LASTSAVE`.
+    pub(crate) fn last_save(&self) -> u64 {
+        self.shared.last_save.load(Ordering::Relaxed)
     }
-}
 
-impl State {
-    fn next_expiration(&self) -> Option<Instant> {
-        self.expirations
+    /// The parameters of the server.
+    pub(crate) fn settings(&self) -> MutexGuard<'_, Settings> {
+        self.shared.settings.lock().unwrap()
+    }
+
+    /// The log of the slowest commands.
+    pub(crate) fn slowlog(&self) -> MutexGuard<'_, SlowLog> {
+        self.shared.slowlog.lock().unwrap()
+    }
+
+    /// Enable tracking for the connection `id`, returning the keys it read which
+    /// are invalidated from now on. Tracking is disabled by dropping the receiver.
+    pub(crate) fn enable_tracking(&self, id: u64) -> Invalidations {
+        self.shared.state.lock().unwrap().tracking.enable(id)
+    }
+
+    /// Remember that the connection `id` read `key`, so that it is sent an
+    /// invalidation once the key is modified, if it has tracking enabled.
+    pub(crate) fn track(&self, id: u64, key: &str) {
+        self.shared.state.lock().unwrap().tracking.track(id, key);
+    }
+
+    /// Enable or disable the purge of expired keys by the background task. Expired
+    /// keys are still removed when read.
+    pub(crate) fn set_active_expire(&self, enabled: bool) {
+        self.shared.active_expire.store(enabled, Ordering::Relaxed);
+        self.shared.background_task.notify_one();
+    }
+
+    /// How long ago the server started.
+    pub(crate) fn uptime(&self) -> Duration {
+        self.shared.started_at.elapsed()
+    }
+
+    /// Returns the index, the number of keys and the number of keys with an
+    /// expiration of each database holding keys.
+    pub(crate) fn keyspace(&self) -> Vec<(usize, usize, usize)> {
+        let state = self.shared.state.lock().unwrap();
+
+        state
+            .databases
             .iter()
-            .next()
-            .map(|expiration| expiration.0)
+            .enumerate()
+            .filter(|(_, keyspace)| !keyspace.entries.is_empty())
+            .map(|(index, keyspace)| (index, keyspace.entries.len(), keyspace.expirations.len()))
+            .collect()
     }
-}
 
-/// Routine executed by the background task.
-///
-/// Wait to be notified. On notification, purge any expired keys from the shared
-/// state handle. If `shutdown` is set, terminate the task.
-async fn purge_expired_tasks(shared: Arc<Shared>) {
-    while !shared.is_shutdown() {
-        // Purge all keys that are expired.
-        if let Some(when) = shared.purge_expired_keys() {
-            tokio::select! {
-                _ = time::sleep_until(when) => {}
-                _ = shared.background_task.notified() => {}
-            }
-        } else {
-            // There are no keys expiring in the future. Wait until the task is notified.
-            shared.background_task.notified().await;
+    /// Swap the contents of the databases at `index1` and `index2`.
+    ///
+    /// Returns `false` if either database does not exist.
+    pub(crate) fn swapdb(&self, index1: usize, index2: usize) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+
+        let len = state.databases.len();
+        if index1 >= len || index2 >= len {
+            return false;
         }
+
+        state.databases.swap(index1, index2);
+
+        true
     }
 
-    debug!("Purge background task shut down")
+    /// Move `key`, along with its expiration, to the database at `index`.
+    ///
+    /// Returns `false` if the key does not exist, or if the destination already
+    /// holds it.
+    pub(crate) fn move_key(&self, key: &str, index: usize) -> crate::Result<bool> {
+        let events = self.settings().notify_keyspace_events();
+        let mut state = self.shared.state.lock().unwrap();
+
+        if index >= state.databases.len() {
+            return Err("ERR DB index is out of range".into());
+        }
+        if index == self.index {
+            return Err("ERR source and destination objects are the same".into());
+        }
+        state.expire_if_needed(events, self.index, key);
+        state.expire_if_needed(events, index, key);
+        if state.databases[index].entries.contains_key(key) {
+            return Ok(false);
+        }
+
+        let Some(mut entry) = state.delete(self.index, key) else {
+            return Ok(false);
+        };
+
+        // The key changes in both databases.
+        entry.version = state.next_version();
+        state.databases[index].insert(key.to_string(), entry);
+
+        state.notify(
+            events,
+            KeyspaceEvents::GENERIC,
+            "move_from",
+            self.index,
+            key,
+        );
+        state.notify(events, KeyspaceEvents::GENERIC, "move_to", index, key);
+
+        Ok(true)
+    }
+
+    /// Rename `src` to `dst`, along with its expiration, replacing the value and
+    /// expiration of `dst` if it exists, unless `nx` is set.
+    ///
+    /// Returns `false` if `nx` is set and `dst` exists, in which case nothing
+    /// changes. Renaming a key to itself leaves it as is. Fails if `src` does not
+    /// exist.
+    pub(crate) fn rename_with_options(
+        &self,
+        src: &str,
+        dst: &str,
+        nx: bool,
+    ) -> crate::Result<bool> {
+        let events = self.settings().notify_keyspace_events();
+        let mut state = self.shared.state.lock().unwrap();
+
+        state.expire_if_needed(events, self.index, src);
+        state.expire_if_needed(events, self.index, dst);
+        let keyspace = &mut state.databases[self.index];
+        let Some(mut entry) = keyspace.remove(src) else {
+            return Err("ERR no such key".into());
+        };
+        if src == dst || (nx && keyspace.entries.contains_key(dst)) {
+            keyspace.insert(src.to_string(), entry);
+            return Ok(!nx);
+        }
+
+        // Inserting the entry removes the expiration of the previous value of `dst`,
+        // which would otherwise remove the key once reached.
+        state.mark_deleted(self.index);
+        entry.version = state.next_version();
+        state.databases[self.index].insert(dst.to_string(), entry);
+
+        state.notify(
+            events,
+            KeyspaceEvents::GENERIC,
+            "rename_from",
+            self.index,
+            src,
+        );
+        state.notify(
+            events,
+            KeyspaceEvents::GENERIC,
+            "rename_to",
+            self.index,
+            dst,
+        );
+
+        Ok(true)
+    }
+
+    /// Returns the value at `key` serialized by `DUMP`, or `None` if there is no
+    /// such key.
+    pub(crate) fn dump(&self, key: &str) -> Option<Vec<u8>> {
+        let events = self.settings().notify_keyspace_events();
+        let mut state = self.shared.state.lock().unwrap();
+        state.expire_if_needed(events, self.index, key);
+
+        state.databases[self.index]
+            .entries
+            .get(key)
+            .map(|entry| rdb::dump(&entry.value))
+    }
+
+    /// Store the value serialized in `payload` by `DUMP` at `key`, expiring at
+    /// `expires_at` if set.
+    ///
+    /// Fails if `key` already exists, unless `replace` is set.
+    pub(crate) fn restore(
+        &self,
+        key: String,
+        payload: &[u8],
+        expires_at: Option<Instant>,
+        replace: bool,
+    ) -> crate::Result<()> {
+        let value = rdb::restore(payload)?;
+        let settings = self.settings();
+        let ((maxmemory, policy), events) =
+            (settings.maxmemory(), settings.notify_keyspace_events());
+        drop(settings);
+        let mut state = self.shared.state.lock().unwrap();
+
+        state.expire_if_needed(events, self.index, &key);
+        if !replace && state.databases[self.index].entries.contains_key(&key) {
+            return Err("BUSYKEY Target key name already exists.".into());
+        }
+
+        let now = Instant::now();
+        let version = state.next_version();
+        state.databases[self.index].insert(
+            key.clone(),
+            Entry {
+                value,
+                expires_at,
+                version,
+                last_accessed: now,
+                frequency: LFU_INIT_VAL,
+            },
+        );
+
+        state.notify(events, KeyspaceEvents::GENERIC, "restore", self.index, &key);
+        let evicted = match maxmemory {
+            Some(maxmemory) => state.evict(maxmemory, policy, events, self.index, &key),
+            None => Vec::new(),
+        };
+        drop(state);
+        self.propagate_evictions(evicted);
+
+        // The next key to expire may have changed.
+        if expires_at.is_some() {
+            self.shared.background_task.notify_one();
+        }
+
+        Ok(())
+    }
+
+    /// Returns the reference count and encoding of the value at `key` and an
+    /// estimate of its serialized length in bytes, or `None` if there is no such
+    /// key.
+    pub(crate) fn debug_object(&self, key: &str) -> Option<(u64, &'static str, usize)> {
+        let events = self.settings().notify_keyspace_events();
+        let mut state = self.shared.state.lock().unwrap();
+        state.expire_if_needed(events, self.index, key);
+
+        state.databases[self.index].entries.get(key).map(|entry| {
+            let value = &entry.value;
+            (value.refcount(), value.encoding(), value.serialized_len())
+        })
+    }
+
+    /// Returns the number of references to the value at `key`, see `OBJECT
+    /// REFCOUNT`, or `None` if there is no such key.
+    pub(crate) fn object_refcount(&self, key: &str) -> Option<u64> {
+        let events = self.settings().notify_keyspace_events();
+        let mut state = self.shared.state.lock().unwrap();
+        state.expire_if_needed(events, self.index, key);
+
+        state.databases[self.index]
+            .entries
+            .get(key)
+            .map(|entry| entry.value.refcount())
+    }
+
+    /// Returns the access frequency counter of `key`, see `OBJECT FREQ`, or `None`
+    /// if there is no such key.
+    ///
+    /// Fails unless the `maxmemory-policy` is an LFU one, like in Redis.
+    pub(crate) fn object_freq(&self, key: &str) -> crate::Result<Option<u8>> {
+        let settings = self.settings();
+        let ((_, policy), events) = (settings.maxmemory(), settings.notify_keyspace_events());
+        drop(settings);
+        if policy != MaxmemoryPolicy::AllkeysLfu {
+            return Err("ERR An LFU maxmemory policy is not selected, access frequency not tracked. Please note that when switching between policies at runtime LRU and LFU data will take some time to adjust.".into());
+        }
+
+        let mut state = self.shared.state.lock().unwrap();
+        state.expire_if_needed(events, self.index, key);
+
+        Ok(state.databases[self.index]
+            .entries
+            .get(key)
+            .map(|entry| entry.frequency))
+    }
+
+    /// Returns an estimate of the number of bytes used to store `key` and its
+    /// value, or `None` if there is no such key.
+    pub(crate) fn memory_usage(&self, key: &str) -> Option<usize> {
+        let events = self.settings().notify_keyspace_events();
+        let mut state = self.shared.state.lock().unwrap();
+        state.expire_if_needed(events, self.index, key);
+
+        state.databases[self.index]
+            .entries
+            .get(key)
+            .map(|entry| entry.memory_usage(key))
+    }
+
+    /// Returns an estimate of the number of bytes used to store the keys of all the
+    /// databases, compared to `maxmemory`.
+    pub(crate) fn used_memory(&self) -> usize {
+        self.shared.state.lock().unwrap().used_memory()
+    }
+
+    /// Returns `true` if writes must be refused, as `maxmemory` is exceeded and the
+    /// `noeviction` policy keeps any key from being evicted.
+    pub(crate) fn is_out_of_memory(&self) -> bool {
+        let (maxmemory, policy) = self.settings().maxmemory();
+        maxmemory.is_some_and(|maxmemory| {
+            policy == MaxmemoryPolicy::NoEviction && self.used_memory() > maxmemory
+        })
+    }
+
+    /// Get the value associated with a key.
+    ///
+    /// Returns `None` if there is no value associated with the key. This may be
+    /// due to never having assigned a value to the key or a previously assigned
+    /// value expired. An expired key is removed at once, rather than by the
+    /// background task.
+    ///
+    /// Fails if the key holds another type of value than a string.
+    pub(crate) fn get(&self, key: &str) -> crate::Result<Option<Bytes>> {
+        let events = self.settings().notify_keyspace_events();
+        let mut state = self.shared.state.lock().unwrap();
+        state.expire_if_needed(events, self.index, key);
+        let entry = state.databases[self.index].entries.get_mut(key);
+        metrics::get().lookup(entry.is_some());
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+        entry.touch(Instant::now());
+
+        // `Bytes::clone` is a shallow clone
+        Ok(Some(entry.value.expect_string()?.clone()))
+    }
+
+    /// Get the value associated with a key like `get`, setting its expiration as
+    /// requested by `expiration` at once. An expiration which is not in the future
+    /// deletes the key, once read.
+    ///
+    /// Fails if the key holds another type of value than a string, or if the
+    /// expiration is too far in the future to be represented.
+    pub(crate) fn getex(
+        &self,
+        key: &str,
+        expiration: Option<Expiration>,
+    ) -> crate::Result<Option<Bytes>> {
+        const INVALID: &str = "ERR invalid expire time in 'getex' command";
+
+        // `Some(None)` removes the expiration.
+        let expires_at = match expiration {
+            None => None,
+            Some(Expiration::After(timeout)) => {
+                Some(Some(Instant::now().checked_add(timeout).ok_or(INVALID)?))
+            }
+            Some(Expiration::At(time)) => Some(Some(instant_at(time).ok_or(INVALID)?)),
+            Some(Expiration::Persist) => Some(None),
+        };
+
+        let events = self.settings().notify_keyspace_events();
+        let mut state = self.shared.state.lock().unwrap();
+        state.expire_if_needed(events, self.index, key);
+        let entry = state.databases[self.index].entries.get_mut(key);
+        metrics::get().lookup(entry.is_some());
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+        entry.touch(Instant::now());
+        let value = entry.value.expect_string()?.clone();
+        let current = entry.expires_at;
+
+        let reschedule = match expires_at {
+            Some(Some(when)) if when <= Instant::now() => {
+                state.delete(self.index, key);
+                state.notify(events, KeyspaceEvents::GENERIC, "del", self.index, key);
+                false
+            }
+            Some(Some(when)) => {
+                let reschedule = state.set_expiration(self.index, key, Some(when));
+                state.notify(events, KeyspaceEvents::GENERIC, "expire", self.index, key);
+                reschedule
+            }
+            Some(None) if current.is_some() => {
+                let reschedule = state.set_expiration(self.index, key, None);
+                state.notify(events, KeyspaceEvents::GENERIC, "persist", self.index, key);
+                reschedule
+            }
+            Some(None) | None => false,
+        };
+        drop(state);
+
+        if reschedule {
+            self.shared.background_task.notify_one();
+        }
+
+        Ok(Some(value))
+    }
+
+    /// Returns the keys matching `pattern`, if any, among about `count` keys from
+    /// `cursor`, along with the cursor to continue from, see `SCAN`. The iteration
+    /// starts and ends with the cursor 0.
+    ///
+    /// Every key present from the start to the end of the iteration is returned,
+    /// whatever the keys added and removed in between.
+    pub(crate) fn scan(
+        &self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+    ) -> (u64, Vec<String>) {
+        let state = self.shared.state.lock().unwrap();
+        state.databases[self.index].scan(cursor, pattern, count)
+    }
+
+    /// Set the value associated with a key, expiring at `expires_at` if any.
+    ///
+    /// If a value is already associated with the key, it is removed.
+    pub(crate) fn set(&self, key: String, value: Bytes, expires_at: Option<Instant>) {
+        let settings = self.settings();
+        let ((maxmemory, policy), events) =
+            (settings.maxmemory(), settings.notify_keyspace_events());
+        drop(settings);
+        let mut state = self.shared.state.lock().unwrap();
+
+        // If this `set` becomes the key that expires **next**, the background
+        // task needs to be notified so it can update its state.
+        let mut notify = false;
+
+        if let Some(when) = expires_at {
+            // Only notify the worker task if the newly inserted expiration is the
+            // **next** key to evict.
+            notify = state
+                .next_expiration()
+                .map(|expiration| expiration > when)
+                .unwrap_or(true);
+        }
+
+        // Insert the entry into the `HashMap`.
+        let version = state.next_version();
+        state.databases[self.index].insert(
+            key.clone(),
+            Entry {
+                value: Value::String(value),
+                expires_at,
+                version,
+                last_accessed: Instant::now(),
+                frequency: LFU_INIT_VAL,
+            },
+        );
+
+        state.notify(events, KeyspaceEvents::STRING, "set", self.index, &key);
+        if expires_at.is_some() {
+            state.notify(events, KeyspaceEvents::GENERIC, "expire", self.index, &key);
+        }
+        let evicted = match maxmemory {
+            Some(maxmemory) => state.evict(maxmemory, policy, events, self.index, &key),
+            None => Vec::new(),
+        };
+        drop(state);
+        self.propagate_evictions(evicted);
+
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+    }
+
+    /// Set `key` to expire at `when`, deleting it at once if `when` is not in the
+    /// future. Returns `false` if there is no such key, or if its current expiration
+    /// does not meet `condition`.
+    pub(crate) fn expire(
+        &self,
+        key: &str,
+        when: Instant,
+        condition: Option<ExpireCondition>,
+    ) -> bool {
+        let events = self.settings().notify_keyspace_events();
+        let mut state = self.shared.state.lock().unwrap();
+
+        state.expire_if_needed(events, self.index, key);
+        let Some(entry) = state.databases[self.index].entries.get(key) else {
+            return false;
+        };
+        if condition.is_some_and(|condition| !condition.allows(entry.expires_at, when)) {
+            return false;
+        }
+        if when <= Instant::now() {
+            state.delete(self.index, key);
+            state.notify(events, KeyspaceEvents::GENERIC, "del", self.index, key);
+            return true;
+        }
+
+        let reschedule = state.set_expiration(self.index, key, Some(when));
+        state.notify(events, KeyspaceEvents::GENERIC, "expire", self.index, key);
+        drop(state);
+
+        if reschedule {
+            self.shared.background_task.notify_one();
+        }
+
+        true
+    }
+
+    /// Delete `keys`, returning how many of them existed.
+    pub(crate) fn del(&self, keys: &[String]) -> u64 {
+        let events = self.settings().notify_keyspace_events();
+        let mut state = self.shared.state.lock().unwrap();
+
+        let mut deleted = 0;
+        for key in keys {
+            state.expire_if_needed(events, self.index, key);
+            if state.delete(self.index, key).is_some() {
+                state.notify(events, KeyspaceEvents::GENERIC, "del", self.index, key);
+                deleted += 1;
+            }
+        }
+
+        deleted
+    }
+
+    /// Remove the expiration of `key`, so that it is kept until deleted. Returns
+    /// `false` if there is no such key, or if it does not expire.
+    pub(crate) fn persist(&self, key: &str) -> bool {
+        let events = self.settings().notify_keyspace_events();
+        let mut state = self.shared.state.lock().unwrap();
+
+        state.expire_if_needed(events, self.index, key);
+        let expires = state.databases[self.index]
+            .entries
+            .get(key)
+            .is_some_and(|entry| entry.expires_at.is_some());
+        if !expires {
+            return false;
+        }
+
+        let reschedule = state.set_expiration(self.index, key, None);
+        state.notify(events, KeyspaceEvents::GENERIC, "persist", self.index, key);
+        drop(state);
+
+        if reschedule {
+            self.shared.background_task.notify_one();
+        }
+
+        true
+    }
+
+    /// Append an entry to the stream stored at `key`, creating the stream if needed.
+    ///
+    /// When `id` is `None`, an ID is generated from the current time. Returns the ID
+    /// of the added entry.
+    pub(crate) fn xadd(
+        &self,
+        key: String,
+        id: Option<StreamId>,
+        fields: Vec<(Bytes, Bytes)>,
+    ) -> crate::Result<StreamId> {
+        let settings = self.settings();
+        let ((maxmemory, policy), events) =
+            (settings.maxmemory(), settings.notify_keyspace_events());
+        drop(settings);
+        let mut state = self.shared.state.lock().unwrap();
+        // An expired stream is replaced by a new one.
+        state.expire_if_needed(events, self.index, &key);
+
+        let version = state.next_version();
+        let now = Instant::now();
+        let keyspace = &mut state.databases[self.index];
+        let id = if let Some(entry) = keyspace.entries.get_mut(&key) {
+            let before = entry.memory_usage(&key);
+            let id = entry
+                .value
+                .expect_stream()
+                .and_then(|stream| stream.add(id, fields))?;
+            entry.version = version;
+            entry.touch(now);
+            keyspace.used_memory = keyspace.used_memory - before + entry.memory_usage(&key);
+            id
+        } else {
+            // The stream is only created once the ID is known to be valid, not to
+            // leave an empty one behind.
+            let mut stream = Stream::default();
+            let id = stream.add(id, fields)?;
+            let entry = Entry {
+                value: Value::Stream(stream),
+                expires_at: None,
+                version,
+                last_accessed: now,
+                frequency: LFU_INIT_VAL,
+            };
+            keyspace.insert(key.clone(), entry);
+            id
+        };
+
+        state.notify(events, KeyspaceEvents::STREAM, "xadd", self.index, &key);
+        let evicted = match maxmemory {
+            Some(maxmemory) => state.evict(maxmemory, policy, events, self.index, &key),
+            None => Vec::new(),
+        };
+        drop(state);
+        self.propagate_evictions(evicted);
+
+        Ok(id)
+    }
+
+    /// Read the entries with an ID greater than the given one from each stream.
+    ///
+    /// An ID of `None` stands for the last ID of the stream, so that only entries added
+    /// afterwards would be returned. Streams without matching entries are left out.
+    pub(crate) fn xread(
+        &self,
+        streams: Vec<(String, Option<StreamId>)>,
+        count: Option<usize>,
+    ) -> crate::Result<Vec<(String, Vec<StreamEntry>)>> {
+        let events = self.settings().notify_keyspace_events();
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+
+        let mut result = vec![];
+        for (key, id) in streams {
+            state.expire_if_needed(events, self.index, &key);
+            let Some(entry) = state.databases[self.index].entries.get_mut(&key) else {
+                continue;
+            };
+            entry.touch(now);
+            let stream = entry.value.expect_stream()?;
+
+            let start = id.unwrap_or(stream.last_id);
+            let entries: Vec<_> = stream
+                .entries
+                .range((Bound::Excluded(start), Bound::Unbounded))
+                .take(count.unwrap_or(usize::MAX))
+                .map(|(id, fields)| (*id, fields.clone()))
+                .collect();
+
+            if !entries.is_empty() {
+                result.push((key, entries));
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Returns the version of the value stored at `key`, which changes whenever the
+    /// key is written, expires or is deleted. A missing key has the version of the
+    /// last deletion in the database, so that it changes once the key was created,
+    /// even if deleted since.
+    pub(crate) fn version(&self, key: &str) -> u64 {
+        let state = self.shared.state.lock().unwrap();
+        let keyspace = &state.databases[self.index];
+
+        keyspace
+            .entries
+            .get(key)
+            .map_or(keyspace.deleted, |entry| entry.version)
+    }
+
+    /// Returns a `ChannelReceiver` for the requested channel.
+    pub(crate) fn subscribe(&self, key: String) -> ChannelReceiver {
+        self.channel_receiver(key, |state| &mut state.pub_sub)
+    }
+
+    /// Returns a `ChannelReceiver` for the requested glob pattern.
+    ///
+    /// The receiver gets messages published to any channel matching `pattern`.
+    pub(crate) fn psubscribe(&self, pattern: String) -> ChannelReceiver<(String, Bytes)> {
+        self.channel_receiver(pattern, |state| &mut state.pattern_pub_sub)
+    }
+
+    /// Returns a `ChannelReceiver` for `channel` of the pub/sub key-space returned by
+    /// `channels`, creating the channel if needed.
+    fn channel_receiver<T: Clone>(
+        &self,
+        channel: String,
+        channels: fn(&mut State) -> &mut HashMap<String, broadcast::Sender<T>>,
+    ) -> ChannelReceiver<T> {
+        use std::collections::hash_map::Entry;
+
+        let capacity = self.settings().pubsub_capacity();
+        let mut state = self.shared.state.lock().unwrap();
+
+        let rx = match channels(&mut state).entry(channel.clone()) {
+            Entry::Occupied(e) => e.get().subscribe(),
+            // No broadcast channel exists yet, so create one.
+            Entry::Vacant(e) => {
+                let (tx, rx) = broadcast::channel(capacity);
+                e.insert(tx);
+
+                rx
+            }
+        };
+
+        ChannelReceiver {
+            rx: Some(rx),
+            channel,
+            db: self.clone(),
+            channels,
+        }
+    }
+
+    /// Returns a `ChannelReceiver` for the requested shard channel.
+    pub(crate) fn ssubscribe(&self, key: String) -> ChannelReceiver {
+        self.channel_receiver(key, |state| &mut state.shard_pub_sub)
+    }
+
+    /// Returns the channels with at least one subscriber, optionally only those
+    /// matching the glob `pattern`.
+    pub(crate) fn channels(&self, pattern: Option<&str>) -> Vec<String> {
+        let state = self.shared.state.lock().unwrap();
+
+        state
+            .pub_sub
+            .iter()
+            .filter(|(_, tx)| tx.receiver_count() > 0)
+            .filter(|(channel, _)| {
+                pattern.is_none_or(|pattern| glob_match(pattern.as_bytes(), channel.as_bytes()))
+            })
+            .map(|(channel, _)| channel.clone())
+            .collect()
+    }
+
+    /// Returns the number of subscribers of each of the given channels.
+    ///
+    /// Pattern subscribers are not counted.
+    pub(crate) fn numsub(&self, channels: Vec<String>) -> Vec<(String, usize)> {
+        let state = self.shared.state.lock().unwrap();
+
+        channels
+            .into_iter()
+            .map(|channel| {
+                let count = state
+                    .pub_sub
+                    .get(&channel)
+                    .map_or(0, broadcast::Sender::receiver_count);
+
+                (channel, count)
+            })
+            .collect()
+    }
+
+    /// Returns the number of unique patterns with at least one subscriber.
+    pub(crate) fn numpat(&self) -> usize {
+        let state = self.shared.state.lock().unwrap();
+
+        state
+            .pattern_pub_sub
+            .values()
+            .filter(|tx| tx.receiver_count() > 0)
+            .count()
+    }
+
+    /// Publish a message to the channel. Returns the number of subscribers listening on the
+    /// channel, including those subscribed by a matching pattern.
+    ///
+    /// Like in Redis, a subscriber is counted whether or not it keeps up: one lagging
+    /// more than `pubsub_capacity` messages behind skips the oldest messages it did not
+    /// read yet, never the latest one.
+    pub(crate) fn publish(&self, key: &str, value: Bytes) -> usize {
+        self.shared.state.lock().unwrap().publish(key, value)
+    }
+
+    /// Publish a message to the shard channel. Returns the number of subscribers
+    /// listening on the shard channel.
+    pub(crate) fn spublish(&self, key: &str, value: Bytes) -> usize {
+        let state = self.shared.state.lock().unwrap();
+
+        state
+            .shard_pub_sub
+            .get(key)
+            .map_or(0, |tx| send_message(tx, value))
+    }
+}
+
+impl<T: Clone> ChannelReceiver<T> {
+    /// Receives the next message published to the channel.
+    pub(crate) async fn recv(&mut self) -> Result<T, broadcast::error::RecvError> {
+        self.rx.as_mut().unwrap().recv().await
+    }
+}
+
+impl<T> Drop for ChannelReceiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.db.shared.state.lock().unwrap();
+        // Drop the receiver while holding the lock, so that no new receiver can be
+        // created between counting the receivers and removing the channel.
+        drop(self.rx.take());
+
+        let channels = (self.channels)(&mut state);
+        let idle = channels
+            .get(&self.channel)
+            .is_some_and(|tx| tx.receiver_count() == 0);
+        if idle {
+            channels.remove(&self.channel);
+        }
+    }
+}
+
+impl Value {
+    /// Returns the string held by the value, for the commands applying to strings.
+    ///
+    /// Fails if the value is of another type.
+    fn expect_string(&self) -> crate::Result<&Bytes> {
+        match self {
+            Value::String(data) => Ok(data),
+            _ => Err(WRONGTYPE.into()),
+        }
+    }
+
+    /// Returns the stream held by the value, for the commands applying to streams.
+    ///
+    /// Fails if the value is of another type.
+    fn expect_stream(&mut self) -> crate::Result<&mut Stream> {
+        match self {
+            Value::Stream(stream) => Ok(stream),
+            _ => Err(WRONGTYPE.into()),
+        }
+    }
+
+    /// The name Redis gives to the internal representation of the value.
+    ///
+    /// Strings holding a 64 bit signed integer are `int`, short strings are
+    /// `embstr` and longer ones are `raw`, like in Redis.
+    fn encoding(&self) -> &'static str {
+        match self {
+            Value::String(data) => {
+                if integer(data).is_some() {
+                    "int"
+                } else if data.len() <= 44 {
+                    "embstr"
+                } else {
+                    "raw"
+                }
+            }
+            Value::Stream(_) => "stream",
+        }
+    }
+
+    /// The number of references to the value, see `OBJECT REFCOUNT`. Values are
+    /// never shared, but small integers are reported as shared like in Redis.
+    fn refcount(&self) -> u64 {
+        match self {
+            Value::String(data) => match integer(data) {
+                Some(n) if (0..SHARED_INTEGERS).contains(&n) => SHARED_REFCOUNT,
+                _ => 1,
+            },
+            Value::Stream(_) => 1,
+        }
+    }
+
+    /// An estimate of the number of bytes needed to serialize the value.
+    fn serialized_len(&self) -> usize {
+        match self {
+            Value::String(data) => data.len(),
+            Value::Stream(stream) => stream
+                .entries
+                .values()
+                .flatten()
+                // Each entry also stores its 16 bytes ID.
+                .fold(16 * stream.entries.len(), |len, (field, value)| {
+                    len + field.len() + value.len()
+                }),
+        }
+    }
+
+    /// An estimate of the number of bytes allocated for the value, beyond the
+    /// `Value` itself.
+    fn memory_usage(&self) -> usize {
+        match self {
+            Value::String(data) => data.len(),
+            Value::Stream(stream) => stream.entries.values().fold(0, |len, fields| {
+                fields.iter().fold(
+                    len + size_of::<(StreamId, Vec<(Bytes, Bytes)>)>(),
+                    |len, (field, value)| {
+                        len + size_of::<(Bytes, Bytes)>() + field.len() + value.len()
+                    },
+                )
+            }),
+        }
+    }
+}
+
+/// The 64 bit signed integer held by `data`, if it is written like Redis writes
+/// integers: in decimal, without leading zeros or sign.
+fn integer(data: &Bytes) -> Option<i64> {
+    if data.len() > 20 {
+        return None;
+    }
+    std::str::from_utf8(data)
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .filter(|n| n.to_string().as_bytes() == data)
+}
+
+impl Entry {
+    /// Record an access to the entry at `now`, for the eviction policies.
+    fn touch(&mut self, now: Instant) {
+        self.last_accessed = now;
+        self.frequency = self.frequency.saturating_add(1);
+    }
+    /// An estimate of the number of bytes used to store the entry at `key`.
+    fn memory_usage(&self, key: &str) -> usize {
+        // The key is stored twice when it expires: also in `expirations`.
+        let expiration = self
+            .expires_at
+            .map_or(0, |_| size_of::<(Instant, String)>() + key.len());
+        size_of::<(String, Entry)>() + key.len() + expiration + self.value.memory_usage()
+    }
+}
+
+impl Stream {
+    /// Append an entry with the given ID, or with an ID generated from the current
+    /// time if `None`, and return the ID.
+    fn add(
+        &mut self,
+        id: Option<StreamId>,
+        fields: Vec<(Bytes, Bytes)>,
+    ) -> crate::Result<StreamId> {
+        let id = match id {
+            Some(id) if id == StreamId::default() => {
+                return Err("ERR The ID specified in XADD must be greater than 0-0".into());
+            }
+            Some(id) if id <= self.last_id => {
+                return Err(
+                    "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+                        .into(),
+                );
+            }
+            Some(id) => id,
+            None => self.last_id.next().ok_or(
+                "ERR The stream has exhausted the last possible ID, unable to add more items",
+            )?,
+        };
+
+        self.entries.insert(id, fields);
+        self.last_id = id;
+
+        Ok(id)
+    }
+}
+
+impl StreamId {
+    /// Generate the ID for a new entry, which is greater than `self`.
+    ///
+    /// The current Unix time in milliseconds is used, unless the clock is behind
+    /// `self`, in which case the sequence number is incremented instead, carrying
+    /// over to the milliseconds. Returns `None` if `self` is the greatest ID.
+    fn next(self) -> Option<StreamId> {
+        let now = u64::try_from(unix_time().as_millis()).unwrap_or(u64::MAX);
+
+        if now > self.ms {
+            return Some(StreamId { ms: now, seq: 0 });
+        }
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId { ms: self.ms, seq }),
+            None => self.ms.checked_add(1).map(|ms| StreamId { ms, seq: 0 }),
+        }
+    }
+}
+
+impl FromStr for StreamId {
+    type Err = crate::Error;
+
+    /// Parse `<ms>-<seq>`. A missing sequence number defaults to `0`.
+    fn from_str(s: &str) -> crate::Result<StreamId> {
+        const MSG: &str = "ERR Invalid stream ID specified as stream command argument";
+
+        let (ms, seq) = match s.split_once('-') {
+            Some((ms, seq)) => (ms, seq),
+            None => (s, "0"),
+        };
+
+        Ok(StreamId {
+            ms: ms.parse().map_err(|_| MSG)?,
+            seq: seq.parse().map_err(|_| MSG)?,
+        })
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl Shared {
+    /// Purge all expired keys and return the `Instant` at which the **next**
+    /// key will expire. The background task will sleep until this instant.
+    ///
+    /// Nothing is purged while active expiration is disabled.
+    ///
+    /// At most `active-expire-batch` keys are purged at once, in which case the next
+    /// key expires before now.
+    fn purge_expired_keys(&self) -> Option<Instant> {
+        let (events, mut budget) = {
+            let settings = self.settings.lock().unwrap();
+            (
+                settings.notify_keyspace_events(),
+                settings.active_expire_batch(),
+            )
+        };
+        let mut state = self.state.lock().unwrap();
+
+        // Once disabled, the task waits to be notified that it is enabled again.
+        if state.shutdown || !self.active_expire.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        // Find all keys scheduled to expire **before** now.
+        let now = Instant::now();
+        let mut next = None;
+        let mut expired = Vec::new();
+
+        for index in 0..state.databases.len() {
+            let when = state.databases[index].purge_expired_keys(now, &mut budget, &mut expired);
+            next = next.into_iter().chain(when).min();
+            if !expired.is_empty() {
+                state.mark_deleted(index);
+            }
+            for key in expired.drain(..) {
+                state.notify(events, KeyspaceEvents::EXPIRED, "expired", index, &key);
+            }
+        }
+
+        next
+    }
+
+    fn is_shutdown(&self) -> bool {
+        self.state.lock().unwrap().shutdown
+    }
+}
+
+impl State {
+    /// Returns a version greater than any given before.
+    fn next_version(&mut self) -> u64 {
+        self.version += 1;
+        self.version
+    }
+
+    /// Remove and return the entry at `key` of the database at `index`, which
+    /// changes the version of the missing key.
+    fn delete(&mut self, index: usize, key: &str) -> Option<Entry> {
+        let entry = self.databases[index].remove(key)?;
+        self.mark_deleted(index);
+
+        Some(entry)
+    }
+
+    /// Give a new version to the missing keys of the database at `index`, after
+    /// some of them were deleted.
+    fn mark_deleted(&mut self, index: usize) {
+        self.databases[index].deleted = self.next_version();
+    }
+
+    /// Set when `key` of the database at `index` expires, `None` for never, if the
+    /// key exists. Returns `true` if this changed when the next key expires, in which
+    /// case the background task must be notified to wake up at that time instead.
+    fn set_expiration(&mut self, index: usize, key: &str, expires_at: Option<Instant>) -> bool {
+        let next = self.next_expiration();
+        let version = self.next_version();
+        self.databases[index].set_expiration(key, expires_at, version);
+
+        self.next_expiration() != next
+    }
+
+    /// Remove `key` from the database at `index` if it expired, like the background
+    /// task would, so that it is not read while waiting to be purged.
+    fn expire_if_needed(&mut self, events: KeyspaceEvents, index: usize, key: &str) {
+        let expired = self.databases[index]
+            .entries
+            .get(key)
+            .and_then(|entry| entry.expires_at)
+            .is_some_and(|when| when <= Instant::now());
+        if expired {
+            self.delete(index, key);
+            self.notify(events, KeyspaceEvents::EXPIRED, "expired", index, key);
+        }
+    }
+
+    /// Publish `value` to the channel `key`, see `Db::publish`.
+    fn publish(&self, key: &str, value: Bytes) -> usize {
+        let n_subscribers = self
+            .pub_sub
+            .get(key)
+            // If there is no entry for the channel key, then there are no subscribers.
+            .map_or(0, |tx| send_message(tx, value.clone()));
+
+        let n_pattern_subscribers: usize = self
+            .pattern_pub_sub
+            .iter()
+            .filter(|(pattern, _)| glob_match(pattern.as_bytes(), key.as_bytes()))
+            .map(|(_, tx)| send_message(tx, (key.to_string(), value.clone())))
+            .sum();
+
+        n_subscribers + n_pattern_subscribers
+    }
+
+    /// Publish the keyspace notification of `event` on `key` of the database at
+    /// `index`, if notifications of `class` are enabled by `events`.
+    ///
+    /// Every modification of a key is notified, so the key is invalidated for the
+    /// connections tracking it in any case.
+    fn notify(
+        &mut self,
+        events: KeyspaceEvents,
+        class: KeyspaceEvents,
+        event: &str,
+        index: usize,
+        key: &str,
+    ) {
+        self.tracking.invalidate(key);
+        if !events.publishes(class) {
+            return;
+        }
+        if events.contains(KeyspaceEvents::KEYSPACE) {
+            let channel = format!("__keyspace@{index}__:{key}");
+            self.publish(&channel, Bytes::copy_from_slice(event.as_bytes()));
+        }
+        if events.contains(KeyspaceEvents::KEYEVENT) {
+            let channel = format!("__keyevent@{index}__:{event}");
+            self.publish(&channel, Bytes::copy_from_slice(key.as_bytes()));
+        }
+    }
+
+    /// Returns an estimate of the number of bytes used to store the keys of all the
+    /// databases.
+    fn used_memory(&self) -> usize {
+        self.databases
+            .iter()
+            .map(|keyspace| keyspace.used_memory)
+            .sum()
+    }
+
+    /// Evict keys of any database according to `policy` until no more than
+    /// `maxmemory` bytes are used, sparing `key` of the database at `index`, which
+    /// was just written. The evictions are notified according to `events`.
+    ///
+    /// Returns the keys evicted, with the index of their database, to be propagated
+    /// by `Db::propagate_evictions` once the lock is released.
+    fn evict(
+        &mut self,
+        maxmemory: usize,
+        policy: MaxmemoryPolicy,
+        events: KeyspaceEvents,
+        index: usize,
+        key: &str,
+    ) -> Vec<(usize, String)> {
+        let mut evicted_keys = Vec::new();
+        while self.used_memory() > maxmemory {
+            let evicted = match policy {
+                MaxmemoryPolicy::NoEviction => break,
+                MaxmemoryPolicy::AllkeysLru => self.least_recently_accessed(index, key),
+                MaxmemoryPolicy::AllkeysLfu => self.least_frequently_accessed(index, key),
+                MaxmemoryPolicy::AllkeysRandom => self.random_key(index, key),
+            };
+            let Some((i, evicted)) = evicted else {
+                break;
+            };
+
+            self.delete(i, &evicted);
+            self.notify(events, KeyspaceEvents::EVICTED, "evicted", i, &evicted);
+            metrics::get().evict();
+            debug!(key = evicted, db = i, "evicted");
+            evicted_keys.push((i, evicted));
+        }
+
+        evicted_keys
+    }
+
+    /// The least recently accessed key of any database, other than `key` of the
+    /// database at `index`, with the index of its database.
+    ///
+    /// Like in Redis, the key is picked among those sampled by `eviction_candidates`,
+    /// so that evicting takes no longer as the number of keys grows.
+    fn least_recently_accessed(&self, index: usize, key: &str) -> Option<(usize, String)> {
+        self.eviction_candidates(index, key)
+            .min_by_key(|(.., entry)| entry.last_accessed)
+            .map(|(i, key, _)| (i, key.clone()))
+    }
+
+    /// The least frequently accessed key of any database, other than `key` of the
+    /// database at `index`, with the index of its database. Among keys accessed as
+    /// often, the least recently accessed one is picked.
+    ///
+    /// Like `least_recently_accessed`, the key is picked among sampled ones.
+    fn least_frequently_accessed(&self, index: usize, key: &str) -> Option<(usize, String)> {
+        self.eviction_candidates(index, key)
+            .min_by_key(|(.., entry)| (entry.frequency, entry.last_accessed))
+            .map(|(i, key, _)| (i, key.clone()))
+    }
+
+    /// The keys sampled from each database to pick the key to evict, see
+    /// `Keyspace::sample`, other than `key` of the database at `index`, with the
+    /// index of their database and their entry.
+    fn eviction_candidates<'a>(
+        &'a self,
+        index: usize,
+        key: &'a str,
+    ) -> impl Iterator<Item = (usize, &'a String, &'a Entry)> {
+        self.databases
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, keyspace)| {
+                let spared = (i == index).then_some(key);
+                keyspace
+                    .sample(MAXMEMORY_SAMPLES, spared)
+                    .into_iter()
+                    .map(move |key| (i, key, &keyspace.entries[key]))
+            })
+    }
+
+    /// A key of any database picked at random, other than `key` of the database at
+    /// `index`, with the index of its database.
+    ///
+    /// The database is picked at random first, in proportion to its number of keys,
+    /// then a key in it, see `Keyspace::sample`, so that picking takes no longer as
+    /// the number of keys grows. The following databases are tried if it only holds
+    /// `key`.
+    fn random_key(&self, index: usize, key: &str) -> Option<(usize, String)> {
+        let keys: usize = self
+            .databases
+            .iter()
+            .map(|keyspace| keyspace.entries.len())
+            .sum();
+        if keys == 0 {
+            return None;
+        }
+        // The hashers of `RandomState` are randomly seeded.
+        let random = RandomState::new().hash_one(keys) % keys as u64;
+        let mut nth = usize::try_from(random).unwrap_or_default();
+        let first = self
+            .databases
+            .iter()
+            .position(|keyspace| {
+                let found = nth < keyspace.entries.len();
+                nth = nth.saturating_sub(keyspace.entries.len());
+                found
+            })
+            .unwrap_or_default();
+
+        let len = self.databases.len();
+        (first..len).chain(0..first).find_map(|i| {
+            let spared = (i == index).then_some(key);
+            let key = self.databases[i].sample(1, spared).pop()?;
+            Some((i, key.clone()))
+        })
+    }
+
+    /// Returns when the next key expires, in any of the databases.
+    fn next_expiration(&self) -> Option<Instant> {
+        self.databases
+            .iter()
+            .filter_map(|keyspace| keyspace.expirations.iter().next())
+            .map(|expiration| expiration.0)
+            .min()
+    }
+}
+
+impl Keyspace {
+    /// Insert `entry` at `key`, replacing the previous entry.
+    fn insert(&mut self, key: String, entry: Entry) {
+        self.remove(&key);
+        self.used_memory += entry.memory_usage(&key);
+        if let Some(when) = entry.expires_at {
+            self.expirations.insert((when, key.clone()));
+        }
+        self.scan_order.insert((scan_position(&key), key.clone()));
+        self.entries.insert(key, entry);
+    }
+
+    /// Set when `key` expires, `None` for never, as a write of `version`. Does nothing
+    /// if there is no such key.
+    fn set_expiration(&mut self, key: &str, expires_at: Option<Instant>, version: u64) {
+        // Reinserted, for its expiration and memory usage to be accounted for.
+        if let Some(mut entry) = self.remove(key) {
+            entry.expires_at = expires_at;
+            entry.version = version;
+            self.insert(key.to_string(), entry);
+        }
+    }
+
+    /// Remove and return the entry at `key`, along with its expiration.
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.used_memory -= entry.memory_usage(key);
+        if let Some(when) = entry.expires_at {
+            self.expirations.remove(&(when, key.to_string()));
+        }
+        self.scan_order
+            .remove(&(scan_position(key), key.to_string()));
+
+        Some(entry)
+    }
+
+    /// Returns `count` keys picked at random other than `spared`, all of them if there
+    /// are no more. A key may be picked more than once.
+    ///
+    /// Each key is the first one from a random position of `scan_order`, the positions
+    /// being hashes spread evenly, so that picking one takes logarithmic time.
+    fn sample(&self, count: usize, spared: Option<&str>) -> Vec<&String> {
+        let not_spared = |key: &&String| Some(key.as_str()) != spared;
+        if self.entries.len() <= count {
+            return self.entries.keys().filter(not_spared).collect();
+        }
+
+        // The hashers of `RandomState` are randomly seeded.
+        let random = RandomState::new();
+        (0..count)
+            .filter_map(|i| {
+                let position = random.hash_one(i);
+                // Wrapping around to the first keys.
+                let keys = self.scan_order.range((position, String::new())..);
+                keys.chain(&self.scan_order)
+                    .map(|(_, key)| key)
+                    .find(not_spared)
+            })
+            .collect()
+    }
+
+    /// Returns the keys matching `pattern`, if any, among about `count` keys from the
+    /// position `cursor`, along with the position to continue from, or 0 once every
+    /// key was visited. Expired keys are skipped.
+    fn scan(&self, cursor: u64, pattern: Option<&str>, count: usize) -> (u64, Vec<String>) {
+        let now = Instant::now();
+        let mut keys = vec![];
+        let mut last = None;
+
+        let from = self.scan_order.range((cursor, String::new())..);
+        for (visited, (position, key)) in from.enumerate() {
+            // The keys sharing a position are visited together, as the cursor cannot
+            // tell them apart.
+            if visited >= count && last != Some(*position) {
+                return (*position, keys);
+            }
+            last = Some(*position);
+
+            let expired = self.entries[key].expires_at.is_some_and(|when| when <= now);
+            let matches =
+                pattern.is_none_or(|pattern| glob_match(pattern.as_bytes(), key.as_bytes()));
+            if !expired && matches {
+                keys.push(key.clone());
+            }
+        }
+
+        (0, keys)
+    }
+
+    /// Remove the keys expiring before `now`, at most `budget` of them, adding them
+    /// to `expired`, and return when the next key expires.
+    fn purge_expired_keys(
+        &mut self,
+        now: Instant,
+        budget: &mut usize,
+        expired: &mut Vec<String>,
+    ) -> Option<Instant> {
+        while let Some(&(when, ref key)) = self.expirations.iter().next() {
+            if when > now || *budget == 0 {
+                return Some(when);
+            }
+
+            // The key expired, remove it
+            let key = key.clone();
+            self.remove(&key);
+            expired.push(key);
+            *budget -= 1;
+        }
+
+        None
+    }
+}
+
+/// Routine executed by the background task.
+///
+/// Wait to be notified. On notification, purge any expired keys from the shared
+/// state handle. If `shutdown` is set, terminate the task.
+async fn purge_expired_tasks(shared: Arc<Shared>) {
+    while !shared.is_shutdown() {
+        // Purge all keys that are expired.
+        if let Some(when) = shared.purge_expired_keys() {
+            // More keys expired than purged at once: the connections get to run
+            // before the next batch, rather than wait for all of them.
+            if when <= Instant::now() {
+                tokio::task::yield_now().await;
+                continue;
+            }
+            tokio::select! {
+                _ = time::sleep_until(when) => {}
+                _ = shared.background_task.notified() => {}
+            }
+        } else {
+            // There are no keys expiring in the future. Wait until the task is notified.
+            shared.background_task.notified().await;
+        }
+    }
+
+    debug!("Purge background task shut down")
+}
+
+/// The position of `key` in the iteration of `SCAN`, the same for the whole life of
+/// the process.
+fn scan_position(key: &str) -> u64 {
+    // Unlike those of `RandomState`, the hashers of `DefaultHasher` are not seeded.
+    BuildHasherDefault::<DefaultHasher>::default().hash_one(key)
+}
+
+/// Send `value` on the broadcast channel `tx`, returning the number of its receivers,
+/// those lagging behind included.
+fn send_message<T>(tx: &broadcast::Sender<T>, value: T) -> usize {
+    // An error indicates there are no receivers.
+    tx.send(value).unwrap_or(0)
+}
+
+/// The instant at `time`, now if it is past. Returns `None` if it is too far in the
+/// future to be represented.
+pub(crate) fn instant_at(time: SystemTime) -> Option<Instant> {
+    let now = Instant::now();
+    match time.duration_since(SystemTime::now()) {
+        Ok(remaining) => now.checked_add(remaining),
+        Err(_) => Some(now),
+    }
+}
+
+/// `args` with their relative timeouts made absolute, so that the keys expire at the
+/// same time once the command is replayed or replicated: `EXPIRE` and `PEXPIRE`
+/// become `PEXPIREAT`, the `EX` and `PX` options of `SET` and `GETEX` become
+/// `PXAT`, and the `ttl` of `RESTORE` is followed by `ABSTTL`. Returns `None` if
+/// `args` have no relative timeout to rewrite.
+fn absolute_expirations(args: &[Bytes]) -> Option<Vec<Bytes>> {
+    let name = args.first()?.to_ascii_lowercase();
+    let (position, unit) = match &name[..] {
+        // Skip the key, the `ttl` and the payload.
+        b"restore"
+            if args[4.min(args.len())..]
+                .iter()
+                .any(|arg| arg.eq_ignore_ascii_case(b"absttl")) =>
+        {
+            return None;
+        }
+        b"expire" => (2, 1000),
+        b"pexpire" | b"restore" => (2, 1),
+        b"set" | b"getex" => {
+            // Skip the key, and the value of `SET`, which may well read `EX`.
+            let first = if name == b"set" { 3 } else { 2 };
+            let position = first
+                + args[first.min(args.len())..].iter().position(|arg| {
+                    arg.eq_ignore_ascii_case(b"ex") || arg.eq_ignore_ascii_case(b"px")
+                })?;
+            let unit = if args[position].eq_ignore_ascii_case(b"ex") {
+                1000
+            } else {
+                1
+            };
+            (position + 1, unit)
+        }
+        _ => return None,
+    };
+
+    // A negative or invalid timeout is kept, to delete the key or fail alike when
+    // replayed.
+    let timeout: u64 = std::str::from_utf8(args.get(position)?)
+        .ok()?
+        .parse()
+        .ok()?;
+    // A `RESTORE` without `ttl` never expires.
+    if name == b"restore" && timeout == 0 {
+        return None;
+    }
+    let now = u64::try_from(unix_time().as_millis()).ok()?;
+    let at = timeout.checked_mul(unit)?.checked_add(now)?;
+
+    let mut rewritten = args.to_vec();
+    if name == b"expire" || name == b"pexpire" {
+        rewritten[0] = Bytes::from_static(b"pexpireat");
+    } else if name == b"restore" {
+        rewritten.push(Bytes::from_static(b"absttl"));
+    } else {
+        rewritten[position - 1] = Bytes::from_static(b"pxat");
+    }
+    rewritten[position] = Bytes::from(at.to_string());
+    Some(rewritten)
+}
+
+/// The time elapsed since the Unix epoch, or zero if the clock is set before it.
+fn unix_time() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn relative_expirations_are_propagated_as_absolute() {
+        let args = |args: &[&'static str]| -> Vec<Bytes> {
+            args.iter()
+                .map(|arg| Bytes::from_static(arg.as_bytes()))
+                .collect()
+        };
+        let now = u64::try_from(unix_time().as_millis()).unwrap();
+        let at = |args: &[Bytes], position: usize| -> u64 {
+            std::str::from_utf8(&args[position])
+                .unwrap()
+                .parse()
+                .unwrap()
+        };
+
+        let set = absolute_expirations(&args(&["SET", "ex", "EX", "ex", "10", "GET"])).unwrap();
+        assert_eq!(set[..4], args(&["SET", "ex", "EX", "pxat"])[..]);
+        assert!((now + 10_000..now + 11_000).contains(&at(&set, 4)));
+        assert_eq!(set[5], "GET");
+
+        let getex = absolute_expirations(&args(&["getex", "hello", "px", "500"])).unwrap();
+        assert_eq!(getex[..3], args(&["getex", "hello", "pxat"])[..]);
+        assert!((now + 500..now + 1_500).contains(&at(&getex, 3)));
+
+        let expire = absolute_expirations(&args(&["Expire", "hello", "10", "NX"])).unwrap();
+        assert_eq!(expire[0], "pexpireat");
+        assert!((now + 10_000..now + 11_000).contains(&at(&expire, 2)));
+        assert_eq!(expire[3], "NX");
+
+        let pexpire = absolute_expirations(&args(&["pexpire", "hello", "10"])).unwrap();
+        assert_eq!(pexpire[0], "pexpireat");
+        assert!((now + 10..now + 1_010).contains(&at(&pexpire, 2)));
+
+        let restore = args(&["RESTORE", "hello", "500", "payload", "REPLACE"]);
+        let restore = absolute_expirations(&restore).unwrap();
+        assert_eq!(restore[..2], args(&["RESTORE", "hello"])[..]);
+        assert!((now + 500..now + 1_500).contains(&at(&restore, 2)));
+        assert_eq!(restore[3..], args(&["payload", "REPLACE", "absttl"])[..]);
+
+        // Nothing to rewrite.
+        assert!(absolute_expirations(&args(&["set", "hello", "world"])).is_none());
+        assert!(absolute_expirations(&args(&["set", "hello", "world", "PXAT", "10"])).is_none());
+        assert!(absolute_expirations(&args(&["expire", "hello", "-1"])).is_none());
+        assert!(absolute_expirations(&args(&["getex", "hello"])).is_none());
+        assert!(absolute_expirations(&args(&["restore", "hello", "0", "payload"])).is_none());
+        let absttl = args(&["restore", "hello", "10", "payload", "ABSTTL"]);
+        assert!(absolute_expirations(&absttl).is_none());
+    }
+
+    #[tokio::test]
+    async fn publish_counts_lagging_subscribers() {
+        let db = Db::new(DEFAULT_DATABASES, 2);
+
+        let mut healthy = db.subscribe("hello".to_string());
+        let mut lagging = db.subscribe("hello".to_string());
+        for i in 0..4 {
+            assert_eq!(db.publish("hello", Bytes::from(format!("message {i}"))), 2);
+            assert_eq!(
+                healthy.recv().await.unwrap(),
+                Bytes::from(format!("message {i}"))
+            );
+        }
+
+        // The lagging subscriber skips the oldest messages, but reads the latest.
+        assert!(matches!(
+            lagging.recv().await,
+            Err(broadcast::error::RecvError::Lagged(2))
+        ));
+        assert_eq!(lagging.recv().await.unwrap(), Bytes::from("message 2"));
+        assert_eq!(lagging.recv().await.unwrap(), Bytes::from("message 3"));
+    }
+
+    #[tokio::test]
+    async fn drops_channel_without_receivers() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+
+        let rx1 = db.subscribe("hello".to_string());
+        let rx2 = db.subscribe("hello".to_string());
+        assert!(
+            db.shared
+                .state
+                .lock()
+                .unwrap()
+                .pub_sub
+                .contains_key("hello")
+        );
+
+        drop(rx1);
+        assert!(
+            db.shared
+                .state
+                .lock()
+                .unwrap()
+                .pub_sub
+                .contains_key("hello")
+        );
+
+        drop(rx2);
+        assert!(db.shared.state.lock().unwrap().pub_sub.is_empty());
+    }
+
+    #[tokio::test]
+    async fn purges_expired_keys_in_batches() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        db.settings().set_active_expire_batch(100);
+        for i in 0..1_050 {
+            db.set(
+                format!("key{i}"),
+                Bytes::from("value"),
+                Some(Instant::now()),
+            );
+        }
+
+        // The background task does not run until the test yields, so every
+        // purge below is a single batch.
+        let mut batches = 0;
+        while let Some(next) = db.shared.purge_expired_keys() {
+            batches += 1;
+            assert!(next <= Instant::now());
+            let keys = db.shared.state.lock().unwrap().databases[0].entries.len();
+            assert_eq!(keys, 1_050 - batches * 100);
+        }
+        assert_eq!(batches, 10);
+        assert!(
+            db.shared.state.lock().unwrap().databases[0]
+                .entries
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn samples_keys_to_evict() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        for i in 0..1_000 {
+            db.set(format!("key{i}"), Bytes::from("value"), None);
+        }
+        db.select(1)
+            .unwrap()
+            .set("hello".to_string(), Bytes::from("world"), None);
+
+        let state = db.shared.state.lock().unwrap();
+        let mut sampled = HashSet::new();
+        for _ in 0..100 {
+            let sample = state.databases[0].sample(MAXMEMORY_SAMPLES, Some("key0"));
+            assert_eq!(sample.len(), MAXMEMORY_SAMPLES);
+            assert!(!sample.contains(&&"key0".to_string()));
+            sampled.extend(sample);
+        }
+        // The keys are spread over the whole database.
+        assert!(sampled.len() > 100, "{}", sampled.len());
+
+        // Databases with few keys are sampled whole.
+        assert_eq!(
+            state.databases[1].sample(MAXMEMORY_SAMPLES, None),
+            ["hello"]
+        );
+        assert!(
+            state.databases[1]
+                .sample(MAXMEMORY_SAMPLES, Some("hello"))
+                .is_empty()
+        );
+        assert!(
+            state.databases[2]
+                .sample(MAXMEMORY_SAMPLES, None)
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn picks_random_key_to_evict() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        db.set("spared".to_string(), Bytes::from("value"), None);
+        let other = db.select(3).unwrap();
+        let mut state = db.shared.state.lock().unwrap();
+        assert_eq!(state.random_key(0, "spared"), None);
+        drop(state);
+
+        for i in 0..100 {
+            other.set(format!("key{i}"), Bytes::from("value"), None);
+        }
+        state = db.shared.state.lock().unwrap();
+        let mut picked = HashSet::new();
+        for _ in 0..100 {
+            let (index, key) = state.random_key(0, "spared").unwrap();
+            picked.insert((index, key));
+        }
+        assert!(picked.iter().all(|(index, _)| *index == 3));
+        assert!(picked.len() > 20, "{}", picked.len());
+    }
+
+    #[tokio::test]
+    async fn drops_pattern_without_receivers() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+
+        let rx1 = db.psubscribe("h*".to_string());
+        let rx2 = db.psubscribe("h*".to_string());
+        drop(rx1);
+        assert_eq!(db.numpat(), 1);
+
+        drop(rx2);
+        assert!(db.shared.state.lock().unwrap().pattern_pub_sub.is_empty());
+        assert_eq!(db.publish("hello", Bytes::from("world")), 0);
+    }
+
+    #[tokio::test]
+    async fn drops_shard_channel_without_receivers() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+
+        let rx1 = db.ssubscribe("hello".to_string());
+        let rx2 = db.ssubscribe("hello".to_string());
+        drop(rx1);
+        assert_eq!(db.spublish("hello", Bytes::from("world")), 1);
+
+        drop(rx2);
+        assert!(db.shared.state.lock().unwrap().shard_pub_sub.is_empty());
+        assert_eq!(db.spublish("hello", Bytes::from("world")), 0);
+    }
+
+    #[tokio::test]
+    async fn get_does_not_return_expired_key() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        db.set(
+            "hello".to_string(),
+            Bytes::from("world"),
+            Some(Instant::now() + Duration::from_millis(1)),
+        );
+        // Without yielding to the runtime, the background task does not run.
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(
+            db.shared.state.lock().unwrap().databases[0].entries.len(),
+            1
+        );
+
+        assert_eq!(db.get("hello").unwrap(), None);
+        let state = db.shared.state.lock().unwrap();
+        assert!(state.databases[0].entries.is_empty());
+        assert!(state.databases[0].expirations.is_empty());
+    }
+
+    #[tokio::test]
+    async fn commands_do_not_read_expired_source_key() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        for key in ["rename", "renamenx", "getex", "persist", "move", "dump"] {
+            db.set(
+                key.to_string(),
+                Bytes::from("value"),
+                Some(Instant::now() + Duration::from_millis(1)),
+            );
+        }
+        // Without yielding to the runtime, the background task does not run.
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(
+            db.shared.state.lock().unwrap().databases[0].entries.len(),
+            6
+        );
+
+        let err = db.rename_with_options("rename", "dst", false).unwrap_err();
+        assert_eq!(err.to_string(), "ERR no such key");
+        let err = db.rename_with_options("renamenx", "dst", true).unwrap_err();
+        assert_eq!(err.to_string(), "ERR no such key");
+        assert_eq!(db.getex("getex", Some(Expiration::Persist)).unwrap(), None);
+        assert!(!db.persist("persist"));
+        assert!(!db.move_key("move", 1).unwrap());
+        assert_eq!(db.dump("dump"), None);
+
+        // Neither were the keys revived in their destination.
+        assert_eq!(db.get("dst").unwrap(), None);
+        assert_eq!(db.select(1).unwrap().get("move").unwrap(), None);
+        let state = db.shared.state.lock().unwrap();
+        assert!(state.databases[0].entries.is_empty());
+        assert!(state.databases[0].expirations.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rename_replaces_expiration_of_destination() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        db.set(
+            "dst".to_string(),
+            Bytes::from("old"),
+            Some(Instant::now() + Duration::from_secs(100)),
+        );
+        db.set(
+            "src".to_string(),
+            Bytes::from("new"),
+            Some(Instant::now() + Duration::from_secs(5)),
+        );
+
+        assert!(db.rename_with_options("src", "dst", false).unwrap());
+        assert_eq!(db.get("src").unwrap(), None);
+        assert_eq!(db.get("dst").unwrap(), Some(Bytes::from("new")));
+
+        let state = db.shared.state.lock().unwrap();
+        let keyspace = &state.databases[0];
+        let when = keyspace.entries["dst"].expires_at.unwrap();
+        let ttl = when.saturating_duration_since(Instant::now());
+        assert!(ttl > Duration::from_secs(4) && ttl <= Duration::from_secs(5));
+        // No expiration of the previous value is left to remove the key early.
+        assert_eq!(
+            keyspace.expirations,
+            BTreeSet::from([(when, "dst".to_string())])
+        );
+    }
+
+    #[tokio::test]
+    async fn expire_and_persist_report_changes() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        let now = Instant::now();
+        db.set("hello".to_string(), Bytes::from("world"), None);
+
+        assert!(!db.expire("missing", now + Duration::from_secs(10), None));
+        assert!(!db.persist("missing"));
+        assert!(!db.persist("hello"));
+        assert!(db.expire("hello", now + Duration::from_secs(10), None));
+        assert!(db.persist("hello"));
+        assert!(!db.persist("hello"));
+
+        // A past expiration deletes the key.
+        assert!(db.expire("hello", now, None));
+        assert_eq!(db.get("hello").unwrap(), None);
+        assert!(
+            db.shared.state.lock().unwrap().databases[0]
+                .expirations
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn expiration_changes_reschedule_only_the_next_one() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        let now = Instant::now();
+        db.set("first".to_string(), Bytes::from("1"), None);
+        db.set("second".to_string(), Bytes::from("2"), None);
+
+        let mut state = db.shared.state.lock().unwrap();
+        let (soon, later) = (now + Duration::from_secs(10), now + Duration::from_secs(20));
+        assert!(state.set_expiration(0, "first", Some(later)));
+        // Expiring after the next key does not change when the task wakes up.
+        assert!(!state.set_expiration(0, "second", Some(now + Duration::from_secs(30))));
+        assert!(state.set_expiration(0, "second", Some(soon)));
+        assert!(!state.set_expiration(0, "first", None));
+        // The next key to expire no longer does.
+        assert!(state.set_expiration(0, "second", None));
+        assert_eq!(state.next_expiration(), None);
+    }
+
+    #[tokio::test]
+    async fn reloads_snapshot() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        db.set("hello".to_string(), Bytes::from("world"), None);
+        db.set(
+            "ttl".to_string(),
+            Bytes::from("soon"),
+            Some(Instant::now() + Duration::from_secs(60)),
+        );
+        let stream = db.select(3).unwrap();
+        let fields = vec![(Bytes::from("field"), Bytes::from("value"))];
+        let id = stream.xadd("events".to_string(), None, fields).unwrap();
+
+        let path =
+            std::env::temp_dir().join(format!("reloads_snapshot-{}.rdb", std::process::id()));
+        db.dump_to(&path).unwrap();
+
+        let fresh = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        fresh.load_from(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(fresh.get("hello").unwrap(), Some(Bytes::from("world")));
+        assert_eq!(fresh.get("ttl").unwrap(), Some(Bytes::from("soon")));
+        let state = fresh.shared.state.lock().unwrap();
+        assert_eq!(state.databases[0].expirations.len(), 1);
+        let Value::Stream(stream) = &state.databases[3].entries["events"].value else {
+            panic!("expected a stream");
+        };
+        assert_eq!(stream.last_id, id);
+        assert_eq!(stream.entries.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn snapshot_has_the_live_keys() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        db.set("hello".to_string(), Bytes::from("world"), None);
+        db.set(
+            "ttl".to_string(),
+            Bytes::from("later"),
+            Some(Instant::now() + Duration::from_secs(60)),
+        );
+        let other = db.select(3).unwrap();
+        other.set("other".to_string(), Bytes::from("db"), None);
+
+        // Expired, but not deleted yet: the background task is not notified.
+        let mut state = db.shared.state.lock().unwrap();
+        let version = state.next_version();
+        state.databases[0].insert(
+            "stale".to_string(),
+            Entry {
+                value: Value::String(Bytes::from("value")),
+                expires_at: Some(Instant::now()),
+                version,
+                last_accessed: Instant::now(),
+                frequency: LFU_INIT_VAL,
+            },
+        );
+        drop(state);
+
+        let mut keys = db.snapshot();
+        keys.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+        let keys: Vec<_> = keys
+            .into_iter()
+            .map(|(index, key, value, expires_at)| {
+                let Value::String(value) = value else {
+                    panic!("expected a string");
+                };
+                (index, key, value, expires_at.is_some())
+            })
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                (0, "hello".to_string(), Bytes::from("world"), false),
+                (0, "ttl".to_string(), Bytes::from("later"), true),
+                (3, "other".to_string(), Bytes::from("db"), false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_keys_expired_since_snapshot() {
+        let db = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        db.set(
+            "short".to_string(),
+            Bytes::from("lived"),
+            Some(Instant::now() + Duration::from_millis(20)),
+        );
+
+        let path = std::env::temp_dir().join(format!("skips_expired-{}.rdb", std::process::id()));
+        db.dump_to(&path).unwrap();
+        time::sleep(Duration::from_millis(50)).await;
+
+        let fresh = Db::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        fresh.load_from(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(fresh.get("short").unwrap(), None);
+        assert!(
+            fresh.shared.state.lock().unwrap().databases[0]
+                .expirations
+                .is_empty()
+        );
+    }
 }
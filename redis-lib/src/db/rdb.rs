@@ -0,0 +1,248 @@
+//!
+//! The binary format of the snapshots written by `SAVE`.
+//!
+//! A snapshot starts with `MAGIC` and the format `VERSION`, followed by the keys of
+//! each non-empty database and `EOF`:
+//!
+//! ```text
+//! MAGIC VERSION
+//! SELECT_DB index
+//! [EXPIRE_MS unix-ms] type key value
+//! ...
+//! EOF
+//! ```
+//!
+//! Integers are big-endian. Strings, keys included, are prefixed by their length as
+//! a `u32`. Expirations are absolute, so that they survive restarts.
+//!
+//! A single value is serialized by `DUMP` in the same format, followed by the format
+//! version and a checksum, so that `RESTORE` rejects corrupted payloads:
+//!
+//! ```text
+//! type value VERSION crc16
+//! ```
+//!
+
+use super::{SnapshotKey, Stream, StreamId, Value};
+use crate::cluster::crc16;
+use bytes::{Buf, BufMut, Bytes};
+use std::time::Duration;
+use tokio::time::Instant;
+
+const MAGIC: &[u8] = b"REDIS-DEMO";
+
+/// Bumped on every incompatible change of the format.
+const VERSION: u8 = 1;
+
+const SELECT_DB: u8 = 0xFE;
+const EXPIRE_MS: u8 = 0xFD;
+const EOF: u8 = 0xFF;
+
+const TYPE_STRING: u8 = 0;
+const TYPE_STREAM: u8 = 1;
+
+/// A key read from a snapshot: the index of its database, the key, its value and
+/// when it expires, as a duration since the Unix epoch.
+pub(super) type LoadedKey = (usize, String, Value, Option<Duration>);
+
+/// Encode the `keys` of a snapshot, ordered by database, see `Db::snapshot`.
+///
+/// `now` and `unix_now` are the same instant, used to convert expirations to Unix
+/// times.
+pub(super) fn encode(keys: &[SnapshotKey], now: Instant, unix_now: Duration) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.put_slice(MAGIC);
+    buf.put_u8(VERSION);
+
+    let mut selected = None;
+    for (index, key, value, expires_at) in keys {
+        if selected != Some(*index) {
+            buf.put_u8(SELECT_DB);
+            buf.put_u32(u32::try_from(*index).unwrap_or(u32::MAX));
+            selected = Some(*index);
+        }
+
+        if let Some(when) = expires_at {
+            let unix = unix_now + when.saturating_duration_since(now);
+            buf.put_u8(EXPIRE_MS);
+            buf.put_u64(u64::try_from(unix.as_millis()).unwrap_or(u64::MAX));
+        }
+
+        buf.put_u8(value_type(value));
+        put_string(&mut buf, key.as_bytes());
+        put_value(&mut buf, value);
+    }
+
+    buf.put_u8(EOF);
+    buf
+}
+
+/// Decode the keys of a snapshot.
+pub(super) fn decode(mut src: &[u8]) -> crate::Result<Vec<LoadedKey>> {
+    if !src.starts_with(MAGIC) {
+        return Err("not a snapshot".into());
+    }
+    src.advance(MAGIC.len());
+
+    let version = get_u8(&mut src)?;
+    if version != VERSION {
+        return Err(format!("unsupported snapshot version {version}").into());
+    }
+
+    let mut keys = vec![];
+    let mut index = 0;
+    loop {
+        let mut kind = get_u8(&mut src)?;
+        let expires_at = match kind {
+            EOF => return Ok(keys),
+            SELECT_DB => {
+                index = get_u32(&mut src)? as usize;
+                continue;
+            }
+            // The expiration precedes the type of the value.
+            EXPIRE_MS => {
+                let unix = Duration::from_millis(get_u64(&mut src)?);
+                kind = get_u8(&mut src)?;
+                Some(unix)
+            }
+            _ => None,
+        };
+
+        let key = String::from_utf8(get_string(&mut src)?.to_vec())?;
+        let value = get_value(kind, &mut src)?;
+
+        keys.push((index, key, value, expires_at));
+    }
+}
+
+/// Serialize `value` for `DUMP`.
+pub(super) fn dump(value: &Value) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.put_u8(value_type(value));
+    put_value(&mut buf, value);
+    buf.put_u8(VERSION);
+    buf.put_u16(crc16(&buf));
+
+    buf
+}
+
+/// Deserialize a value serialized by `dump`.
+pub(super) fn restore(payload: &[u8]) -> crate::Result<Value> {
+    let invalid = || "ERR DUMP payload version or checksum are wrong".into();
+
+    // The version and the checksum take the last 3 bytes.
+    let Some(len) = payload.len().checked_sub(3) else {
+        return Err(invalid());
+    };
+    let (mut src, trailer) = payload.split_at(len);
+    let checksum = u16::from_be_bytes([trailer[1], trailer[2]]);
+    if trailer[0] != VERSION || checksum != crc16(&payload[..=len]) {
+        return Err(invalid());
+    }
+
+    let kind = get_u8(&mut src)?;
+    let value = get_value(kind, &mut src)?;
+    if !src.is_empty() {
+        return Err("ERR Bad data format".into());
+    }
+
+    Ok(value)
+}
+
+fn value_type(value: &Value) -> u8 {
+    match value {
+        Value::String(_) => TYPE_STRING,
+        Value::Stream(_) => TYPE_STREAM,
+    }
+}
+
+fn put_value(buf: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::String(data) => put_string(buf, data),
+        Value::Stream(stream) => put_stream(buf, stream),
+    }
+}
+
+fn get_value(kind: u8, src: &mut &[u8]) -> crate::Result<Value> {
+    match kind {
+        TYPE_STRING => Ok(Value::String(get_string(src)?)),
+        TYPE_STREAM => Ok(Value::Stream(get_stream(src)?)),
+        kind => Err(format!("unknown value type {kind} in snapshot").into()),
+    }
+}
+
+fn put_string(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.put_u32(u32::try_from(data.len()).unwrap_or(u32::MAX));
+    buf.put_slice(data);
+}
+
+fn put_stream(buf: &mut Vec<u8>, stream: &Stream) {
+    put_stream_id(buf, stream.last_id);
+    buf.put_u32(u32::try_from(stream.entries.len()).unwrap_or(u32::MAX));
+    for (id, fields) in &stream.entries {
+        put_stream_id(buf, *id);
+        buf.put_u32(u32::try_from(fields.len()).unwrap_or(u32::MAX));
+        for (field, value) in fields {
+            put_string(buf, field);
+            put_string(buf, value);
+        }
+    }
+}
+
+fn put_stream_id(buf: &mut Vec<u8>, id: StreamId) {
+    buf.put_u64(id.ms);
+    buf.put_u64(id.seq);
+}
+
+fn get_u8(src: &mut &[u8]) -> crate::Result<u8> {
+    check_remaining(src, 1)?;
+    Ok(src.get_u8())
+}
+
+fn get_u32(src: &mut &[u8]) -> crate::Result<u32> {
+    check_remaining(src, 4)?;
+    Ok(src.get_u32())
+}
+
+fn get_u64(src: &mut &[u8]) -> crate::Result<u64> {
+    check_remaining(src, 8)?;
+    Ok(src.get_u64())
+}
+
+fn get_string(src: &mut &[u8]) -> crate::Result<Bytes> {
+    let len = get_u32(src)? as usize;
+    check_remaining(src, len)?;
+    Ok(src.copy_to_bytes(len))
+}
+
+fn get_stream(src: &mut &[u8]) -> crate::Result<Stream> {
+    let mut stream = Stream {
+        last_id: get_stream_id(src)?,
+        ..Stream::default()
+    };
+
+    for _ in 0..get_u32(src)? {
+        let id = get_stream_id(src)?;
+        let fields = (0..get_u32(src)?)
+            .map(|_| Ok((get_string(src)?, get_string(src)?)))
+            .collect::<crate::Result<_>>()?;
+        stream.entries.insert(id, fields);
+    }
+
+    Ok(stream)
+}
+
+fn get_stream_id(src: &mut &[u8]) -> crate::Result<StreamId> {
+    Ok(StreamId {
+        ms: get_u64(src)?,
+        seq: get_u64(src)?,
+    })
+}
+
+fn check_remaining(src: &[u8], len: usize) -> crate::Result<()> {
+    if src.len() < len {
+        return Err("truncated snapshot".into());
+    }
+
+    Ok(())
+}
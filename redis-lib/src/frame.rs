@@ -3,7 +3,6 @@
 //!
 
 use bytes::{Buf, Bytes};
-use std::convert::TryInto;
 use std::fmt;
 use std::io::Cursor;
 use std::num::TryFromIntError;
@@ -12,14 +11,39 @@ use std::string::FromUtf8Error;
 /// A frame in the Redis protocol.
 ///
 /// Each entry in the `Frame` is a "token".
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Frame {
     Simple(String),
     Error(String),
-    Integer(u64),
+    Integer(i64),
     Bulk(Bytes),
+    /// Read from the RESP3 null, and the null bulk string and null array of RESP2.
+    /// Written as a null bulk string to RESP2 clients.
     Null,
+    /// RESP3 floating point number. It is sent as a bulk string to RESP2 clients.
+    Double(f64),
+    /// RESP3 boolean. It is sent as the integer `1` or `0` to RESP2 clients.
+    Boolean(bool),
+    /// RESP3 integer of any size, as a signed decimal string. It is sent as a bulk
+    /// string to RESP2 clients.
+    BigNumber(String),
+    /// RESP3 string along with its three-letter format, such as `txt` for plain
+    /// text or `mkd` for markdown. It is sent as a bulk string of `data` to RESP2
+    /// clients.
+    Verbatim {
+        format: [u8; 3],
+        data: Bytes,
+    },
     Array(Vec<Frame>),
+    /// RESP3 map of key-value pairs. It is sent as a flat array of keys and values
+    /// to RESP2 clients.
+    Map(Vec<(Frame, Frame)>),
+    /// RESP3 unordered collection of distinct frames. It is sent as an array to
+    /// RESP2 clients.
+    Set(Vec<Frame>),
+    /// RESP3 out-of-band data, such as pub/sub messages, which clients can tell
+    /// apart from replies. It is sent as an array to RESP2 clients.
+    Push(Vec<Frame>),
 }
 
 pub trait PushFrame {
@@ -34,7 +58,7 @@ impl PushFrame for Vec<Frame> {
     }
 
     fn push_int(&mut self, num: u64) {
-        self.push(Frame::Integer(num));
+        self.push(Frame::unsigned(num));
     }
 }
 
@@ -44,6 +68,35 @@ impl From<Vec<Frame>> for Frame {
     }
 }
 
+/// The bounds on the frames read, so that a peer cannot make the reader buffer or
+/// allocate arbitrarily large frames. Frames announcing more are rejected as soon as
+/// their header is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum length of a bulk or verbatim string, in bytes.
+    pub max_bulk_len: usize,
+
+    /// The maximum number of entries of an array, set or push frame, or of pairs
+    /// of a map.
+    pub max_array_len: usize,
+
+    /// The maximum number of aggregate frames nested in one another, so that
+    /// decoding does not overflow the stack.
+    pub max_depth: usize,
+}
+
+impl Default for Limits {
+    /// 512 MiB strings and 1M entries, like `proto-max-bulk-len` and the multibulk
+    /// limit in Redis, nested up to 64 levels.
+    fn default() -> Limits {
+        Limits {
+            max_bulk_len: 512 * 1024 * 1024,
+            max_array_len: 1024 * 1024,
+            max_depth: 64,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     /// Not enough data is available to parse a message
@@ -54,10 +107,35 @@ pub enum Error {
 }
 
 impl Frame {
-    /// Checks if `src` can be decoded.
+    /// An integer frame holding `num`, clamped to `i64::MAX`, the greatest RESP
+    /// integer.
+    pub(crate) fn unsigned(num: u64) -> Frame {
+        Frame::Integer(i64::try_from(num).unwrap_or(i64::MAX))
+    }
+
+    /// Returns true if `byte` starts a frame, as opposed to an inline command.
+    pub(crate) fn is_type_byte(byte: u8) -> bool {
+        matches!(
+            byte,
+            b'+' | b'-'
+                | b':'
+                | b','
+                | b'_'
+                | b'#'
+                | b'('
+                | b'='
+                | b'$'
+                | b'*'
+                | b'~'
+                | b'>'
+                | b'%'
+        )
+    }
+
+    /// Checks if `src` can be decoded into a frame within `limits`.
     ///
     /// advance the cursor until the end of the frame.
-    pub fn check(src: &mut Cursor<&[u8]>) -> Result<(), Error> {
+    pub fn check(src: &mut Cursor<&[u8]>, limits: &Limits) -> Result<(), Error> {
         match get_u8(src)? {
             b'+' => {
                 get_line(src)?;
@@ -68,26 +146,59 @@ impl Frame {
                 Ok(())
             }
             b':' => {
-                let _ = get_decimal(src)?;
+                let _ = get_signed_decimal(src)?;
+                Ok(())
+            }
+            b',' => {
+                get_line(src)?;
+                Ok(())
+            }
+            b'_' => {
+                // Skip '\r\n'
+                skip(src, 2)
+            }
+            b'#' => {
+                // Skip 't\r\n' or 'f\r\n'
+                skip(src, 3)
+            }
+            b'(' => {
+                get_line(src)?;
                 Ok(())
             }
+            b'=' => {
+                let len = get_bulk_len(src, limits)?;
+
+                // skip that number of bytes + 2 (\r\n).
+                skip(src, len + 2)
+            }
             b'$' => {
                 if b'-' == peek_u8(src)? {
                     // Skip '-1\r\n'
                     skip(src, 4)
                 } else {
                     // Read the bulk string
-                    let len: usize = get_decimal(src)?.try_into()?;
+                    let len = get_bulk_len(src, limits)?;
 
                     // skip that number of bytes + 2 (\r\n).
                     skip(src, len + 2)
                 }
             }
-            b'*' => {
-                let len = get_decimal(src)?;
+            // Skip the null array '*-1\r\n'
+            b'*' if b'-' == peek_u8(src)? => skip(src, 4),
+            b'*' | b'~' | b'>' => {
+                let (len, limits) = get_array_len(src, limits)?;
 
                 for _ in 0..len {
-                    Frame::check(src)?;
+                    Frame::check(src, &limits)?;
+                }
+
+                Ok(())
+            }
+            b'%' => {
+                let (len, limits) = get_array_len(src, limits)?;
+
+                for _ in 0..2 * len {
+                    Frame::check(src, &limits)?;
                 }
 
                 Ok(())
@@ -96,7 +207,26 @@ impl Frame {
         }
     }
 
-    pub fn parse(src: &mut Cursor<&[u8]>) -> Result<Frame, Error> {
+    /// Checks if `src` starts with the header of a bulk string within `limits`, and
+    /// returns the length of its payload, following the header.
+    ///
+    /// Returns `None` for other frames, including the null bulk string.
+    pub(crate) fn check_bulk_header(
+        src: &mut Cursor<&[u8]>,
+        limits: &Limits,
+    ) -> Result<Option<usize>, Error> {
+        if get_u8(src)? != b'$' || peek_u8(src)? == b'-' {
+            return Ok(None);
+        }
+
+        get_bulk_len(src, limits).map(Some)
+    }
+
+    /// Decodes a frame from `src`, rejecting it if it exceeds `limits`.
+    ///
+    /// Fails with `Incomplete` if `src` holds only the start of the frame, so that
+    /// it need not be checked first.
+    pub fn parse(src: &mut Cursor<&[u8]>, limits: &Limits) -> Result<Frame, Error> {
         match get_u8(src)? {
             b'+' => {
                 let line = get_line(src)?.to_vec();
@@ -111,9 +241,52 @@ impl Frame {
                 Ok(Frame::Error(string))
             }
             b':' => {
-                let len = get_decimal(src)?;
+                let num = get_signed_decimal(src)?;
+
+                Ok(Frame::Integer(num))
+            }
+            b',' => {
+                let line = get_line(src)?;
+
+                Ok(Frame::Double(parse_double(line)?))
+            }
+            b'_' => {
+                if !get_line(src)?.is_empty() {
+                    return Err("protocol error; invalid frame format".into());
+                }
 
-                Ok(Frame::Integer(len))
+                Ok(Frame::Null)
+            }
+            b'#' => match get_line(src)? {
+                b"t" => Ok(Frame::Boolean(true)),
+                b"f" => Ok(Frame::Boolean(false)),
+                _ => Err("protocol error; invalid boolean".into()),
+            },
+            b'(' => {
+                let line = get_line(src)?;
+                let digits = line.strip_prefix(b"-").unwrap_or(line);
+                if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+                    return Err("protocol error; invalid big number".into());
+                }
+
+                Ok(Frame::BigNumber(String::from_utf8(line.to_vec())?))
+            }
+            b'=' => {
+                let len = get_bulk_len(src, limits)?;
+                let n = len + 2;
+
+                if src.remaining() < n {
+                    return Err(Error::Incomplete);
+                }
+
+                // The data is prefixed by its format and a colon.
+                let (format, data) = match &src.chunk()[..len] {
+                    [a, b, c, b':', data @ ..] => ([*a, *b, *c], Bytes::copy_from_slice(data)),
+                    _ => return Err("protocol error; invalid verbatim string".into()),
+                };
+                skip(src, n)?;
+
+                Ok(Frame::Verbatim { format, data })
             }
             b'$' => {
                 if b'-' == peek_u8(src)? {
@@ -123,7 +296,7 @@ impl Frame {
 
                     Ok(Frame::Null)
                 } else {
-                    let len = get_decimal(src)?.try_into()?;
+                    let len = get_bulk_len(src, limits)?;
                     let n = len + 2;
 
                     if src.remaining() < n {
@@ -138,16 +311,55 @@ impl Frame {
                 }
             }
             b'*' => {
-                let len = get_decimal(src)?.try_into()?;
-                let mut out = Vec::with_capacity(len);
+                // The null array of RESP2, like the reply of `XREAD` timing out, is
+                // as much of a null as the null bulk string.
+                if b'-' == peek_u8(src)? {
+                    if get_line(src)? != b"-1" {
+                        return Err("protocol error; invalid frame format".into());
+                    }
+
+                    return Ok(Frame::Null);
+                }
+                let (len, limits) = get_array_len(src, limits)?;
+                let mut out = Vec::with_capacity(capacity(len, src));
 
                 for _ in 0..len {
-                    out.push(Frame::parse(src)?);
+                    out.push(Frame::parse(src, &limits)?);
                 }
 
                 Ok(Frame::Array(out))
             }
-            _ => unimplemented!(),
+            b'%' => {
+                let (len, limits) = get_array_len(src, limits)?;
+                let mut out = Vec::with_capacity(capacity(len, src));
+
+                for _ in 0..len {
+                    out.push((Frame::parse(src, &limits)?, Frame::parse(src, &limits)?));
+                }
+
+                Ok(Frame::Map(out))
+            }
+            b'~' => {
+                let (len, limits) = get_array_len(src, limits)?;
+                let mut out = Vec::with_capacity(capacity(len, src));
+
+                for _ in 0..len {
+                    out.push(Frame::parse(src, &limits)?);
+                }
+
+                Ok(Frame::Set(out))
+            }
+            b'>' => {
+                let (len, limits) = get_array_len(src, limits)?;
+                let mut out = Vec::with_capacity(capacity(len, src));
+
+                for _ in 0..len {
+                    out.push(Frame::parse(src, &limits)?);
+                }
+
+                Ok(Frame::Push(out))
+            }
+            other => Err(format!("protocol error; invalid frame type byte `{other}`").into()),
         }
     }
 
@@ -156,43 +368,86 @@ impl Frame {
     }
 }
 
-impl PartialEq<&str> for Frame {
-    fn eq(&self, other: &&str) -> bool {
+/// Simple and bulk strings equal the same text. Other frames equal no string.
+impl PartialEq<str> for Frame {
+    fn eq(&self, other: &str) -> bool {
         match self {
-            Frame::Simple(s) => s.eq(other),
-            Frame::Bulk(s) => s.eq(other),
+            Frame::Simple(s) => s == other,
+            Frame::Bulk(s) => s == other.as_bytes(),
             _ => false,
         }
     }
 }
 
+impl PartialEq<&str> for Frame {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+/// Renders frames like `redis-cli`: strings as their text, lossily for binary data,
+/// and the entries of arrays on numbered lines, nested ones being indented.
 impl fmt::Display for Frame {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        use std::str;
-
         match self {
             Frame::Simple(string) => string.fmt(fmt),
-            Frame::Error(msg) => write!(fmt, "error: {}", msg),
+            Frame::Error(msg) => write!(fmt, "(error) {msg}"),
             Frame::Integer(num) => num.fmt(fmt),
-            Frame::Bulk(bytes) => match str::from_utf8(bytes) {
-                Ok(string) => string.fmt(fmt),
-                Err(_) => write!(fmt, "{:?}", bytes),
-            },
+            Frame::Bulk(bytes) => String::from_utf8_lossy(bytes).fmt(fmt),
             Frame::Null => "(nil)".fmt(fmt),
-            Frame::Array(frames) => {
-                for (i, frame) in frames.iter().enumerate() {
-                    if i > 0 {
-                        // use space as the array element display separator
-                        write!(fmt, " ")?;
-                    }
+            Frame::Double(val) => val.fmt(fmt),
+            Frame::Boolean(val) => val.fmt(fmt),
+            Frame::BigNumber(val) => val.fmt(fmt),
+            Frame::Verbatim { data, .. } => String::from_utf8_lossy(data).fmt(fmt),
+            Frame::Array(frames) | Frame::Push(frames) => {
+                let entries: Vec<_> = frames.iter().map(ToString::to_string).collect();
+                fmt_entries(fmt, ')', &entries, "(empty array)")
+            }
+            Frame::Set(frames) => {
+                let entries: Vec<_> = frames.iter().map(ToString::to_string).collect();
+                fmt_entries(fmt, '~', &entries, "(empty set)")
+            }
+            Frame::Map(entries) => {
+                let entries: Vec<_> = entries
+                    .iter()
+                    .map(|(key, value)| format!("{key} => {value}"))
+                    .collect();
+                fmt_entries(fmt, '#', &entries, "(empty hash)")
+            }
+        }
+    }
+}
 
-                    frame.fmt(fmt)?;
-                }
+/// Write `entries` on lines numbered from 1, followed by `marker`, such as `1) a`.
+/// The following lines of an entry are indented under its first one.
+fn fmt_entries(
+    fmt: &mut fmt::Formatter,
+    marker: char,
+    entries: &[String],
+    empty: &str,
+) -> fmt::Result {
+    if entries.is_empty() {
+        return fmt.write_str(empty);
+    }
 
-                Ok(())
+    // Numbers are right-aligned.
+    let width = entries.len().to_string().len();
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            writeln!(fmt)?;
+        }
+
+        let number = format!("{:>width$}{marker} ", i + 1);
+        for (j, line) in entry.split('\n').enumerate() {
+            if j == 0 {
+                write!(fmt, "{number}{line}")?;
+            } else {
+                write!(fmt, "\n{:indent$}{line}", "", indent = number.len())?;
             }
         }
     }
+
+    Ok(())
 }
 
 fn peek_u8(src: &mut Cursor<&[u8]>) -> Result<u8, Error> {
@@ -221,6 +476,19 @@ fn skip(src: &mut Cursor<&[u8]>, n: usize) -> Result<(), Error> {
     Ok(())
 }
 
+/// Parse the payload of a double frame, which may be `inf`, `-inf` or `nan`.
+fn parse_double(line: &[u8]) -> Result<f64, Error> {
+    match line {
+        b"inf" | b"+inf" => Ok(f64::INFINITY),
+        b"-inf" => Ok(f64::NEG_INFINITY),
+        b"nan" => Ok(f64::NAN),
+        line => std::str::from_utf8(line)
+            .ok()
+            .and_then(|line| line.parse().ok())
+            .ok_or_else(|| "protocol error; invalid double".into()),
+    }
+}
+
 /// Read a new-line, advance the cursor to the next line.
 fn get_decimal(src: &mut Cursor<&[u8]>) -> Result<u64, Error> {
     use atoi::atoi;
@@ -230,6 +498,53 @@ fn get_decimal(src: &mut Cursor<&[u8]>) -> Result<u64, Error> {
     atoi::<u64>(line).ok_or_else(|| "protocol error; invalid frame format".into())
 }
 
+/// Read the length of a bulk or verbatim string, which may not exceed `limits`.
+fn get_bulk_len(src: &mut Cursor<&[u8]>, limits: &Limits) -> Result<usize, Error> {
+    let len = usize::try_from(get_decimal(src)?)?;
+    if len > limits.max_bulk_len {
+        return Err("protocol error; invalid bulk length".into());
+    }
+
+    Ok(len)
+}
+
+/// Read the number of entries of an aggregate frame, which may not exceed `limits`.
+/// Returns it along with the limits of the entries, nested one level deeper.
+fn get_array_len(src: &mut Cursor<&[u8]>, limits: &Limits) -> Result<(usize, Limits), Error> {
+    let Some(max_depth) = limits.max_depth.checked_sub(1) else {
+        return Err("protocol error; too deeply nested frame".into());
+    };
+    let len = usize::try_from(get_decimal(src)?)?;
+    if len > limits.max_array_len {
+        return Err("protocol error; invalid multibulk length".into());
+    }
+
+    Ok((
+        len,
+        Limits {
+            max_depth,
+            ..*limits
+        },
+    ))
+}
+
+/// The capacity to reserve for the `len` entries of an aggregate frame, whose
+/// remaining bytes are in `src`. Entries take several bytes each, so a frame
+/// announcing many entries allocates no more than its buffered bytes.
+fn capacity(len: usize, src: &Cursor<&[u8]>) -> usize {
+    len.min(src.remaining())
+}
+
+/// Read a new-line holding a possibly negative number, advance the cursor to the
+/// next line.
+fn get_signed_decimal(src: &mut Cursor<&[u8]>) -> Result<i64, Error> {
+    use atoi::atoi;
+
+    let line = get_line(src)?;
+
+    atoi::<i64>(line).ok_or_else(|| "protocol error; invalid frame format".into())
+}
+
 /// advance the cursor to the next line.
 fn get_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], Error> {
     let start = src.position() as usize;
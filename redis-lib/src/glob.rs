@@ -0,0 +1,76 @@
+//!
+//! Redis-style glob matching, as used by pattern subscriptions.
+//!
+//! Supported syntax:
+//!
+//! * `?` matches any single byte.
+//! * `*` matches any sequence of bytes, including an empty one.
+//! * `[abc]`, `[^abc]` and `[a-z]` match a byte from (or not from) a set.
+//! * `\x` matches `x` literally.
+//!
+
+/// Returns `true` if `string` matches the glob `pattern`.
+pub(crate) fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => string.is_empty(),
+        Some((b'*', rest)) => {
+            // Try every possible length for the sequence matched by `*`.
+            (0..=string.len()).any(|i| glob_match(rest, &string[i..]))
+        }
+        Some((b'?', rest)) => match string.split_first() {
+            Some((_, string)) => glob_match(rest, string),
+            None => false,
+        },
+        Some((b'[', rest)) => match string.split_first() {
+            Some((&c, string)) => match match_class(rest, c) {
+                Some((true, rest)) => glob_match(rest, string),
+                _ => false,
+            },
+            None => false,
+        },
+        Some((b'\\', [escaped, rest @ ..])) => match string.split_first() {
+            Some((c, string)) if c == escaped => glob_match(rest, string),
+            _ => false,
+        },
+        Some((p, rest)) => match string.split_first() {
+            Some((c, string)) if c == p => glob_match(rest, string),
+            _ => false,
+        },
+    }
+}
+
+/// Match `c` against the class following a `[`.
+///
+/// Returns whether `c` is matched, together with the remaining pattern after the
+/// closing `]`. Returns `None` if the class is not terminated.
+fn match_class(pattern: &[u8], c: u8) -> Option<(bool, &[u8])> {
+    let (negate, mut pattern) = match pattern.split_first() {
+        Some((b'^', rest)) => (true, rest),
+        _ => (false, pattern),
+    };
+
+    let mut matched = false;
+    loop {
+        match pattern {
+            [] => return None,
+            [b']', rest @ ..] => return Some((matched != negate, rest)),
+            [b'\\', escaped, rest @ ..] => {
+                matched |= *escaped == c;
+                pattern = rest;
+            }
+            [start, b'-', end, rest @ ..] if *end != b']' => {
+                let (low, high) = if start <= end {
+                    (*start, *end)
+                } else {
+                    (*end, *start)
+                };
+                matched |= (low..=high).contains(&c);
+                pattern = rest;
+            }
+            [other, rest @ ..] => {
+                matched |= *other == c;
+                pattern = rest;
+            }
+        }
+    }
+}
@@ -7,11 +7,72 @@
 //!
 //! # Supported commands
 //!
+//! * [AUTH](https://redis.io/commands/auth)
+//! * [HELLO](https://redis.io/commands/hello)
 //! * [PING](https://redis.io/commands/ping)
+//! * [CLIENT SETNAME](https://redis.io/commands/client-setname)
+//! * [CLIENT GETNAME](https://redis.io/commands/client-getname)
+//! * [CLIENT ID](https://redis.io/commands/client-id)
+//! * [CLIENT LIST](https://redis.io/commands/client-list)
+//! * [CLIENT KILL](https://redis.io/commands/client-kill)
+//! * [CLIENT PAUSE](https://redis.io/commands/client-pause)
+//! * [CLIENT NO-EVICT](https://redis.io/commands/client-no-evict)
+//! * [CLIENT TRACKING](https://redis.io/commands/client-tracking)
+//! * [QUIT](https://redis.io/commands/quit)
+//! * [RESET](https://redis.io/commands/reset)
+//! * [SELECT](https://redis.io/commands/select)
+//! * [SWAPDB](https://redis.io/commands/swapdb)
+//! * [MOVE](https://redis.io/commands/move)
+//! * [RENAME](https://redis.io/commands/rename)
+//! * [RENAMENX](https://redis.io/commands/renamenx)
+//! * [EXPIRE](https://redis.io/commands/expire)
+//! * [PEXPIRE](https://redis.io/commands/pexpire)
+//! * [PEXPIREAT](https://redis.io/commands/pexpireat)
+//! * [PERSIST](https://redis.io/commands/persist)
+//! * [DEL](https://redis.io/commands/del)
+//! * [DUMP](https://redis.io/commands/dump)
+//! * [RESTORE](https://redis.io/commands/restore)
+//! * [SCAN](https://redis.io/commands/scan)
+//! * [COMMAND](https://redis.io/commands/command)
+//! * [COMMAND COUNT](https://redis.io/commands/command-count)
+//! * [COMMAND DOCS](https://redis.io/commands/command-docs)
+//! * [INFO](https://redis.io/commands/info)
+//! * [CONFIG GET](https://redis.io/commands/config-get)
+//! * [CONFIG SET](https://redis.io/commands/config-set)
+//! * [LASTSAVE](https://redis.io/commands/lastsave)
+//! * [DEBUG SLEEP](https://redis.io/commands/debug)
+//! * [DEBUG OBJECT](https://redis.io/commands/debug)
+//! * [SLOWLOG GET](https://redis.io/commands/slowlog-get)
+//! * [SLOWLOG LEN](https://redis.io/commands/slowlog-len)
+//! * [SLOWLOG RESET](https://redis.io/commands/slowlog-reset)
+//! * [MONITOR](https://redis.io/commands/monitor)
+//! * [REPLICAOF](https://redis.io/commands/replicaof)
+//! * [PSYNC](https://redis.io/commands/psync)
+//! * [WAIT](https://redis.io/commands/wait)
+//! * [WAITAOF](https://redis.io/commands/waitaof)
+//! * [MEMORY USAGE](https://redis.io/commands/memory-usage)
+//! * [OBJECT ENCODING](https://redis.io/commands/object-encoding)
+//! * [OBJECT REFCOUNT](https://redis.io/commands/object-refcount)
+//! * [OBJECT FREQ](https://redis.io/commands/object-freq)
+//! * [SAVE](https://redis.io/commands/save)
+//! * [BGSAVE](https://redis.io/commands/bgsave)
+//! * [BGREWRITEAOF](https://redis.io/commands/bgrewriteaof)
 //! * [GET](https://redis.io/commands/get)
+//! * [GETEX](https://redis.io/commands/getex)
 //! * [SET](https://redis.io/commands/set)
+//! * [MULTI](https://redis.io/commands/multi)
+//! * [EXEC](https://redis.io/commands/exec)
+//! * [DISCARD](https://redis.io/commands/discard)
+//! * [WATCH](https://redis.io/commands/watch)
+//! * [UNWATCH](https://redis.io/commands/unwatch)
 //! * [PUBLISH](https://redis.io/commands/publish)
+//! * [PUBSUB](https://redis.io/commands/pubsub)
 //! * [SUBSCRIBE](https://redis.io/commands/subscribe)
+//! * [PSUBSCRIBE](https://redis.io/commands/psubscribe)
+//! * [SPUBLISH](https://redis.io/commands/spublish)
+//! * [SSUBSCRIBE](https://redis.io/commands/ssubscribe)
+//! * [XADD](https://redis.io/commands/xadd)
+//! * [XREAD](https://redis.io/commands/xread)
 //!
 //!  
 //!
@@ -20,14 +81,22 @@
 #![warn(clippy::pedantic)]
 #![warn(clippy::cargo)]
 
+mod access_log;
+use access_log::AccessLog;
+
+mod aof;
+use aof::Aof;
+
 pub mod clients;
 pub use clients::Client;
 
+pub mod cluster;
+
 pub mod cmd;
 pub use cmd::Command;
 
 mod connection;
-pub use connection::Connection;
+pub use connection::{BulkReader, Connection, StreamedFrame, TcpOptions};
 
 pub mod frame;
 pub use frame::Frame;
@@ -36,11 +105,31 @@ mod db;
 use db::Db;
 use db::DbDropGuard;
 
+mod glob;
+
+mod metrics;
+
 mod parse;
 use parse::{Parse, ParseError};
 
+mod registry;
+use registry::{ClientHandle, ClientRegistry};
+
+mod replication;
+
 pub mod server;
 
+mod settings;
+use settings::Settings;
+
+#[cfg(feature = "tls")]
+pub mod tls;
+
+mod slowlog;
+use slowlog::SlowLog;
+
+mod tracking;
+
 mod shutdown;
 use shutdown::Shutdown;
 
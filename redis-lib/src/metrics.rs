@@ -0,0 +1,200 @@
+//!
+//! The counters of the server, reported by `INFO stats` and `server::metrics`.
+//!
+//! Like in Redis, the counters are kept for the whole process, since the server was
+//! started, and are never reset.
+//!
+
+use std::collections::BTreeMap;
+use std::io;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+static METRICS: Metrics = Metrics::new();
+
+/// The name the calls of unknown commands are counted under, whatever their name.
+pub(crate) const UNKNOWN_COMMAND: &str = "unknown";
+
+/// The counters of the connections and commands served.
+#[derive(Debug)]
+pub struct Metrics {
+    total_connections: AtomicU64,
+    connected: AtomicU64,
+    commands_processed: AtomicU64,
+
+    /// The number of calls of each command, by name.
+    commands: Mutex<BTreeMap<String, u64>>,
+
+    net_input_bytes: AtomicU64,
+    net_output_bytes: AtomicU64,
+    keyspace_hits: AtomicU64,
+    keyspace_misses: AtomicU64,
+    evicted_keys: AtomicU64,
+}
+
+/// Counts the connection as connected until dropped.
+#[derive(Debug)]
+pub(crate) struct Connected(());
+
+/// A stream counting the bytes read from and written to it, as received from and
+/// sent to a client.
+#[derive(Debug)]
+pub(crate) struct Metered<S>(S);
+
+/// Returns the counters of the server.
+pub(crate) fn get() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    const fn new() -> Metrics {
+        Metrics {
+            total_connections: AtomicU64::new(0),
+            connected: AtomicU64::new(0),
+            commands_processed: AtomicU64::new(0),
+            commands: Mutex::new(BTreeMap::new()),
+            net_input_bytes: AtomicU64::new(0),
+            net_output_bytes: AtomicU64::new(0),
+            keyspace_hits: AtomicU64::new(0),
+            keyspace_misses: AtomicU64::new(0),
+            evicted_keys: AtomicU64::new(0),
+        }
+    }
+
+    /// The number of connections accepted.
+    pub fn total_connections(&self) -> u64 {
+        self.total_connections.load(Ordering::Relaxed)
+    }
+
+    /// The number of connections currently open.
+    pub fn connected(&self) -> u64 {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// The number of commands processed, of any type.
+    pub fn commands_processed(&self) -> u64 {
+        self.commands_processed.load(Ordering::Relaxed)
+    }
+
+    /// The number of calls of each command processed, by lowercase name. The calls of
+    /// unknown commands are counted together, under `unknown`.
+    pub fn commands(&self) -> BTreeMap<String, u64> {
+        self.commands.lock().unwrap().clone()
+    }
+
+    /// The number of bytes received from clients.
+    pub fn net_input_bytes(&self) -> u64 {
+        self.net_input_bytes.load(Ordering::Relaxed)
+    }
+
+    /// The number of bytes sent to clients.
+    pub fn net_output_bytes(&self) -> u64 {
+        self.net_output_bytes.load(Ordering::Relaxed)
+    }
+
+    /// The number of successful lookups of keys.
+    pub fn keyspace_hits(&self) -> u64 {
+        self.keyspace_hits.load(Ordering::Relaxed)
+    }
+
+    /// The number of failed lookups of keys.
+    pub fn keyspace_misses(&self) -> u64 {
+        self.keyspace_misses.load(Ordering::Relaxed)
+    }
+
+    /// The number of keys evicted to stay within `maxmemory`.
+    pub fn evicted_keys(&self) -> u64 {
+        self.evicted_keys.load(Ordering::Relaxed)
+    }
+
+    /// Count an accepted connection, open until the returned guard is dropped.
+    pub(crate) fn connect(&self) -> Connected {
+        self.total_connections.fetch_add(1, Ordering::Relaxed);
+        self.connected.fetch_add(1, Ordering::Relaxed);
+        Connected(())
+    }
+
+    /// Count a call of the command `name`.
+    pub(crate) fn command(&self, name: &str) {
+        self.commands_processed.fetch_add(1, Ordering::Relaxed);
+        let mut commands = self.commands.lock().unwrap();
+        match commands.get_mut(name) {
+            Some(calls) => *calls += 1,
+            None => {
+                commands.insert(name.to_string(), 1);
+            }
+        }
+    }
+
+    /// Count a lookup of a key, successful if `hit`.
+    pub(crate) fn lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.keyspace_hits
+        } else {
+            &self.keyspace_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a key evicted to stay within `maxmemory`.
+    pub(crate) fn evict(&self) {
+        self.evicted_keys.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Connected {
+    fn drop(&mut self) {
+        METRICS.connected.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<S> Metered<S> {
+    pub(crate) fn new(stream: S) -> Metered<S> {
+        Metered(stream)
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Metered<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let res = Pin::new(&mut self.0).poll_read(cx, buf);
+        let read = buf.filled().len() - filled;
+        METRICS
+            .net_input_bytes
+            .fetch_add(read as u64, Ordering::Relaxed);
+
+        res
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Metered<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let res = Pin::new(&mut self.0).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = res {
+            METRICS
+                .net_output_bytes
+                .fetch_add(written as u64, Ordering::Relaxed);
+        }
+
+        res
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
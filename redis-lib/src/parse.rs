@@ -18,16 +18,51 @@ pub(crate) enum ParseError {
     /// Attempting to extract a value failed due to the frame being fully consumed.
     EndOfStream,
 
+    /// The frame is not of the expected type.
+    UnexpectedFrame { expected: Expected, got: Frame },
+
+    /// A string is not valid UTF-8.
+    InvalidUtf8,
+
+    /// An integer is not a number, or does not fit the expected type.
+    InvalidInteger,
+
+    /// The command, named in lowercase, is given too few or too many arguments.
+    /// The connection is kept, the error being replied.
+    WrongArity(&'static str),
+
+    /// An argument is invalid, such as an unknown option. The connection is kept,
+    /// the error being replied as is, starting with its code like `ERR`.
+    Reply(String),
+
     /// `Other` result in the connection being terminated.
     Other(crate::Error),
 }
 
+/// The type of frame expected by `Parse`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Expected {
+    /// An array of frames, the command.
+    Array,
+
+    /// A simple or bulk string.
+    String,
+
+    /// An integer, or a string holding one.
+    Integer,
+}
+
 impl Parse {
     /// Returns `Err` if `frame` is not an array frame.
     pub(crate) fn new(frame: Frame) -> Result<Parse, ParseError> {
         let array = match frame {
             Frame::Array(array) => array,
-            other => return Err(format!("protocol error; expected array, got {:?}", other).into()),
+            other => {
+                return Err(ParseError::UnexpectedFrame {
+                    expected: Expected::Array,
+                    got: other,
+                });
+            }
         };
 
         Ok(Parse {
@@ -35,6 +70,11 @@ impl Parse {
         })
     }
 
+    /// The number of frames left to extract.
+    pub(crate) fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+
     fn next(&mut self) -> Result<Frame, ParseError> {
         self.frames.next().ok_or(ParseError::EndOfStream)
     }
@@ -46,12 +86,11 @@ impl Parse {
             Frame::Simple(s) => Ok(s),
             Frame::Bulk(bytes) => str::from_utf8(&bytes)
                 .map(|s| s.to_string())
-                .map_err(|_| "protocol error; invalid string".into()),
-            other => Err(format!(
-                "protocol error; expected simple frame or bulk frame, got {:?}",
-                other
-            )
-            .into()),
+                .map_err(|_| ParseError::InvalidUtf8),
+            other => Err(ParseError::UnexpectedFrame {
+                expected: Expected::String,
+                got: other,
+            }),
         }
     }
 
@@ -61,11 +100,10 @@ impl Parse {
             // Both `Simple` and `Bulk` representation may be raw bytes.
             Frame::Simple(s) => Ok(Bytes::from(s.into_bytes())),
             Frame::Bulk(bytes) => Ok(bytes),
-            frame => Err(format!(
-                "protocol error; expected simple frame or bulk frame, got {:?}",
-                frame
-            )
-            .into()),
+            frame => Err(ParseError::UnexpectedFrame {
+                expected: Expected::String,
+                got: frame,
+            }),
         }
     }
 
@@ -75,13 +113,31 @@ impl Parse {
     pub(crate) fn next_int(&mut self) -> Result<u64, ParseError> {
         use atoi::atoi;
 
-        const MSG: &str = "protocol error; invalid number";
+        match self.next()? {
+            Frame::Integer(num) => u64::try_from(num).map_err(|_| ParseError::InvalidInteger),
+            Frame::Simple(data) => atoi::<u64>(data.as_bytes()).ok_or(ParseError::InvalidInteger),
+            Frame::Bulk(data) => atoi::<u64>(&data).ok_or(ParseError::InvalidInteger),
+            other => Err(ParseError::UnexpectedFrame {
+                expected: Expected::Integer,
+                got: other,
+            }),
+        }
+    }
+
+    /// covert the next `Frame` as a possibly negative integer.
+    ///
+    /// This includes `Simple`, `Bulk`, and `Integer` frame types.
+    pub(crate) fn next_signed_int(&mut self) -> Result<i64, ParseError> {
+        use atoi::atoi;
 
         match self.next()? {
             Frame::Integer(num) => Ok(num),
-            Frame::Simple(data) => atoi::<u64>(data.as_bytes()).ok_or_else(|| MSG.into()),
-            Frame::Bulk(data) => atoi::<u64>(&data).ok_or_else(|| MSG.into()),
-            other => Err(format!("protocol error; expected int frame but got {:?}", other).into()),
+            Frame::Simple(data) => atoi::<i64>(data.as_bytes()).ok_or(ParseError::InvalidInteger),
+            Frame::Bulk(data) => atoi::<i64>(&data).ok_or(ParseError::InvalidInteger),
+            other => Err(ParseError::UnexpectedFrame {
+                expected: Expected::Integer,
+                got: other,
+            }),
         }
     }
 
@@ -111,9 +167,83 @@ impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ParseError::EndOfStream => "protocol error; unexpected end of stream".fmt(f),
+            ParseError::UnexpectedFrame { expected, got } => match expected {
+                Expected::Array => write!(f, "protocol error; expected array, got {got:?}"),
+                Expected::String => write!(
+                    f,
+                    "protocol error; expected simple frame or bulk frame, got {got:?}"
+                ),
+                Expected::Integer => {
+                    write!(f, "protocol error; expected int frame but got {got:?}")
+                }
+            },
+            ParseError::InvalidUtf8 => "protocol error; invalid string".fmt(f),
+            ParseError::InvalidInteger => "protocol error; invalid number".fmt(f),
+            ParseError::WrongArity(command) => {
+                write!(f, "wrong number of arguments for '{command}' command")
+            }
+            ParseError::Reply(msg) => msg.fmt(f),
             ParseError::Other(err) => err.fmt(f),
         }
     }
 }
 
 impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(frames: Vec<Frame>) -> Parse {
+        Parse::new(Frame::Array(frames)).unwrap()
+    }
+
+    #[test]
+    fn integer_where_string_expected() {
+        let err = parse(vec![Frame::Integer(1)]).next_string().unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::UnexpectedFrame {
+                expected: Expected::String,
+                got: Frame::Integer(1),
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "protocol error; expected simple frame or bulk frame, got Integer(1)"
+        );
+    }
+
+    #[test]
+    fn command_not_an_array() {
+        let err = Parse::new(Frame::Simple("PING".into())).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::UnexpectedFrame {
+                expected: Expected::Array,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let mut parse = parse(vec![
+            Frame::Bulk(Bytes::from_static(b"\xff")),
+            Frame::Bulk(Bytes::from_static(b"ten")),
+            Frame::Integer(-1),
+            Frame::Null,
+        ]);
+        assert!(matches!(parse.next_string(), Err(ParseError::InvalidUtf8)));
+        assert!(matches!(parse.next_int(), Err(ParseError::InvalidInteger)));
+        assert!(matches!(parse.next_int(), Err(ParseError::InvalidInteger)));
+        assert!(matches!(
+            parse.next_signed_int(),
+            Err(ParseError::UnexpectedFrame {
+                expected: Expected::Integer,
+                got: Frame::Null,
+            })
+        ));
+        assert!(matches!(parse.next_bytes(), Err(ParseError::EndOfStream)));
+    }
+}
@@ -0,0 +1,209 @@
+//!
+//! The registry of the connections served by the server.
+//!
+
+use crate::cmd::{KillFilter, PauseMode};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+/// The connections currently served, keyed by connection id.
+///
+/// Shared by all connections, so that they can inspect each other.
+#[derive(Debug, Default)]
+pub(crate) struct ClientRegistry {
+    clients: Mutex<HashMap<u64, ClientInfo>>,
+
+    /// Set by `CLIENT PAUSE`, until when and which commands are suspended.
+    pause: Mutex<Option<(Instant, PauseMode)>>,
+}
+
+/// What is known about a connection.
+#[derive(Debug)]
+struct ClientInfo {
+    /// `ip:port`, or `path:0` for a Unix domain socket.
+    addr: String,
+
+    /// Set by `CLIENT SETNAME`.
+    name: Option<String>,
+
+    /// When the connection was accepted.
+    created_at: Instant,
+
+    /// The name of the last command issued on the connection.
+    last_command: String,
+
+    /// Closes the connection. Taken when the connection is killed.
+    kill: Option<oneshot::Sender<()>>,
+}
+
+/// The entry of a single connection in the registry.
+///
+/// The entry is removed when the handle is dropped, that is when the connection
+/// is closed.
+#[derive(Debug)]
+pub(crate) struct ClientHandle {
+    id: u64,
+    registry: Arc<ClientRegistry>,
+}
+
+impl ClientRegistry {
+    /// Add the connection `id` from `addr` to the registry.
+    ///
+    /// The returned receiver completes when the connection is killed.
+    pub(crate) fn register(
+        self: &Arc<Self>,
+        id: u64,
+        addr: String,
+    ) -> (ClientHandle, oneshot::Receiver<()>) {
+        let (kill_tx, kill_rx) = oneshot::channel();
+        let info = ClientInfo {
+            addr,
+            name: None,
+            created_at: Instant::now(),
+            last_command: String::new(),
+            kill: Some(kill_tx),
+        };
+        self.clients.lock().unwrap().insert(id, info);
+
+        let handle = ClientHandle {
+            id,
+            registry: self.clone(),
+        };
+
+        (handle, kill_rx)
+    }
+
+    /// Kill the connections matching `filter`, except the connection `skip`.
+    ///
+    /// Returns the number of connections killed.
+    pub(crate) fn kill(&self, filter: &KillFilter, skip: u64) -> usize {
+        let mut clients = self.clients.lock().unwrap();
+
+        let mut killed = 0;
+        for (id, info) in clients.iter_mut().filter(|(id, _)| **id != skip) {
+            let matches = match filter {
+                KillFilter::Id(target) => id == target,
+                KillFilter::Addr(target) => info.addr == *target,
+            };
+            if !matches {
+                continue;
+            }
+
+            // A connection already killed is not counted again.
+            if let Some(kill) = info.kill.take() {
+                let _ = kill.send(());
+                killed += 1;
+            }
+        }
+
+        killed
+    }
+
+    /// Suspend the commands selected by `mode` for `timeout`.
+    ///
+    /// An ongoing pause is only ever extended, like in Redis: it lasts until the
+    /// later deadline, and suspends every command if either pause does.
+    pub(crate) fn pause(&self, timeout: Duration, mode: PauseMode) {
+        let now = Instant::now();
+        let mut pause = self.pause.lock().unwrap();
+
+        let (until, mode) = match *pause {
+            Some((until, PauseMode::All)) if until > now => {
+                (until.max(now + timeout), PauseMode::All)
+            }
+            Some((until, _)) if until > now => (until.max(now + timeout), mode),
+            _ => (now + timeout, mode),
+        };
+        *pause = Some((until, mode));
+    }
+
+    /// Returns until when a command is suspended, or `None` if it may run now.
+    ///
+    /// `is_write` tells whether the command is a write, see `Command::is_write`.
+    pub(crate) fn paused_until(&self, is_write: bool) -> Option<Instant> {
+        match *self.pause.lock().unwrap() {
+            Some((until, mode))
+                if until > Instant::now() && (is_write || mode == PauseMode::All) =>
+            {
+                Some(until)
+            }
+            _ => None,
+        }
+    }
+
+    /// The number of connections.
+    pub(crate) fn len(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// Describe every connection, one line each, ordered by id.
+    pub(crate) fn list(&self) -> String {
+        let clients = self.clients.lock().unwrap();
+
+        let mut ids: Vec<_> = clients.keys().copied().collect();
+        ids.sort_unstable();
+
+        let mut list = String::new();
+        for id in ids {
+            let info = &clients[&id];
+            let _ = writeln!(
+                list,
+                "id={} addr={} name={} age={} cmd={}",
+                id,
+                info.addr,
+                info.name.as_deref().unwrap_or_default(),
+                info.created_at.elapsed().as_secs(),
+                info.last_command,
+            );
+        }
+
+        list
+    }
+}
+
+impl ClientHandle {
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The registry holding all the connections.
+    pub(crate) fn registry(&self) -> &ClientRegistry {
+        &self.registry
+    }
+
+    pub(crate) fn addr(&self) -> String {
+        self.with_info(|info| info.addr.clone())
+    }
+
+    pub(crate) fn name(&self) -> Option<String> {
+        self.with_info(|info| info.name.clone())
+    }
+
+    pub(crate) fn set_name(&self, name: Option<String>) {
+        self.with_info(|info| info.name = name);
+    }
+
+    /// Record `command` as the last command issued on the connection.
+    pub(crate) fn set_last_command(&self, command: &str) {
+        self.with_info(|info| {
+            info.last_command.clear();
+            info.last_command.push_str(command);
+        });
+    }
+
+    fn with_info<T>(&self, f: impl FnOnce(&mut ClientInfo) -> T) -> T {
+        let mut clients = self.registry.clients.lock().unwrap();
+        // The entry lives as long as the handle.
+        f(clients.get_mut(&self.id).unwrap())
+    }
+}
+
+impl Drop for ClientHandle {
+    fn drop(&mut self) {
+        self.registry.clients.lock().unwrap().remove(&self.id);
+    }
+}
@@ -0,0 +1,209 @@
+//!
+//! Replication of the databases to other servers, see `REPLICAOF` and `PSYNC`.
+//!
+//! A replica connects to its primary and sends `PSYNC`. The primary replies
+//! `+FULLRESYNC <replid> <offset>`, followed by a snapshot of its databases as a bulk
+//! string, in the format written by `SAVE`. It then forwards the write commands it
+//! applies, as sent by its clients, preceded by a `SELECT` whenever they apply to
+//! another database than the previous one. The replica loads the snapshot, then
+//! applies the commands in turn.
+//!
+//! Unlike Redis, the snapshot ends with a CRLF like any bulk string, and every
+//! `PSYNC` is answered with a full resynchronization.
+//!
+
+use crate::cmd::{PSync, Protocol};
+use crate::server::request_args;
+use crate::{Command, Connection, Db, Frame};
+use bytes::Bytes;
+use std::fmt::Write;
+use std::hash::{BuildHasher, RandomState};
+use std::io::{Error, ErrorKind};
+use std::sync::Mutex;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Duration};
+use tracing::{debug, info, warn};
+
+/// The number of write commands a replica may fall behind on. A replica falling
+/// further behind is disconnected, and synchronizes again.
+const FEED_CAPACITY: usize = 16 * 1024;
+
+/// How long a replica waits before connecting again to its primary, once the link
+/// is lost.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The write commands applied by a primary, with the index of their database.
+pub(crate) type Feed = broadcast::Receiver<(usize, Vec<Bytes>)>;
+
+/// The replication state of a server.
+#[derive(Debug)]
+pub(crate) struct Replication {
+    /// Identifies the history of the databases, as sent to the replicas.
+    replid: String,
+
+    /// Sends the write commands applied to the replicas.
+    feed: broadcast::Sender<(usize, Vec<Bytes>)>,
+
+    /// The link to the primary, while the server is a replica.
+    link: Mutex<Option<Link>>,
+}
+
+/// The link of a replica to its primary.
+#[derive(Debug)]
+struct Link {
+    host: String,
+    port: u16,
+
+    /// Follows the primary, until aborted.
+    task: JoinHandle<()>,
+}
+
+impl Replication {
+    pub(crate) fn new() -> Replication {
+        Replication {
+            replid: new_replid(),
+            feed: broadcast::channel(FEED_CAPACITY).0,
+            link: Mutex::new(None),
+        }
+    }
+
+    /// The replication id of the server, sent to the replicas.
+    pub(crate) fn replid(&self) -> &str {
+        &self.replid
+    }
+
+    /// Send the write command `args`, applied to the database at `index`, to the
+    /// replicas.
+    pub(crate) fn feed(&self, index: usize, args: &[Bytes]) {
+        if self.feed.receiver_count() > 0 {
+            let _ = self.feed.send((index, args.to_vec()));
+        }
+    }
+
+    /// Returns the write commands applied from now on.
+    pub(crate) fn subscribe(&self) -> Feed {
+        self.feed.subscribe()
+    }
+
+    /// The number of replicas synchronized with the server.
+    pub(crate) fn replicas(&self) -> usize {
+        self.feed.receiver_count()
+    }
+
+    /// The host and port of the primary, or `None` if the server is a primary.
+    pub(crate) fn primary(&self) -> Option<(String, u16)> {
+        let link = self.link.lock().unwrap();
+        link.as_ref().map(|link| (link.host.clone(), link.port))
+    }
+
+    /// Returns `true` if the server follows a primary, in which case it refuses the
+    /// write commands of its clients.
+    pub(crate) fn is_replica(&self) -> bool {
+        self.link.lock().unwrap().is_some()
+    }
+
+    /// Follow the primary at `host` and `port` with the `task`, instead of the
+    /// current one if any.
+    fn follow(&self, host: String, port: u16, task: JoinHandle<()>) {
+        let link = Link { host, port, task };
+        if let Some(previous) = self.link.lock().unwrap().replace(link) {
+            previous.task.abort();
+        }
+    }
+
+    /// Stop following the primary, keeping the keys replicated so far. Returns
+    /// `false` if the server was not a replica.
+    pub(crate) fn unfollow(&self) -> bool {
+        match self.link.lock().unwrap().take() {
+            Some(link) => {
+                link.task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Make `db` a replica of the primary at `host` and `port`, following it from a
+/// background task instead of the current primary if any.
+pub(crate) fn start(db: &Db, host: String, port: u16) {
+    let task = tokio::spawn(follow(db.clone(), format!("{host}:{port}")));
+    db.replication().follow(host, port, task);
+}
+
+/// Replicate the primary at `addr` into `db`, until the task is aborted.
+///
+/// The link is established again after `RETRY_DELAY` whenever it is lost, starting
+/// over from a new snapshot.
+async fn follow(db: Db, addr: String) {
+    loop {
+        match sync(&db, &addr).await {
+            Ok(()) => info!(addr, "replication link closed by the primary"),
+            Err(err) => warn!(addr, cause = %err, "replication link failed"),
+        }
+        time::sleep(RETRY_DELAY).await;
+    }
+}
+
+/// Load the snapshot of the primary at `addr` into `db`, then apply the write
+/// commands it forwards until the connection is closed.
+async fn sync(db: &Db, addr: &str) -> crate::Result<()> {
+    let socket = TcpStream::connect(addr).await?;
+    let mut connection = Connection::new(socket);
+
+    let frame = PSync::new("?", -1).into_frame();
+    debug!(request = ?frame);
+    connection.write_frame(&frame).await?;
+
+    match read_frame(&mut connection).await? {
+        Frame::Simple(reply) if reply.starts_with("FULLRESYNC ") => debug!(reply),
+        frame => return Err(frame.to_error()),
+    }
+    match read_frame(&mut connection).await? {
+        Frame::Bulk(snapshot) => db.load(&snapshot)?,
+        frame => return Err(frame.to_error()),
+    }
+    info!(addr, "synchronized with the primary");
+
+    // The replies are not sent anywhere.
+    let mut dst = Connection::discarding();
+    let mut db = db.clone();
+    while let Some(frame) = connection.read_frame().await? {
+        let args = request_args(&frame);
+        match Command::from_frame(frame)? {
+            Command::Select(cmd) => cmd.apply(&mut db, &mut dst).await?,
+            // Propagated in turn, to the append-only file and the replicas of the
+            // replica.
+            cmd if cmd.is_logged() => {
+                let _applying = db.applying().await;
+                cmd.apply(&db, &mut dst).await?;
+                db.propagate(&args);
+            }
+            cmd => cmd.apply(&db, &mut dst).await?,
+        }
+    }
+
+    Ok(())
+}
+
+async fn read_frame(connection: &mut Connection) -> crate::Result<Frame> {
+    match connection.read_frame().await? {
+        Some(frame) => Ok(frame),
+        None => Err(Error::new(ErrorKind::ConnectionReset, "connection reset by primary").into()),
+    }
+}
+
+/// A random replication id of 40 hexadecimal characters, like in Redis.
+fn new_replid() -> String {
+    // The hashers of `RandomState` are randomly seeded.
+    let state = RandomState::new();
+    let mut replid = String::new();
+    for i in 0..3u8 {
+        let _ = write!(replid, "{:016x}", state.hash_one(i));
+    }
+    replid.truncate(40);
+
+    replid
+}
@@ -2,15 +2,214 @@
 //! spawning a task per connection.
 //!
 
-use crate::{Command, Connection, Db, DbDropGuard, Shutdown};
-use std::future::Future;
+use crate::aof;
+pub use crate::aof::AppendFsync;
+use crate::cmd::{Transaction, Watched, format_command};
+use crate::connection::{BUF_SIZE, WriteBuffer};
+use crate::db::{DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY};
+use crate::frame;
+pub use crate::metrics::Metrics;
+use crate::metrics::{self, Metered};
+use crate::settings::DEFAULT_ACTIVE_EXPIRE_BATCH;
+use crate::tracking::{self, Invalidations};
+use crate::{
+    AccessLog, Aof, ClientHandle, ClientRegistry, Command, Connection, Db, DbDropGuard, Frame,
+    ParseError, Shutdown, TcpOptions,
+};
+use bytes::Bytes;
+use std::future::{self, Future};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+#[cfg(unix)]
+use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{Semaphore, broadcast, mpsc};
-use tokio::time::{self, Duration};
-use tracing::{debug, error, info, instrument};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{Semaphore, broadcast, mpsc, oneshot};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::{self, Duration, Instant};
+#[cfg(feature = "tls")]
+use tokio_rustls::TlsAcceptor;
+use tracing::{Instrument, debug, error, field, info, info_span, instrument, warn};
 
-const MAX_CONNECTIONS: usize = 500;
+/// The default maximum number of connections served at once.
+pub(crate) const DEFAULT_MAX_CONNECTIONS: usize = 500;
+
+/// The default time given to the connections to finish processing on shutdown,
+/// like in Redis.
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The time given to a client to complete the TLS handshake, after which its
+/// connection is closed and its slot freed.
+#[cfg(feature = "tls")]
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The longest pause between two attempts to accept a connection. Accepting gives up
+/// once the attempt following it fails too.
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(64);
+
+/// Server configuration, supplied to `run_with_config`.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The number of logical databases, selected with `SELECT`.
+    ///
+    /// Must be positive.
+    pub databases: usize,
+
+    /// The number of messages each pub/sub channel buffers for a subscriber.
+    /// Subscribers falling further behind skip the oldest messages. Adjustable at
+    /// runtime with `CONFIG SET pubsub-capacity`.
+    ///
+    /// Must be positive, and at most 1048576.
+    pub pubsub_capacity: usize,
+
+    /// The maximum number of connections served at once, reported by `CONFIG GET
+    /// maxclients`. Further connections wait for one to be closed before being
+    /// served.
+    ///
+    /// Must be positive.
+    pub max_connections: usize,
+
+    /// The number of tasks accepting connections concurrently, sharing the
+    /// listening sockets. More than one helps under heavy connection churn, a single
+    /// task setting up every connection otherwise.
+    ///
+    /// Must be positive.
+    pub acceptors: usize,
+
+    /// When set, connections must authenticate with `AUTH` and this password before
+    /// issuing any other command.
+    pub password: Option<String>,
+
+    /// The snapshot file written by `SAVE`. If it exists, the databases are loaded
+    /// from it before accepting connections, unless `aof_path` is set.
+    ///
+    /// Without it, `SAVE` writes `dump.rdb` in the working directory and nothing is
+    /// loaded.
+    pub dump_path: Option<PathBuf>,
+
+    /// When set, the write commands are appended to this file once applied. If it
+    /// exists, its commands are replayed before accepting connections.
+    pub aof_path: Option<PathBuf>,
+
+    /// When the append-only file is flushed to disk.
+    pub appendfsync: AppendFsync,
+
+    /// When set, each command processed is recorded to this file, with the time,
+    /// the id of the client, the command name and the key it applies to.
+    pub access_log: Option<PathBuf>,
+
+    /// The bounds on the frames read from clients. A client sending a larger frame
+    /// is disconnected.
+    pub frame_limits: frame::Limits,
+
+    /// The initial capacity of the buffer each connection reads frames into, 4 KiB
+    /// by default. A larger buffer suits clients sending large values, a smaller
+    /// one many connections sending small commands.
+    pub read_buffer_size: usize,
+
+    /// How long to wait for the connections to finish processing once the shutdown
+    /// signal is received. The server returns afterwards, even if some are still
+    /// active, such as in the middle of a long command.
+    pub shutdown_timeout: Duration,
+
+    /// The options of the TCP sockets of the connections. `TCP_NODELAY` is set and
+    /// TCP keepalive probes start after 300 seconds by default, like in Redis.
+    pub tcp: TcpOptions,
+
+    /// The maximum number of expired keys removed at once by the background task,
+    /// so that commands are not delayed for long when many keys expire together.
+    /// Adjustable at runtime with `CONFIG SET active-expire-batch`. Must be positive.
+    pub active_expire_batch: usize,
+
+    /// When set, connections sending no command for this long are closed, freeing
+    /// their slot. Adjustable at runtime with `CONFIG SET timeout`, in seconds.
+    ///
+    /// Like in Redis, subscribed and monitoring connections are never closed for
+    /// being idle, as they wait for messages rather than send commands.
+    pub idle_timeout: Option<Duration>,
+
+    /// When set, a command still running after this long is interrupted, replying
+    /// `ERR command exceeded time limit` rather than holding the connection.
+    /// Adjustable at runtime with `CONFIG SET command-time-limit`, in milliseconds.
+    ///
+    /// A command is only interrupted while it waits before replying, like `DEBUG
+    /// SLEEP`, not while it runs or writes its reply. Blocking commands like `WAIT`,
+    /// `EXEC` and subscriptions are not limited, nor are write commands, which are
+    /// always replied to once applied.
+    pub command_time_limit: Option<Duration>,
+
+    /// When set, connections are served over TLS with this configuration, see
+    /// `tls::server_config`.
+    #[cfg(feature = "tls")]
+    pub tls: Option<Arc<crate::tls::rustls::ServerConfig>>,
+
+    /// When set, connections are also accepted on a Unix domain socket at this path,
+    /// replacing any file there. The file is removed on shutdown.
+    #[cfg(unix)]
+    pub unixsocket: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            databases: DEFAULT_DATABASES,
+            pubsub_capacity: DEFAULT_PUBSUB_CAPACITY,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            acceptors: 1,
+            password: None,
+            dump_path: None,
+            aof_path: None,
+            appendfsync: AppendFsync::default(),
+            access_log: None,
+            frame_limits: frame::Limits::default(),
+            read_buffer_size: BUF_SIZE,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            tcp: TcpOptions {
+                nodelay: true,
+                keepalive: Some(Duration::from_secs(300)),
+            },
+            idle_timeout: None,
+            command_time_limit: None,
+            active_expire_batch: DEFAULT_ACTIVE_EXPIRE_BATCH,
+            #[cfg(feature = "tls")]
+            tls: None,
+            #[cfg(unix)]
+            unixsocket: None,
+        }
+    }
+}
+
+/// A server running in the process on an ephemeral port of the loopback interface,
+/// for tests to connect to. See `TestServer::start`.
+#[derive(Debug)]
+pub struct TestServer {
+    addr: SocketAddr,
+
+    /// Signals the shutdown, taken by `shutdown`.
+    shutdown: Option<oneshot::Sender<()>>,
+
+    /// Serves the connections until shut down.
+    task: JoinHandle<()>,
+}
+
+/// The sockets the server accepts connections on.
+#[derive(Debug, Default)]
+struct Sockets {
+    tcp: Option<TcpListener>,
+    #[cfg(unix)]
+    unix: Option<UnixListener>,
+}
+
+/// An accepted connection.
+enum Socket {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
 
 /// Server listener state. Created in the `run` call.
 #[derive(Debug)]
@@ -21,10 +220,32 @@ struct Listener {
     db_holder: DbDropGuard,
 
     /// supplied by the `run` caller.
-    tcp_listener: TcpListener,
+    sockets: Sockets,
 
     limit_connections: Arc<Semaphore>,
 
+    /// The password connections must authenticate with, if any.
+    password: Option<Arc<str>>,
+
+    /// The bounds on the frames read from connections.
+    frame_limits: frame::Limits,
+
+    /// The initial capacity of the read buffer of each connection.
+    read_buffer_size: usize,
+
+    /// The options set on the accepted TCP sockets.
+    tcp: TcpOptions,
+
+    /// The TLS configuration of the TCP connections, if served over TLS.
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<crate::tls::rustls::ServerConfig>>,
+
+    /// The id to assign to the next accepted connection.
+    next_client_id: AtomicU64,
+
+    /// The connections being served.
+    clients: Arc<ClientRegistry>,
+
     /// Broadcasts a shutdown signal to all active connections.
     ///
     /// When shutdown is initiated, a `()` value is sent via the broadcast::Sender.
@@ -40,56 +261,286 @@ struct Listener {
     shutdown_complete_tx: mpsc::Sender<()>,
 }
 
+/// Listen on `ip` and `port`, to be served by `run`. The error names the address,
+/// like when it is in use or not local to the machine.
+pub async fn bind(ip: IpAddr, port: u16) -> crate::Result<TcpListener> {
+    let addr = SocketAddr::new(ip, port);
+    TcpListener::bind(addr)
+        .await
+        .map_err(|err| format!("failed to listen on {addr}: {err}").into())
+}
+
+/// The counters of the connections, commands and keys served by the process, also
+/// reported by `INFO stats`.
+pub fn metrics() -> &'static Metrics {
+    metrics::get()
+}
+
 /// spawn a task to handle each inbound tcp connection. The server runs until the
 /// `shutdown` future completes,
 ///
 /// `tokio::signal::ctrl_c()` can be used as the `shutdown` argument.
 pub async fn run(listener: TcpListener, shutdown: impl Future) {
+    run_with_config(listener, shutdown, Config::default()).await;
+}
+
+/// Listen on `ip` and `port` and serve the connections with `config` from a
+/// background task, until `shutdown` completes. Returns the address listened on,
+/// which tells the port picked when `port` is `0`. Clients can connect to it as
+/// soon as it is returned.
+///
+/// # Panics
+///
+/// The background task panics like `run_with_config`.
+pub async fn spawn(
+    ip: IpAddr,
+    port: u16,
+    shutdown: impl Future + Send + 'static,
+    config: Config,
+) -> crate::Result<SocketAddr> {
+    let listener = bind(ip, port).await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(run_with_config(listener, shutdown, config));
+
+    Ok(addr)
+}
+
+impl TestServer {
+    /// Start a server with `config` on an ephemeral port of `127.0.0.1`, served from a
+    /// background task until `shutdown` is called or the `TestServer` is dropped.
+    ///
+    /// # Panics
+    ///
+    /// The background task panics like `run_with_config`.
+    pub async fn start(config: Config) -> crate::Result<TestServer> {
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        let listener = bind(IpAddr::from([127, 0, 0, 1]), 0).await?;
+        let addr = listener.local_addr()?;
+        let task = tokio::spawn(run_with_config(listener, shutdown_rx, config));
+
+        Ok(TestServer {
+            addr,
+            shutdown: Some(shutdown),
+            task,
+        })
+    }
+
+    /// The address the server listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Shut the server down, waiting for its connections to be closed.
+    pub async fn shutdown(mut self) -> crate::Result<()> {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        (&mut self.task).await?;
+
+        Ok(())
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        // Also stops the server if the test panics before shutting it down.
+        self.task.abort();
+    }
+}
+
+/// Like `run`, accepting the connections on a Unix domain socket instead.
+#[cfg(unix)]
+pub async fn run_unix(listener: UnixListener, shutdown: impl Future) {
+    let sockets = Sockets {
+        unix: Some(listener),
+        ..Sockets::default()
+    };
+    serve(sockets, shutdown, Config::default()).await;
+}
+
+/// Like `run`, with the given `config` instead of the default one.
+///
+/// # Panics
+///
+/// Panics if `config.databases`, `config.pubsub_capacity`,
+/// `config.max_connections`, `config.acceptors` or `config.active_expire_batch` is
+/// zero, if `config.pubsub_capacity` exceeds 1048576, if the
+/// snapshot at `config.dump_path` cannot be loaded, if the append-only file at
+/// `config.aof_path` cannot be replayed or opened, if the access log at
+/// `config.access_log` cannot be opened, or if the Unix domain socket at
+/// `config.unixsocket` cannot be bound.
+pub async fn run_with_config(listener: TcpListener, shutdown: impl Future, config: Config) {
+    let sockets = Sockets {
+        tcp: Some(listener),
+        #[cfg(unix)]
+        unix: config.unixsocket.as_deref().map(bind_unix),
+    };
+    #[cfg(unix)]
+    let unixsocket = config.unixsocket.clone();
+
+    serve(sockets, shutdown, config).await;
+
+    #[cfg(unix)]
+    if let Some(path) = unixsocket {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Listen on the Unix domain socket at `path`, replacing the file left behind if
+/// the server was not shut down.
+#[cfg(unix)]
+fn bind_unix(path: &Path) -> UnixListener {
+    let _ = std::fs::remove_file(path);
+    match UnixListener::bind(path) {
+        Ok(listener) => listener,
+        Err(err) => panic!("failed to listen on {}: {err}", path.display()),
+    }
+}
+
+async fn serve(sockets: Sockets, shutdown: impl Future, config: Config) {
+    info!(
+        databases = config.databases,
+        pubsub_capacity = config.pubsub_capacity,
+        max_connections = config.max_connections,
+        requires_password = config.password.is_some(),
+        dump_path = ?config.dump_path,
+        "server configured"
+    );
+
+    assert!(
+        config.max_connections > 0,
+        "the maximum number of connections must be positive"
+    );
+    let db_holder = DbDropGuard::new(config.databases, config.pubsub_capacity);
+    let db = db_holder.db();
+    db.settings().set_maxclients(config.max_connections);
+    db.settings().set_idle_timeout(config.idle_timeout);
+    db.settings()
+        .set_command_time_limit(config.command_time_limit);
+    db.settings()
+        .set_active_expire_batch(config.active_expire_batch);
+    if let Some(path) = &config.dump_path {
+        db.settings().set_dump_path(path);
+    }
+    match (&config.aof_path, &config.dump_path) {
+        // The append-only file is more recent than any snapshot.
+        (Some(path), _) if path.exists() => {
+            match aof::replay(path, &db, &config.frame_limits).await {
+                Ok(replayed) => info!(?path, replayed, "append-only file replayed"),
+                Err(err) => panic!(
+                    "failed to replay the append-only file {}: {err}",
+                    path.display()
+                ),
+            }
+        }
+        (None, Some(path)) if path.exists() => {
+            if let Err(err) = db.load_from(path) {
+                panic!("failed to load the snapshot {}: {err}", path.display());
+            }
+            info!(?path, "snapshot loaded");
+        }
+        _ => {}
+    }
+    // Enabled after the replay, which must not log the commands again.
+    if let Some(path) = &config.aof_path {
+        match Aof::open(path, config.appendfsync) {
+            Ok(aof) => db.enable_aof(aof),
+            Err(err) => panic!(
+                "failed to open the append-only file {}: {err}",
+                path.display()
+            ),
+        }
+    }
+    if let Some(path) = &config.access_log {
+        match AccessLog::open(path) {
+            Ok(access_log) => db.enable_access_log(access_log),
+            Err(err) => panic!("failed to open the access log {}: {err}", path.display()),
+        }
+    }
+
     // When the provided `shutdown` future completes, we must send a shutdown
     // message to all active connections.
     let (shutdown_sender, _) = broadcast::channel(1);
     let (shutdown_complete_tx, mut shutdown_complete_rx) = mpsc::channel(1);
 
-    let mut server = Listener {
-        tcp_listener: listener,
-        db_holder: DbDropGuard::new(),
-        limit_connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
+    assert!(
+        config.acceptors > 0,
+        "the number of acceptors must be positive"
+    );
+    let server = Arc::new(Listener {
+        sockets,
+        db_holder,
+        limit_connections: Arc::new(Semaphore::new(config.max_connections)),
+        password: config.password.map(Arc::from),
+        frame_limits: config.frame_limits,
+        read_buffer_size: config.read_buffer_size,
+        tcp: config.tcp,
+        #[cfg(feature = "tls")]
+        tls: config.tls,
+        next_client_id: AtomicU64::new(1),
+        clients: Arc::default(),
         shutdown_sender,
         shutdown_complete_tx,
-    };
+    });
 
-    // Concurrently run the server and listen for the `shutdown` signal.
+    info!(
+        acceptors = config.acceptors,
+        "accepting inbound connections"
+    );
+    let mut acceptors = JoinSet::new();
+    for _ in 0..config.acceptors {
+        let server = server.clone();
+        acceptors.spawn(async move { server.run().await });
+    }
+
+    // Concurrently run the server and listen for the `shutdown` signal. The
+    // server stops accepting connections as soon as one of the acceptors fails.
     tokio::select! {
-        res = server.run() => {
-            if let Err(err) = res {
-                error!(cause = %err, "failed to accept");
+        Some(res) = acceptors.join_next() => {
+            match res {
+                Ok(Err(err)) => error!(cause = %err, "failed to accept"),
+                Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+                _ => {}
             }
         }
         _ = shutdown => {
             info!("The shutdown signal has been received");
         }
     }
+    // Waits for the acceptors to be aborted, releasing the server.
+    acceptors.shutdown().await;
 
-    let Listener {
+    let Some(Listener {
         shutdown_complete_tx,
         shutdown_sender,
+        clients,
         ..
-    } = server;
+    }) = Arc::into_inner(server)
+    else {
+        unreachable!("the acceptors are done");
+    };
     drop(shutdown_sender);
     // Drop final `Sender` so the `Receiver` below can complete
     drop(shutdown_complete_tx);
 
-    // Wait for all active connections to finish processing.
-    let _ = shutdown_complete_rx.recv().await;
+    // Wait for all active connections to finish processing, up to the timeout.
+    let drained = time::timeout(config.shutdown_timeout, shutdown_complete_rx.recv()).await;
+    if drained.is_err() {
+        warn!(
+            active = clients.len(),
+            timeout = ?config.shutdown_timeout,
+            "connections still active after the shutdown timeout"
+        );
+    }
 }
 
 impl Listener {
-    /// performs the TCP listening and initialization of per-connection state.
+    /// performs the listening and initialization of per-connection state.
     ///
     /// For each inbound connection, spawn a task to process that connection.
-    async fn run(&mut self) -> crate::Result<()> {
-        info!("accepting inbound connections");
-
+    ///
+    /// Several acceptors may run concurrently, sharing the listener.
+    async fn run(&self) -> crate::Result<()> {
         loop {
             // Wait for a permit to become available
             let permit = self
@@ -99,44 +550,146 @@ impl Listener {
                 .await
                 .unwrap();
 
-            let socket = self.accept().await?;
+            let (socket, addr) = self.accept().await?;
+            if let Socket::Tcp(socket) = &socket {
+                // The connection is still served with the default options.
+                if let Err(err) = self.tcp.apply(socket) {
+                    warn!(cause = %err, addr, "failed to set the TCP options");
+                }
+            }
 
             let db = self.db_holder.db();
-            let shutdown = Shutdown::new(self.shutdown_sender.subscribe());
+            let password = self.password.clone();
+            let frame_limits = self.frame_limits;
+            let capacity = self.read_buffer_size;
+            let id = self.next_client_id.fetch_add(1, Ordering::Relaxed);
+            let (client, kill) = self.clients.register(id, addr.clone());
+            let shutdown = Shutdown::new(self.shutdown_sender.subscribe(), kill);
+            let shutdown_complete = self.shutdown_complete_tx.clone();
+            #[cfg(feature = "tls")]
+            let tls = self.tls.clone();
             // Spawn a new task to process the connections.
             tokio::spawn(async move {
-                if let Err(err) = process(Connection::new(socket), db, shutdown).await {
+                #[cfg(feature = "tls")]
+                let mut shutdown = shutdown;
+                let connected = metrics::get().connect();
+                let mut connection = match socket {
+                    // The handshake is performed by the task, not to delay
+                    // accepting other connections.
+                    #[cfg(feature = "tls")]
+                    Socket::Tcp(socket) => match tls {
+                        Some(config) => {
+                            // A client stalling the handshake must not hold its
+                            // slot forever, nor delay the shutdown.
+                            let accept = TlsAcceptor::from(config).accept(socket);
+                            let accepted = tokio::select! {
+                                res = time::timeout(TLS_HANDSHAKE_TIMEOUT, accept) => res,
+                                _ = shutdown.recv() => return,
+                            };
+                            match accepted {
+                                Ok(Ok(stream)) => {
+                                    Connection::with_capacity(Metered::new(stream), capacity)
+                                }
+                                Ok(Err(err)) => {
+                                    debug!(cause = %err, addr, "TLS handshake failed");
+                                    return;
+                                }
+                                Err(_) => {
+                                    debug!(addr, "TLS handshake timed out");
+                                    return;
+                                }
+                            }
+                        }
+                        None => Connection::with_capacity(Metered::new(socket), capacity),
+                    },
+                    #[cfg(not(feature = "tls"))]
+                    Socket::Tcp(socket) => {
+                        Connection::with_capacity(Metered::new(socket), capacity)
+                    }
+                    #[cfg(unix)]
+                    Socket::Unix(socket) => {
+                        Connection::with_capacity(Metered::new(socket), capacity)
+                    }
+                };
+                connection.set_limits(frame_limits);
+                if let Err(err) = process(connection, db, shutdown, password, client).await {
                     error!(cause = ?err, "connection error");
                 }
 
+                drop(connected);
                 // returns the permit back to the semaphore.
                 drop(permit);
+                // Lets the shutdown complete once every connection is processed.
+                drop(shutdown_complete);
             });
         }
     }
 
     /// Accept an inbound connection.
     ///
-    /// Errors are handled by backing off and retrying. An exponential backoff
-    /// strategy is used. If accepting fails on the 6th try after
-    /// waiting for 64 seconds, then this function returns with an error.
-    async fn accept(&mut self) -> crate::Result<TcpStream> {
-        let mut backoff = 1;
+    /// Errors are handled by backing off and retrying, see `with_backoff`.
+    async fn accept(&self) -> crate::Result<(Socket, String)> {
+        let sockets = &self.sockets;
 
-        loop {
-            match self.tcp_listener.accept().await {
-                Ok((socket, _)) => return Ok(socket),
-                Err(err) => {
-                    if backoff > 64 {
-                        return Err(err.into());
-                    }
-                }
-            }
+        Ok(with_backoff(|| sockets.accept()).await?)
+    }
+}
+
+/// Call `attempt` until it succeeds, with an exponential backoff.
+///
+/// After each failure, execution pauses for 1 second, then twice as long as the
+/// previous pause, up to `MAX_ACCEPT_BACKOFF`. If the attempt following the pause
+/// of 64 seconds fails too, the 8th, its error is returned. Every call starts over
+/// from a pause of 1 second.
+async fn with_backoff<T, F>(mut attempt: impl FnMut() -> F) -> io::Result<T>
+where
+    F: Future<Output = io::Result<T>>,
+{
+    let mut backoff = Duration::from_secs(1);
+
+    loop {
+        match attempt().await {
+            Ok(res) => return Ok(res),
+            Err(err) if backoff > MAX_ACCEPT_BACKOFF => return Err(err),
+            Err(err) => warn!(cause = %err, ?backoff, "failed to accept, retrying"),
+        }
+
+        // Pause execution
+        time::sleep(backoff).await;
+
+        backoff *= 2;
+    }
+}
 
-            // Pause execution
-            time::sleep(Duration::from_secs(backoff)).await;
+impl Sockets {
+    /// Accept a connection on any of the sockets, returning it with the address of
+    /// the client.
+    async fn accept(&self) -> io::Result<(Socket, String)> {
+        let tcp = async {
+            let Some(listener) = &self.tcp else {
+                return future::pending().await;
+            };
+            let (socket, addr) = listener.accept().await?;
+            Ok((Socket::Tcp(socket), addr.to_string()))
+        };
+        #[cfg(unix)]
+        let unix = async {
+            let Some(listener) = &self.unix else {
+                return future::pending().await;
+            };
+            let (socket, _) = listener.accept().await?;
+            // Like in Redis, the clients of a Unix domain socket are described by
+            // its path, having no address of their own.
+            let path = listener.local_addr()?;
+            let path = path.as_pathname().unwrap_or(Path::new(""));
+            Ok((Socket::Unix(socket), format!("{}:0", path.display())))
+        };
+        #[cfg(not(unix))]
+        let unix = future::pending();
 
-            backoff *= 2;
+        tokio::select! {
+            res = tcp => res,
+            res = unix => res,
         }
     }
 }
@@ -145,16 +698,56 @@ impl Listener {
 ///
 /// When the shutdown signal is received, the connection is processed until
 /// it reaches a safe state, at which point it is terminated.
-#[instrument]
-async fn process(mut connection: Connection, db: Db, mut shutdown: Shutdown) -> crate::Result<()> {
+///
+/// When `password` is set, only `AUTH`, `HELLO`, `QUIT` and `RESET` are accepted
+/// until the connection authenticates. `client` is the entry of the connection in the client registry,
+/// holding the address of the client.
+#[instrument(skip(password, client), fields(id = client.id(), addr = client.addr()))]
+async fn process(
+    mut connection: Connection,
+    mut db: Db,
+    mut shutdown: Shutdown,
+    password: Option<Arc<str>>,
+    client: ClientHandle,
+) -> crate::Result<()> {
+    let mut authenticated = password.is_none();
+    // `Some` while the client is in a transaction, from `MULTI` until `EXEC` or `DISCARD`.
+    let mut transaction: Option<Transaction> = None;
+    // The keys watched for the next `EXEC`.
+    let mut watched = Watched::default();
+    // `Some` while the client has tracking enabled, see `CLIENT TRACKING`.
+    let mut invalidations: Option<Invalidations> = None;
+
     // As long as the shutdown signal has not been received, try to read a new request frame.
     while !shutdown.is_shutdown() {
-        // While reading a request frame, also listen for the shutdown signal.
+        // While reading a request frame, also listen for the shutdown signal and
+        // give up on the connection once it is idle for too long. The keys it
+        // tracks are invalidated in between commands.
+        let idle_timeout = db.settings().idle_timeout();
         let maybe_frame = tokio::select! {
-            res = connection.read_frame() => res?,
-            _ = shutdown.recv() => {
+            res = connection.read_frame() => match res {
+                Ok(maybe_frame) => maybe_frame,
+                // The client went away in the middle of a request, which is no
+                // failure of the server.
+                Err(err) if is_reset(&err) => {
+                    debug!(cause = %err, "connection reset by the client");
+                    return Ok(());
+                }
+                Err(err) if err.is::<frame::Error>() => {
+                    warn!(cause = %err, "closing the connection on malformed input");
+                    break;
+                }
+                Err(err) => return Err(err),
+            },
+            Some(key) = invalidated(&mut invalidations) => {
+                connection.write_frame(&tracking::message(key)).await?;
+                continue;
+            }
+            () = idle(idle_timeout) => {
+                debug!(timeout = ?idle_timeout, "closing idle connection");
                 return Ok(());
             }
+            _ = shutdown.recv() => break,
         };
 
         // If `None` is returned from `read_frame()` then the peer closed
@@ -164,11 +757,521 @@ async fn process(mut connection: Connection, db: Db, mut shutdown: Shutdown) ->
             None => return Ok(()),
         };
 
-        let cmd = Command::from_frame(frame)?;
+        let args = request_args(&frame);
+        let cmd = match (Command::from_frame(frame), &mut transaction) {
+            (Ok(cmd), _) => cmd,
+            // A malformed command aborts the transaction instead of the connection,
+            // and so does a command given invalid arguments outside one.
+            (Err(err), transaction)
+                if transaction.is_some()
+                    || matches!(
+                        err.downcast_ref(),
+                        Some(ParseError::WrongArity(_) | ParseError::Reply(_))
+                    ) =>
+            {
+                if let Some(transaction) = transaction {
+                    transaction.abort();
+                }
+                let msg = match err.downcast_ref() {
+                    Some(ParseError::Reply(msg)) => msg.clone(),
+                    _ => format!("ERR {err}"),
+                };
+                connection.write_frame_no_flush(&Frame::Error(msg)).await?;
+                continue;
+            }
+            (Err(err), _) => {
+                connection.flush().await?;
+                return Err(err);
+            }
+        };
         debug!(?cmd);
+        client.set_last_command(cmd.get_name());
+        // Unknown commands are counted together, as their names are up to the client.
+        match &cmd {
+            Command::Unknown(_) => metrics::get().command(metrics::UNKNOWN_COMMAND),
+            cmd => metrics::get().command(cmd.get_name()),
+        }
+
+        let cmd = match cmd {
+            Command::Auth(cmd) => {
+                cmd.apply(password.as_deref(), &mut authenticated, &mut connection)
+                    .await?;
+                continue;
+            }
+            // The connection is closed once the reply is written, even before
+            // authentication or in the middle of a transaction.
+            Command::Quit(cmd) => {
+                cmd.apply(&mut connection).await?;
+                return Ok(());
+            }
+            Command::Hello(cmd) => {
+                cmd.apply(
+                    password.as_deref(),
+                    &mut authenticated,
+                    client.id(),
+                    &mut connection,
+                )
+                .await?;
+                continue;
+            }
+            // Like in Redis, `RESET` does not require authentication.
+            cmd @ Command::Reset(_) => cmd,
+            _ if !authenticated => {
+                let resp_frame = Frame::Error("NOAUTH Authentication required.".to_string());
+                connection.write_frame_no_flush(&resp_frame).await?;
+                continue;
+            }
+            cmd => cmd,
+        };
+
+        // Wait for `CLIENT PAUSE` to elapse, still listening for the shutdown signal.
+        if let Some(until) = client.registry().paused_until(cmd.is_write()) {
+            connection.flush().await?;
+            tokio::select! {
+                () = time::sleep_until(until) => {}
+                _ = shutdown.recv() => return Ok(()),
+            }
+        }
+
+        // Like in Redis, a command refused while queued aborts the transaction.
+        if cmd.may_use_memory() && db.is_out_of_memory() {
+            if let Some(transaction) = &mut transaction {
+                transaction.abort();
+            }
+            let msg = "OOM command not allowed when used memory > 'maxmemory'.";
+            connection
+                .write_frame_no_flush(&Frame::Error(msg.to_string()))
+                .await?;
+            continue;
+        }
+        // The keys of a replica are only written by its primary.
+        if cmd.is_logged() && db.replication().is_replica() {
+            if let Some(transaction) = &mut transaction {
+                transaction.abort();
+            }
+            let msg = "READONLY You can't write against a read only replica.";
+            connection
+                .write_frame_no_flush(&Frame::Error(msg.to_string()))
+                .await?;
+            continue;
+        }
 
-        cmd.apply(&db, &mut connection, &mut shutdown).await?;
+        db.feed_monitors(|| format_command(db.index(), &client.addr(), &args));
+
+        // Subscribing enters the subscribed state, which may end with a `RESET`.
+        // Monitoring and replicating last until the connection is closed.
+        let cmd = match (cmd, &transaction) {
+            (Command::Monitor(cmd), None) => {
+                return cmd.apply(&db, &mut connection, &mut shutdown).await;
+            }
+            (Command::PSync(cmd), None) => {
+                return cmd.apply(&db, &mut connection, &mut shutdown).await;
+            }
+            (Command::Subscribe(cmd), None) => {
+                let reset = cmd.apply(&db, &mut connection, &mut shutdown).await?;
+                reset.map(Command::Reset)
+            }
+            (Command::PSubscribe(cmd), None) => {
+                let reset = cmd.apply(&db, &mut connection, &mut shutdown).await?;
+                reset.map(Command::Reset)
+            }
+            (Command::SSubscribe(cmd), None) => {
+                let reset = cmd.apply(&db, &mut connection, &mut shutdown).await?;
+                reset.map(Command::Reset)
+            }
+            (cmd, _) => Some(cmd),
+        };
+        let Some(cmd) = cmd else {
+            continue;
+        };
+
+        // Commands queued in a transaction are propagated by `EXEC`.
+        let logged = transaction.is_none() && cmd.is_logged();
+        db.log_access(client.id(), cmd.get_name(), cmd.key());
+        // Tracked before it is read, so that no modification is missed.
+        if let (Some(_), false, Some(key)) = (&invalidations, cmd.is_write(), cmd.key()) {
+            db.track(client.id(), key);
+        }
+        // The replies to pipelined requests are flushed together, once no request
+        // is left buffered, see `Connection::read_frame`. Those written so far are
+        // flushed before blocking.
+        if cmd.is_blocking() {
+            connection.flush().await?;
+        }
+        connection.set_flush_deferred(connection.has_buffered_input());
+        // Each command is traced in its own span, so that slow ones can be told
+        // apart from the rest of the connection.
+        let span = info_span!(
+            "command",
+            name = cmd.get_name(),
+            key = cmd.key(),
+            elapsed = field::Empty,
+        );
+        // Blocking commands wait as long as they were asked to, and transactions and
+        // write commands are applied as a whole.
+        let time_limit = db.settings().command_time_limit();
+        let time_limit = time_limit
+            .filter(|_| !cmd.is_blocking() && !logged && !matches!(cmd, Command::Exec(_)));
+        let frames_written = connection.frames_written();
+        let written_before = frames_written.load(Ordering::Relaxed);
+        let start = Instant::now();
+        let applied = async {
+            match (cmd, &mut transaction) {
+                (Command::Reset(cmd), _) => {
+                    // The connection has to authenticate again, if the server requires it,
+                    // and no longer tracks keys.
+                    authenticated = password.is_none();
+                    invalidations = None;
+                    cmd.apply(
+                        &mut db,
+                        &client,
+                        &mut transaction,
+                        &mut watched,
+                        &mut connection,
+                    )
+                    .await?;
+                }
+                // Inside a transaction, `CLIENT` and `INFO` are refused by `Transaction::queue`.
+                (Command::Client(cmd), None) => {
+                    cmd.apply(&client, &db, &mut invalidations, &mut connection)
+                        .await?;
+                }
+                (Command::Info(cmd), None) => {
+                    cmd.apply(&db, client.registry(), &mut connection).await?;
+                }
+                (Command::Multi(cmd), _) => cmd.apply(&mut transaction, &mut connection).await?,
+                (Command::Discard(cmd), _) => {
+                    cmd.apply(&mut transaction, &mut watched, &mut connection)
+                        .await?;
+                }
+                (Command::Exec(cmd), _) => {
+                    cmd.apply(&mut db, &mut connection, &mut transaction, &mut watched)
+                        .await?;
+                }
+                (Command::Watch(cmd), transaction) => {
+                    cmd.apply(&db, transaction.is_some(), &mut watched, &mut connection)
+                        .await?;
+                }
+                // Inside a transaction, `UNWATCH` is queued like other commands.
+                (Command::Unwatch(cmd), None) => cmd.apply(&mut watched, &mut connection).await?,
+                (Command::Select(cmd), None) => cmd.apply(&mut db, &mut connection).await?,
+                (cmd, Some(transaction)) => {
+                    transaction
+                        .queue(cmd, args.clone(), &mut connection)
+                        .await?;
+                }
+                (cmd, None) if logged => apply_logged(cmd, &args, &db, &mut connection).await?,
+                (cmd, None) => cmd.apply(&db, &mut connection).await?,
+            }
+
+            Ok::<_, crate::Error>(())
+        }
+        .instrument(span.clone());
+        match time_limit {
+            Some(limit) => {
+                let mut applied = Box::pin(applied);
+                let res = tokio::select! {
+                    res = &mut applied => Some(res),
+                    () = time::sleep(limit) => None,
+                };
+                match res {
+                    Some(res) => res?,
+                    // Once the command started replying, it is left to complete the
+                    // reply rather than send a partial one.
+                    None if frames_written.load(Ordering::Relaxed) != written_before => {
+                        applied.await?;
+                    }
+                    None => {
+                        drop(applied);
+                        warn!(?limit, "command exceeded time limit");
+                        let resp_frame =
+                            Frame::Error("ERR command exceeded time limit".to_string());
+                        connection.write_frame(&resp_frame).await?;
+                    }
+                }
+            }
+            None => applied.await?,
+        }
+        let duration = start.elapsed();
+        span.record("elapsed", field::debug(duration));
+        connection.set_flush_deferred(false);
+        log_if_slow(&db, &client, duration, args);
     }
 
+    // The replies left in the write buffer, when closing the connection.
+    connection.flush().await?;
+
     Ok(())
 }
+
+/// Apply the write command `cmd`, sent as `args`, and propagate it before writing
+/// its reply to `dst`, like Redis: a command applied is logged even if the client
+/// goes away meanwhile, and is logged by the time it is acknowledged.
+///
+/// The guard of `Db::applying` is held until the command is propagated. The reply
+/// is buffered meanwhile and written once the guard is dropped, so that a peer not
+/// reading its replies does not hold up the other connections, like in `EXEC`.
+async fn apply_logged(
+    cmd: Command,
+    args: &[Bytes],
+    db: &Db,
+    dst: &mut Connection,
+) -> crate::Result<()> {
+    let buffer = WriteBuffer::default();
+    let mut reply = Connection::buffering(buffer.clone());
+    reply.set_protocol(dst.protocol());
+    let applying = db.applying().await;
+    cmd.apply(db, &mut reply).await?;
+    reply.flush().await?;
+    db.propagate(args);
+    drop(applying);
+    db.synced_if_always().await;
+
+    dst.write_encoded(&buffer.take()).await?;
+
+    Ok(())
+}
+
+/// Returns `true` if `err`, as returned by `Connection::read_frame`, is the
+/// connection being closed in the middle of a frame.
+fn is_reset(err: &crate::Error) -> bool {
+    err.downcast_ref::<io::Error>()
+        .is_some_and(|err| err.kind() == io::ErrorKind::ConnectionReset)
+}
+
+/// Completes once `timeout` elapses, never if it is `None`.
+async fn idle(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => time::sleep(timeout).await,
+        None => future::pending().await,
+    }
+}
+
+/// Receives the next key invalidated for the connection, never if it has tracking
+/// disabled.
+async fn invalidated(invalidations: &mut Option<Invalidations>) -> Option<String> {
+    match invalidations {
+        Some(invalidations) => invalidations.recv().await,
+        None => future::pending().await,
+    }
+}
+
+/// The command name and arguments of a request frame.
+pub(crate) fn request_args(frame: &Frame) -> Vec<Bytes> {
+    match frame {
+        Frame::Array(frames) => frames
+            .iter()
+            .map(|frame| match frame {
+                Frame::Bulk(bytes) => bytes.clone(),
+                other => Bytes::from(other.to_string()),
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Record the command `args` issued by `client` in the slow log, if it took longer
+/// than `slowlog-log-slower-than`.
+fn log_if_slow(db: &Db, client: &ClientHandle, duration: Duration, args: Vec<Bytes>) {
+    let settings = db.settings();
+    let (threshold, max_len) = (settings.slowlog_threshold(), settings.slowlog_max_len());
+    drop(settings);
+
+    if threshold.is_some_and(|threshold| duration >= threshold) {
+        let addr = client.addr();
+        let name = client.name().unwrap_or_default();
+        db.slowlog().push(duration, args, addr, name, max_len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::fmt::{self, Write};
+    use std::pin::Pin;
+    use std::sync::Mutex;
+    use std::sync::atomic::AtomicUsize;
+    use std::task::{Context, Poll};
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf};
+    use tracing::Subscriber;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing_subscriber::layer::{self, Layer, SubscriberExt};
+
+    /// A stream counting how many times it is flushed.
+    #[derive(Debug)]
+    struct CountFlushes {
+        stream: DuplexStream,
+        flushes: Arc<AtomicUsize>,
+    }
+
+    impl AsyncRead for CountFlushes {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.stream).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for CountFlushes {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.stream).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.flushes.fetch_add(1, Ordering::Relaxed);
+            Pin::new(&mut self.stream).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.stream).poll_shutdown(cx)
+        }
+    }
+
+    /// The values of the fields of a span, by name.
+    type SpanFields = BTreeMap<&'static str, String>;
+
+    /// Records the fields of the spans named `command`, in the order they are
+    /// created.
+    #[derive(Debug, Clone, Default)]
+    struct CommandSpans(Arc<Mutex<Vec<(Id, SpanFields)>>>);
+
+    /// Visits the fields of a span, recording their values.
+    struct Fields<'a>(&'a mut SpanFields);
+
+    impl Visit for Fields<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.insert(field.name(), format!("{value:?}"));
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for CommandSpans {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, _: layer::Context<'_, S>) {
+            if attrs.metadata().name() == "command" {
+                let mut fields = BTreeMap::new();
+                attrs.record(&mut Fields(&mut fields));
+                self.0.lock().unwrap().push((id.clone(), fields));
+            }
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, _: layer::Context<'_, S>) {
+            // Ids are reused once spans are closed, the last span is the live one.
+            let mut spans = self.0.lock().unwrap();
+            if let Some((_, fields)) = spans.iter_mut().rev().find(|(span, _)| span == id) {
+                values.record(&mut Fields(fields));
+            }
+        }
+    }
+
+    /// Process the requests read from `connection` until it is closed, as a client
+    /// of a new server.
+    async fn process_until_closed(connection: Connection) {
+        let db = DbDropGuard::new(DEFAULT_DATABASES, DEFAULT_PUBSUB_CAPACITY);
+        let (_notify, notify_rx) = broadcast::channel(1);
+        let (client, kill) = Arc::new(ClientRegistry::default()).register(1, String::new());
+        let shutdown = Shutdown::new(notify_rx, kill);
+
+        process(connection, db.db(), shutdown, None, client)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn pipelined_replies_are_flushed_together() {
+        let (mut peer, stream) = tokio::io::duplex(64 * 1024);
+        let flushes = Arc::new(AtomicUsize::new(0));
+        let connection = Connection::from_stream(CountFlushes {
+            stream,
+            flushes: flushes.clone(),
+        });
+
+        // The requests are all buffered before the first one is applied.
+        let mut requests = String::new();
+        let mut replies = String::new();
+        for i in 0..10 {
+            write!(requests, "SET key{i} value{i}\r\nGET key{i}\r\n").unwrap();
+            write!(replies, "+OK\r\n$6\r\nvalue{i}\r\n").unwrap();
+        }
+        peer.write_all(requests.as_bytes()).await.unwrap();
+        peer.shutdown().await.unwrap();
+        process_until_closed(connection).await;
+
+        let mut written = String::new();
+        peer.read_to_string(&mut written).await.unwrap();
+        assert_eq!(written, replies);
+        assert_eq!(flushes.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn accept_retries_until_success() {
+        let start = Instant::now();
+        let mut attempts = 0;
+
+        let res = with_backoff(|| {
+            attempts += 1;
+            let res = if attempts < 4 {
+                Err(io::Error::other("too many open files"))
+            } else {
+                Ok(attempts)
+            };
+            async move { res }
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), 4);
+        // Paused for 1, 2 then 4 seconds.
+        assert_eq!(start.elapsed(), Duration::from_secs(7));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn accept_gives_up_after_longest_backoff() {
+        let start = Instant::now();
+        let mut attempts = 0;
+
+        let res: io::Result<()> = with_backoff(|| {
+            attempts += 1;
+            async { Err(io::Error::other("too many open files")) }
+        })
+        .await;
+
+        assert!(res.is_err());
+        // Paused for 1, 2, 4, 8, 16, 32 then 64 seconds.
+        assert_eq!(attempts, 8);
+        assert_eq!(start.elapsed(), Duration::from_secs(127));
+    }
+
+    #[tokio::test]
+    async fn commands_are_traced_in_their_own_span() {
+        let spans = CommandSpans::default();
+        let subscriber = tracing_subscriber::registry().with(spans.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let (mut peer, stream) = tokio::io::duplex(1024);
+        peer.write_all(b"SET hello world\r\nPING\r\n")
+            .await
+            .unwrap();
+        peer.shutdown().await.unwrap();
+        process_until_closed(Connection::from_stream(stream)).await;
+
+        let spans = spans.0.lock().unwrap();
+        let spans: Vec<_> = spans.iter().map(|(_, fields)| fields).collect();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0]["name"], "set");
+        assert_eq!(spans[0]["key"], "hello");
+        assert!(spans[0].contains_key("elapsed"));
+        assert_eq!(spans[1]["name"], "ping");
+        assert!(!spans[1].contains_key("key"));
+        assert!(spans[1].contains_key("elapsed"));
+    }
+}
@@ -0,0 +1,459 @@
+//!
+//! The server parameters, read with `CONFIG GET` and changed with `CONFIG SET`.
+//!
+
+use crate::db::MAX_PUBSUB_CAPACITY;
+use crate::glob::glob_match;
+use crate::server::DEFAULT_MAX_CONNECTIONS;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The default maximum number of expired keys removed at once.
+pub(crate) const DEFAULT_ACTIVE_EXPIRE_BATCH: usize = 1000;
+
+/// The parameters of the server, shared by all connections.
+#[derive(Debug)]
+pub(crate) struct Settings {
+    /// The number of logical databases, fixed when the server starts.
+    databases: usize,
+
+    /// The maximum number of connections served at once, fixed when the server
+    /// starts.
+    maxclients: usize,
+
+    /// The memory limit in bytes, `0` for no limit.
+    maxmemory: u64,
+
+    /// What happens when a write exceeds `maxmemory`.
+    maxmemory_policy: MaxmemoryPolicy,
+
+    /// The keyspace notifications published, none by default.
+    notify_keyspace_events: KeyspaceEvents,
+
+    /// Connections sending no command for this long are closed, reported in
+    /// seconds as `timeout`.
+    idle_timeout: Option<Duration>,
+
+    /// Commands still running after this long are interrupted, reported in
+    /// milliseconds as `command-time-limit`.
+    command_time_limit: Option<Duration>,
+
+    /// The maximum number of expired keys removed at once by the background task,
+    /// which lets the connections run in between.
+    active_expire_batch: usize,
+
+    /// The capacity of the pub/sub broadcast channels created from now on. A
+    /// subscriber falling further behind than this skips the oldest messages.
+    pubsub_capacity: usize,
+
+    /// When to snapshot the keyspace, as pairs of seconds and number of changes.
+    save: String,
+
+    /// The directory of the snapshot file.
+    dir: PathBuf,
+
+    /// The name of the snapshot file, see `SAVE`.
+    dbfilename: String,
+
+    /// Commands taking at least this many microseconds are recorded in the slow
+    /// log. A negative value disables the slow log.
+    slowlog_log_slower_than: i64,
+
+    /// The maximum number of entries of the slow log.
+    slowlog_max_len: usize,
+}
+
+impl Settings {
+    pub(crate) fn new(databases: usize, pubsub_capacity: usize) -> Settings {
+        Settings {
+            databases,
+            maxclients: DEFAULT_MAX_CONNECTIONS,
+            maxmemory: 0,
+            maxmemory_policy: MaxmemoryPolicy::default(),
+            notify_keyspace_events: KeyspaceEvents::default(),
+            idle_timeout: None,
+            command_time_limit: None,
+            active_expire_batch: DEFAULT_ACTIVE_EXPIRE_BATCH,
+            pubsub_capacity,
+            save: "3600 1 300 100 60 10000".to_string(),
+            dir: PathBuf::from("."),
+            dbfilename: "dump.rdb".to_string(),
+            slowlog_log_slower_than: 10_000,
+            slowlog_max_len: 128,
+        }
+    }
+
+    pub(crate) fn set_maxclients(&mut self, maxclients: usize) {
+        self.maxclients = maxclients;
+    }
+
+    pub(crate) fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    pub(crate) fn set_idle_timeout(&mut self, idle_timeout: Option<Duration>) {
+        self.idle_timeout = idle_timeout.filter(|timeout| !timeout.is_zero());
+    }
+
+    pub(crate) fn command_time_limit(&self) -> Option<Duration> {
+        self.command_time_limit
+    }
+
+    pub(crate) fn set_command_time_limit(&mut self, limit: Option<Duration>) {
+        self.command_time_limit = limit.filter(|limit| !limit.is_zero());
+    }
+
+    pub(crate) fn active_expire_batch(&self) -> usize {
+        self.active_expire_batch
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `batch` is zero.
+    pub(crate) fn set_active_expire_batch(&mut self, batch: usize) {
+        assert!(batch > 0, "the active expire batch must be positive");
+        self.active_expire_batch = batch;
+    }
+
+    /// The memory limit in bytes, `None` if there is none, and the policy applied
+    /// when it is exceeded.
+    pub(crate) fn maxmemory(&self) -> (Option<usize>, MaxmemoryPolicy) {
+        let maxmemory =
+            (self.maxmemory > 0).then(|| usize::try_from(self.maxmemory).unwrap_or(usize::MAX));
+        (maxmemory, self.maxmemory_policy)
+    }
+
+    pub(crate) fn notify_keyspace_events(&self) -> KeyspaceEvents {
+        self.notify_keyspace_events
+    }
+
+    pub(crate) fn pubsub_capacity(&self) -> usize {
+        self.pubsub_capacity
+    }
+
+    /// The path of the snapshot file.
+    pub(crate) fn dump_path(&self) -> PathBuf {
+        self.dir.join(&self.dbfilename)
+    }
+
+    /// Set `dir` and `dbfilename` so that the snapshot file is `path`.
+    pub(crate) fn set_dump_path(&mut self, path: &Path) {
+        if let Some(dbfilename) = path.file_name() {
+            self.dbfilename = dbfilename.to_string_lossy().into_owned();
+        }
+        self.dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+    }
+
+    /// The duration from which commands are recorded in the slow log, or `None` if
+    /// the slow log is disabled.
+    pub(crate) fn slowlog_threshold(&self) -> Option<Duration> {
+        u64::try_from(self.slowlog_log_slower_than)
+            .ok()
+            .map(Duration::from_micros)
+    }
+
+    pub(crate) fn slowlog_max_len(&self) -> usize {
+        self.slowlog_max_len
+    }
+
+    /// Returns the name and value of the parameters whose name matches the glob
+    /// `pattern`, sorted by name.
+    pub(crate) fn get(&self, pattern: &str) -> Vec<(&'static str, String)> {
+        let params = [
+            ("active-expire-batch", self.active_expire_batch.to_string()),
+            (
+                "command-time-limit",
+                self.command_time_limit
+                    .map_or(0, |limit| limit.as_millis())
+                    .to_string(),
+            ),
+            ("databases", self.databases.to_string()),
+            ("dbfilename", self.dbfilename.clone()),
+            ("dir", self.dir.display().to_string()),
+            ("maxclients", self.maxclients.to_string()),
+            ("maxmemory", self.maxmemory.to_string()),
+            ("maxmemory-policy", self.maxmemory_policy.to_string()),
+            (
+                "notify-keyspace-events",
+                self.notify_keyspace_events.to_string(),
+            ),
+            ("pubsub-capacity", self.pubsub_capacity.to_string()),
+            ("save", self.save.clone()),
+            (
+                "slowlog-log-slower-than",
+                self.slowlog_log_slower_than.to_string(),
+            ),
+            ("slowlog-max-len", self.slowlog_max_len.to_string()),
+            (
+                "timeout",
+                self.idle_timeout
+                    .map_or(0, |timeout| timeout.as_secs())
+                    .to_string(),
+            ),
+        ];
+
+        params
+            .into_iter()
+            .filter(|(name, _)| glob_match(pattern.as_bytes(), name.as_bytes()))
+            .collect()
+    }
+
+    /// Set the parameter `name` to `value`.
+    ///
+    /// Returns the error to reply with if there is no such parameter, the parameter
+    /// is fixed, or `value` is invalid. The parameter is then left unchanged.
+    pub(crate) fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let failed = |reason: &str| {
+            format!("ERR CONFIG SET failed (possibly related to argument '{name}') - {reason}")
+        };
+
+        match name {
+            "active-expire-batch" => {
+                self.active_expire_batch = value
+                    .parse()
+                    .ok()
+                    .filter(|batch| *batch > 0)
+                    .ok_or_else(|| failed("argument must be a positive integer"))?;
+            }
+            "command-time-limit" => {
+                let milliseconds = value
+                    .parse()
+                    .map_err(|_| failed("argument couldn't be parsed into an integer"))?;
+                self.set_command_time_limit(Some(Duration::from_millis(milliseconds)));
+            }
+            "databases" | "maxclients" => return Err(failed("can't set immutable config")),
+            "dbfilename" => {
+                if Path::new(value).file_name() != Some(value.as_ref()) {
+                    return Err(failed("dbfilename can't be a path, just a filename"));
+                }
+                self.dbfilename = value.to_string();
+            }
+            "dir" => {
+                if !Path::new(value).is_dir() {
+                    return Err(failed("No such file or directory"));
+                }
+                self.dir = PathBuf::from(value);
+            }
+            "maxmemory" => {
+                self.maxmemory = value
+                    .parse()
+                    .map_err(|_| failed("argument couldn't be parsed into an integer"))?;
+            }
+            "maxmemory-policy" => {
+                self.maxmemory_policy = value.parse().map_err(|err: String| failed(&err))?;
+            }
+            "notify-keyspace-events" => {
+                self.notify_keyspace_events = value
+                    .parse()
+                    .map_err(|()| failed("Invalid event class character. Use 'Ag$lshzxeKEtmn'."))?;
+            }
+            "pubsub-capacity" => {
+                self.pubsub_capacity = value
+                    .parse()
+                    .ok()
+                    .filter(|capacity| (1..=MAX_PUBSUB_CAPACITY).contains(capacity))
+                    .ok_or_else(|| {
+                        failed(&format!(
+                            "argument must be between 1 and {MAX_PUBSUB_CAPACITY} inclusive"
+                        ))
+                    })?;
+            }
+            "save" => {
+                let numbers: Option<Vec<u64>> =
+                    value.split_whitespace().map(|n| n.parse().ok()).collect();
+                match numbers {
+                    Some(numbers) if numbers.len() % 2 == 0 => {
+                        let numbers: Vec<_> = numbers.iter().map(ToString::to_string).collect();
+                        self.save = numbers.join(" ");
+                    }
+                    _ => return Err(failed("Invalid save parameters")),
+                }
+            }
+            "slowlog-log-slower-than" => {
+                self.slowlog_log_slower_than = value
+                    .parse()
+                    .map_err(|_| failed("argument couldn't be parsed into an integer"))?;
+            }
+            "slowlog-max-len" => {
+                self.slowlog_max_len = value
+                    .parse()
+                    .map_err(|_| failed("argument couldn't be parsed into an integer"))?;
+            }
+            "timeout" => {
+                let seconds = value
+                    .parse()
+                    .map_err(|_| failed("argument couldn't be parsed into an integer"))?;
+                self.set_idle_timeout(Some(Duration::from_secs(seconds)));
+            }
+            _ => {
+                return Err(format!(
+                    "ERR Unknown option or number of arguments for CONFIG SET - '{name}'"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// What happens when a write makes the keys use more than `maxmemory`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MaxmemoryPolicy {
+    /// Further writes are refused until keys are removed.
+    #[default]
+    NoEviction,
+
+    /// The least recently accessed keys are evicted.
+    AllkeysLru,
+
+    /// The least frequently accessed keys are evicted.
+    AllkeysLfu,
+
+    /// Keys picked at random are evicted, which is cheaper than finding the least
+    /// recently accessed ones.
+    AllkeysRandom,
+}
+
+impl FromStr for MaxmemoryPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MaxmemoryPolicy, String> {
+        match s.to_lowercase().as_str() {
+            "noeviction" => Ok(MaxmemoryPolicy::NoEviction),
+            "allkeys-lru" => Ok(MaxmemoryPolicy::AllkeysLru),
+            "allkeys-lfu" => Ok(MaxmemoryPolicy::AllkeysLfu),
+            "allkeys-random" => Ok(MaxmemoryPolicy::AllkeysRandom),
+            _ => Err(
+                "argument(s) must be one of the following: noeviction, allkeys-lru, allkeys-lfu, allkeys-random"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for MaxmemoryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            MaxmemoryPolicy::NoEviction => "noeviction",
+            MaxmemoryPolicy::AllkeysLru => "allkeys-lru",
+            MaxmemoryPolicy::AllkeysLfu => "allkeys-lfu",
+            MaxmemoryPolicy::AllkeysRandom => "allkeys-random",
+        })
+    }
+}
+
+/// The classes of keyspace notifications published, as the flags of
+/// `notify-keyspace-events`.
+///
+/// Notifications are published to the `__keyspace@<db>__:<key>` channel with the
+/// event as message if `K` is set, and to the `__keyevent@<db>__:<event>` channel
+/// with the key as message if `E` is set.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct KeyspaceEvents(u16);
+
+impl KeyspaceEvents {
+    /// `K`, publish to the `__keyspace@<db>__` channels.
+    pub(crate) const KEYSPACE: KeyspaceEvents = KeyspaceEvents(1);
+    /// `E`, publish to the `__keyevent@<db>__` channels.
+    pub(crate) const KEYEVENT: KeyspaceEvents = KeyspaceEvents(1 << 1);
+    /// `g`, the events common to every type, such as `expire` or `move_from`.
+    pub(crate) const GENERIC: KeyspaceEvents = KeyspaceEvents(1 << 2);
+    /// `$`, the events of the string commands.
+    pub(crate) const STRING: KeyspaceEvents = KeyspaceEvents(1 << 3);
+    /// `x`, keys expiring.
+    pub(crate) const EXPIRED: KeyspaceEvents = KeyspaceEvents(1 << 4);
+    /// `e`, keys evicted to stay within `maxmemory`.
+    pub(crate) const EVICTED: KeyspaceEvents = KeyspaceEvents(1 << 5);
+    /// `t`, the events of the stream commands.
+    pub(crate) const STREAM: KeyspaceEvents = KeyspaceEvents(1 << 6);
+
+    // The classes of the types and events this server does not have, accepted like
+    // in Redis but never published.
+    const LIST: u16 = 1 << 7;
+    const SET: u16 = 1 << 8;
+    const HASH: u16 = 1 << 9;
+    const ZSET: u16 = 1 << 10;
+    const KEY_MISS: u16 = 1 << 11;
+    const NEW: u16 = 1 << 12;
+
+    /// `A`, an alias for `g$lshzxet`.
+    const ALL: u16 = Self::GENERIC.0
+        | Self::STRING.0
+        | Self::LIST
+        | Self::SET
+        | Self::HASH
+        | Self::ZSET
+        | Self::EXPIRED.0
+        | Self::EVICTED.0
+        | Self::STREAM.0;
+
+    /// Returns `true` if notifications of `class` are published to either kind of
+    /// channel.
+    pub(crate) fn publishes(self, class: KeyspaceEvents) -> bool {
+        self.0 & class.0 != 0 && self.0 & (Self::KEYSPACE.0 | Self::KEYEVENT.0) != 0
+    }
+
+    pub(crate) fn contains(self, flag: KeyspaceEvents) -> bool {
+        self.0 & flag.0 == flag.0
+    }
+
+    /// The flags of the character `c`, `None` if it is not a valid flag.
+    fn flags(c: char) -> Option<u16> {
+        let flags = match c {
+            'A' => Self::ALL,
+            'K' => Self::KEYSPACE.0,
+            'E' => Self::KEYEVENT.0,
+            'g' => Self::GENERIC.0,
+            '$' => Self::STRING.0,
+            'l' => Self::LIST,
+            's' => Self::SET,
+            'h' => Self::HASH,
+            'z' => Self::ZSET,
+            'x' => Self::EXPIRED.0,
+            'e' => Self::EVICTED.0,
+            't' => Self::STREAM.0,
+            'm' => Self::KEY_MISS,
+            'n' => Self::NEW,
+            _ => return None,
+        };
+
+        Some(flags)
+    }
+}
+
+impl FromStr for KeyspaceEvents {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<KeyspaceEvents, ()> {
+        s.chars()
+            .try_fold(0, |flags, c| Some(flags | KeyspaceEvents::flags(c)?))
+            .map(KeyspaceEvents)
+            .ok_or(())
+    }
+}
+
+impl fmt::Display for KeyspaceEvents {
+    /// The flags in the order Redis writes them, with `A` standing for all the
+    /// classes it covers.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut flags = self.0;
+        if flags & KeyspaceEvents::ALL == KeyspaceEvents::ALL {
+            f.write_str("A")?;
+            flags &= !KeyspaceEvents::ALL;
+        }
+        for c in [
+            'g', '$', 'l', 's', 'h', 'z', 'x', 'e', 't', 'K', 'E', 'm', 'n',
+        ] {
+            if flags & KeyspaceEvents::flags(c).unwrap_or(0) != 0 {
+                write!(f, "{c}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
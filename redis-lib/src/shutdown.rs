@@ -1,34 +1,82 @@
-use tokio::sync::broadcast;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tokio::sync::oneshot;
 
 /// The `Shutdown` struct tracks that the signal has been received.
+///
+/// The signal is either the server shutting down, or the connection being killed
+/// with `CLIENT KILL`.
 #[derive(Debug)]
 pub(crate) struct Shutdown {
     /// `true` if the shutdown signal has been received
     is_shutdown: bool,
 
     notify: broadcast::Receiver<()>,
+
+    kill: oneshot::Receiver<()>,
 }
 
 impl Shutdown {
-    pub(crate) fn new(notify: broadcast::Receiver<()>) -> Shutdown {
+    pub(crate) fn new(notify: broadcast::Receiver<()>, kill: oneshot::Receiver<()>) -> Shutdown {
         Shutdown {
             is_shutdown: false,
             notify,
+            kill,
         }
     }
 
-    pub(crate) fn is_shutdown(&self) -> bool {
+    /// Returns `true` if the shutdown signal has been received, checking for it
+    /// without waiting.
+    pub(crate) fn is_shutdown(&mut self) -> bool {
+        if !self.is_shutdown {
+            // The server signals its shutdown by dropping the sender: a receiver
+            // subscribed just before sees the channel closed, like the others.
+            let notified = !matches!(self.notify.try_recv(), Err(TryRecvError::Empty));
+            let killed = !matches!(
+                self.kill.try_recv(),
+                Err(oneshot::error::TryRecvError::Empty)
+            );
+            self.is_shutdown = notified || killed;
+        }
+
         self.is_shutdown
     }
 
     /// Receive the shutdown notice, waiting if necessary.
+    ///
+    /// Any outcome of the channels counts as the notice, their senders being
+    /// dropped or the receiver lagging behind included, so that no connection is
+    /// left running once the server shuts down.
     pub(crate) async fn recv(&mut self) {
         if self.is_shutdown {
             return;
         }
 
-        let _ = self.notify.recv().await;
+        tokio::select! {
+            _ = self.notify.recv() => {}
+            _ = &mut self.kill => {}
+        }
 
         self.is_shutdown = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn late_subscriber_is_shut_down() {
+        let (notify, _) = broadcast::channel(1);
+        let (_kill_tx, kill) = oneshot::channel();
+        let mut shutdown = Shutdown::new(notify.subscribe(), kill);
+        assert!(!shutdown.is_shutdown());
+
+        // Subscribed as the server shuts down, before the sender is dropped.
+        let (_kill_tx, kill) = oneshot::channel();
+        let mut late = Shutdown::new(notify.subscribe(), kill);
+        drop(notify);
+        assert!(shutdown.is_shutdown());
+        assert!(late.is_shutdown());
+        late.recv().await;
+    }
+}
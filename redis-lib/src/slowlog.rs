@@ -0,0 +1,98 @@
+//!
+//! The log of the commands which took the longest to execute, read with `SLOWLOG`.
+//!
+
+use bytes::{BufMut, Bytes, BytesMut};
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The maximum number of arguments recorded per command, like in Redis.
+const MAX_ARGS: usize = 32;
+
+/// The maximum length of a recorded argument, like in Redis.
+const MAX_ARG_LEN: usize = 128;
+
+/// The most recent slow commands, newest first.
+#[derive(Debug, Default)]
+pub(crate) struct SlowLog {
+    entries: VecDeque<SlowLogEntry>,
+
+    /// The id of the next entry. Ids keep increasing when the log is reset.
+    next_id: u64,
+}
+
+/// A command which took longer to execute than `slowlog-log-slower-than`.
+#[derive(Debug, Clone)]
+pub(crate) struct SlowLogEntry {
+    pub(crate) id: u64,
+
+    /// The Unix time in seconds when the command was logged.
+    pub(crate) timestamp: u64,
+
+    pub(crate) duration: Duration,
+
+    /// The command name and its arguments, truncated when too many or too long.
+    pub(crate) args: Vec<Bytes>,
+
+    /// The `ip:port` address of the connection.
+    pub(crate) addr: String,
+
+    /// The name of the connection, empty if it has none.
+    pub(crate) name: String,
+}
+
+impl SlowLog {
+    /// Record a command issued by the connection from `addr` named `name`, which
+    /// took `duration`. The oldest entries are dropped to keep at most `max_len`.
+    pub(crate) fn push(
+        &mut self,
+        duration: Duration,
+        mut args: Vec<Bytes>,
+        addr: String,
+        name: String,
+        max_len: usize,
+    ) {
+        if args.len() > MAX_ARGS {
+            let more = args.len() - MAX_ARGS + 1;
+            args.truncate(MAX_ARGS - 1);
+            args.push(Bytes::from(format!("... ({more} more arguments)")));
+        }
+        for arg in &mut args {
+            if arg.len() > MAX_ARG_LEN {
+                let mut truncated = BytesMut::from(&arg[..MAX_ARG_LEN]);
+                truncated.put(format!("... ({} more bytes)", arg.len() - MAX_ARG_LEN).as_bytes());
+                *arg = truncated.freeze();
+            }
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let entry = SlowLogEntry {
+            id: self.next_id,
+            timestamp,
+            duration,
+            args,
+            addr,
+            name,
+        };
+        self.next_id += 1;
+
+        self.entries.push_front(entry);
+        self.entries.truncate(max_len);
+    }
+
+    /// Returns up to `count` entries, newest first.
+    pub(crate) fn get(&self, count: usize) -> Vec<SlowLogEntry> {
+        self.entries.iter().take(count).cloned().collect()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.entries.clear();
+    }
+}
@@ -0,0 +1,57 @@
+//!
+//! Loading the TLS configurations of the server and clients, from PEM files.
+//!
+//! The server serves TLS connections when `server::Config::tls` is set, and clients
+//! connect with `Client::connect_tls`.
+//!
+
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+
+pub use tokio_rustls::rustls;
+
+/// The configuration of a server presenting the certificate chain at `cert_path`,
+/// signed with the private key at `key_path`.
+pub fn server_config(cert_path: &Path, key_path: &Path) -> crate::Result<Arc<ServerConfig>> {
+    let certs = load_certs(cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|err| format!("failed to load the key {}: {err}", key_path.display()))?;
+
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+
+    Ok(Arc::new(config))
+}
+
+/// The configuration of a client trusting the certificates at `ca_path`, such as
+/// the certificate of a server or of the authority which signed it.
+pub fn client_config(ca_path: &Path) -> crate::Result<Arc<ClientConfig>> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(ca_path)? {
+        roots.add(cert)?;
+    }
+
+    let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    Ok(Arc::new(config))
+}
+
+fn load_certs(path: &Path) -> crate::Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .map_err(|err| format!("failed to load the certificates {}: {err}", path.display()))?;
+    if certs.is_empty() {
+        return Err(format!("no certificate found in {}", path.display()).into());
+    }
+
+    Ok(certs)
+}
@@ -0,0 +1,91 @@
+//!
+//! Client-side caching, see `CLIENT TRACKING`.
+//!
+//! The server remembers the keys read by the connections with tracking enabled.
+//! Once such a key is modified, each of them is sent an invalidation message, and
+//! the key is forgotten until read again:
+//!
+//! ```text
+//! >2
+//! $10
+//! invalidate
+//! *1
+//! $5
+//! hello
+//! ```
+//!
+//! Like in Redis, keys are tracked by name, whatever the database they are read
+//! from. Unlike Redis, the messages are only sent as RESP3 pushes: they cannot be
+//! redirected to the `__redis__:invalidate` channel of another connection.
+//!
+
+use crate::Frame;
+use bytes::Bytes;
+use std::collections::{HashMap, HashSet};
+use tokio::sync::mpsc;
+
+/// The keys invalidated for a connection, received as they are modified.
+pub(crate) type Invalidations = mpsc::UnboundedReceiver<String>;
+
+/// The keys read by the connections with tracking enabled.
+#[derive(Debug, Default)]
+pub(crate) struct Tracking {
+    /// The ids of the connections which read each key since it was last modified.
+    keys: HashMap<String, HashSet<u64>>,
+
+    /// Sends the invalidated keys to each connection with tracking enabled. A
+    /// connection disables tracking by dropping its receiver.
+    clients: HashMap<u64, mpsc::UnboundedSender<String>>,
+}
+
+impl Tracking {
+    /// Enable tracking for the connection `id`, returning the keys invalidated from
+    /// now on. Any previous receiver of the connection gets no more keys.
+    pub(crate) fn enable(&mut self, id: u64) -> Invalidations {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.clients.insert(id, tx);
+
+        rx
+    }
+
+    /// Remember that the connection `id` read `key`, if it has tracking enabled.
+    pub(crate) fn track(&mut self, id: u64, key: &str) {
+        let enabled = self.clients.get(&id).is_some_and(|tx| !tx.is_closed());
+        if !enabled {
+            return;
+        }
+        match self.keys.get_mut(key) {
+            Some(ids) => {
+                ids.insert(id);
+            }
+            None => {
+                self.keys.insert(key.to_string(), HashSet::from([id]));
+            }
+        }
+    }
+
+    /// Invalidate `key` for the connections which read it, as it was modified.
+    pub(crate) fn invalidate(&mut self, key: &str) {
+        let Some(ids) = self.keys.remove(key) else {
+            return;
+        };
+        for id in ids {
+            // The connection disabled tracking or was closed.
+            let sent = self
+                .clients
+                .get(&id)
+                .is_some_and(|tx| tx.send(key.to_string()).is_ok());
+            if !sent {
+                self.clients.remove(&id);
+            }
+        }
+    }
+}
+
+/// The message pushed to a connection when `key` is invalidated.
+pub(crate) fn message(key: String) -> Frame {
+    Frame::Push(vec![
+        Frame::Bulk(Bytes::from("invalidate")),
+        Frame::Array(vec![Frame::Bulk(Bytes::from(key))]),
+    ])
+}
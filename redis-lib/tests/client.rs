@@ -1,13 +1,27 @@
-use redis_lib::{clients::Client, server};
+use bytes::Bytes;
+use redis_lib::clients::{Client, MultiplexedClient, Pool, ReconnectingClient};
+use redis_lib::cmd::{
+    DebugCommand, Expiration, ExpireCondition, Get, KillFilter, PauseMode, Select, Set,
+};
+use redis_lib::{Frame, TcpOptions, server};
 use std::net::SocketAddr;
-use tokio::net::TcpListener;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::task::JoinHandle;
 
 async fn start_server() -> (SocketAddr, JoinHandle<()>) {
+    start_server_with_config(server::Config::default()).await
+}
+
+async fn start_server_with_config(config: server::Config) -> (SocketAddr, JoinHandle<()>) {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
 
-    let handle = tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+    let handle = tokio::spawn(async move {
+        server::run_with_config(listener, tokio::signal::ctrl_c(), config).await
+    });
 
     (addr, handle)
 }
@@ -58,6 +72,48 @@ async fn receive_message_subscribed_channel() {
     assert_eq!(b"world", &message.content[..])
 }
 
+#[tokio::test]
+async fn publish_subscribe_on_test_server() {
+    let server = server::TestServer::start(server::Config::default())
+        .await
+        .unwrap();
+
+    let client = Client::connect(server.addr()).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+    let mut publisher = Client::connect(server.addr()).await.unwrap();
+    assert_eq!(publisher.publish("hello", "world".into()).await.unwrap(), 1);
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("hello", &message.channel);
+    assert_eq!(b"world", &message.content[..]);
+
+    // The connections are closed by the shutdown.
+    server.shutdown().await.unwrap();
+    assert!(subscriber.next_message().await.unwrap().is_none());
+    assert!(publisher.ping(None).await.is_err());
+}
+
+/// test that a RESP3 client gets the messages pushed to it
+#[tokio::test]
+async fn receive_message_under_resp3() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.hello(Some(3)).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+
+    tokio::spawn(async move {
+        let mut client = Client::connect(addr).await.unwrap();
+        client.publish("hello", "world".into()).await.unwrap()
+    });
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("hello", &message.channel);
+    assert_eq!(b"world", &message.content[..]);
+
+    subscriber.unsubscribe(&[]).await.unwrap();
+}
+
 /// test that a client gets messages from multiple subscribed channels
 #[tokio::test]
 async fn receive_message_multiple_subscribed_channels() {
@@ -103,3 +159,2959 @@ async fn unsubscribes_from_channels() {
     subscriber.unsubscribe(&[]).await.unwrap();
     assert_eq!(subscriber.get_subscribed().len(), 0);
 }
+
+/// test that unsubscribing from all channels reads every confirmation, however many
+/// subscriptions the server holds
+#[tokio::test]
+async fn unsubscribes_from_all_after_duplicate_subscribe() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["foo".into()]).await.unwrap();
+    subscriber
+        .subscribe(&["foo".into(), "bar".into()])
+        .await
+        .unwrap();
+    subscriber.psubscribe(&["news.*".into()]).await.unwrap();
+
+    subscriber.unsubscribe(&[]).await.unwrap();
+    assert!(subscriber.get_subscribed().is_empty());
+    // Nothing is left to unsubscribe from.
+    subscriber.unsubscribe(&[]).await.unwrap();
+    assert_eq!(subscriber.get_subscribed_patterns(), ["news.*"]);
+
+    // No confirmation is left unread.
+    let pong = subscriber.ping(Some("hello".into())).await.unwrap();
+    assert_eq!(pong, "hello");
+}
+
+#[tokio::test]
+async fn subscriber_counts_subscriptions() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client
+        .subscribe(vec!["news".into(), "sports".into()])
+        .await
+        .unwrap();
+    subscriber.psubscribe(&["weather.*".into()]).await.unwrap();
+    assert_eq!(subscriber.len(), 3);
+    assert!(subscriber.is_subscribed("news"));
+    assert!(subscriber.is_subscribed("sports"));
+    // Patterns are not channels.
+    assert!(!subscriber.is_subscribed("weather.*"));
+
+    subscriber.unsubscribe(&["news".into()]).await.unwrap();
+    assert_eq!(subscriber.len(), 2);
+    assert!(!subscriber.is_subscribed("news"));
+    assert!(subscriber.is_subscribed("sports"));
+
+    subscriber.unsubscribe(&[]).await.unwrap();
+    subscriber.punsubscribe(&[]).await.unwrap();
+    assert!(subscriber.is_empty());
+}
+
+#[tokio::test]
+async fn subscriber_back_to_client() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    let mut subscriber = client
+        .subscribe(vec!["news".into(), "sports".into()])
+        .await
+        .unwrap();
+    subscriber.unsubscribe(&[]).await.unwrap();
+
+    let mut client = subscriber.into_client().await.unwrap();
+    assert_eq!(client.get("hello").await.unwrap().unwrap(), "world");
+
+    // The remaining subscriptions are dropped.
+    let subscriber = client.psubscribe(vec!["news.*".into()]).await.unwrap();
+    let mut client = subscriber.into_client().await.unwrap();
+    assert_eq!(client.get("hello").await.unwrap().unwrap(), "world");
+}
+
+#[tokio::test]
+async fn xread_null_array_reply() {
+    // A server replying with the null array of RESP2, like Redis when `XREAD`
+    // times out.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = [0; 64];
+        let _ = socket.read(&mut request).await.unwrap();
+        socket.write_all(b"*-1\r\n").await.unwrap();
+    });
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let streams = client.xread(None, &[("stream", "$")]).await.unwrap();
+    assert!(streams.is_empty());
+}
+
+#[tokio::test]
+async fn xread_entries_after_id() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let first = client
+        .xadd("stream", "1-1", vec![("name".into(), "a".into())])
+        .await
+        .unwrap();
+    assert_eq!("1-1", first);
+    client
+        .xadd("stream", "1-2", vec![("name".into(), "b".into())])
+        .await
+        .unwrap();
+    let third = client
+        .xadd("stream", "*", vec![("name".into(), "c".into())])
+        .await
+        .unwrap();
+
+    let streams = client.xread(None, &[("stream", &first)]).await.unwrap();
+    assert_eq!(1, streams.len());
+    let (key, entries) = &streams[0];
+    assert_eq!("stream", key);
+    assert_eq!(2, entries.len());
+    assert_eq!("1-2", entries[0].id);
+    assert_eq!(third, entries[1].id);
+    assert_eq!(b"c", &entries[1].fields[0].1[..]);
+
+    // `COUNT` limits the entries returned per stream
+    let streams = client.xread(Some(1), &[("stream", "0")]).await.unwrap();
+    assert_eq!(1, streams[0].1.len());
+    assert_eq!("1-1", streams[0].1[0].id);
+
+    // `$` only returns entries added after the call
+    let streams = client.xread(None, &[("stream", "$")]).await.unwrap();
+    assert!(streams.is_empty());
+}
+
+#[tokio::test]
+async fn xadd_after_the_greatest_id() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let max = format!("{}-{}", u64::MAX, u64::MAX);
+    client
+        .xadd("stream", &max, vec![("name".into(), "a".into())])
+        .await
+        .unwrap();
+    let err = client
+        .xadd("stream", "*", vec![("name".into(), "b".into())])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "ERR The stream has exhausted the last possible ID, unable to add more items"
+    );
+
+    // The keys are still usable.
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(client.get("hello").await.unwrap().unwrap(), "world");
+}
+
+#[tokio::test]
+async fn failed_xadd_does_not_create_the_stream() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let err = client
+        .xadd("stream", "0-0", vec![("name".into(), "a".into())])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "ERR The ID specified in XADD must be greater than 0-0"
+    );
+
+    let (cursor, keys) = client.scan(0, None, None).await.unwrap();
+    assert_eq!(cursor, 0);
+    assert!(keys.is_empty());
+    let streams = client.xread(None, &[("stream", "0")]).await.unwrap();
+    assert!(streams.is_empty());
+}
+
+/// test that the commands applied to a key holding another type of value fail
+#[tokio::test]
+async fn wrong_type_errors() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let wrong_type = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+    client
+        .xadd("stream", "*", vec![("name".into(), "a".into())])
+        .await
+        .unwrap();
+    let err = client.get("stream").await.unwrap_err();
+    assert_eq!(err.to_string(), wrong_type);
+
+    client.set("string", "value".into()).await.unwrap();
+    let err = client
+        .xadd("string", "*", vec![("name".into(), "a".into())])
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), wrong_type);
+    let err = client.xread(None, &[("string", "0")]).await.unwrap_err();
+    assert_eq!(err.to_string(), wrong_type);
+
+    // The connection is kept open.
+    assert_eq!(client.get("string").await.unwrap().unwrap(), "value");
+}
+
+#[tokio::test]
+async fn receive_message_matching_pattern() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.psubscribe(vec!["news.*".into()]).await.unwrap();
+    assert_eq!(
+        &["news.*".to_string()],
+        subscriber.get_subscribed_patterns()
+    );
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    assert_eq!(0, publisher.publish("sports", "goal".into()).await.unwrap());
+    assert_eq!(
+        1,
+        publisher.publish("news.tech", "rust".into()).await.unwrap()
+    );
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("news.tech", &message.channel);
+    assert_eq!(Some("news.*"), message.pattern.as_deref());
+    assert_eq!(b"rust", &message.content[..]);
+}
+
+/// test that pattern messages stop arriving once the pattern is unsubscribed
+#[tokio::test]
+async fn punsubscribes_from_patterns() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client
+        .psubscribe(vec!["news.*".into(), "sports.*".into()])
+        .await
+        .unwrap();
+
+    subscriber.punsubscribe(&["news.*".into()]).await.unwrap();
+    assert_eq!(
+        &["sports.*".to_string()],
+        subscriber.get_subscribed_patterns()
+    );
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    assert_eq!(
+        0,
+        publisher.publish("news.tech", "rust".into()).await.unwrap()
+    );
+    assert_eq!(
+        1,
+        publisher.publish("sports.f1", "race".into()).await.unwrap()
+    );
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("sports.f1", &message.channel);
+
+    // unsubscribe from all patterns
+    subscriber.punsubscribe(&[]).await.unwrap();
+    assert!(subscriber.get_subscribed_patterns().is_empty());
+    assert_eq!(
+        0,
+        publisher.publish("sports.f1", "race".into()).await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn pubsub_channels_lists_subscribed_channels() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    assert!(client.pubsub_channels(None).await.unwrap().is_empty());
+
+    let subscriber = Client::connect(addr).await.unwrap();
+    let _subscriber = subscriber
+        .subscribe(vec!["news.tech".into(), "sports".into()])
+        .await
+        .unwrap();
+
+    let mut channels = client.pubsub_channels(None).await.unwrap();
+    channels.sort();
+    assert_eq!(
+        vec!["news.tech".to_string(), "sports".to_string()],
+        channels
+    );
+
+    let channels = client.pubsub_channels(Some("news.*")).await.unwrap();
+    assert_eq!(vec!["news.tech".to_string()], channels);
+}
+
+#[tokio::test]
+async fn pubsub_numsub_tracks_subscribers() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let counts = client.pubsub_numsub(&["hello", "world"]).await.unwrap();
+    assert_eq!(vec![("hello".into(), 0), ("world".into(), 0)], counts);
+
+    let subscriber1 = Client::connect(addr).await.unwrap();
+    let mut subscriber1 = subscriber1.subscribe(vec!["hello".into()]).await.unwrap();
+    let subscriber2 = Client::connect(addr).await.unwrap();
+    let subscriber2 = subscriber2
+        .subscribe(vec!["hello".into(), "world".into()])
+        .await
+        .unwrap();
+
+    let counts = client.pubsub_numsub(&["hello", "world"]).await.unwrap();
+    assert_eq!(vec![("hello".into(), 2), ("world".into(), 1)], counts);
+
+    // Leaving by unsubscribing
+    subscriber1.unsubscribe(&["hello".into()]).await.unwrap();
+    let counts = client.pubsub_numsub(&["hello"]).await.unwrap();
+    assert_eq!(vec![("hello".into(), 1)], counts);
+
+    // Leaving by disconnecting
+    drop(subscriber2);
+    let mut counts = vec![];
+    for _ in 0..100 {
+        counts = client.pubsub_numsub(&["hello", "world"]).await.unwrap();
+        if counts == vec![("hello".into(), 0), ("world".into(), 0)] {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert_eq!(vec![("hello".into(), 0), ("world".into(), 0)], counts);
+}
+
+#[tokio::test]
+async fn pubsub_numpat_counts_unique_patterns() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(0, client.pubsub_numpat().await.unwrap());
+
+    let subscriber1 = Client::connect(addr).await.unwrap();
+    let _subscriber1 = subscriber1.psubscribe(vec!["news.*".into()]).await.unwrap();
+    let subscriber2 = Client::connect(addr).await.unwrap();
+    let _subscriber2 = subscriber2
+        .psubscribe(vec!["sports.*".into()])
+        .await
+        .unwrap();
+    assert_eq!(2, client.pubsub_numpat().await.unwrap());
+
+    // The same pattern subscribed by another client is not counted twice
+    let subscriber3 = Client::connect(addr).await.unwrap();
+    let _subscriber3 = subscriber3.psubscribe(vec!["news.*".into()]).await.unwrap();
+    assert_eq!(2, client.pubsub_numpat().await.unwrap());
+}
+
+/// test that sharded and regular channels with the same name do not see each
+/// other's messages
+#[tokio::test]
+async fn sharded_and_regular_channels_are_isolated() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut shard_subscriber = client.ssubscribe(vec!["orders".into()]).await.unwrap();
+    assert_eq!(
+        &["orders".to_string()],
+        shard_subscriber.get_subscribed_shard_channels()
+    );
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["orders".into()]).await.unwrap();
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    assert_eq!(
+        1,
+        publisher
+            .spublish("orders", "sharded".into())
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        1,
+        publisher.publish("orders", "regular".into()).await.unwrap()
+    );
+    assert_eq!(
+        1,
+        publisher
+            .spublish("orders", "sharded again".into())
+            .await
+            .unwrap()
+    );
+
+    let message = shard_subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!("orders", &message.channel);
+    assert_eq!(b"sharded", &message.content[..]);
+    let message = shard_subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(b"sharded again", &message.content[..]);
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(b"regular", &message.content[..]);
+
+    shard_subscriber.sunsubscribe(&[]).await.unwrap();
+    assert!(shard_subscriber.get_subscribed_shard_channels().is_empty());
+    assert_eq!(
+        0,
+        publisher
+            .spublish("orders", "sharded".into())
+            .await
+            .unwrap()
+    );
+}
+
+/// test that a burst of messages within the configured pub/sub capacity is
+/// delivered without loss, even though the subscriber is not reading meanwhile
+#[tokio::test]
+async fn burst_within_pubsub_capacity_is_delivered() {
+    const BURST: usize = 4000;
+
+    let (addr, _) = start_server_with_config(server::Config {
+        pubsub_capacity: BURST,
+        ..server::Config::default()
+    })
+    .await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["burst".into()]).await.unwrap();
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    for i in 0..BURST {
+        publisher
+            .publish("burst", i.to_string().into())
+            .await
+            .unwrap();
+    }
+
+    for i in 0..BURST {
+        let message = subscriber.next_message().await.unwrap().unwrap();
+        assert_eq!(i.to_string().as_bytes(), &message.content[..]);
+    }
+}
+
+/// test that non-UTF8 payloads round-trip byte-for-byte
+#[tokio::test]
+async fn receive_binary_message() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["binary".into()]).await.unwrap();
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    publisher
+        .publish("binary", Bytes::from_static(b"\xff\x00\xfe"))
+        .await
+        .unwrap();
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(b"\xff\x00\xfe", &message.content[..]);
+}
+
+/// test that keepalive PINGs succeed while subscribed, without losing messages
+#[tokio::test]
+async fn ping_while_subscribed() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+
+    let pong = subscriber.ping(None).await.unwrap();
+    assert!(pong.is_empty());
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    publisher.publish("hello", "world".into()).await.unwrap();
+
+    let pong = subscriber.ping(Some("keepalive".into())).await.unwrap();
+    assert_eq!(b"keepalive", &pong[..]);
+
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(b"world", &message.content[..]);
+}
+
+/// test that a subscribed RESP2 client receives `[pong, message]`, while a RESP3
+/// client receives the usual reply
+#[tokio::test]
+async fn ping_reply_while_subscribed() {
+    let (addr, _) = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*2\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 34];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$4\r\nping\r\n$9\r\nkeepalive\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 29];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*2\r\n$4\r\npong\r\n$9\r\nkeepalive\r\n", &response);
+
+    stream.write_all(b"*1\r\n$4\r\nping\r\n").await.unwrap();
+    let mut response = [0; 20];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*2\r\n$4\r\npong\r\n$0\r\n\r\n", &response);
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.hello(Some(3)).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+    assert_eq!(
+        b"keepalive",
+        &subscriber.ping_with("keepalive").await.unwrap()[..]
+    );
+    assert!(subscriber.ping(None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn transaction_set_then_get() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.multi().await.unwrap();
+    client
+        .queue(Set::new("hello", "world".into(), None))
+        .await
+        .unwrap();
+    client.queue(Get::new("hello")).await.unwrap();
+
+    let replies = client.exec().await.unwrap().unwrap();
+    assert!(
+        matches!(&replies[..], [Frame::Simple(ok), Frame::Bulk(value)] if ok == "OK" && value == "world")
+    );
+}
+
+#[tokio::test]
+async fn discarded_transaction_is_not_applied() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    // outside of a transaction
+    assert!(client.discard().await.is_err());
+
+    client.multi().await.unwrap();
+    client
+        .queue(Set::new("hello", "world".into(), None))
+        .await
+        .unwrap();
+    client.discard().await.unwrap();
+
+    assert!(client.get("hello").await.unwrap().is_none());
+}
+
+/// test that a concurrent SET on a watched key aborts the transaction
+#[tokio::test]
+async fn watched_key_modification_aborts_transaction() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+
+    client.watch(&["balance"]).await.unwrap();
+    other.set("balance", "100".into()).await.unwrap();
+
+    client.multi().await.unwrap();
+    client
+        .queue(Set::new("balance", "50".into(), None))
+        .await
+        .unwrap();
+    assert!(client.exec().await.unwrap().is_none());
+    assert_eq!(b"100", &client.get("balance").await.unwrap().unwrap()[..]);
+
+    // the watch ended with `EXEC`, so the next transaction is executed
+    client.multi().await.unwrap();
+    client
+        .queue(Set::new("balance", "50".into(), None))
+        .await
+        .unwrap();
+    assert!(client.exec().await.unwrap().is_some());
+
+    // unwatched keys do not abort the transaction
+    client.watch(&["balance"]).await.unwrap();
+    other.set("balance", "100".into()).await.unwrap();
+    client.unwatch().await.unwrap();
+    client.multi().await.unwrap();
+    assert_eq!(Some(0), client.exec().await.unwrap().map(|r| r.len()));
+}
+
+/// test that no write of another connection is applied in the middle of a transaction
+#[tokio::test]
+async fn transaction_is_not_interleaved_with_other_writes() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+
+    client.multi().await.unwrap();
+    client
+        .queue(Set::new("hello", "mine".into(), None))
+        .await
+        .unwrap();
+    client
+        .queue(DebugCommand::Sleep {
+            duration: Duration::from_millis(200),
+        })
+        .await
+        .unwrap();
+    client.queue(Get::new("hello")).await.unwrap();
+    let exec = tokio::spawn(async move { client.exec().await.unwrap().unwrap() });
+
+    // Applied once the transaction is, although sent while it sleeps.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    other.set("hello", "other".into()).await.unwrap();
+
+    let replies = exec.await.unwrap();
+    assert_eq!(replies[2], Frame::Bulk("mine".into()));
+    assert_eq!(other.get("hello").await.unwrap().unwrap(), "other");
+}
+
+/// test that a client not reading the replies to its writes holds up no other writes
+#[tokio::test]
+async fn writes_of_a_client_not_reading_replies_do_not_block_others() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+    client
+        .set("big", Bytes::from(vec![b'x'; 1024 * 1024]))
+        .await
+        .unwrap();
+
+    // The replies fill the socket buffers, until the server is left writing one.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&b"GETEX big\r\n".repeat(64))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let writes = async {
+        client.multi().await.unwrap();
+        client
+            .queue(Set::new("hello", "mine".into(), None))
+            .await
+            .unwrap();
+        let exec = client.exec();
+        let set = other.set("foo", "bar".into());
+        let (exec, set) = tokio::join!(exec, set);
+        exec.unwrap().unwrap();
+        set.unwrap();
+    };
+    tokio::time::timeout(Duration::from_secs(5), writes)
+        .await
+        .expect("writes blocked by a client not reading its replies");
+}
+
+/// test that a missing key created then deleted since `WATCH` aborts the transaction
+#[tokio::test]
+async fn watched_missing_key_created_then_deleted_aborts_transaction() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+
+    client.watch(&["lock"]).await.unwrap();
+    other.set("lock", "taken".into()).await.unwrap();
+    assert!(other.expire("lock", -1, None).await.unwrap());
+
+    client.multi().await.unwrap();
+    client
+        .queue(Set::new("lock", "mine".into(), None))
+        .await
+        .unwrap();
+    assert!(client.exec().await.unwrap().is_none());
+    assert!(client.get("lock").await.unwrap().is_none());
+
+    // Expiring counts as a deletion too.
+    client.watch(&["lock"]).await.unwrap();
+    other
+        .set_expires("lock", "taken".into(), Duration::from_millis(10))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    client.multi().await.unwrap();
+    assert!(client.exec().await.unwrap().is_none());
+
+    // While a key still missing leaves the transaction be.
+    client.watch(&["lock"]).await.unwrap();
+    client.multi().await.unwrap();
+    assert_eq!(Some(0), client.exec().await.unwrap().map(|r| r.len()));
+}
+
+#[tokio::test]
+async fn auth_with_wrong_password() {
+    let (addr, _) = start_server_with_config(server::Config {
+        password: Some("secret".into()),
+        ..server::Config::default()
+    })
+    .await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let err = client.get("hello").await.unwrap_err();
+    assert!(err.to_string().starts_with("NOAUTH"));
+
+    let err = client.auth("wrong").await.unwrap_err();
+    assert!(err.to_string().starts_with("WRONGPASS"));
+
+    let err = client.get("hello").await.unwrap_err();
+    assert!(err.to_string().starts_with("NOAUTH"));
+}
+
+#[tokio::test]
+async fn auth_with_correct_password() {
+    let (addr, _) = start_server_with_config(server::Config {
+        password: Some("secret".into()),
+        ..server::Config::default()
+    })
+    .await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.auth("secret").await.unwrap();
+
+    client.set("hello", "world".into()).await.unwrap();
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..]);
+}
+
+#[tokio::test]
+async fn hello_negotiates_protocol() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    for protover in [3, 2] {
+        let properties = client.hello(Some(protover)).await.unwrap();
+        let proto = properties.iter().find(|(key, _)| key == "proto");
+        assert!(
+            matches!(proto, Some((_, Frame::Integer(proto))) if u64::try_from(*proto) == Ok(protover))
+        );
+    }
+
+    // the protocol is unsupported
+    assert!(client.hello(Some(4)).await.is_err());
+}
+
+#[tokio::test]
+async fn connection_name_survives_across_commands() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(None, client.get_name().await.unwrap());
+    assert!(client.set_name("with space").await.is_err());
+
+    client.set_name("worker-1").await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    client.ping(None).await.unwrap();
+    assert_eq!(
+        Some("worker-1"),
+        client.get_name().await.unwrap().as_deref()
+    );
+
+    // other connections have their own name
+    let mut other = Client::connect(addr).await.unwrap();
+    assert_eq!(None, other.get_name().await.unwrap());
+}
+
+#[tokio::test]
+async fn connections_have_distinct_ids() {
+    let (addr, _) = start_server().await;
+    let mut first = Client::connect(addr).await.unwrap();
+    let mut second = Client::connect(addr).await.unwrap();
+
+    let first_id = first.client_id().await.unwrap();
+    let second_id = second.client_id().await.unwrap();
+    assert_ne!(first_id, second_id);
+
+    // the id is stable for the connection
+    assert_eq!(first_id, first.client_id().await.unwrap());
+}
+
+#[tokio::test]
+async fn client_list_includes_current_connection() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set_name("lister").await.unwrap();
+    let id = client.client_id().await.unwrap();
+
+    let list = client.client_list().await.unwrap();
+    let line = list
+        .lines()
+        .find(|line| line.starts_with(&format!("id={id} ")))
+        .unwrap();
+    assert!(line.contains(" name=lister "));
+    assert!(line.ends_with(" cmd=client"));
+}
+
+/// test that one connection can close another
+#[tokio::test]
+async fn client_kill_closes_connection() {
+    let (addr, _) = start_server().await;
+
+    let mut victim = Client::connect(addr).await.unwrap();
+    let victim_id = victim.client_id().await.unwrap();
+    let mut subscriber = victim.subscribe(vec!["hello".into()]).await.unwrap();
+
+    let mut killer = Client::connect(addr).await.unwrap();
+    // a connection does not kill itself
+    let killer_id = killer.client_id().await.unwrap();
+    assert_eq!(
+        0,
+        killer.client_kill(KillFilter::Id(killer_id)).await.unwrap()
+    );
+    assert_eq!(
+        0,
+        killer
+            .client_kill(KillFilter::Addr("127.0.0.1:1".into()))
+            .await
+            .unwrap()
+    );
+
+    assert_eq!(
+        1,
+        killer.client_kill(KillFilter::Id(victim_id)).await.unwrap()
+    );
+    assert!(subscriber.next_message().await.unwrap().is_none());
+}
+
+/// test that a command issued during `CLIENT PAUSE` waits for the pause to elapse
+#[tokio::test]
+async fn client_pause_delays_commands() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+
+    let start = Instant::now();
+    client
+        .client_pause(Duration::from_millis(200), PauseMode::All)
+        .await
+        .unwrap();
+    other.get("hello").await.unwrap();
+
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+/// test that `QUIT` is acknowledged before the connection is closed
+#[tokio::test]
+async fn quit_is_acknowledged() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    client.quit().await.unwrap();
+}
+
+/// test that `RESET` ends a transaction, so that commands execute normally
+#[tokio::test]
+async fn reset_ends_transaction() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set_name("before-reset").await.unwrap();
+    client.multi().await.unwrap();
+    client
+        .queue(Set::new("hello", "queued".into(), None))
+        .await
+        .unwrap();
+
+    client.reset().await.unwrap();
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+    assert!(client.get_name().await.unwrap().is_none());
+    assert!(client.exec().await.is_err());
+}
+
+/// test that `RESET` leaves the subscribed state
+#[tokio::test]
+async fn reset_leaves_subscribed_state() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+
+    let mut client = subscriber.reset().await.unwrap();
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    assert_eq!(0, publisher.publish("hello", "world".into()).await.unwrap());
+
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+}
+
+/// test that each logical database holds its own keys
+#[tokio::test]
+async fn select_isolates_databases() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+
+    client.select(1).await.unwrap();
+    assert!(client.get("hello").await.unwrap().is_none());
+
+    client.select(0).await.unwrap();
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+
+    let err = client.select(16).await.unwrap_err();
+    assert_eq!("ERR DB index is out of range", err.to_string());
+}
+
+/// test that `SWAPDB` exchanges the keys of two databases
+#[tokio::test]
+async fn swapdb_exchanges_keys() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("first", "0".into()).await.unwrap();
+    client.select(1).await.unwrap();
+    client.set("second", "1".into()).await.unwrap();
+
+    client.swapdb(0, 1).await.unwrap();
+
+    assert_eq!(b"0", &client.get("first").await.unwrap().unwrap()[..]);
+    assert!(client.get("second").await.unwrap().is_none());
+
+    client.select(0).await.unwrap();
+    assert_eq!(b"1", &client.get("second").await.unwrap().unwrap()[..]);
+    assert!(client.get("first").await.unwrap().is_none());
+
+    assert!(client.swapdb(0, 16).await.is_err());
+}
+
+/// test that `RENAMENX` does not overwrite an existing key, unlike `RENAME`
+#[tokio::test]
+async fn rename_keys() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    client.set("taken", "value".into()).await.unwrap();
+
+    assert!(!client.renamenx("hello", "taken").await.unwrap());
+    assert_eq!(b"value", &client.get("taken").await.unwrap().unwrap()[..]);
+
+    client.rename("hello", "taken").await.unwrap();
+    assert!(client.get("hello").await.unwrap().is_none());
+    assert_eq!(b"world", &client.get("taken").await.unwrap().unwrap()[..]);
+
+    assert!(client.renamenx("taken", "hello").await.unwrap());
+    assert!(client.rename("missing", "other").await.is_err());
+}
+
+#[tokio::test]
+async fn expire_and_persist_keys() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    client.set("kept", "value".into()).await.unwrap();
+
+    assert!(!client.expire("missing", 10, None).await.unwrap());
+    assert!(!client.persist("kept").await.unwrap());
+    assert!(client.expire("kept", 10, None).await.unwrap());
+    assert!(client.persist("kept").await.unwrap());
+
+    assert!(client.pexpire("hello", 20, None).await.unwrap());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(client.get("hello").await.unwrap().is_none());
+    assert!(client.get("kept").await.unwrap().is_some());
+
+    // Not positive timeouts delete the key at once.
+    assert!(client.expire("kept", -1, None).await.unwrap());
+    assert!(client.get("kept").await.unwrap().is_none());
+    assert!(client.expire("any", i64::MAX, None).await.is_err());
+
+    // Absolute times, in the past delete the key at once too.
+    client.set("hello", "world".into()).await.unwrap();
+    let unix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let at = i64::try_from(unix.as_millis()).unwrap() + 20;
+    assert!(
+        client
+            .pexpireat("hello", at, Some(ExpireCondition::Nx))
+            .await
+            .unwrap()
+    );
+    assert!(
+        !client
+            .pexpireat("hello", at, Some(ExpireCondition::Nx))
+            .await
+            .unwrap()
+    );
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(client.get("hello").await.unwrap().is_none());
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(client.pexpireat("hello", 1, None).await.unwrap());
+    assert!(client.get("hello").await.unwrap().is_none());
+    assert!(!client.pexpireat("missing", at, None).await.unwrap());
+}
+
+#[tokio::test]
+async fn expire_with_conditions() {
+    use ExpireCondition::{Gt, Lt, Nx, Xx};
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+
+    // Without timeout, the key is considered to never expire.
+    assert!(!client.expire("hello", 100, Some(Xx)).await.unwrap());
+    assert!(!client.expire("hello", 100, Some(Gt)).await.unwrap());
+    assert!(client.expire("hello", 100, Some(Nx)).await.unwrap());
+
+    assert!(!client.expire("hello", 200, Some(Nx)).await.unwrap());
+    assert!(client.expire("hello", 200, Some(Xx)).await.unwrap());
+    assert!(!client.pexpire("hello", 100_000, Some(Gt)).await.unwrap());
+    assert!(client.expire("hello", 300, Some(Gt)).await.unwrap());
+    assert!(!client.expire("hello", 400, Some(Lt)).await.unwrap());
+    assert!(client.expire("hello", 50, Some(Lt)).await.unwrap());
+
+    assert!(client.persist("hello").await.unwrap());
+    assert!(client.expire("hello", 50, Some(Lt)).await.unwrap());
+    assert!(!client.expire("missing", 50, Some(Lt)).await.unwrap());
+}
+
+#[tokio::test]
+async fn getex_sets_the_expiration() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    client.set("kept", "value".into()).await.unwrap();
+
+    let at = SystemTime::now() + Duration::from_millis(200);
+    let value = client.getex("hello", Some(Expiration::At(at))).await;
+    assert_eq!(b"world", &value.unwrap().unwrap()[..]);
+    let value = client.getex("kept", Some(Expiration::After(Duration::from_millis(200))));
+    assert_eq!(b"value", &value.await.unwrap().unwrap()[..]);
+    assert!(client.getex("missing", None).await.unwrap().is_none());
+
+    // The key is not persisted by being read.
+    assert!(client.getex("kept", None).await.unwrap().is_some());
+    let value = client.getex("kept", Some(Expiration::Persist)).await;
+    assert_eq!(b"value", &value.unwrap().unwrap()[..]);
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(client.get("hello").await.unwrap().is_some());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(client.get("hello").await.unwrap().is_none());
+    assert!(client.get("kept").await.unwrap().is_some());
+
+    // A time in the past deletes the key once read.
+    let past = Some(Expiration::At(UNIX_EPOCH));
+    assert_eq!(
+        b"value",
+        &client.getex("kept", past).await.unwrap().unwrap()[..]
+    );
+    assert!(client.get("kept").await.unwrap().is_none());
+}
+
+/// test that `MOVE` does not overwrite a key existing in the destination
+#[tokio::test]
+async fn move_key_between_databases() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    client.set("taken", "source".into()).await.unwrap();
+    client.select(1).await.unwrap();
+    client.set("taken", "destination".into()).await.unwrap();
+    client.select(0).await.unwrap();
+
+    assert!(client.move_key("hello", 1).await.unwrap());
+    assert!(client.get("hello").await.unwrap().is_none());
+    assert!(!client.move_key("missing", 1).await.unwrap());
+
+    // The destination already holds the key, nothing is moved.
+    assert!(!client.move_key("taken", 1).await.unwrap());
+    assert_eq!(b"source", &client.get("taken").await.unwrap().unwrap()[..]);
+
+    client.select(1).await.unwrap();
+    assert_eq!(b"world", &client.get("hello").await.unwrap().unwrap()[..]);
+    assert_eq!(
+        b"destination",
+        &client.get("taken").await.unwrap().unwrap()[..]
+    );
+}
+
+/// test that `COMMAND` describes the supported commands
+#[tokio::test]
+async fn command_info_describes_commands() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let commands = client.command_info().await.unwrap();
+
+    let get = commands.iter().find(|cmd| cmd.name == "get").unwrap();
+    assert_eq!(2, get.arity);
+    assert!(get.flags.iter().any(|flag| flag == "readonly"));
+
+    let set = commands.iter().find(|cmd| cmd.name == "set").unwrap();
+    assert_eq!(-3, set.arity);
+    assert!(set.flags.iter().any(|flag| flag == "write"));
+
+    let subscribe = commands.iter().find(|cmd| cmd.name == "subscribe").unwrap();
+    assert!(subscribe.flags.iter().any(|flag| flag == "pubsub"));
+}
+
+/// test that the command flags, sent as sets under RESP3, are decoded
+#[tokio::test]
+async fn command_info_under_resp3() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.hello(Some(3)).await.unwrap();
+    let commands = client.command_info().await.unwrap();
+
+    let get = commands.iter().find(|cmd| cmd.name == "get").unwrap();
+    assert!(get.flags.iter().any(|flag| flag == "readonly"));
+
+    let docs = client.command_docs(&["ping"]).await.unwrap();
+    assert!(docs[0].1.arguments[0].optional);
+}
+
+/// test that `COMMAND COUNT` agrees with `COMMAND`
+#[tokio::test]
+async fn command_count_matches_command_info() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let count = client.command_count().await.unwrap();
+    let commands = client.command_info().await.unwrap();
+
+    assert_eq!(commands.len() as u64, count);
+}
+
+/// test that `COMMAND DOCS` documents the expiration options of `SET`
+#[tokio::test]
+async fn command_docs_for_set() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let docs = client
+        .command_docs(&["set", "no-such-command"])
+        .await
+        .unwrap();
+
+    assert_eq!(1, docs.len());
+    let (name, docs) = &docs[0];
+    assert_eq!("set", name);
+    assert!(!docs.summary.is_empty());
+
+    let expiration = docs
+        .arguments
+        .iter()
+        .find(|arg| arg.name == "expiration")
+        .unwrap();
+    assert!(expiration.optional);
+    let tokens: Vec<_> = expiration
+        .arguments
+        .iter()
+        .filter_map(|arg| arg.token.as_deref())
+        .collect();
+    assert_eq!(vec!["EX", "PX", "EXAT", "PXAT"], tokens);
+
+    // Without arguments, every command is documented.
+    let all = client.command_docs(&[]).await.unwrap();
+    assert_eq!(client.command_count().await.unwrap(), all.len() as u64);
+}
+
+/// test that `COMMAND GETKEYS` finds the keys of an invocation
+#[tokio::test]
+async fn command_getkeys_finds_the_keys() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let keys = client.command_getkeys(&["GET", "hello"]).await.unwrap();
+    assert_eq!(vec!["hello"], keys);
+    let keys = client
+        .command_getkeys(&["set", "hello", "world", "EX", "10"])
+        .await
+        .unwrap();
+    assert_eq!(vec!["hello"], keys);
+    let keys = client.command_getkeys(&["rename", "a", "b"]).await.unwrap();
+    assert_eq!(vec!["a", "b"], keys);
+    let keys = client
+        .command_getkeys(&["watch", "a", "b", "c"])
+        .await
+        .unwrap();
+    assert_eq!(vec!["a", "b", "c"], keys);
+    let keys = client
+        .command_getkeys(&["xread", "COUNT", "2", "STREAMS", "a", "b", "0", "0"])
+        .await
+        .unwrap();
+    assert_eq!(vec!["a", "b"], keys);
+
+    // Nothing is run.
+    assert_eq!(None, client.get("hello").await.unwrap());
+
+    let err = client.command_getkeys(&["ping"]).await.unwrap_err();
+    assert_eq!("ERR The command has no key arguments", err.to_string());
+    let err = client.command_getkeys(&["get"]).await.unwrap_err();
+    assert_eq!(
+        "ERR Invalid number of arguments specified for command",
+        err.to_string()
+    );
+    let err = client
+        .command_getkeys(&["mset", "a", "1"])
+        .await
+        .unwrap_err();
+    assert_eq!("ERR Invalid command specified", err.to_string());
+}
+
+#[tokio::test]
+async fn info_reports_clients_and_keyspace() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let _other = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+
+    let info = client.info(None).await.unwrap();
+    assert!(info.starts_with("# Server\r\n"));
+    let connected = info
+        .lines()
+        .find_map(|line| line.strip_prefix("connected_clients:"))
+        .unwrap();
+    assert_eq!(connected, "2");
+    assert!(info.contains("db0:keys=1,expires=0\r\n"));
+
+    let clients = client.info(Some("clients")).await.unwrap();
+    assert_eq!(clients, "# Clients\r\nconnected_clients:2\r\n");
+}
+
+#[tokio::test]
+async fn info_under_resp3() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.hello(Some(3)).await.unwrap();
+    let info = client.info(Some("server")).await.unwrap();
+
+    assert!(info.starts_with("# Server\r\n"));
+}
+
+#[tokio::test]
+async fn expired_key_is_removed_when_read_without_active_expire() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.debug_set_active_expire(false).await.unwrap();
+    client
+        .set_expires("hello", "world".into(), Duration::from_millis(10))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // The expired key is kept until read.
+    let info = client.info(Some("keyspace")).await.unwrap();
+    assert!(info.contains("db0:keys=1,expires=1"), "{info}");
+    assert_eq!(client.get("hello").await.unwrap(), None);
+    let info = client.info(Some("keyspace")).await.unwrap();
+    assert!(!info.contains("db0:"), "{info}");
+}
+
+#[tokio::test]
+async fn tracking_invalidates_modified_keys() {
+    let (addr, _) = start_server().await;
+    let mut tracking = Client::connect(addr).await.unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+
+    // Invalidations are pushed, which requires RESP3.
+    assert!(tracking.client_tracking(true).await.is_err());
+    tracking.hello(Some(3)).await.unwrap();
+    tracking.client_tracking(true).await.unwrap();
+    other.set("hello", "world".into()).await.unwrap();
+    assert!(tracking.get("hello").await.unwrap().is_some());
+    // Keys not read are not tracked.
+    other.set("foo", "bar".into()).await.unwrap();
+    other.set("hello", "again".into()).await.unwrap();
+
+    assert_eq!(
+        tracking.next_invalidation().await.unwrap().as_deref(),
+        Some("hello")
+    );
+    // A key is invalidated once, until read again.
+    other.set("hello", "world".into()).await.unwrap();
+    assert!(tracking.get("foo").await.unwrap().is_some());
+    other.set("foo", "baz".into()).await.unwrap();
+    assert_eq!(
+        tracking.next_invalidation().await.unwrap().as_deref(),
+        Some("foo")
+    );
+}
+
+#[tokio::test]
+async fn scan_returns_keys_present_throughout() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    for i in 0..200 {
+        client
+            .set(&format!("key{i}"), "value".into())
+            .await
+            .unwrap();
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    let mut cursor = 0;
+    let mut calls = 0;
+    loop {
+        let (next, keys) = client.scan(cursor, None, Some(10)).await.unwrap();
+        seen.extend(keys);
+        calls += 1;
+        // Keys come and go during the iteration: the first half is moved away, and
+        // as many keys are added, making the keyspace grow.
+        if calls <= 10 {
+            for i in (calls - 1) * 10..calls * 10 {
+                client.move_key(&format!("key{i}"), 1).await.unwrap();
+            }
+            for i in 0..50 {
+                let key = format!("new{calls}-{i}");
+                client.set(&key, "value".into()).await.unwrap();
+            }
+        }
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+
+    for i in 100..200 {
+        assert!(seen.contains(&format!("key{i}")), "key{i}");
+    }
+
+    // The keys not matching are visited, but not returned.
+    let (cursor, keys) = client.scan(0, Some("key19?"), Some(1000)).await.unwrap();
+    let mut keys: Vec<_> = keys.iter().map(String::as_str).collect();
+    keys.sort_unstable();
+    assert_eq!(
+        keys,
+        [
+            "key190", "key191", "key192", "key193", "key194", "key195", "key196", "key197",
+            "key198", "key199"
+        ]
+    );
+    assert_eq!(cursor, 0);
+}
+
+#[tokio::test]
+async fn dump_and_restore_round_trip() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let value = Bytes::from_static(b"\x00\xffbinary\r\n\x80");
+    client.set("string", value.clone()).await.unwrap();
+    let payload = client.dump("string").await.unwrap().unwrap();
+    client
+        .restore("copy", None, payload.clone(), false)
+        .await
+        .unwrap();
+    assert_eq!(client.get("copy").await.unwrap(), Some(value));
+
+    // The target must not exist, unless replaced.
+    let err = client
+        .restore("copy", None, payload.clone(), false)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().starts_with("BUSYKEY"), "{err}");
+    client.set("other", "value".into()).await.unwrap();
+    let other = client.dump("other").await.unwrap().unwrap();
+    client.restore("copy", None, other, true).await.unwrap();
+    assert_eq!(client.get("copy").await.unwrap().unwrap(), "value");
+
+    let fields = vec![(Bytes::from("field"), Bytes::from_static(b"\x00\x01"))];
+    let id = client.xadd("stream", "*", fields).await.unwrap();
+    let payload = client.dump("stream").await.unwrap().unwrap();
+    client
+        .restore(
+            "stream-copy",
+            Some(Duration::from_secs(100)),
+            payload.clone(),
+            false,
+        )
+        .await
+        .unwrap();
+    let read = client.xread(None, &[("stream-copy", "0")]).await.unwrap();
+    assert_eq!(read[0].1[0].id, id);
+    assert_eq!(read[0].1[0].fields[0].1, Bytes::from_static(b"\x00\x01"));
+    let info = client.info(Some("keyspace")).await.unwrap();
+    assert!(info.contains("db0:keys=5,expires=1"), "{info}");
+
+    assert_eq!(client.dump("missing").await.unwrap(), None);
+    let mut corrupted = payload.to_vec();
+    corrupted[1] ^= 1;
+    let err = client
+        .restore("corrupted", None, corrupted.into(), false)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("checksum"), "{err}");
+}
+
+#[tokio::test]
+async fn config_get_matches_pattern() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let params = client.config_get("max*").await.unwrap();
+    let names: Vec<_> = params.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["maxclients", "maxmemory", "maxmemory-policy"]);
+    assert_eq!(params[1].1, "0");
+
+    assert!(client.config_get("nosuchparam").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn connections_beyond_the_limit_wait() {
+    let config = server::Config {
+        max_connections: 2,
+        ..server::Config::default()
+    };
+    let (addr, _) = start_server_with_config(config).await;
+
+    let mut first = Client::connect(addr).await.unwrap();
+    let params = first.config_get("maxclients").await.unwrap();
+    assert_eq!(params[0].1, "2");
+    let mut second = Client::connect(addr).await.unwrap();
+    second.ping(None).await.unwrap();
+
+    // The third connection is not served until another one is closed.
+    let mut third = Client::connect(addr).await.unwrap();
+    let waiting = tokio::time::timeout(Duration::from_millis(200), third.ping(None)).await;
+    assert!(waiting.is_err());
+
+    drop(first);
+    let pong = tokio::time::timeout(Duration::from_secs(1), third.ping(None)).await;
+    assert_eq!(pong.unwrap().unwrap(), "PONG");
+}
+
+#[tokio::test]
+async fn idle_connections_are_closed() {
+    let config = server::Config {
+        idle_timeout: Some(Duration::from_millis(200)),
+        ..server::Config::default()
+    };
+    let (addr, _) = start_server_with_config(config).await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let subscriber = Client::connect(addr).await.unwrap();
+    let mut subscriber = subscriber.subscribe(vec!["hello".into()]).await.unwrap();
+    client.ping(None).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert!(client.ping(None).await.is_err());
+
+    // Subscribers are not idle while waiting for messages.
+    let mut publisher = Client::connect(addr).await.unwrap();
+    assert_eq!(publisher.publish("hello", "world".into()).await.unwrap(), 1);
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(&message.content[..], b"world");
+}
+
+#[tokio::test]
+async fn config_set_then_get() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.config_set("maxmemory", "1048576").await.unwrap();
+    client.config_set("SAVE", "900  1").await.unwrap();
+
+    let params = client.config_get("maxmemory").await.unwrap();
+    assert_eq!(params, [("maxmemory".to_string(), "1048576".to_string())]);
+    let params = client.config_get("save").await.unwrap();
+    assert_eq!(params, [("save".to_string(), "900 1".to_string())]);
+
+    // Invalid values, fixed and unknown parameters are rejected.
+    assert!(client.config_set("maxmemory", "lots").await.is_err());
+    assert!(client.config_set("databases", "4").await.is_err());
+    assert!(client.config_set("nosuchparam", "1").await.is_err());
+    let params = client.config_get("maxmemory").await.unwrap();
+    assert_eq!(params[0].1, "1048576");
+
+    // Channels are allocated with their capacity, which is bounded.
+    client
+        .config_set("pubsub-capacity", "1048576")
+        .await
+        .unwrap();
+    let err = client.config_set("pubsub-capacity", "1048577").await;
+    assert!(
+        err.unwrap_err()
+            .to_string()
+            .contains("between 1 and 1048576")
+    );
+    assert!(client.config_set("pubsub-capacity", "0").await.is_err());
+    let params = client.config_get("pubsub-capacity").await.unwrap();
+    assert_eq!(params[0].1, "1048576");
+    let subscriber = Client::connect(addr).await.unwrap();
+    let mut subscriber = subscriber.subscribe(vec!["hello".into()]).await.unwrap();
+    assert_eq!(client.publish("hello", "world".into()).await.unwrap(), 1);
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(&message.content[..], b"world");
+}
+
+#[tokio::test]
+async fn del_keys() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    client.set("hello", "world".into()).await.unwrap();
+    client
+        .set_expires("foo", "bar".into(), Duration::from_secs(100))
+        .await
+        .unwrap();
+    assert_eq!(client.del(&["hello", "foo", "missing"]).await.unwrap(), 2);
+    assert!(client.get("hello").await.unwrap().is_none());
+    assert_eq!(client.del(&["hello"]).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn maxmemory_evicts_least_recently_accessed_key() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let value = Bytes::from(vec![b'x'; 100]);
+    client.set("a", value.clone()).await.unwrap();
+    // Room for three keys like `a`, but not four.
+    let usage = client.memory_usage("a").await.unwrap().unwrap();
+    let maxmemory = (3 * usage + usage / 2).to_string();
+    client.config_set("maxmemory", &maxmemory).await.unwrap();
+
+    // Writes are refused once the limit is exceeded, by default.
+    client.set("b", value.clone()).await.unwrap();
+    client.set("c", value.clone()).await.unwrap();
+    client.set("d", value.clone()).await.unwrap();
+    let err = client.set("e", value.clone()).await.unwrap_err();
+    assert!(err.to_string().starts_with("OOM"));
+
+    client
+        .config_set("maxmemory-policy", "allkeys-lru")
+        .await
+        .unwrap();
+    client.get("a").await.unwrap();
+    client.set("e", value.clone()).await.unwrap();
+
+    // `b` and `c` were accessed the least recently.
+    for (key, kept) in [
+        ("a", true),
+        ("b", false),
+        ("c", false),
+        ("d", true),
+        ("e", true),
+    ] {
+        assert_eq!(client.get(key).await.unwrap().is_some(), kept, "{key}");
+    }
+    let info = client.info(Some("stats")).await.unwrap();
+    assert!(!info.contains("evicted_keys:0\r\n"));
+}
+
+#[tokio::test]
+async fn maxmemory_evicts_random_keys() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let value = Bytes::from(vec![b'x'; 100]);
+    client.set("key0", value.clone()).await.unwrap();
+    // Room for ten keys like `key0`.
+    let usage = client.memory_usage("key0").await.unwrap().unwrap();
+    let maxmemory = 10 * usage + usage / 2;
+    client
+        .config_set("maxmemory", &maxmemory.to_string())
+        .await
+        .unwrap();
+    client
+        .config_set("maxmemory-policy", "allkeys-random")
+        .await
+        .unwrap();
+
+    for i in 1..100 {
+        client.set(&format!("key{i}"), value.clone()).await.unwrap();
+    }
+
+    let info = client.info(Some("memory")).await.unwrap();
+    let used_memory: u64 = info
+        .lines()
+        .find_map(|line| line.strip_prefix("used_memory:"))
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(used_memory <= maxmemory, "{info}");
+    assert!(info.contains("maxmemory_policy:allkeys-random\r\n"));
+    // The key just written is never evicted.
+    assert!(client.get("key99").await.unwrap().is_some());
+    let info = client.info(Some("keyspace")).await.unwrap();
+    assert!(info.contains("db0:keys=10,"), "{info}");
+}
+
+#[tokio::test]
+async fn keyspace_notifications_publish_key_events() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client
+        .config_set("notify-keyspace-events", "E$x")
+        .await
+        .unwrap();
+    let params = client.config_get("notify-keyspace-events").await.unwrap();
+    assert_eq!(params[0].1, "$xE");
+    assert!(
+        client
+            .config_set("notify-keyspace-events", "Ew")
+            .await
+            .is_err()
+    );
+
+    let subscriber = Client::connect(addr).await.unwrap();
+    let channels = vec!["__keyevent@0__:set".into(), "__keyevent@0__:expired".into()];
+    let mut subscriber = subscriber.subscribe(channels).await.unwrap();
+
+    client.set("hello", "world".into()).await.unwrap();
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(message.channel, "__keyevent@0__:set");
+    assert_eq!(&message.content[..], b"hello");
+
+    client
+        .set_expires("short", "lived".into(), Duration::from_millis(10))
+        .await
+        .unwrap();
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(message.channel, "__keyevent@0__:set");
+    assert_eq!(&message.content[..], b"short");
+    let message = subscriber.next_message().await.unwrap().unwrap();
+    assert_eq!(message.channel, "__keyevent@0__:expired");
+    assert_eq!(&message.content[..], b"short");
+}
+
+#[tokio::test]
+async fn lastsave_defaults_to_server_start() {
+    let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let lastsave = client.lastsave().await.unwrap();
+
+    let after = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    assert!((before.as_secs()..=after.as_secs()).contains(&lastsave));
+}
+
+#[tokio::test]
+async fn debug_sleep_delays_reply() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let start = Instant::now();
+    client
+        .debug_sleep(Duration::from_millis(200))
+        .await
+        .unwrap();
+
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn commands_exceeding_the_time_limit_are_interrupted() {
+    let config = server::Config {
+        command_time_limit: Some(Duration::from_millis(100)),
+        ..server::Config::default()
+    };
+    let (addr, _) = start_server_with_config(config).await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let start = Instant::now();
+    let err = client
+        .debug_sleep(Duration::from_secs(5))
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "ERR command exceeded time limit");
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // The connection is still usable afterwards.
+    assert_eq!(client.ping(None).await.unwrap(), "PONG");
+
+    // A transaction is applied as a whole, however long it takes.
+    client.multi().await.unwrap();
+    client
+        .queue(DebugCommand::Sleep {
+            duration: Duration::from_millis(300),
+        })
+        .await
+        .unwrap();
+    client
+        .queue(Set::new("hello", "world".into(), None))
+        .await
+        .unwrap();
+    let replies = client.exec().await.unwrap().unwrap();
+    assert_eq!(
+        replies,
+        [Frame::Simple("OK".into()), Frame::Simple("OK".into())]
+    );
+    assert_eq!(client.get("hello").await.unwrap().unwrap(), "world");
+
+    client.config_set("command-time-limit", "0").await.unwrap();
+    client
+        .debug_sleep(Duration::from_millis(200))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn shutdown_does_not_wait_past_the_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let config = server::Config {
+        shutdown_timeout: Duration::from_millis(200),
+        ..server::Config::default()
+    };
+    let server = tokio::spawn(server::run_with_config(listener, stopped, config));
+
+    let mut client = Client::connect(addr).await.unwrap();
+    tokio::spawn(async move { client.debug_sleep(Duration::from_secs(30)).await });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let start = Instant::now();
+    stop.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn command_timeout_on_hung_server() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr)
+        .await
+        .unwrap()
+        .with_command_timeout(Duration::from_millis(100));
+    client.set("hello", "world".into()).await.unwrap();
+
+    let start = Instant::now();
+    let err = client
+        .debug_sleep(Duration::from_secs(2))
+        .await
+        .unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    let err = err.downcast::<std::io::Error>().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+    // The late reply must not be taken for the reply to another command.
+    assert!(client.get("hello").await.is_err());
+}
+
+#[tokio::test]
+async fn command_timeout_applies_to_pipelines() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr)
+        .await
+        .unwrap()
+        .with_command_timeout(Duration::from_millis(100));
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(client.get_multi(&["hello"]).await.unwrap().len(), 1);
+
+    let start = Instant::now();
+    let mut pipeline = client.pipeline();
+    pipeline
+        .push(DebugCommand::Sleep {
+            duration: Duration::from_secs(2),
+        })
+        .get("hello");
+    let err = pipeline.execute().await.unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(1));
+    let err = err.downcast::<std::io::Error>().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+    // The late replies must not be taken for the replies to other commands.
+    assert!(client.get_multi(&["hello"]).await.is_err());
+}
+
+#[tokio::test]
+async fn debug_object_reports_encoding() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("counter", "12345".into()).await.unwrap();
+    client.set("short", "hello".into()).await.unwrap();
+    client.set("long", "x".repeat(100).into()).await.unwrap();
+
+    let details = client.debug_object("counter").await.unwrap();
+    assert!(details.contains("encoding:int"), "{details}");
+    let details = client.debug_object("short").await.unwrap();
+    assert!(details.contains("encoding:embstr"), "{details}");
+    assert!(details.contains("serializedlength:5"), "{details}");
+    let details = client.debug_object("long").await.unwrap();
+    assert!(details.contains("encoding:raw"), "{details}");
+
+    let err = client.debug_object("missing").await.unwrap_err();
+    assert_eq!(err.to_string(), "ERR no such key");
+}
+
+/// test that small integers are reported as shared by `OBJECT REFCOUNT`
+#[tokio::test]
+async fn object_refcount_of_shared_integers() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("small", "42".into()).await.unwrap();
+    client.set("large", "12345".into()).await.unwrap();
+    client.set("text", "hello".into()).await.unwrap();
+
+    let shared = client.object_refcount("small").await.unwrap();
+    assert_eq!(Some(i32::MAX as u64), shared);
+    assert_eq!(Some(1), client.object_refcount("large").await.unwrap());
+    assert_eq!(Some(1), client.object_refcount("text").await.unwrap());
+    assert_eq!(None, client.object_refcount("missing").await.unwrap());
+
+    let details = client.debug_object("small").await.unwrap();
+    assert!(
+        details.contains(&format!("refcount:{}", i32::MAX)),
+        "{details}"
+    );
+}
+
+/// test that `OBJECT FREQ` counts the accesses under an LFU policy
+#[tokio::test]
+async fn object_freq_counts_accesses() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+
+    let err = client.object_freq("hello").await.unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("ERR An LFU maxmemory policy is not selected")
+    );
+
+    client
+        .config_set("maxmemory-policy", "allkeys-lfu")
+        .await
+        .unwrap();
+    let initial = client.object_freq("hello").await.unwrap().unwrap();
+    for _ in 0..3 {
+        client.get("hello").await.unwrap();
+    }
+    // `OBJECT FREQ` itself is not an access.
+    assert_eq!(
+        Some(initial + 3),
+        client.object_freq("hello").await.unwrap()
+    );
+    assert_eq!(None, client.object_freq("missing").await.unwrap());
+}
+
+#[tokio::test]
+async fn slowlog_records_slow_commands() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client
+        .config_set("slowlog-log-slower-than", "100000")
+        .await
+        .unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    client
+        .debug_sleep(Duration::from_millis(150))
+        .await
+        .unwrap();
+
+    let entries = client.slowlog_get(None).await.unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].args, ["debug", "sleep", "0.15"]);
+    assert!(entries[0].duration >= Duration::from_millis(150));
+    assert_eq!(client.slowlog_get(Some(-1)).await.unwrap().len(), 1);
+    assert!(client.slowlog_get(Some(0)).await.unwrap().is_empty());
+    assert_eq!(client.slowlog_len().await.unwrap(), 1);
+
+    client.slowlog_reset().await.unwrap();
+    assert_eq!(client.slowlog_len().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn monitor_receives_commands() {
+    let (addr, _) = start_server().await;
+
+    let mut monitor = Client::connect(addr)
+        .await
+        .unwrap()
+        .monitor()
+        .await
+        .unwrap();
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+
+    let line = monitor.next_command().await.unwrap().unwrap();
+    assert!(line.ends_with(r#"] "set" "hello" "world""#), "{line}");
+    assert!(line.contains(" [0 127.0.0.1:"), "{line}");
+}
+
+#[tokio::test]
+async fn keepalive_pings_idle_connection() {
+    let (addr, _) = start_server().await;
+
+    let mut monitor = Client::connect(addr)
+        .await
+        .unwrap()
+        .monitor()
+        .await
+        .unwrap();
+    let mut client = Client::builder(addr.to_string())
+        .keepalive(Duration::from_millis(50))
+        .connect()
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        let line = monitor.next_command().await.unwrap().unwrap();
+        assert!(line.ends_with(r#"] "ping""#), "{line}");
+    }
+
+    // Commands are not interleaved with the pings.
+    for i in 0..20 {
+        client.set("hello", i.to_string().into()).await.unwrap();
+        assert_eq!(client.get("hello").await.unwrap().unwrap(), i.to_string());
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
+
+#[tokio::test]
+async fn keepalive_does_not_ping_within_transactions() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::builder(addr.to_string())
+        .keepalive(Duration::from_millis(20))
+        .connect()
+        .await
+        .unwrap();
+
+    client.multi().await.unwrap();
+    client
+        .queue(Set::new("hello", "world".into(), None))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let replies = client.exec().await.unwrap().unwrap();
+    assert_eq!(replies, [Frame::Simple("OK".into())]);
+
+    // Pinged again once the transaction ended.
+    client.multi().await.unwrap();
+    client.discard().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(client.get("hello").await.unwrap().unwrap(), "world");
+}
+
+#[tokio::test]
+async fn keepalive_keeps_invalidations_received_while_pinging() {
+    let (addr, _) = start_server().await;
+    let mut tracking = Client::builder(addr.to_string())
+        .keepalive(Duration::from_millis(20))
+        .connect()
+        .await
+        .unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+
+    tracking.hello(Some(3)).await.unwrap();
+    tracking.client_tracking(true).await.unwrap();
+    other.set("hello", "world".into()).await.unwrap();
+    assert!(tracking.get("hello").await.unwrap().is_some());
+    other.set("hello", "again".into()).await.unwrap();
+    // Pushed while the connection is idle, so read along with a `PONG`.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(tracking.ping(None).await.unwrap(), "PONG");
+    assert_eq!(
+        tracking.next_invalidation().await.unwrap().as_deref(),
+        Some("hello")
+    );
+}
+
+#[tokio::test]
+async fn wait_without_replicas() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+
+    let acknowledged = client.wait(1, Duration::from_millis(100)).await.unwrap();
+    assert_eq!(acknowledged, 0);
+}
+
+#[tokio::test]
+async fn replica_follows_primary() {
+    let (primary_addr, _) = start_server().await;
+    let (replica_addr, _) = start_server().await;
+
+    let mut primary = Client::connect(primary_addr).await.unwrap();
+    // Sent with the snapshot.
+    primary.set("before", "snapshot".into()).await.unwrap();
+
+    let mut replica = Client::connect(replica_addr).await.unwrap();
+    replica.set("stale", "value".into()).await.unwrap();
+    replica
+        .replicaof("127.0.0.1", primary_addr.port())
+        .await
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while replica.get("before").await.unwrap().is_none() {
+        assert!(Instant::now() < deadline, "snapshot not loaded");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    // The keys of the replica are replaced by those of the primary.
+    assert_eq!(replica.get("stale").await.unwrap(), None);
+
+    // Sent as they are applied, to the database they apply to.
+    primary.select(1).await.unwrap();
+    primary.set("hello", "world".into()).await.unwrap();
+    replica.select(1).await.unwrap();
+    while replica.get("hello").await.unwrap().is_none() {
+        assert!(Instant::now() < deadline, "write command not replicated");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(
+        replica.get("hello").await.unwrap(),
+        Some(Bytes::from("world"))
+    );
+}
+
+#[tokio::test]
+async fn promoted_replica_accepts_writes() {
+    let (primary_addr, _) = start_server().await;
+    let (replica_addr, _) = start_server().await;
+
+    let mut primary = Client::connect(primary_addr).await.unwrap();
+    primary.set("hello", "world".into()).await.unwrap();
+    let mut replica = Client::connect(replica_addr).await.unwrap();
+    replica
+        .replicaof("127.0.0.1", primary_addr.port())
+        .await
+        .unwrap();
+
+    let info = replica.info(Some("replication")).await.unwrap();
+    assert!(info.contains("role:slave\r\n"), "{info}");
+    assert!(info.contains(&format!("master_port:{}\r\n", primary_addr.port())));
+    let err = replica.set("foo", "bar".into()).await.unwrap_err();
+    assert!(err.to_string().starts_with("READONLY"), "{err}");
+
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while replica.get("hello").await.unwrap().is_none() {
+        assert!(Instant::now() < deadline, "snapshot not loaded");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    replica.replicaof_no_one().await.unwrap();
+    let info = replica.info(Some("replication")).await.unwrap();
+    assert!(info.contains("role:master\r\n"), "{info}");
+    replica.set("foo", "bar".into()).await.unwrap();
+    assert_eq!(replica.get("foo").await.unwrap(), Some(Bytes::from("bar")));
+    // The keys replicated so far are kept, and the primary is not followed anymore.
+    assert_eq!(
+        replica.get("hello").await.unwrap(),
+        Some(Bytes::from("world"))
+    );
+    primary.set("hello", "again".into()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(
+        replica.get("hello").await.unwrap(),
+        Some(Bytes::from("world"))
+    );
+}
+
+#[tokio::test]
+async fn memory_usage_grows_with_value() {
+    let (addr, _) = start_server().await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("small", "x".into()).await.unwrap();
+    client.set("large", "x".repeat(1000).into()).await.unwrap();
+
+    let small = client.memory_usage("small").await.unwrap().unwrap();
+    let large = client.memory_usage("large").await.unwrap().unwrap();
+    assert_eq!(large - small, 999);
+
+    assert_eq!(client.memory_usage("missing").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn save_writes_snapshot() {
+    let (addr, _) = start_server().await;
+    let dir = std::env::temp_dir().join(format!("save_writes_snapshot-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client
+        .config_set("dir", dir.to_str().unwrap())
+        .await
+        .unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    let started = client.lastsave().await.unwrap();
+
+    // `LASTSAVE` has a resolution of a second.
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    client.save().await.unwrap();
+
+    assert!(client.lastsave().await.unwrap() > started);
+    assert!(std::fs::metadata(dir.join("dump.rdb")).unwrap().len() > 0);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn snapshot_loaded_on_startup() {
+    let path = std::env::temp_dir().join(format!(
+        "snapshot_loaded_on_startup-{}.rdb",
+        std::process::id()
+    ));
+    let config = server::Config {
+        dump_path: Some(path.clone()),
+        ..server::Config::default()
+    };
+
+    let (addr, _) = start_server_with_config(config.clone()).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    client
+        .set_expires("ttl", "soon".into(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    client.save().await.unwrap();
+
+    // A new server restores the keys of the snapshot.
+    let (addr, _) = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(client.get("hello").await.unwrap().unwrap(), "world");
+    assert_eq!(client.get("ttl").await.unwrap().unwrap(), "soon");
+    // Only removed once loaded, which happens before the first reply.
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn bgsave_writes_snapshot() {
+    let (addr, _) = start_server().await;
+    let dir = std::env::temp_dir().join(format!("bgsave_writes_snapshot-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client
+        .config_set("dir", dir.to_str().unwrap())
+        .await
+        .unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    client.bgsave().await.unwrap();
+
+    let path = dir.join("dump.rdb");
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while !path.exists() {
+        assert!(Instant::now() < deadline, "no snapshot written");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn access_log_records_commands() {
+    let path = std::env::temp_dir().join(format!(
+        "access_log_records_commands-{}.log",
+        std::process::id()
+    ));
+    let config = server::Config {
+        access_log: Some(path.clone()),
+        ..server::Config::default()
+    };
+
+    let (addr, _) = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    client.ping(None).await.unwrap();
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let lines: Vec<_> = log.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with(" db=0 cmd=set key=\"hello\""), "{log}");
+    assert!(lines[1].ends_with(" db=0 cmd=ping"), "{log}");
+}
+
+#[tokio::test]
+async fn aof_logs_write_commands() {
+    let path = std::env::temp_dir().join(format!(
+        "aof_logs_write_commands-{}.aof",
+        std::process::id()
+    ));
+    let config = server::Config {
+        aof_path: Some(path.clone()),
+        appendfsync: server::AppendFsync::Always,
+        ..server::Config::default()
+    };
+
+    let (addr, _) = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    // Reads are not logged.
+    client.get("hello").await.unwrap();
+    client.select(1).await.unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+    // Queued commands are logged when executed.
+    client.multi().await.unwrap();
+    client
+        .queue(Set::new("foo", "baz".into(), None))
+        .await
+        .unwrap();
+    client.exec().await.unwrap();
+
+    let aof = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        aof,
+        b"*2\r\n$6\r\nSELECT\r\n$1\r\n0\r\n*3\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n\
+          *2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n*3\r\n$3\r\nset\r\n$3\r\nfoo\r\n$3\r\nbar\r\n\
+          *3\r\n$3\r\nset\r\n$3\r\nfoo\r\n$3\r\nbaz\r\n"
+    );
+}
+
+#[tokio::test]
+async fn aof_logs_absolute_expirations() {
+    let path = std::env::temp_dir().join(format!(
+        "aof_logs_absolute_expirations-{}.aof",
+        std::process::id()
+    ));
+    let config = server::Config {
+        aof_path: Some(path.clone()),
+        appendfsync: server::AppendFsync::Always,
+        ..server::Config::default()
+    };
+
+    let (addr, _) = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    client
+        .set_expires("hello", "world".into(), Duration::from_secs(100))
+        .await
+        .unwrap();
+    client.set("foo", "bar".into()).await.unwrap();
+    client.expire("foo", 100, None).await.unwrap();
+
+    let aof = String::from_utf8(std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // Both timeouts are logged as the Unix time they end at, in milliseconds.
+    let unix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let now = u64::try_from(unix.as_millis()).unwrap();
+    for token in ["\r\npxat\r\n", "\r\npexpireat\r\n$3\r\nfoo\r\n"] {
+        let (_, rest) = aof.split_once(token).expect(token);
+        let at: u64 = rest.lines().nth(1).unwrap().parse().unwrap();
+        assert!((now + 90_000..=now + 100_000).contains(&at), "{aof:?}");
+    }
+    assert!(!aof.contains("\r\npx\r\n"), "{aof:?}");
+    assert!(!aof.contains("\r\nexpire\r\n"), "{aof:?}");
+}
+
+#[tokio::test]
+async fn aof_logs_writes_whose_reply_is_not_read() {
+    let path = std::env::temp_dir().join(format!(
+        "aof_logs_writes_whose_reply_is_not_read-{}.aof",
+        std::process::id()
+    ));
+    let config = server::Config {
+        aof_path: Some(path.clone()),
+        appendfsync: server::AppendFsync::Always,
+        ..server::Config::default()
+    };
+
+    let (addr, _) = start_server_with_config(config).await;
+    // The client goes away before the reply is written.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"SET hello world\r\n").await.unwrap();
+    drop(stream);
+
+    let mut client = Client::connect(addr).await.unwrap();
+    while client.get("hello").await.unwrap().is_none() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let aof = String::from_utf8(std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(aof.ends_with("$5\r\nhello\r\n$5\r\nworld\r\n"), "{aof:?}");
+}
+
+#[tokio::test]
+async fn aof_logs_evicted_keys_as_deleted() {
+    let path = std::env::temp_dir().join(format!(
+        "aof_logs_evicted_keys_as_deleted-{}.aof",
+        std::process::id()
+    ));
+    let config = server::Config {
+        aof_path: Some(path.clone()),
+        ..server::Config::default()
+    };
+
+    let (addr, _) = start_server_with_config(config.clone()).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let value = Bytes::from(vec![b'x'; 100]);
+    client.set("a", value.clone()).await.unwrap();
+    // Room for one key like `a`, but not two.
+    let usage = client.memory_usage("a").await.unwrap().unwrap();
+    let maxmemory = (usage + usage / 2).to_string();
+    client.config_set("maxmemory", &maxmemory).await.unwrap();
+    client
+        .config_set("maxmemory-policy", "allkeys-lru")
+        .await
+        .unwrap();
+    client.set("b", value).await.unwrap();
+    assert!(client.get("a").await.unwrap().is_none());
+
+    // Replayed without the limit, `a` is deleted again.
+    let (addr, _) = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    assert!(client.get("a").await.unwrap().is_none());
+    // Only removed once replayed, which happens before the first reply.
+    std::fs::remove_file(&path).unwrap();
+    assert!(client.get("b").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn waitaof_waits_for_the_local_fsync() {
+    let path = std::env::temp_dir().join(format!(
+        "waitaof_waits_for_the_local_fsync-{}.aof",
+        std::process::id()
+    ));
+    let config = server::Config {
+        aof_path: Some(path.clone()),
+        appendfsync: server::AppendFsync::Always,
+        ..server::Config::default()
+    };
+
+    let (addr, _) = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    let counts = client.waitaof(1, 0, Duration::from_secs(1)).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(counts, (1, 0));
+}
+
+#[tokio::test]
+async fn aof_replayed_on_startup() {
+    let path = std::env::temp_dir().join(format!(
+        "aof_replayed_on_startup-{}.aof",
+        std::process::id()
+    ));
+    // The last command was cut short by a crash.
+    std::fs::write(
+        &path,
+        b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n\
+          *2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n\
+          *3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n\
+          *3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$2\r\nba",
+    )
+    .unwrap();
+    let config = server::Config {
+        aof_path: Some(path.clone()),
+        ..server::Config::default()
+    };
+
+    let (addr, _) = start_server_with_config(config.clone()).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(client.get("hello").await.unwrap().unwrap(), "world");
+    client.select(1).await.unwrap();
+    assert_eq!(client.get("foo").await.unwrap().unwrap(), "bar");
+    client.set("foo", "baz".into()).await.unwrap();
+
+    // A restarted server reproduces the keyspace from the file.
+    let (addr, _) = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    assert_eq!(client.get("hello").await.unwrap().unwrap(), "world");
+    client.select(1).await.unwrap();
+    assert_eq!(client.get("foo").await.unwrap().unwrap(), "baz");
+    // Only removed once replayed, which happens before the first reply.
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn bgrewriteaof_compacts_overwrites() {
+    let path = std::env::temp_dir().join(format!(
+        "bgrewriteaof_compacts_overwrites-{}.aof",
+        std::process::id()
+    ));
+    let config = server::Config {
+        aof_path: Some(path.clone()),
+        ..server::Config::default()
+    };
+
+    let (addr, _) = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    for i in 0..100 {
+        client.set("counter", i.to_string().into()).await.unwrap();
+    }
+    client.bgrewriteaof().await.unwrap();
+
+    let rewritten =
+        b"*2\r\n$6\r\nSELECT\r\n$1\r\n0\r\n*3\r\n$3\r\nSET\r\n$7\r\ncounter\r\n$2\r\n99\r\n";
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while std::fs::read(&path).unwrap() != rewritten {
+        assert!(Instant::now() < deadline, "append-only file not rewritten");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    // Later commands are appended to the rewritten file.
+    client.set("counter", "100".into()).await.unwrap();
+    let aof = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(aof.ends_with(b"*3\r\n$3\r\nset\r\n$7\r\ncounter\r\n$3\r\n100\r\n"));
+}
+
+#[tokio::test]
+async fn bgrewriteaof_keeps_timeouts() {
+    let path = std::env::temp_dir().join(format!(
+        "bgrewriteaof_keeps_timeouts-{}.aof",
+        std::process::id()
+    ));
+    let config = server::Config {
+        aof_path: Some(path.clone()),
+        ..server::Config::default()
+    };
+
+    let (addr, _) = start_server_with_config(config.clone()).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    client
+        .set_expires("hello", "world".into(), Duration::from_secs(100))
+        .await
+        .unwrap();
+    let fields = vec![(Bytes::from("field"), Bytes::from("value"))];
+    client.xadd("stream", "1-1", fields).await.unwrap();
+    client.expire("stream", 100, None).await.unwrap();
+    client.bgrewriteaof().await.unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(1);
+    let aof = loop {
+        let aof = String::from_utf8(std::fs::read(&path).unwrap()).unwrap();
+        // Logged commands are lowercase, rewritten ones uppercase.
+        if aof.contains("\r\nPXAT\r\n") {
+            break aof;
+        }
+        assert!(Instant::now() < deadline, "append-only file not rewritten");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+
+    // Both keys expire at the same Unix time once replayed.
+    let unix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let now = u64::try_from(unix.as_millis()).unwrap();
+    for token in ["\r\nPXAT\r\n", "\r\nPEXPIREAT\r\n$6\r\nstream\r\n"] {
+        let (_, rest) = aof.split_once(token).expect(token);
+        let at: u64 = rest.lines().nth(1).unwrap().parse().unwrap();
+        assert!((now + 90_000..=now + 100_000).contains(&at), "{aof:?}");
+    }
+
+    let (addr, _) = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    assert_eq!(client.get("hello").await.unwrap().unwrap(), "world");
+    // Only removed once replayed, which happens before the first reply.
+    std::fs::remove_file(&path).unwrap();
+    let streams = client.xread(None, &[("stream", "0")]).await.unwrap();
+    assert_eq!(streams[0].1.len(), 1);
+    assert!(
+        !client
+            .expire("stream", 100, Some(ExpireCondition::Nx))
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn pipeline_sets_then_gets() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let mut pipeline = client.pipeline();
+    for i in 0..100 {
+        pipeline.set(&format!("key{i}"), i.to_string().into());
+    }
+    for i in 0..100 {
+        pipeline.get(&format!("key{i}"));
+    }
+    assert_eq!(pipeline.len(), 200);
+
+    let replies = pipeline.execute().await.unwrap();
+    assert_eq!(replies.len(), 200);
+    for reply in &replies[..100] {
+        assert_eq!(*reply.as_ref().unwrap(), "OK");
+    }
+    for (i, reply) in replies[100..].iter().enumerate() {
+        assert_eq!(*reply.as_ref().unwrap(), *i.to_string());
+    }
+}
+
+#[tokio::test]
+async fn pipeline_sets_invalidations_aside() {
+    let (addr, _) = start_server().await;
+    let mut tracking = Client::connect(addr).await.unwrap();
+    let mut other = Client::connect(addr).await.unwrap();
+
+    tracking.hello(Some(3)).await.unwrap();
+    tracking.client_tracking(true).await.unwrap();
+    other.set("hello", "world".into()).await.unwrap();
+    other.set("foo", "bar".into()).await.unwrap();
+    assert!(tracking.get("hello").await.unwrap().is_some());
+    other.set("hello", "again".into()).await.unwrap();
+    // Pushed while the connection is idle, so read before the replies.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let mut pipeline = tracking.pipeline();
+    pipeline.get("foo").ping(None);
+    let replies = pipeline.execute().await.unwrap();
+    assert_eq!(*replies[0].as_ref().unwrap(), "bar");
+    assert_eq!(*replies[1].as_ref().unwrap(), "PONG");
+
+    assert_eq!(
+        tracking.next_invalidation().await.unwrap().as_deref(),
+        Some("hello")
+    );
+    assert_eq!(tracking.ping(None).await.unwrap(), "PONG");
+}
+
+#[tokio::test]
+async fn pipeline_reports_errors_per_command() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let mut pipeline = client.pipeline();
+    pipeline
+        .set("hello", "world".into())
+        .push(Select::new(100))
+        .get("hello");
+    let replies = pipeline.execute().await.unwrap();
+
+    assert_eq!(*replies[0].as_ref().unwrap(), "OK");
+    assert_eq!(
+        replies[1].as_ref().unwrap_err().to_string(),
+        "ERR DB index is out of range"
+    );
+    assert_eq!(*replies[2].as_ref().unwrap(), "world");
+
+    // The connection is still usable afterwards.
+    assert_eq!(client.get("hello").await.unwrap().unwrap(), "world");
+}
+
+#[tokio::test]
+async fn get_multi_pipelines_gets() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("a", "1".into()).await.unwrap();
+    client.set("c", "3".into()).await.unwrap();
+
+    let values = client.get_multi(&["a", "b", "c", "d"]).await.unwrap();
+    assert_eq!(values, [Some("1".into()), None, Some("3".into()), None]);
+    assert!(client.get_multi(&[]).await.unwrap().is_empty());
+
+    // An error reply fails the whole call.
+    let config = server::Config {
+        password: Some("secret".into()),
+        ..server::Config::default()
+    };
+    let (addr, _) = start_server_with_config(config).await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let err = client.get_multi(&["a", "b"]).await.unwrap_err();
+    assert_eq!(err.to_string(), "NOAUTH Authentication required.");
+}
+
+#[tokio::test]
+async fn pool_shared_by_concurrent_tasks() {
+    let (addr, _) = start_server().await;
+    let pool = Pool::new(addr.to_string(), 3);
+
+    let tasks: Vec<_> = (0..20)
+        .map(|i| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let mut client = pool.get().await.unwrap();
+                let key = format!("key{i}");
+                client.set(&key, i.to_string().into()).await.unwrap();
+                let value = client.get(&key).await.unwrap().unwrap();
+                assert_eq!(value, i.to_string());
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    // No more connections than the size of the pool were established.
+    let idle = pool.idle();
+    assert!((1..=3).contains(&idle));
+
+    pool.get().await.unwrap().discard();
+    assert_eq!(pool.idle(), idle - 1);
+}
+
+#[tokio::test]
+async fn pool_discards_clients_not_idle() {
+    let (addr, _) = start_server().await;
+    let pool = Pool::new(addr.to_string(), 1);
+
+    let mut client = pool.get().await.unwrap();
+    client.watch(&["hello"]).await.unwrap();
+    client.unwatch().await.unwrap();
+    drop(client);
+    assert_eq!(pool.idle(), 1);
+
+    // A transaction left open.
+    let mut client = pool.get().await.unwrap();
+    client.multi().await.unwrap();
+    drop(client);
+    assert_eq!(pool.idle(), 0);
+
+    // Keys left watched.
+    let mut client = pool.get().await.unwrap();
+    client.watch(&["hello"]).await.unwrap();
+    drop(client);
+    assert_eq!(pool.idle(), 0);
+
+    // A command whose reply was not read, as its future was dropped.
+    let mut client = pool.get().await.unwrap();
+    let sleep = client.debug_sleep(Duration::from_secs(1));
+    let res = tokio::time::timeout(Duration::from_millis(50), sleep).await;
+    assert!(res.is_err());
+    drop(client);
+    assert_eq!(pool.idle(), 0);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn connect_over_unix_socket() {
+    let path = std::env::temp_dir().join(format!("redis-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = tokio::net::UnixListener::bind(&path).unwrap();
+    tokio::spawn(async move { server::run_unix(listener, tokio::signal::ctrl_c()).await });
+
+    let mut client = Client::connect_unix(&path).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(value, "world");
+
+    // Clients are described by the path of the socket.
+    let list = client.client_list().await.unwrap();
+    assert!(
+        list.contains(&format!(" addr={}:0 ", path.display())),
+        "{list}"
+    );
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn serve_tcp_and_unix_socket() {
+    let path = std::env::temp_dir().join(format!("redis-both-{}.sock", std::process::id()));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let config = server::Config {
+        unixsocket: Some(path.clone()),
+        ..server::Config::default()
+    };
+    let server = tokio::spawn(server::run_with_config(listener, stopped, config));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let mut tcp = Client::connect(addr).await.unwrap();
+    tcp.set("hello", "world".into()).await.unwrap();
+    let mut unix = Client::connect_unix(&path).await.unwrap();
+    assert_eq!(unix.get("hello").await.unwrap().unwrap(), "world");
+
+    // The socket file is removed on shutdown.
+    drop((tcp, unix));
+    stop.send(()).unwrap();
+    server.await.unwrap();
+    assert!(!path.exists());
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn connect_over_tls() {
+    use redis_lib::tls;
+    use std::path::Path;
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/tls");
+    let config = server::Config {
+        tls: Some(tls::server_config(&dir.join("cert.pem"), &dir.join("key.pem")).unwrap()),
+        ..server::Config::default()
+    };
+    let (addr, _) = start_server_with_config(config).await;
+
+    let config = tls::client_config(&dir.join("cert.pem")).unwrap();
+    let mut client = Client::connect_tls(addr, "localhost", config.clone())
+        .await
+        .unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(value, "world");
+
+    // The certificate is not valid for another name.
+    assert!(
+        Client::connect_tls(addr, "example.com", config)
+            .await
+            .is_err()
+    );
+    // The server does not serve plain connections.
+    let mut plain = Client::connect(addr).await.unwrap();
+    assert!(plain.ping(None).await.is_err());
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn shutdown_interrupts_tls_handshake() {
+    use redis_lib::tls;
+    use std::path::Path;
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/tls");
+    let config = server::Config {
+        tls: Some(tls::server_config(&dir.join("cert.pem"), &dir.join("key.pem")).unwrap()),
+        ..server::Config::default()
+    };
+    let server = server::TestServer::start(config).await.unwrap();
+
+    // The client never starts the handshake.
+    let _stalled = TcpStream::connect(server.addr()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let start = Instant::now();
+    server.shutdown().await.unwrap();
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn multiplexed_client_shared_by_concurrent_tasks() {
+    let (addr, _) = start_server().await;
+    let client = Arc::new(MultiplexedClient::connect(addr).await.unwrap());
+    for i in 0..10 {
+        client
+            .set(&format!("key{i}"), i.to_string().into())
+            .await
+            .unwrap();
+    }
+
+    let tasks: Vec<_> = (0..50)
+        .map(|i| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                let value = client.get(&format!("key{}", i % 10)).await.unwrap();
+                assert_eq!(value.unwrap(), (i % 10).to_string());
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    assert!(client.get("missing").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn reconnecting_client_survives_restart() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(server::run(listener, stopped));
+
+    let mut client = ReconnectingClient::connect(addr.to_string())
+        .await
+        .unwrap()
+        .with_backoff(Duration::from_millis(10), 5);
+    client.set("hello", "world".into()).await.unwrap();
+
+    // Restart the server on the same address, dropping the connection.
+    stop.send(()).unwrap();
+    server.await.unwrap();
+    let listener = TcpListener::bind(addr).await.unwrap();
+    tokio::spawn(server::run(listener, std::future::pending::<()>()));
+
+    // The keys of the previous server are gone, but the command succeeds.
+    assert!(client.get("hello").await.unwrap().is_none());
+    client.set("hello", "again".into()).await.unwrap();
+    assert_eq!(client.get("hello").await.unwrap().unwrap(), "again");
+}
+
+#[tokio::test]
+async fn reconnecting_client_replaces_a_lost_client() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(server::run(listener, stopped));
+
+    let mut client = ReconnectingClient::connect(addr.to_string())
+        .await
+        .unwrap()
+        .with_backoff(Duration::from_millis(10), 5);
+    client
+        .client()
+        .await
+        .unwrap()
+        .set("hello", "world".into())
+        .await
+        .unwrap();
+
+    stop.send(()).unwrap();
+    server.await.unwrap();
+    let listener = TcpListener::bind(addr).await.unwrap();
+    tokio::spawn(server::run(listener, std::future::pending::<()>()));
+
+    // Not retried, but the next command goes through a new connection.
+    let lost = client.client().await.unwrap();
+    assert!(lost.get("hello").await.is_err());
+    assert!(lost.is_broken());
+    let reconnected = client.client().await.unwrap();
+    assert!(!reconnected.is_broken());
+    assert!(reconnected.get("hello").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn connect_timeout_to_unresponsive_host() {
+    // A listener never accepting connections: once its backlog is full, connecting
+    // to it hangs like connecting to an unreachable host.
+    let socket = tokio::net::TcpSocket::new_v4().unwrap();
+    socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let listener = socket.listen(0).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut connected = vec![];
+    let err = loop {
+        let start = Instant::now();
+        match Client::connect_timeout(addr, Duration::from_millis(100)).await {
+            Ok(client) => connected.push(client),
+            Err(err) => {
+                assert!(start.elapsed() < Duration::from_secs(1));
+                break err;
+            }
+        }
+        assert!(connected.len() < 16, "the backlog is never full");
+    };
+
+    let err = err.downcast::<std::io::Error>().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+}
+
+#[tokio::test]
+async fn connect_timeout_to_server() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect_timeout(addr, Duration::from_secs(1))
+        .await
+        .unwrap();
+
+    assert_eq!(b"PONG", &client.ping(None).await.unwrap()[..]);
+}
+
+#[tokio::test]
+async fn builder_sets_up_the_connection() {
+    let config = server::Config {
+        password: Some("secret".into()),
+        ..server::Config::default()
+    };
+    let (addr, _) = start_server_with_config(config).await;
+
+    let mut client = Client::builder(addr.to_string())
+        .connect_timeout(Duration::from_secs(1))
+        .password("secret")
+        .database(1)
+        .name("worker")
+        .nodelay(true)
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(client.get_name().await.unwrap().as_deref(), Some("worker"));
+    client.set("hello", "world".into()).await.unwrap();
+
+    // The key was set in the selected database.
+    let mut other = Client::builder(addr.to_string())
+        .password("secret")
+        .connect()
+        .await
+        .unwrap();
+    assert!(other.get("hello").await.unwrap().is_none());
+    other.select(1).await.unwrap();
+    assert_eq!(other.get("hello").await.unwrap().unwrap(), "world");
+
+    // Connecting fails with a wrong password.
+    let res = Client::builder(addr.to_string())
+        .password("wrong")
+        .connect()
+        .await;
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn tcp_options_set_on_stream() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (accepted, _) = listener.accept().await.unwrap();
+
+    let options = TcpOptions {
+        nodelay: true,
+        keepalive: Some(Duration::from_secs(60)),
+    };
+    options.apply(&stream).unwrap();
+    assert!(stream.nodelay().unwrap());
+    assert!(socket2::SockRef::from(&stream).keepalive().unwrap());
+
+    let defaults = TcpOptions {
+        nodelay: false,
+        keepalive: None,
+    };
+    defaults.apply(&accepted).unwrap();
+    assert!(!accepted.nodelay().unwrap());
+    assert!(!socket2::SockRef::from(&accepted).keepalive().unwrap());
+}
+
+#[tokio::test]
+async fn send_raw_commands() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let reply = client.send_command(&["PING".into()]).await.unwrap();
+    assert_eq!(reply, Frame::Simple("PONG".into()));
+
+    let reply = client
+        .send_command(&["SET".into(), "hello".into(), "world".into()])
+        .await
+        .unwrap();
+    assert_eq!(reply, Frame::Simple("OK".into()));
+    let reply = client
+        .send_command(&["GET".into(), "hello".into()])
+        .await
+        .unwrap();
+    assert_eq!(reply, Frame::Bulk("world".into()));
+
+    // Error replies are returned as frames.
+    let reply = client.send_command(&["NOPE".into()]).await.unwrap();
+    assert!(matches!(reply, Frame::Error(msg) if msg.starts_with("ERR unknown command")));
+}
+
+#[cfg(feature = "json")]
+#[tokio::test]
+async fn json_round_trip() {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct User {
+        name: String,
+        age: u32,
+        tags: Vec<String>,
+    }
+
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    let user = User {
+        name: "ada".into(),
+        age: 36,
+        tags: vec!["admin".into()],
+    };
+
+    client.set_json("user", &user).await.unwrap();
+    assert_eq!(client.get_json::<User>("user").await.unwrap(), Some(user));
+    assert_eq!(client.get_json::<User>("missing").await.unwrap(), None);
+
+    // A value which is not the JSON of a `User` fails to be read as one.
+    client.set("hello", "world".into()).await.unwrap();
+    assert!(client.get_json::<User>("hello").await.is_err());
+}
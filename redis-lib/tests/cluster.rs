@@ -0,0 +1,31 @@
+use redis_lib::cluster::{SLOTS, key_slot};
+
+#[test]
+fn key_slot_within_slots() {
+    for key in [&b""[..], b"foo", b"{a}", b"\xff\xfe"] {
+        assert!(key_slot(key) < SLOTS);
+    }
+}
+
+#[test]
+fn key_slot_matches_redis() {
+    // As reported by `CLUSTER KEYSLOT`.
+    assert_eq!(key_slot(b"foo"), 12182);
+    assert_eq!(key_slot(b"bar"), 5061);
+    assert_eq!(key_slot(b"hello"), 866);
+    assert_eq!(key_slot(b"123456789"), 12739);
+    assert_eq!(key_slot(b""), 0);
+}
+
+#[test]
+fn key_slot_hashes_tag_only() {
+    let slot = key_slot(b"user1000");
+    assert_eq!(key_slot(b"{user1000}.following"), slot);
+    assert_eq!(key_slot(b"{user1000}.followers"), slot);
+    assert_eq!(key_slot(b"foo{user1000}bar{baz}"), slot);
+
+    // Without a closing brace or with an empty tag, the whole key is hashed.
+    assert_ne!(key_slot(b"{user1000"), slot);
+    assert_ne!(key_slot(b"{}user1000"), slot);
+    assert_ne!(key_slot(b"{}user1000"), key_slot(b"{}"));
+}
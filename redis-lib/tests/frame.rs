@@ -0,0 +1,497 @@
+use redis_lib::frame::{self, Limits};
+use redis_lib::{Connection, Frame, StreamedFrame};
+use std::io::Cursor;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Returns both ends of a TCP connection.
+async fn socket_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (stream, accepted) = tokio::join!(TcpStream::connect(addr), listener.accept());
+
+    (stream.unwrap(), accepted.unwrap().0)
+}
+
+/// Write `frame` on a connection speaking RESP `protocol`, and read it back from
+/// the other end.
+async fn round_trip(frame: &Frame, protocol: u8) -> Frame {
+    let (stream, peer) = socket_pair().await;
+    let mut writer = Connection::new(stream);
+    writer.set_protocol(protocol);
+    let mut reader = Connection::new(peer);
+
+    writer.write_frame(frame).await.unwrap();
+    reader.read_frame().await.unwrap().unwrap()
+}
+
+/// The bytes of `frame` written on a connection speaking RESP `protocol`.
+async fn encode(frame: &Frame, protocol: u8) -> Vec<u8> {
+    let (stream, mut peer) = socket_pair().await;
+    let mut writer = Connection::new(stream);
+    writer.set_protocol(protocol);
+
+    writer.write_frame(frame).await.unwrap();
+    drop(writer);
+    let mut buf = vec![];
+    peer.read_to_end(&mut buf).await.unwrap();
+
+    buf
+}
+
+#[test]
+fn frames_compare_by_value() {
+    let frame = Frame::Array(vec![
+        Frame::Bulk("subscribe".into()),
+        Frame::Bulk("news".into()),
+        Frame::Integer(1),
+    ]);
+
+    assert_eq!(
+        frame,
+        Frame::Array(vec![
+            Frame::Bulk("subscribe".into()),
+            Frame::Bulk("news".into()),
+            Frame::Integer(1),
+        ])
+    );
+    assert_ne!(frame, Frame::Push(vec![Frame::Bulk("subscribe".into())]));
+    assert_ne!(Frame::Integer(1), Frame::Integer(2));
+    assert_ne!(Frame::Simple("OK".into()), Frame::Bulk("OK".into()));
+}
+
+#[test]
+fn strings_compare_with_str() {
+    assert!(Frame::Simple("OK".into()) == "OK");
+    assert!(Frame::Bulk("OK".into()) == *"OK");
+    assert!(Frame::Bulk("OK".into()) != "KO");
+    assert!(Frame::Error("OK".into()) != "OK");
+    assert!(Frame::Integer(1) != "1");
+}
+
+#[test]
+fn display_like_redis_cli() {
+    assert_eq!(Frame::Simple("OK".into()).to_string(), "OK");
+    assert_eq!(Frame::Bulk("hello".into()).to_string(), "hello");
+    assert_eq!(
+        Frame::Bulk(vec![b'a', 0xff].into()).to_string(),
+        "a\u{fffd}"
+    );
+    assert_eq!(Frame::Integer(-3).to_string(), "-3");
+    assert_eq!(Frame::Null.to_string(), "(nil)");
+    assert_eq!(
+        Frame::Error("ERR unknown command".into()).to_string(),
+        "(error) ERR unknown command"
+    );
+}
+
+#[test]
+fn display_nested_arrays() {
+    let frame = Frame::Array(vec![
+        Frame::Bulk("a".into()),
+        Frame::Array(vec![
+            Frame::Integer(1),
+            Frame::Array(vec![Frame::Null, Frame::Bulk("b".into())]),
+        ]),
+        Frame::Array(vec![]),
+    ]);
+    assert_eq!(
+        frame.to_string(),
+        [
+            "1) a",
+            "2) 1) 1",
+            "   2) 1) (nil)",
+            "      2) b",
+            "3) (empty array)",
+        ]
+        .join("\n")
+    );
+
+    // Numbers are aligned once there are ten entries or more.
+    let frame = Frame::Array((1..=10).map(Frame::Integer).collect());
+    assert!(frame.to_string().starts_with(" 1) 1\n 2) 2\n"));
+    assert!(frame.to_string().ends_with("\n10) 10"));
+}
+
+#[tokio::test]
+async fn double_round_trip() {
+    for val in [1.5, -0.25, f64::INFINITY, f64::NEG_INFINITY] {
+        assert_eq!(round_trip(&Frame::Double(val), 3).await, Frame::Double(val));
+    }
+
+    let Frame::Double(nan) = round_trip(&Frame::Double(f64::NAN), 3).await else {
+        panic!("NaN was not read back as a double");
+    };
+    assert!(nan.is_nan());
+}
+
+#[tokio::test]
+async fn double_as_bulk_for_resp2() {
+    assert_eq!(
+        round_trip(&Frame::Double(f64::NEG_INFINITY), 2).await,
+        Frame::Bulk("-inf".into())
+    );
+    assert_eq!(
+        round_trip(&Frame::Double(2.5), 2).await,
+        Frame::Bulk("2.5".into())
+    );
+}
+
+#[tokio::test]
+async fn boolean_round_trip() {
+    for val in [true, false] {
+        assert_eq!(
+            round_trip(&Frame::Boolean(val), 3).await,
+            Frame::Boolean(val)
+        );
+        assert_eq!(
+            round_trip(&Frame::Boolean(val), 2).await,
+            Frame::Integer(val.into())
+        );
+    }
+}
+
+#[tokio::test]
+async fn big_number_round_trip() {
+    let digits = "-1234567890123456789012345678901234567890";
+    let frame = Frame::BigNumber(digits.to_string());
+
+    assert_eq!(round_trip(&frame, 3).await, frame);
+    assert_eq!(round_trip(&frame, 2).await, Frame::Bulk(digits.into()));
+}
+
+#[test]
+fn big_number_must_be_decimal() {
+    for payload in [&b"(12a\r\n"[..], b"(-\r\n", b"(\r\n"] {
+        let mut src = Cursor::new(payload);
+        assert!(Frame::parse(&mut src, &Limits::default()).is_err());
+    }
+}
+
+#[tokio::test]
+async fn verbatim_round_trip() {
+    let frame = Frame::Verbatim {
+        format: *b"mkd",
+        data: "# Title".into(),
+    };
+
+    assert_eq!(round_trip(&frame, 3).await, frame);
+    assert_eq!(round_trip(&frame, 2).await, Frame::Bulk("# Title".into()));
+}
+
+#[tokio::test]
+async fn null_encoding_depends_on_protocol() {
+    assert_eq!(encode(&Frame::Null, 3).await, b"_\r\n");
+    assert_eq!(encode(&Frame::Null, 2).await, b"$-1\r\n");
+
+    assert_eq!(round_trip(&Frame::Null, 3).await, Frame::Null);
+    assert_eq!(round_trip(&Frame::Null, 2).await, Frame::Null);
+}
+
+#[test]
+fn null_array_is_null() {
+    let limits = Limits::default();
+    let bytes = b"*-1\r\n:1\r\n";
+
+    let mut checked = Cursor::new(&bytes[..]);
+    Frame::check(&mut checked, &limits).unwrap();
+    let mut src = Cursor::new(&bytes[..]);
+    assert_eq!(Frame::parse(&mut src, &limits).unwrap(), Frame::Null);
+    assert_eq!(src.position(), checked.position());
+    assert_eq!(Frame::parse(&mut src, &limits).unwrap(), Frame::Integer(1));
+
+    let mut src = Cursor::new(&b"*-2\r\n"[..]);
+    assert!(Frame::parse(&mut src, &limits).is_err());
+}
+
+#[tokio::test]
+async fn negative_integer_round_trip() {
+    assert_eq!(encode(&Frame::Integer(-2), 2).await, b":-2\r\n");
+    assert_eq!(round_trip(&Frame::Integer(-2), 3).await, Frame::Integer(-2));
+}
+
+#[tokio::test]
+async fn close_in_the_middle_of_a_frame_is_a_reset() {
+    let (mut stream, peer) = socket_pair().await;
+    let mut reader = Connection::new(peer);
+
+    stream.write_all(b"$5\r\nhel").await.unwrap();
+    drop(stream);
+
+    let err = reader.read_frame().await.unwrap_err();
+    let err = err.downcast_ref::<std::io::Error>().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::ConnectionReset);
+}
+
+#[tokio::test]
+async fn malformed_data_is_a_protocol_error() {
+    for data in [&b"$abc\r\n"[..], b"GET \"hello\r\n"] {
+        let (mut stream, peer) = socket_pair().await;
+        let mut reader = Connection::new(peer);
+
+        stream.write_all(data).await.unwrap();
+        let err = reader.read_frame().await.unwrap_err();
+        assert!(err.is::<frame::Error>(), "{err}");
+    }
+}
+
+#[tokio::test]
+async fn extreme_integers_round_trip() {
+    for val in [i64::MIN, i64::MAX] {
+        assert_eq!(
+            round_trip(&Frame::Integer(val), 2).await,
+            Frame::Integer(val)
+        );
+    }
+}
+
+#[test]
+fn oversized_headers_are_rejected() {
+    // Rejected from the header alone, instead of waiting for the payload.
+    for payload in [
+        &b"$1000000000\r\n"[..],
+        b"*1000000000\r\n",
+        b"%1000000000\r\n",
+    ] {
+        let mut src = Cursor::new(payload);
+        assert!(matches!(
+            Frame::check(&mut src, &Limits::default()),
+            Err(frame::Error::Other(_))
+        ));
+    }
+}
+
+#[tokio::test]
+async fn huge_array_count_is_rejected() {
+    let (mut stream, peer) = socket_pair().await;
+    let mut reader = Connection::new(peer);
+
+    stream.write_all(b"*100000000\r\n").await.unwrap();
+    stream.shutdown().await.unwrap();
+    let err = reader.read_frame().await.unwrap_err();
+    assert!(err.to_string().contains("multibulk length"), "{err}");
+
+    // Without a limit, the entries are awaited without reserving room for them.
+    let limits = Limits {
+        max_array_len: usize::MAX,
+        ..Limits::default()
+    };
+    let mut src = Cursor::new(&b"*100000000\r\n:1\r\n"[..]);
+    assert!(matches!(
+        Frame::parse(&mut src, &limits),
+        Err(frame::Error::Incomplete)
+    ));
+}
+
+#[test]
+fn deep_nesting_is_rejected() {
+    let limits = Limits {
+        max_depth: 3,
+        ..Limits::default()
+    };
+
+    let mut nested = b"*1\r\n".repeat(3);
+    nested.extend_from_slice(b":1\r\n");
+    assert!(Frame::parse(&mut Cursor::new(&nested[..]), &limits).is_ok());
+
+    let mut nested = b"*1\r\n".repeat(4);
+    nested.extend_from_slice(b":1\r\n");
+    assert!(Frame::check(&mut Cursor::new(&nested[..]), &limits).is_err());
+    assert!(Frame::parse(&mut Cursor::new(&nested[..]), &limits).is_err());
+}
+
+#[tokio::test]
+async fn read_buffer_with_custom_capacity() {
+    let (stream, peer) = socket_pair().await;
+    let mut writer = Connection::new(stream);
+    let mut reader = Connection::with_capacity(peer, 64 * 1024);
+
+    // Larger than the default capacity of 4 KiB.
+    let frame = Frame::Bulk("x".repeat(16 * 1024).into());
+    let (written, read) = tokio::join!(writer.write_frame(&frame), reader.read_frame());
+    written.unwrap();
+    assert_eq!(read.unwrap().unwrap(), frame);
+
+    // Smaller than a single frame, grown as needed.
+    let (stream, peer) = socket_pair().await;
+    let mut writer = Connection::new(stream);
+    let mut reader = Connection::with_capacity(peer, 16);
+    let (written, read) = tokio::join!(writer.write_frame(&frame), reader.read_frame());
+    written.unwrap();
+    assert_eq!(read.unwrap().unwrap(), frame);
+}
+
+#[tokio::test]
+async fn frames_over_in_memory_pipe() {
+    let (client, server) = tokio::io::duplex(64);
+    let mut client = Connection::from_stream(client);
+    let mut server = Connection::from_stream(server);
+
+    let request = Frame::Array(vec![
+        Frame::Bulk("SET".into()),
+        Frame::Bulk("hello".into()),
+        Frame::Bulk("x".repeat(200).into()),
+    ]);
+    // The frame is larger than the pipe, which must be read while writing it.
+    let (written, read) = tokio::join!(client.write_frame(&request), server.read_frame());
+    written.unwrap();
+    assert_eq!(read.unwrap().unwrap(), request);
+
+    server
+        .write_frame(&Frame::Simple("OK".into()))
+        .await
+        .unwrap();
+    assert_eq!(client.read_frame().await.unwrap().unwrap(), "OK");
+
+    drop(server);
+    assert!(client.read_frame().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn connection_enforces_limits() {
+    let (mut stream, peer) = socket_pair().await;
+    let mut reader = Connection::new(peer);
+    reader.set_limits(Limits {
+        max_bulk_len: 8,
+        max_array_len: 2,
+        ..Limits::default()
+    });
+
+    stream
+        .write_all(b"*2\r\n$8\r\nfits-ok!\r\n$1\r\nx\r\n")
+        .await
+        .unwrap();
+    assert!(reader.read_frame().await.unwrap().is_some());
+
+    stream.write_all(b"$9\r\n").await.unwrap();
+    assert!(reader.read_frame().await.is_err());
+}
+
+#[tokio::test]
+async fn large_bulk_is_streamed() {
+    const LEN: usize = 1024 * 1024;
+
+    let (mut stream, peer) = socket_pair().await;
+    let mut reader = Connection::new(peer);
+    let payload: Vec<u8> = (0..LEN).map(|i| (i % 251) as u8).collect();
+
+    let expected = payload.clone();
+    tokio::spawn(async move {
+        for _ in 0..2 {
+            stream
+                .write_all(format!("${LEN}\r\n").as_bytes())
+                .await
+                .unwrap();
+            stream.write_all(&payload).await.unwrap();
+            stream.write_all(b"\r\n+OK\r\n").await.unwrap();
+        }
+    });
+
+    let Some(StreamedFrame::Bulk(mut bulk)) = reader.read_frame_streaming(64 * 1024).await.unwrap()
+    else {
+        panic!("the value was not streamed");
+    };
+    assert_eq!(bulk.len(), LEN);
+
+    // Read by chunks, never holding the whole value.
+    let mut chunk = [0; 8 * 1024];
+    let mut read = 0;
+    loop {
+        let n = bulk.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        assert_eq!(&chunk[..n], &expected[read..read + n]);
+        read += n;
+    }
+    assert_eq!(read, LEN);
+    assert_eq!(
+        reader.read_frame().await.unwrap(),
+        Some(Frame::Simple("OK".into()))
+    );
+
+    // The rest of a partially read value is skipped.
+    let Some(StreamedFrame::Bulk(mut bulk)) = reader.read_frame_streaming(64 * 1024).await.unwrap()
+    else {
+        panic!("the value was not streamed");
+    };
+    bulk.read_exact(&mut chunk[..10]).await.unwrap();
+    assert_eq!(
+        reader.read_frame().await.unwrap(),
+        Some(Frame::Simple("OK".into()))
+    );
+}
+
+/// A frame of each type, as sent to a RESP3 peer.
+fn sample_frames() -> Vec<Frame> {
+    vec![
+        Frame::Simple("OK".into()),
+        Frame::Error("ERR oops".into()),
+        Frame::Integer(-42),
+        Frame::Bulk("hello\r\nworld".into()),
+        Frame::Null,
+        Frame::Double(1.5),
+        Frame::Boolean(true),
+        Frame::BigNumber("123456789012345678901234567890".into()),
+        Frame::Verbatim {
+            format: *b"txt",
+            data: "some text".into(),
+        },
+        Frame::Array(vec![
+            Frame::Bulk("nested".into()),
+            Frame::Array(vec![Frame::Integer(1), Frame::Null]),
+        ]),
+        Frame::Map(vec![(Frame::Bulk("key".into()), Frame::Integer(7))]),
+        Frame::Set(vec![Frame::Bulk("member".into())]),
+        Frame::Push(vec![Frame::Bulk("message".into())]),
+    ]
+}
+
+#[tokio::test]
+async fn parse_in_a_single_pass() {
+    let limits = Limits::default();
+
+    for frame in sample_frames() {
+        let bytes = encode(&frame, 3).await;
+
+        // Any strict prefix is incomplete, instead of an error or another frame.
+        for end in 0..bytes.len() {
+            let mut src = Cursor::new(&bytes[..end]);
+            assert!(matches!(
+                Frame::parse(&mut src, &limits),
+                Err(frame::Error::Incomplete)
+            ));
+        }
+
+        // The same frame as after a check, and the cursor ends at the same position.
+        let mut checked = Cursor::new(&bytes[..]);
+        Frame::check(&mut checked, &limits).unwrap();
+        let mut src = Cursor::new(&bytes[..]);
+        assert_eq!(Frame::parse(&mut src, &limits).unwrap(), frame);
+        assert_eq!(src.position(), checked.position());
+    }
+}
+
+#[tokio::test]
+async fn fragmented_reads() {
+    let frames = sample_frames();
+    let mut bytes = vec![];
+    for frame in &frames {
+        bytes.extend(encode(frame, 3).await);
+    }
+
+    let (mut stream, peer) = socket_pair().await;
+    stream.set_nodelay(true).unwrap();
+    let mut reader = Connection::new(peer);
+    tokio::spawn(async move {
+        for chunk in bytes.chunks(3) {
+            stream.write_all(chunk).await.unwrap();
+            tokio::task::yield_now().await;
+        }
+    });
+
+    for frame in frames {
+        assert_eq!(reader.read_frame().await.unwrap(), Some(frame));
+    }
+    assert_eq!(reader.read_frame().await.unwrap(), None);
+}
@@ -0,0 +1,55 @@
+//! The metrics are counted for the whole process: the tests of this file are kept
+//! apart from the others, so that no other server updates them concurrently.
+
+use bytes::Bytes;
+use redis_lib::clients::Client;
+use redis_lib::server;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[tokio::test]
+async fn metrics_count_connections_commands_and_lookups() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", Bytes::from("world")).await.unwrap();
+    assert!(client.get("hello").await.unwrap().is_some());
+    assert!(client.get("hello").await.unwrap().is_some());
+    assert!(client.get("missing").await.unwrap().is_none());
+
+    let metrics = server::metrics();
+    assert_eq!(metrics.total_connections(), 1);
+    assert_eq!(metrics.connected(), 1);
+    assert_eq!(metrics.commands_processed(), 4);
+    assert_eq!(metrics.commands().get("get"), Some(&3));
+    assert_eq!(metrics.commands().get("set"), Some(&1));
+    assert_eq!(metrics.keyspace_hits(), 2);
+    assert_eq!(metrics.keyspace_misses(), 1);
+    assert!(metrics.net_input_bytes() > 0);
+    assert!(metrics.net_output_bytes() > 0);
+
+    let info = client.info(Some("stats")).await.unwrap();
+    assert!(info.starts_with("# Stats\r\n"));
+    assert!(info.contains("total_connections_received:1\r\n"));
+    assert!(info.contains("total_commands_processed:5\r\n"));
+    assert!(info.contains("keyspace_hits:2\r\n"));
+    assert!(info.contains("keyspace_misses:1\r\n"));
+
+    // Whatever their name, unknown commands are counted together.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*1\r\n$3\r\nfoo\r\n*1\r\n$3\r\nbar\r\n*1\r\n$4\r\nPING\r\n")
+        .await
+        .unwrap();
+    let mut replies = vec![0; 1024];
+    let mut read = 0;
+    while !replies[..read].ends_with(b"+PONG\r\n") {
+        read += stream.read(&mut replies[read..]).await.unwrap();
+    }
+    let commands = server::metrics().commands();
+    assert_eq!(commands.get("unknown"), Some(&2));
+    assert!(!commands.contains_key("foo"));
+    assert!(!commands.contains_key("bar"));
+}
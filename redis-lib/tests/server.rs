@@ -1,4 +1,5 @@
-use redis_lib::server;
+use redis_lib::clients::Client;
+use redis_lib::{Command, Connection, Frame, server};
 use std::net::SocketAddr;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
@@ -13,6 +14,86 @@ async fn start_server() -> SocketAddr {
     addr
 }
 
+#[tokio::test]
+async fn bind_to_an_interface() {
+    let listener = server::bind("127.0.0.1".parse().unwrap(), 0).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    assert!(addr.ip().is_loopback());
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"PING\r\n").await.unwrap();
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+PONG\r\n", &response);
+
+    // The port is in use.
+    let err = server::bind(addr.ip(), addr.port()).await.unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with(&format!("failed to listen on {addr}: "))
+    );
+}
+
+#[tokio::test]
+async fn spawn_on_any_port() {
+    let ip = "127.0.0.1".parse().unwrap();
+    let config = server::Config::default();
+    let addr = server::spawn(ip, 0, tokio::signal::ctrl_c(), config)
+        .await
+        .unwrap();
+    assert_ne!(addr.port(), 0);
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("hello", "world".into()).await.unwrap();
+    assert_eq!(client.get("hello").await.unwrap().unwrap(), "world");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn burst_of_connections_with_several_acceptors() {
+    let config = server::Config {
+        acceptors: 4,
+        ..server::Config::default()
+    };
+    let ip = "127.0.0.1".parse().unwrap();
+    let addr = server::spawn(ip, 0, tokio::signal::ctrl_c(), config)
+        .await
+        .unwrap();
+
+    let mut connections = tokio::task::JoinSet::new();
+    for _ in 0..64 {
+        connections.spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(b"PING\r\n").await.unwrap();
+            let mut response = [0; 7];
+            stream.read_exact(&mut response).await.unwrap();
+            assert_eq!(b"+PONG\r\n", &response);
+        });
+    }
+    let served = connections.join_all().await;
+    assert_eq!(served.len(), 64);
+}
+
+#[tokio::test]
+async fn connection_accepted_during_shutdown_is_closed() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(server::run(listener, shutdown_rx));
+
+    // Connect just as the shutdown signal fires.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    shutdown_tx.send(()).unwrap();
+
+    let mut response = [0; 1];
+    let read = time::timeout(Duration::from_secs(1), stream.read(&mut response)).await;
+    assert!(matches!(read, Ok(Ok(0)) | Ok(Err(_))));
+    time::timeout(Duration::from_secs(1), server)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
 #[tokio::test]
 async fn key_value_get_set() {
     let addr = start_server().await;
@@ -60,8 +141,6 @@ async fn key_value_get_set() {
 
 #[tokio::test]
 async fn key_value_timeout() {
-    time::pause();
-
     let addr = start_server().await;
     let mut stream = TcpStream::connect(addr).await.unwrap();
 
@@ -92,7 +171,9 @@ async fn key_value_timeout() {
     stream.read_exact(&mut response).await.unwrap();
     assert_eq!(b"$5\r\nworld\r\n", &response);
 
-    // Wait for the key to expire
+    // Wait for the key to expire. The clock is only paused now, as a paused clock
+    // jumps to the expiration whenever the runtime waits for the socket.
+    time::pause();
     time::advance(Duration::from_secs(1)).await;
 
     // Get a key, data is missing
@@ -340,11 +421,289 @@ async fn send_error_unknown_command() {
 
     stream.read_exact(&mut response).await.unwrap();
 
-    assert_eq!(b"-ERR unknown command \'foo\'\r\n", &response);
+    assert_eq!(b"-ERR unknown command \'FOO\'\r\n", &response);
+}
+
+#[tokio::test]
+async fn wrong_number_of_arguments() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let requests: [&[&str]; 4] = [
+        &["GET"],
+        &["GET", "hello", "world"],
+        &["SET", "hello"],
+        &["SET", "hello", "world", "EX", "10", "extra"],
+    ];
+    for args in requests {
+        let request = Frame::Array(args.iter().map(|&arg| Frame::Bulk(arg.into())).collect());
+        connection.write_frame(&request).await.unwrap();
+
+        // The connection is kept open.
+        let name = args[0].to_lowercase();
+        let expected = format!("ERR wrong number of arguments for '{name}' command");
+        let reply = connection.read_frame().await.unwrap();
+        assert_eq!(reply, Some(Frame::Error(expected)), "{args:?}");
+    }
+}
+
+#[tokio::test]
+async fn invalid_options() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let requests: [(&[&str], &str); 8] = [
+        (
+            &["HELLO", "3", "FOO"],
+            "ERR Syntax error in HELLO option 'FOO'",
+        ),
+        (
+            &["HELLO", "three"],
+            "ERR Protocol version is not an integer or out of range",
+        ),
+        (&["SCAN", "0", "COUNT", "0"], "ERR syntax error"),
+        (&["SCAN", "0", "TYPE", "string"], "ERR syntax error"),
+        (
+            &["RESTORE", "hello", "0", "payload", "FOO"],
+            "ERR syntax error",
+        ),
+        (&["MEMORY", "USAGE", "hello", "FOO"], "ERR syntax error"),
+        (
+            &["XREAD", "BLOCK", "0", "STREAMS", "hello", "0"],
+            "ERR syntax error",
+        ),
+        (
+            &["XREAD", "STREAMS", "hello", "world", "0"],
+            "ERR Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.",
+        ),
+    ];
+    for (args, expected) in requests {
+        let request = Frame::Array(args.iter().map(|&arg| Frame::Bulk(arg.into())).collect());
+        connection.write_frame(&request).await.unwrap();
+
+        // The connection is kept open.
+        let reply = connection.read_frame().await.unwrap();
+        assert_eq!(reply, Some(Frame::Error(expected.to_string())), "{args:?}");
+    }
+}
+
+#[tokio::test]
+async fn restore_with_absolute_ttl() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut request = async |args: &[&[u8]]| {
+        let request = Frame::Array(
+            args.iter()
+                .map(|&arg| Frame::Bulk(arg.to_vec().into()))
+                .collect(),
+        );
+        connection.write_frame(&request).await.unwrap();
+        connection.read_frame().await.unwrap().unwrap()
+    };
+
+    request(&[b"SET", b"hello", b"world"]).await;
+    let Frame::Bulk(payload) = request(&[b"DUMP", b"hello"]).await else {
+        panic!("no payload");
+    };
+    let unix = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap();
+    let later = (unix.as_millis() + 100_000).to_string();
+
+    let reply = request(&[b"RESTORE", b"later", later.as_bytes(), &payload, b"ABSTTL"]).await;
+    assert_eq!(reply, Frame::Simple("OK".into()));
+    assert_eq!(
+        request(&[b"GET", b"later"]).await,
+        Frame::Bulk("world".into())
+    );
+
+    // A time in the past expires the key at once.
+    let reply = request(&[b"RESTORE", b"past", b"1", &payload, b"ABSTTL", b"REPLACE"]).await;
+    assert_eq!(reply, Frame::Simple("OK".into()));
+    assert_eq!(request(&[b"GET", b"past"]).await, Frame::Null);
+}
+
+#[tokio::test]
+async fn commands_not_allowed_in_transaction_abort_it() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let refused: [(&[&str], &str); 3] = [
+        (&["PSYNC", "?", "-1"], "psync"),
+        (&["CLIENT", "SETNAME", "conn"], "client"),
+        (&["INFO"], "info"),
+    ];
+    for (command, name) in refused {
+        let requests: [(&[&str], Frame); 4] = [
+            (&["MULTI"], Frame::Simple("OK".into())),
+            (&["SET", "hello", "world"], Frame::Simple("QUEUED".into())),
+            (
+                command,
+                Frame::Error(format!(
+                    "ERR Command not allowed inside a transaction: '{name}'"
+                )),
+            ),
+            (
+                &["EXEC"],
+                Frame::Error("EXECABORT Transaction discarded because of previous errors.".into()),
+            ),
+        ];
+        for (args, expected) in requests {
+            let request = Frame::Array(args.iter().map(|&arg| Frame::Bulk(arg.into())).collect());
+            connection.write_frame(&request).await.unwrap();
+            let reply = connection.read_frame().await.unwrap();
+            assert_eq!(reply, Some(expected), "{args:?}");
+        }
+    }
+
+    // The connection is kept open, and nothing was applied.
+    let get = Frame::Array(vec![Frame::Bulk("GET".into()), Frame::Bulk("hello".into())]);
+    connection.write_frame(&get).await.unwrap();
+    assert_eq!(connection.read_frame().await.unwrap(), Some(Frame::Null));
+    let getname = Frame::Array(vec![
+        Frame::Bulk("CLIENT".into()),
+        Frame::Bulk("GETNAME".into()),
+    ]);
+    connection.write_frame(&getname).await.unwrap();
+    let reply = connection.read_frame().await.unwrap();
+    assert_eq!(reply, Some(Frame::Bulk("".into())));
+}
+
+#[tokio::test]
+async fn client_address_recorded() {
+    let addr = start_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let local_addr = stream.local_addr().unwrap();
+    let mut connection = Connection::new(stream);
+
+    let request = Frame::Array(vec![
+        Frame::Bulk("CLIENT".into()),
+        Frame::Bulk("LIST".into()),
+    ]);
+    connection.write_frame(&request).await.unwrap();
+
+    let Some(Frame::Bulk(list)) = connection.read_frame().await.unwrap() else {
+        panic!("expected a bulk string");
+    };
+    let list = String::from_utf8(list.to_vec()).unwrap();
+    assert!(list.contains(&format!(" addr={local_addr} ")), "{list}");
+}
+
+#[test]
+fn command_names_are_case_insensitive() {
+    for name in ["PING", "ping", "Ping"] {
+        let frame = Frame::Array(vec![Frame::Bulk(name.into())]);
+        assert!(
+            matches!(Command::from_frame(frame), Ok(Command::Ping(_))),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn command_flags_tell_reads_from_writes() {
+    let command = |args: &[&'static str]| {
+        let frame = Frame::Array(
+            args.iter()
+                .map(|arg| Frame::Bulk(arg.as_bytes().into()))
+                .collect(),
+        );
+        Command::from_frame(frame).unwrap()
+    };
+
+    let get = command(&["GET", "hello"]);
+    assert!(get.flags().contains(&"readonly"));
+    assert!(!get.is_write());
+
+    let set = command(&["SET", "hello", "world"]);
+    assert!(set.flags().contains(&"write"));
+    assert!(set.is_write());
+
+    let subscribe = command(&["SUBSCRIBE", "channel"]);
+    assert!(subscribe.flags().contains(&"pubsub"));
+    assert!(!subscribe.is_write());
+
+    let config = command(&["CONFIG", "GET", "maxmemory"]);
+    assert!(config.flags().contains(&"admin"));
 }
 
 // In this case we test that server Responds with an Error message if a client
 // sends an GET or SET command after a SUBSCRIBE
+#[tokio::test]
+async fn subscribe_twice_to_a_channel() {
+    let addr = start_server().await;
+    let mut subscriber = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let request = |args: &[&'static str]| {
+        Frame::Array(
+            args.iter()
+                .map(|arg| Frame::Bulk(arg.as_bytes().into()))
+                .collect(),
+        )
+    };
+    let confirmation = Frame::Array(vec![
+        Frame::Bulk("subscribe".into()),
+        Frame::Bulk("foo".into()),
+        Frame::Integer(1),
+    ]);
+
+    // Each channel is confirmed, with the count of distinct subscriptions.
+    subscriber
+        .write_frame(&request(&["SUBSCRIBE", "foo", "foo"]))
+        .await
+        .unwrap();
+    for _ in 0..2 {
+        let reply = subscriber.read_frame().await.unwrap();
+        assert_eq!(reply, Some(confirmation.clone()));
+    }
+    subscriber
+        .write_frame(&request(&["SUBSCRIBE", "foo"]))
+        .await
+        .unwrap();
+    let reply = subscriber.read_frame().await.unwrap();
+    assert_eq!(reply, Some(confirmation));
+
+    publisher
+        .write_frame(&request(&["PUBLISH", "foo", "bar"]))
+        .await
+        .unwrap();
+    let reply = publisher.read_frame().await.unwrap();
+    assert_eq!(reply, Some(Frame::Integer(1)));
+
+    // The message is received once.
+    let reply = subscriber.read_frame().await.unwrap();
+    let message = request(&["message", "foo", "bar"]);
+    assert_eq!(reply, Some(message));
+    subscriber.write_frame(&request(&["PING"])).await.unwrap();
+    let reply = subscriber.read_frame().await.unwrap();
+    assert_eq!(reply, Some(request(&["pong", ""])));
+}
+
+#[tokio::test]
+async fn subscribe_without_channels() {
+    let addr = start_server().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*1\r\n$9\r\nSUBSCRIBE\r\n")
+        .await
+        .unwrap();
+
+    let expected = b"-ERR wrong number of arguments for 'subscribe' command\r\n";
+    let mut response = vec![0; expected.len()];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(&expected[..], &response[..]);
+
+    // The connection is kept open, and not in the subscribed state.
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+}
+
 #[tokio::test]
 async fn send_error_get_set_after_subscribe() {
     let addr = start_server().await;
@@ -386,3 +745,389 @@ async fn send_error_get_set_after_subscribe() {
     stream.read_exact(&mut response).await.unwrap();
     assert_eq!(b"-ERR unknown command \'get\'\r\n", &response);
 }
+
+#[tokio::test]
+async fn pattern_pub_sub() {
+    let addr = start_server().await;
+
+    let mut publisher = TcpStream::connect(addr).await.unwrap();
+
+    let mut sub = TcpStream::connect(addr).await.unwrap();
+    sub.write_all(b"*2\r\n$10\r\nPSUBSCRIBE\r\n$6\r\nnews.*\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 37];
+    sub.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$10\r\npsubscribe\r\n$6\r\nnews.*\r\n:1\r\n"[..],
+        &response[..]
+    );
+
+    // A channel subscription is counted along with the pattern subscription
+    sub.write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$9\r\nnews.tech\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 38];
+    sub.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$9\r\nnews.tech\r\n:2\r\n"[..],
+        &response[..]
+    );
+
+    // Both the channel and the pattern subscription receive the message
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$9\r\nnews.tech\r\n$4\r\nrust\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    publisher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":2\r\n", &response);
+
+    let mut response = [0; 42 + 55];
+    sub.read_exact(&mut response).await.unwrap();
+    let message = &b"*3\r\n$7\r\nmessage\r\n$9\r\nnews.tech\r\n$4\r\nrust\r\n"[..];
+    let pmessage =
+        &b"*4\r\n$8\r\npmessage\r\n$6\r\nnews.*\r\n$9\r\nnews.tech\r\n$4\r\nrust\r\n"[..];
+    assert!(
+        response[..] == [message, pmessage].concat()[..]
+            || response[..] == [pmessage, message].concat()[..]
+    );
+}
+
+#[tokio::test]
+async fn quit_closes_connection() {
+    let addr = start_server().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"*1\r\n$4\r\nQUIT\r\n").await.unwrap();
+
+    // Read OK
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // The server closed the socket
+    assert_eq!(0, stream.read(&mut response).await.unwrap());
+}
+
+#[tokio::test]
+async fn inline_commands() {
+    let addr = start_server().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // A command without RESP framing
+    stream.write_all(b"PING\r\n").await.unwrap();
+
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+PONG\r\n", &response);
+
+    // Quoted arguments may hold spaces, and empty lines are skipped
+    stream
+        .write_all(b"SET greeting \"hello world\"\r\n\r\nGET greeting\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 23];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n$11\r\nhello world\r\n", &response);
+
+    // Unbalanced quotes are a protocol error closing the connection
+    stream.write_all(b"GET \"greeting\r\n").await.unwrap();
+    assert_eq!(0, stream.read(&mut response).await.unwrap());
+}
+
+#[tokio::test]
+async fn map_reply_round_trip() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let config_get = Frame::Array(vec![
+        Frame::Bulk("CONFIG".into()),
+        Frame::Bulk("GET".into()),
+        Frame::Bulk("databases".into()),
+    ]);
+
+    // RESP2 has no maps, so the entries are flattened into an array.
+    connection.write_frame(&config_get).await.unwrap();
+    assert_eq!(
+        connection.read_frame().await.unwrap().unwrap(),
+        Frame::Array(vec![
+            Frame::Bulk("databases".into()),
+            Frame::Bulk("16".into())
+        ])
+    );
+
+    let hello = Frame::Array(vec![Frame::Bulk("HELLO".into()), Frame::Bulk("3".into())]);
+    connection.write_frame(&hello).await.unwrap();
+    let Frame::Map(_) = connection.read_frame().await.unwrap().unwrap() else {
+        panic!("HELLO 3 did not reply with a map");
+    };
+
+    connection.write_frame(&config_get).await.unwrap();
+    assert_eq!(
+        connection.read_frame().await.unwrap().unwrap(),
+        Frame::Map(vec![(
+            Frame::Bulk("databases".into()),
+            Frame::Bulk("16".into())
+        )])
+    );
+}
+
+/// test that `SET` refuses an expire time which is not positive, keeping the
+/// connection
+#[tokio::test]
+async fn set_with_non_positive_expiration() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for (option, timeout) in [("EX", "0"), ("PX", "0"), ("EX", "-1"), ("px", "-100")] {
+        let set = Frame::Array(vec![
+            Frame::Bulk("SET".into()),
+            Frame::Bulk("hello".into()),
+            Frame::Bulk("world".into()),
+            Frame::Bulk(option.into()),
+            Frame::Bulk(timeout.into()),
+        ]);
+        connection.write_frame(&set).await.unwrap();
+        assert_eq!(
+            connection.read_frame().await.unwrap().unwrap(),
+            Frame::Error("ERR invalid expire time in 'set' command".into()),
+            "{option} {timeout}"
+        );
+    }
+
+    // The key was never set.
+    let get = Frame::Array(vec![Frame::Bulk("GET".into()), Frame::Bulk("hello".into())]);
+    connection.write_frame(&get).await.unwrap();
+    assert_eq!(connection.read_frame().await.unwrap().unwrap(), Frame::Null);
+}
+
+/// test that the container commands describe their subcommands with `HELP`
+#[tokio::test]
+async fn container_commands_help() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let help = |command: &str| {
+        Frame::Array(vec![
+            Frame::Bulk(command.to_string().into()),
+            Frame::Bulk("help".into()),
+        ])
+    };
+
+    connection.write_frame(&help("OBJECT")).await.unwrap();
+    let Frame::Array(lines) = connection.read_frame().await.unwrap().unwrap() else {
+        panic!("OBJECT HELP did not reply with an array");
+    };
+    assert!(!lines.is_empty());
+    assert!(
+        lines
+            .iter()
+            .any(|line| matches!(line, Frame::Simple(line) if line.starts_with("ENCODING"))),
+        "{lines:?}"
+    );
+
+    for (command, subcommand) in [
+        ("CLIENT", "SETNAME"),
+        ("CONFIG", "GET"),
+        ("PUBSUB", "NUMSUB"),
+        ("COMMAND", "GETKEYS"),
+    ] {
+        connection.write_frame(&help(command)).await.unwrap();
+        let Frame::Array(lines) = connection.read_frame().await.unwrap().unwrap() else {
+            panic!("{command} HELP did not reply with an array");
+        };
+        assert_eq!(
+            Frame::Simple(format!(
+                "{command} <subcommand> [<arg> [value] [opt] ...]. Subcommands are:"
+            )),
+            lines[0]
+        );
+        assert!(
+            lines
+                .iter()
+                .any(|line| matches!(line, Frame::Simple(line) if line.starts_with(subcommand))),
+            "{lines:?}"
+        );
+    }
+}
+
+/// test that `OBJECT ENCODING` tells integers apart from other strings
+#[tokio::test]
+async fn object_encoding() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+    client.set("counter", "12345".into()).await.unwrap();
+    client.set("short", "hello".into()).await.unwrap();
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    for (key, encoding) in [
+        ("counter", Frame::Bulk("int".into())),
+        ("short", Frame::Bulk("embstr".into())),
+        ("missing", Frame::Null),
+    ] {
+        let object_encoding = Frame::Array(vec![
+            Frame::Bulk("OBJECT".into()),
+            Frame::Bulk("ENCODING".into()),
+            Frame::Bulk(key.to_string().into()),
+        ]);
+        connection.write_frame(&object_encoding).await.unwrap();
+        assert_eq!(connection.read_frame().await.unwrap().unwrap(), encoding);
+    }
+}
+
+#[tokio::test]
+async fn set_reply_round_trip() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let command_docs = Frame::Array(vec![
+        Frame::Bulk("COMMAND".into()),
+        Frame::Bulk("DOCS".into()),
+        Frame::Bulk("ping".into()),
+    ]);
+    // The flags of the `message` argument of `PING`, wrapped by `flags`.
+    let docs = |flags: Frame| {
+        let message = Frame::Map(vec![
+            (Frame::Bulk("name".into()), Frame::Bulk("message".into())),
+            (Frame::Bulk("type".into()), Frame::Bulk("string".into())),
+            (Frame::Bulk("flags".into()), flags),
+        ]);
+        Frame::Map(vec![(
+            Frame::Bulk("ping".into()),
+            Frame::Map(vec![
+                (
+                    Frame::Bulk("summary".into()),
+                    Frame::Bulk("Returns the server's liveliness response.".into()),
+                ),
+                (Frame::Bulk("arguments".into()), Frame::Array(vec![message])),
+            ]),
+        )])
+    };
+
+    let hello = Frame::Array(vec![Frame::Bulk("HELLO".into()), Frame::Bulk("3".into())]);
+    connection.write_frame(&hello).await.unwrap();
+    connection.read_frame().await.unwrap().unwrap();
+
+    connection.write_frame(&command_docs).await.unwrap();
+    assert_eq!(
+        connection.read_frame().await.unwrap().unwrap(),
+        docs(Frame::Set(vec![Frame::Simple("optional".into())]))
+    );
+
+    // RESP2 has no sets, so the members are sent as an array.
+    let hello = Frame::Array(vec![Frame::Bulk("HELLO".into()), Frame::Bulk("2".into())]);
+    connection.write_frame(&hello).await.unwrap();
+    connection.read_frame().await.unwrap().unwrap();
+
+    connection.write_frame(&command_docs).await.unwrap();
+    let Frame::Array(entries) = connection.read_frame().await.unwrap().unwrap() else {
+        panic!("COMMAND DOCS did not reply with an array");
+    };
+    let Frame::Array(ping) = &entries[1] else {
+        panic!("the docs of PING are not an array");
+    };
+    let Frame::Array(arguments) = &ping[3] else {
+        panic!("the arguments of PING are not an array");
+    };
+    let Frame::Array(message) = &arguments[0] else {
+        panic!("the message argument is not an array");
+    };
+    assert_eq!(
+        message[5],
+        Frame::Array(vec![Frame::Simple("optional".into())])
+    );
+}
+
+#[tokio::test]
+async fn move_reply_depends_on_protocol() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let request = |args: &[&'static str]| {
+        Frame::Array(
+            args.iter()
+                .map(|arg| Frame::Bulk(arg.as_bytes().into()))
+                .collect(),
+        )
+    };
+
+    connection
+        .write_frame(&request(&["SET", "hello", "world"]))
+        .await
+        .unwrap();
+    connection.read_frame().await.unwrap().unwrap();
+
+    // RESP2 clients see an integer.
+    connection
+        .write_frame(&request(&["MOVE", "hello", "1"]))
+        .await
+        .unwrap();
+    assert_eq!(
+        connection.read_frame().await.unwrap().unwrap(),
+        Frame::Integer(1)
+    );
+
+    // RESP3 clients see a boolean. The key is no longer in this database.
+    connection
+        .write_frame(&request(&["HELLO", "3"]))
+        .await
+        .unwrap();
+    connection.read_frame().await.unwrap().unwrap();
+    connection
+        .write_frame(&request(&["MOVE", "hello", "1"]))
+        .await
+        .unwrap();
+    assert_eq!(
+        connection.read_frame().await.unwrap().unwrap(),
+        Frame::Boolean(false)
+    );
+}
+
+#[tokio::test]
+async fn pub_sub_pushes_under_resp3() {
+    let addr = start_server().await;
+    let mut subscriber = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let request = |args: &[&'static str]| {
+        Frame::Array(
+            args.iter()
+                .map(|arg| Frame::Bulk(arg.as_bytes().into()))
+                .collect(),
+        )
+    };
+
+    subscriber
+        .write_frame(&request(&["HELLO", "3"]))
+        .await
+        .unwrap();
+    subscriber.read_frame().await.unwrap().unwrap();
+    subscriber
+        .write_frame(&request(&["SUBSCRIBE", "hello"]))
+        .await
+        .unwrap();
+    assert_eq!(
+        subscriber.read_frame().await.unwrap().unwrap(),
+        Frame::Push(vec![
+            Frame::Bulk("subscribe".into()),
+            Frame::Bulk("hello".into()),
+            Frame::Integer(1),
+        ])
+    );
+
+    publisher
+        .write_frame(&request(&["PUBLISH", "hello", "world"]))
+        .await
+        .unwrap();
+    publisher.read_frame().await.unwrap().unwrap();
+
+    assert_eq!(
+        subscriber.read_frame().await.unwrap().unwrap(),
+        Frame::Push(vec![
+            Frame::Bulk("message".into()),
+            Frame::Bulk("hello".into()),
+            Frame::Bulk("world".into()),
+        ])
+    );
+}